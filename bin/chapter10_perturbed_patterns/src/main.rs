@@ -29,9 +29,9 @@ fn main() -> ExitCode {
     floor.material.ambient = 0.2;
     floor.material.specular = 0.0;
     let mut floor_pattern_1 =
-        perturbed_pattern(&stripe_pattern(&RED, &WHITE), 2.0 * perturb_factor, 4, 0.9);
+        perturbed_pattern(stripe_pattern(&RED, &WHITE), 2.0 * perturb_factor, 4, 0.9);
     let mut floor_pattern_2 =
-        perturbed_pattern(&stripe_pattern(&RED, &WHITE), 2.0 * perturb_factor, 4, 0.9);
+        perturbed_pattern(stripe_pattern(&RED, &WHITE), 2.0 * perturb_factor, 4, 0.9);
     let scale = 0.4;
     floor_pattern_1.set_transform(&scaling(scale, scale, scale).then(&rotation_y(PI / 4.0)));
     floor_pattern_2.set_transform(&scaling(scale, scale, scale).then(&rotation_y(-PI / 4.0)));
@@ -47,7 +47,7 @@ fn main() -> ExitCode {
     middle.material.diffuse = 0.9;
     middle.material.specular = 0.7;
     let mut middle_pattern = perturbed_pattern(
-        &stripe_pattern(&colori(13, 104, 53), &colori(15, 158, 79)),
+        stripe_pattern(&colori(13, 104, 53), &colori(15, 158, 79)),
         2.0 * perturb_factor,
         3,
         0.8,
@@ -67,7 +67,7 @@ fn main() -> ExitCode {
     right.material.diffuse = 0.9;
     right.material.specular = 0.3;
     let mut right_pattern = perturbed_pattern(
-        &gradient_pattern(&colori(200, 40, 0), &colori(200, 180, 0)),
+        gradient_pattern(&colori(200, 40, 0), &colori(200, 180, 0)),
         0.8 * perturb_factor,
         4,
         0.9,
@@ -87,7 +87,7 @@ fn main() -> ExitCode {
     left.material.diffuse = 0.9;
     left.material.specular = 0.3;
     let mut left_pattern = perturbed_pattern(
-        &ring_pattern(&colori(199, 240, 194), &colori(95, 191, 95)),
+        ring_pattern(&colori(199, 240, 194), &colori(95, 191, 95)),
         1.5 * perturb_factor,
         4,
         0.9,
@@ -30,9 +30,9 @@ fn main() -> ExitCode {
     floor.material.specular = 0.0;
     floor.material.reflective = 0.2;
     let mut floor_pattern_1 =
-        perturbed_pattern(&stripe_pattern(&RED, &WHITE), 2.0 * perturb_factor, 4, 0.9);
+        perturbed_pattern(stripe_pattern(&RED, &WHITE), 2.0 * perturb_factor, 4, 0.9);
     let mut floor_pattern_2 =
-        perturbed_pattern(&stripe_pattern(&RED, &WHITE), 2.0 * perturb_factor, 4, 0.9);
+        perturbed_pattern(stripe_pattern(&RED, &WHITE), 2.0 * perturb_factor, 4, 0.9);
     let scale = 0.4;
     floor_pattern_1.set_transform(&scaling(scale, scale, scale).then(&rotation_y(PI / 4.0)));
     floor_pattern_2.set_transform(&scaling(scale, scale, scale).then(&rotation_y(-PI / 4.0)));
@@ -49,7 +49,7 @@ fn main() -> ExitCode {
     middle.material.specular = 0.7;
     middle.material.reflective = 0.2;
     let mut middle_pattern = perturbed_pattern(
-        &stripe_pattern(&colori(13, 104, 53), &colori(15, 158, 79)),
+        stripe_pattern(&colori(13, 104, 53), &colori(15, 158, 79)),
         2.0 * perturb_factor,
         3,
         0.8,
@@ -70,7 +70,7 @@ fn main() -> ExitCode {
     right.material.specular = 0.3;
     right.material.reflective = 0.2;
     let mut right_pattern = perturbed_pattern(
-        &gradient_pattern(&colori(200, 40, 0), &colori(200, 180, 0)),
+        gradient_pattern(&colori(200, 40, 0), &colori(200, 180, 0)),
         0.8 * perturb_factor,
         4,
         0.9,
@@ -91,7 +91,7 @@ fn main() -> ExitCode {
     left.material.specular = 0.3;
     left.material.reflective = 0.2;
     let mut left_pattern = perturbed_pattern(
-        &ring_pattern(&colori(199, 240, 194), &colori(95, 191, 95)),
+        ring_pattern(&colori(199, 240, 194), &colori(95, 191, 95)),
         1.5 * perturb_factor,
         4,
         0.9,
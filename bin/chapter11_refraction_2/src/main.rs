@@ -123,6 +123,7 @@ fn main() -> ExitCode {
             &point(0.0, 0.0, 0.0),
             &vector(0.0, 1.0, 0.0),
         ),
+        ..Default::default()
     };
 
     ExitCode::from(match utils::render_world(&w, options, &cli.common) {
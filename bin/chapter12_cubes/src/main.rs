@@ -58,16 +58,7 @@ fn main() -> ExitCode {
 
     let mut w = world();
 
-    let mut room = cube();
-    room.set_transform(&uniform_scaling(200.0));
-    room.material.color = color(0.8, 0.8, 1.0);
-    room.material.diffuse = 0.3;
-    room.material.ambient = 0.2;
-    room.material.specular = 0.0;
-    room.material.shininess = 1.0;
-    room.material.casts_shadow = false;
-    room.material.receives_shadow = false;
-    w.add_object(room);
+    w.add_object(utils::room(200.0, color(0.8, 0.8, 1.0)));
 
     let sep = 3.0;
     let rotation = PI / 6.0;
@@ -1,9 +1,9 @@
 use rust_rtc::colors::color;
 use rust_rtc::lights::point_light;
 use rust_rtc::materials::default_material;
-
-use rust_rtc::shapes::{plane, sphere};
-use rust_rtc::transformations::{rotation_x, rotation_y, scaling, translation, view_transform};
+use rust_rtc::scenes;
+use rust_rtc::shapes::plane;
+use rust_rtc::transformations::{rotation_x, rotation_y, translation, view_transform};
 use rust_rtc::tuples::{point, vector};
 use rust_rtc::utils;
 use rust_rtc::utils::RenderOptions;
@@ -16,11 +16,7 @@ fn main() -> ExitCode {
 
     let mut w = world();
 
-    let mut floor = plane();
-    floor.material = default_material();
-    floor.material.color = color(1.0, 0.9, 0.9);
-    floor.material.specular = 0.0;
-    w.add_object(floor);
+    scenes::reflective_floor(&mut w, 0.0);
 
     let mut wall = plane();
     wall.set_transform(
@@ -33,29 +29,7 @@ fn main() -> ExitCode {
     wall.material.specular = 0.0;
     w.add_object(wall);
 
-    let mut middle = sphere(4);
-    middle.set_transform(&translation(-0.5, 1.0, 0.5));
-    middle.material = default_material();
-    middle.material.color = color(0.1, 1.0, 0.5);
-    middle.material.diffuse = 0.7;
-    middle.material.specular = 0.3;
-    w.add_object(middle);
-
-    let mut right = sphere(5);
-    right.set_transform(&(translation(1.5, 0.5, -0.5) * scaling(0.5, 0.5, 0.5)));
-    right.material = default_material();
-    right.material.color = color(0.5, 1.0, 0.1);
-    right.material.diffuse = 0.7;
-    right.material.specular = 0.3;
-    w.add_object(right);
-
-    let mut left = sphere(6);
-    left.set_transform(&(translation(-1.5, 0.33, -0.75) * scaling(0.33, 0.33, 0.33)));
-    left.material = default_material();
-    left.material.color = color(1.0, 0.8, 0.1);
-    left.material.diffuse = 0.7;
-    left.material.specular = 0.3;
-    w.add_object(left);
+    scenes::three_spheres(&mut w);
 
     w.add_light(point_light(
         point(-10.0, 10.0, -10.0),
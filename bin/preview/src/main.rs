@@ -0,0 +1,106 @@
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
+use minifb::{Key, Window, WindowOptions};
+use rust_rtc::camera::CancelToken;
+use rust_rtc::canvas::Canvas;
+use rust_rtc::preview::progressive_render;
+use rust_rtc::utils;
+use rust_rtc::utils::parse_filename;
+use rust_rtc::world::{color_at, color_at_fogged};
+use rust_rtc::world_loader::load_world;
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+
+#[derive(Parser)]
+pub struct Cli {
+    /// World JSON5 filename
+    #[arg(short = 'i', long = "input", value_name = "FILE")]
+    #[arg(value_parser = parse_filename)]
+    pub input: String,
+
+    #[clap(flatten)]
+    pub common: utils::CommonArgs,
+}
+
+// Pack a canvas into a window buffer, one 0RGB pixel per u32
+fn to_window_buffer(canvas: &Canvas, srgb: bool) -> Vec<u32> {
+    let canvas = if srgb {
+        canvas.to_srgb()
+    } else {
+        canvas.clone()
+    };
+    let channel = |v: f64| (v.clamp(0.0, 1.0) * 255.0).round() as u32;
+    canvas
+        .pixels
+        .iter()
+        .map(|p| channel(p.red()) << 16 | channel(p.green()) << 8 | channel(p.blue()))
+        .collect()
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    let (mut world, render_options) = load_world(Path::new(&cli.input))?;
+    let options = render_options
+        .get(&cli.common.render.camera_name)
+        .context("No camera")?
+        .clone()
+        .merge_cli(&cli.common);
+    options.configure_world(&mut world);
+
+    let cam = options.camera();
+    let resolution = cam.resolution();
+    let depth = cli.common.render.max_recursive_depth;
+    let srgb = cli.common.render.srgb;
+
+    let mut window = Window::new(
+        &format!("Preview - {}", cli.input),
+        resolution.hsize as usize,
+        resolution.vsize as usize,
+        WindowOptions::default(),
+    )?;
+    window.set_target_fps(30);
+
+    // Render on another thread, sending each improved image to the window
+    let cancel = CancelToken::new();
+    let (sender, receiver) = mpsc::channel();
+    let render_cancel = cancel.clone();
+    let renderer = thread::spawn(move || {
+        let world = &world;
+        let shader = |ray: &_| match &options.fog {
+            Some(fog) => color_at_fogged(world, ray, depth, fog),
+            None => color_at(world, ray, depth),
+        };
+        progressive_render(&cam, options.ssaa, shader, &render_cancel, |image| {
+            // The window may have closed already
+            let _ = sender.send(image.clone());
+        })
+    });
+
+    let mut buffer = vec![0; resolution.num_pixels() as usize];
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        if let Some(image) = receiver.try_iter().last() {
+            buffer = to_window_buffer(&image, srgb);
+        }
+        window.update_with_buffer(
+            &buffer,
+            resolution.hsize as usize,
+            resolution.vsize as usize,
+        )?;
+    }
+    cancel.cancel();
+
+    let image = renderer
+        .join()
+        .map_err(|_| anyhow!("Rendering failed"))?
+        .context("Preview closed before rendering finished")?;
+
+    if srgb {
+        utils::write_canvas(&image.to_srgb(), &cli.common.render.output)?;
+    } else {
+        utils::write_canvas(&image, &cli.common.render.output)?;
+    }
+
+    Ok(())
+}
@@ -1,7 +1,7 @@
 use anyhow::{Context, Result};
 use clap::Parser;
 use rust_rtc::utils;
-use rust_rtc::utils::parse_filename;
+use rust_rtc::utils::{parse_filename, RenderSettings};
 use rust_rtc::world_loader::load_world;
 use std::path::Path;
 
@@ -9,8 +9,28 @@ use std::path::Path;
 pub struct Cli {
     /// World JSON5 filename (or use - for stdin)
     #[arg(short = 'i', long = "input", value_name = "FILE")]
+    #[arg(value_parser = parse_filename, required_unless_present = "batch")]
+    pub input: Option<String>,
+
+    /// Render every JSON5 world in this directory, rather than a single input
+    #[arg(long = "batch", value_name = "DIR", requires = "out")]
+    #[arg(value_parser = parse_filename, conflicts_with_all = ["input", "inspect", "settings"])]
+    pub batch: Option<String>,
+
+    /// Directory for the images rendered by --batch, named after each world
+    #[arg(long = "out", value_name = "DIR", requires = "batch")]
     #[arg(value_parser = parse_filename)]
-    pub input: String,
+    pub out: Option<String>,
+
+    /// Print the scene's objects, bounds and lights, rather than rendering it
+    #[arg(long = "inspect")]
+    pub inspect: bool,
+
+    /// Render settings JSON5 filename, overriding those of the camera that it gives;
+    /// other command-line options take precedence
+    #[arg(long = "settings", value_name = "FILE")]
+    #[arg(value_parser = parse_filename)]
+    pub settings: Option<String>,
 
     #[clap(flatten)]
     pub common: utils::CommonArgs,
@@ -19,7 +39,20 @@ pub struct Cli {
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
-    let (world, render_options) = load_world(Path::new(&cli.input))?;
+    if let (Some(batch), Some(out)) = (&cli.batch, &cli.out) {
+        let results = utils::render_batch(Path::new(batch), Path::new(out), &cli.common)?;
+        print!("{}", utils::batch_summary(&results));
+        return Ok(());
+    }
+
+    let input = cli.input.as_deref().context("No input")?;
+    let (mut world, render_options) = load_world(Path::new(input))?;
+
+    if cli.inspect {
+        world.prepare();
+        print!("{}", world.inspect());
+        return Ok(());
+    }
 
     dbg!("{world:#?}", &world);
     dbg!("{render_options:#?}", &render_options);
@@ -27,11 +60,17 @@ fn main() -> Result<()> {
     let options = render_options
         .get(&cli.common.render.camera_name)
         .context("No camera")?;
+    let options = match &cli.settings {
+        Some(settings) => options
+            .clone()
+            .with_settings(&RenderSettings::from_json_file(Path::new(settings))?),
+        None => options.clone(),
+    };
 
     // TODO:
     //  - Port other scenes to JSON5.
 
-    utils::render_world(&world, *options, &cli.common)?;
+    utils::render_world(&world, options, &cli.common)?;
 
     Ok(())
 }
@@ -0,0 +1,89 @@
+// Render the standard scenes at a small, fixed resolution, to catch performance
+// regressions. Run with `cargo bench -p rust-rtc`.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use rust_rtc::camera::Resolution;
+use rust_rtc::colors::color;
+use rust_rtc::lights::point_light;
+use rust_rtc::shapes::{cone, cylinder};
+use rust_rtc::transformations::{scaling, translation, view_transform};
+use rust_rtc::tuples::{point, vector};
+use rust_rtc::utils::{bench_render, ground_plane, RenderOptions};
+use rust_rtc::world::{default_world, world, World};
+use std::time::Duration;
+
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 48;
+const SEED: u64 = 1;
+
+fn options(from: (f64, f64, f64)) -> RenderOptions {
+    RenderOptions {
+        default_resolution: Resolution::new(WIDTH, HEIGHT),
+        camera_transform: view_transform(
+            &point(from.0, from.1, from.2),
+            &point(0.0, 1.0, 0.0),
+            &vector(0.0, 1.0, 0.0),
+        ),
+        ..Default::default()
+    }
+}
+
+// A few cylinders and cones, some reflective or transparent, on a reflective floor
+fn cylinders_and_cones() -> World {
+    let mut w = world();
+    w.add_light(point_light(point(-10.0, 10.0, -10.0), color(1.0, 1.0, 1.0)));
+    w.add_object(ground_plane(color(0.5, 0.5, 0.5), 0.3));
+
+    for i in 0..4 {
+        let x = i as f64 * 1.5 - 2.25;
+
+        let mut c = cylinder(0.0, 1.5, true, true);
+        c.set_transform(&(translation(x, 0.0, 0.0) * scaling(0.5, 1.0, 0.5)));
+        c.material.color = color(0.2, 0.4, 0.8);
+        c.material.reflective = 0.2 * i as f64;
+        w.add_object(c);
+
+        let mut k = cone();
+        if let Some(p) = k.as_cone_primitive() {
+            p.minimum_y = -1.0;
+            p.maximum_y = 0.0;
+        }
+        k.set_transform(&(translation(x, 2.5, 0.0) * scaling(0.5, 1.0, 0.5)));
+        k.material.color = color(0.8, 0.3, 0.2);
+        k.material.transparency = 0.25 * i as f64;
+        k.material.refractive_index = 1.5;
+        w.add_object(k);
+    }
+
+    w
+}
+
+fn bench_scenes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("render");
+    // Report pixels per second
+    group.throughput(Throughput::Elements(WIDTH as u64 * HEIGHT as u64));
+
+    let scenes = [
+        ("default_world", default_world(), options((0.0, 1.0, -5.0))),
+        (
+            "cylinders_and_cones",
+            cylinders_and_cones(),
+            options((0.0, 3.0, -8.0)),
+        ),
+    ];
+    for (name, mut w, options) in scenes {
+        w.set_seed(SEED);
+        group.bench_function(name, |b| {
+            b.iter_custom(|iters| {
+                (0..iters)
+                    .map(|_| bench_render(&mut w, &options).1)
+                    .sum::<Duration>()
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_scenes);
+criterion_main!(benches);
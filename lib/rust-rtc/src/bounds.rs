@@ -0,0 +1,275 @@
+// Axis-aligned bounding boxes
+
+use crate::matrices::Matrix4;
+use crate::rays::Ray;
+use crate::tuples::{point, Point};
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Bounds {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl Default for Bounds {
+    // An empty box, which contains nothing and is the identity for `union`
+    fn default() -> Self {
+        Bounds {
+            min: point(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            max: point(-f64::INFINITY, -f64::INFINITY, -f64::INFINITY),
+        }
+    }
+}
+
+impl Bounds {
+    pub fn new(min: Point, max: Point) -> Bounds {
+        Bounds { min, max }
+    }
+
+    pub fn infinite() -> Bounds {
+        Bounds {
+            min: point(-f64::INFINITY, -f64::INFINITY, -f64::INFINITY),
+            max: point(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x() > self.max.x() || self.min.y() > self.max.y() || self.min.z() > self.max.z()
+    }
+
+    pub fn add_point(&mut self, p: &Point) {
+        self.min = point(
+            self.min.x().min(p.x()),
+            self.min.y().min(p.y()),
+            self.min.z().min(p.z()),
+        );
+        self.max = point(
+            self.max.x().max(p.x()),
+            self.max.y().max(p.y()),
+            self.max.z().max(p.z()),
+        );
+    }
+
+    pub fn union(&self, other: &Bounds) -> Bounds {
+        let mut b = *self;
+        if !other.is_empty() {
+            b.add_point(&other.min);
+            b.add_point(&other.max);
+        }
+        b
+    }
+
+    pub fn contains_point(&self, p: &Point) -> bool {
+        (self.min.x()..=self.max.x()).contains(&p.x())
+            && (self.min.y()..=self.max.y()).contains(&p.y())
+            && (self.min.z()..=self.max.z()).contains(&p.z())
+    }
+
+    /// Bounds of this box after transformation by `m`.
+    pub fn transform(&self, m: &Matrix4) -> Bounds {
+        if self.is_empty() {
+            return *self;
+        }
+
+        // Interval arithmetic per output axis gives the same result as transforming
+        // all eight corners, but copes with infinite extents: zero matrix elements
+        // are skipped rather than producing 0 * inf = NaN.
+        let lo = [self.min.x(), self.min.y(), self.min.z()];
+        let hi = [self.max.x(), self.max.y(), self.max.z()];
+        let mut out_min = [0.0; 3];
+        let mut out_max = [0.0; 3];
+
+        for row in 0..3 {
+            let mut a = m.at(row, 3);
+            let mut b = a;
+            for col in 0..3 {
+                let e = m.at(row, col);
+                if e == 0.0 {
+                    continue;
+                }
+                let (p, q) = (e * lo[col], e * hi[col]);
+                a += p.min(q);
+                b += p.max(q);
+            }
+            out_min[row] = a;
+            out_max[row] = b;
+        }
+
+        Bounds {
+            min: point(out_min[0], out_min[1], out_min[2]),
+            max: point(out_max[0], out_max[1], out_max[2]),
+        }
+    }
+
+    /// True if every extent of this box is finite, e.g. not a plane.
+    pub fn is_finite(&self) -> bool {
+        !self.is_empty()
+            && [self.min, self.max]
+                .iter()
+                .all(|p| p.x().is_finite() && p.y().is_finite() && p.z().is_finite())
+    }
+
+    /// True if the infinite line through the ray passes through this box, in either
+    /// direction. Intersections behind the ray origin are still needed to track
+    /// refraction containers, so a box behind the ray is not culled.
+    pub fn intersects(&self, ray: &Ray) -> bool {
+        self.ray_interval(ray).is_some()
+    }
+
+    /// The range of t, (entry, exit), over which the infinite line through the ray
+    /// lies within this box, or None if it misses.
+    pub fn ray_interval(&self, ray: &Ray) -> Option<(f64, f64)> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let mut tmin = -f64::INFINITY;
+        let mut tmax = f64::INFINITY;
+
+        for axis in 0..3 {
+            let origin = ray.origin.at(axis).expect("valid axis");
+            let direction = ray.direction.at(axis).expect("valid axis");
+            let (lo, hi) = (
+                self.min.at(axis).expect("valid axis"),
+                self.max.at(axis).expect("valid axis"),
+            );
+
+            if direction.abs() < f64::EPSILON {
+                // Parallel to this slab, so the origin must lie within it
+                if origin < lo || origin > hi {
+                    return None;
+                }
+            } else {
+                let t0 = (lo - origin) / direction;
+                let t1 = (hi - origin) / direction;
+                tmin = tmin.max(t0.min(t1));
+                tmax = tmax.min(t0.max(t1));
+                if tmin > tmax {
+                    return None;
+                }
+            }
+        }
+
+        Some((tmin, tmax))
+    }
+
+    /// Number of axes on which `p` lies within `tolerance` of one of this box's faces.
+    /// A point on the surface with a count of two or more is on an edge.
+    pub fn faces_near(&self, p: &Point, tolerance: f64) -> usize {
+        (0..3)
+            .filter(|&axis| {
+                let v = p.at(axis).expect("valid axis");
+                let lo = self.min.at(axis).expect("valid axis");
+                let hi = self.max.at(axis).expect("valid axis");
+                (v - lo).abs() <= tolerance || (v - hi).abs() <= tolerance
+            })
+            .count()
+    }
+}
+
+pub fn bounds(min: Point, max: Point) -> Bounds {
+    Bounds::new(min, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rays::ray;
+    use crate::transformations::{rotation_y, scaling, translation};
+    use crate::tuples::vector;
+    use approx::assert_relative_eq;
+    use rstest::rstest;
+    use std::f64::consts::PI;
+
+    // Creating an empty bounding box
+    #[test]
+    fn creating_empty_bounding_box() {
+        let b = Bounds::default();
+        assert!(b.is_empty());
+        assert!(!b.contains_point(&point(0.0, 0.0, 0.0)));
+    }
+
+    // Adding points to an empty bounding box
+    #[test]
+    fn adding_points_to_empty_bounding_box() {
+        let mut b = Bounds::default();
+        b.add_point(&point(-5.0, 2.0, 0.0));
+        b.add_point(&point(7.0, 0.0, -3.0));
+        assert_eq!(b.min, point(-5.0, 0.0, -3.0));
+        assert_eq!(b.max, point(7.0, 2.0, 0.0));
+    }
+
+    // Union of two bounding boxes
+    #[test]
+    fn union_of_bounding_boxes() {
+        let a = bounds(point(-5.0, -2.0, 0.0), point(7.0, 4.0, 4.0));
+        let b = bounds(point(8.0, -7.0, -2.0), point(14.0, 2.0, 8.0));
+        let u = a.union(&b);
+        assert_eq!(u.min, point(-5.0, -7.0, -2.0));
+        assert_eq!(u.max, point(14.0, 4.0, 8.0));
+        assert_eq!(a.union(&Bounds::default()), a);
+    }
+
+    // Transforming a bounding box
+    #[test]
+    fn transforming_bounding_box() {
+        let b = bounds(point(-1.0, -1.0, -1.0), point(1.0, 1.0, 1.0));
+        let m = translation(1.0, 2.0, 3.0) * rotation_y(PI / 4.0) * scaling(1.0, 2.0, 1.0);
+        let t = b.transform(&m);
+        let k = f64::sqrt(2.0);
+        assert_relative_eq!(t.min, point(1.0 - k, 0.0, 3.0 - k), epsilon = 1e-9);
+        assert_relative_eq!(t.max, point(1.0 + k, 4.0, 3.0 + k), epsilon = 1e-9);
+    }
+
+    // Transforming an infinite bounding box does not produce NaN
+    #[test]
+    fn transforming_infinite_bounding_box() {
+        let b = bounds(
+            point(-f64::INFINITY, 0.0, -f64::INFINITY),
+            point(f64::INFINITY, 0.0, f64::INFINITY),
+        );
+        let t = b.transform(&translation(0.0, 1.0, 0.0));
+        assert_eq!(t.min, point(-f64::INFINITY, 1.0, -f64::INFINITY));
+        assert_eq!(t.max, point(f64::INFINITY, 1.0, f64::INFINITY));
+    }
+
+    // Intersecting a ray with a bounding box
+    #[rstest]
+    #[case(point(5.0, 0.5, 0.0), vector(-1.0, 0.0, 0.0), true)]
+    #[case(point(0.5, 0.0, -5.0), vector(0.0, 0.0, 1.0), true)]
+    #[case(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 1.0), true)]
+    #[case(point(0.5, 0.0, 5.0), vector(0.0, 0.0, 1.0), true)] // box is behind the ray
+    #[case(point(-2.0, 0.0, 0.0), vector(2.0, 4.0, 6.0), false)]
+    #[case(point(2.0, 2.0, 0.0), vector(0.0, 0.0, 1.0), false)]
+    #[case(point(0.0, 2.0, 2.0), vector(0.0, -1.0, 0.0), false)]
+    fn intersecting_ray_with_bounding_box(
+        #[case] origin: Point,
+        #[case] direction: crate::tuples::Vector,
+        #[case] expected: bool,
+    ) {
+        let b = bounds(point(-1.0, -1.0, -1.0), point(1.0, 1.0, 1.0));
+        let r = ray(origin, direction.normalize());
+        assert_eq!(b.intersects(&r), expected);
+    }
+
+    // The interval over which a ray passes through a bounding box
+    #[test]
+    fn ray_interval_through_bounding_box() {
+        let b = bounds(point(-1.0, -1.0, -1.0), point(1.0, 1.0, 1.0));
+        let r = ray(point(0.5, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert_eq!(b.ray_interval(&r), Some((4.0, 6.0)));
+        assert!(b.is_finite());
+        assert!(!Bounds::infinite().is_finite());
+        assert!(!Bounds::default().is_finite());
+    }
+
+    // Points on the edges of a bounding box are near two faces
+    #[rstest]
+    #[case(point(0.0, 0.0, -1.0), 1)]
+    #[case(point(1.0, 0.0, -1.0), 2)]
+    #[case(point(1.0, 1.0, -1.0), 3)]
+    #[case(point(0.0, 0.0, 0.0), 0)]
+    fn counting_faces_near_point(#[case] p: Point, #[case] expected: usize) {
+        let b = bounds(point(-1.0, -1.0, -1.0), point(1.0, 1.0, 1.0));
+        assert_eq!(b.faces_near(&p, 1e-6), expected);
+    }
+}
@@ -1,17 +1,23 @@
 // Chapter 7: Implementing a Camera
 
+use crate::bounds::Bounds;
 use crate::canvas::{canvas, Canvas};
 use crate::colors::Color;
+use crate::math::EPSILON;
 use crate::matrices::{identity4, Matrix4};
-use crate::rays::{ray, Ray};
-use crate::tuples::{normalize, point};
-use crate::world::{color_at, World};
+use crate::rays::{ray, Ray, RayDifferential};
+use crate::transformations::view_transform;
+use crate::tuples::{normalize, point, vector};
+use crate::world::{color_at, color_at_differential, World};
+use anyhow::{bail, Result};
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::f64::consts::PI;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 //use std::time::Instant;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct Resolution {
     pub hsize: u32,
@@ -27,6 +33,18 @@ impl Resolution {
     pub fn num_pixels(&self) -> u64 {
         self.hsize as u64 * self.vsize as u64
     }
+
+    /// Width divided by height.
+    pub fn aspect_ratio(&self) -> f64 {
+        self.hsize as f64 / self.vsize as f64
+    }
+
+    /// Resolution with the given width and aspect ratio (width / height),
+    /// with the height rounded to the nearest pixel.
+    pub fn from_width_aspect(hsize: u32, aspect_ratio: f64) -> Self {
+        let vsize = (hsize as f64 / aspect_ratio).round().max(1.0) as u32;
+        Resolution::new(hsize, vsize)
+    }
 }
 
 impl Default for Resolution {
@@ -46,13 +64,174 @@ impl Resolution {
     pub const FHD: Resolution = Resolution::new(1920, 1080);
     pub const QHD: Resolution = Resolution::new(2560, 1440);
     pub const UHD_4K: Resolution = Resolution::new(3840, 2160);
+
+    // Digital cinema (DCI) containers
+    pub const DCI_2K: Resolution = Resolution::new(2048, 1080);
+    pub const DCI_4K: Resolution = Resolution::new(4096, 2160);
+    pub const CINEMA_SCOPE: Resolution = Resolution::new(2048, 858); // 2.39:1
+}
+
+/// Appearance of the bounding box overlay drawn by `Camera::render_bounds_overlay`.
+#[derive(Debug, Copy, Clone)]
+pub struct BoundsOverlayOptions {
+    pub color: Color,
+    pub line_width: f64, // in pixels
+}
+
+impl Default for BoundsOverlayOptions {
+    fn default() -> Self {
+        BoundsOverlayOptions {
+            color: Color::new(1.0, 0.0, 1.0),
+            line_width: 2.0,
+        }
+    }
+}
+
+/// The order in which `Camera::render_tiles_ordered_with` renders tiles. It only
+/// changes when each tile is reported, not the finished image.
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub enum TileOrder {
+    /// A row of tiles at a time, from the top left.
+    #[default]
+    RowMajor,
+    /// From the centre tile outwards, in a square spiral, so a preview fills in
+    /// around the subject first.
+    Spiral,
+    /// Along a Hilbert curve, so consecutive tiles are neighbours.
+    Hilbert,
+}
+
+impl TileOrder {
+    /// The (column, row) of each tile in a grid of `columns` by `rows`, in order.
+    pub fn tiles(&self, columns: u32, rows: u32) -> Vec<(u32, u32)> {
+        let count = columns as usize * rows as usize;
+        match self {
+            TileOrder::RowMajor => (0..rows)
+                .flat_map(|row| (0..columns).map(move |column| (column, row)))
+                .collect(),
+            TileOrder::Spiral => {
+                let mut tiles = Vec::with_capacity(count);
+                if count == 0 {
+                    return tiles;
+                }
+                let in_grid = |c: i64, r: i64| {
+                    (0..columns as i64).contains(&c) && (0..rows as i64).contains(&r)
+                };
+                let (mut c, mut r) = ((columns as i64 - 1) / 2, (rows as i64 - 1) / 2);
+                tiles.push((c as u32, r as u32));
+
+                // Right 1, down 1, left 2, up 2, right 3, ..., skipping steps outside
+                // the grid, until every tile is visited
+                let steps = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+                let mut run = 1;
+                for (i, (dc, dr)) in steps.iter().cycle().enumerate() {
+                    for _ in 0..run {
+                        c += dc;
+                        r += dr;
+                        if in_grid(c, r) {
+                            tiles.push((c as u32, r as u32));
+                        }
+                    }
+                    if tiles.len() == count {
+                        break;
+                    }
+                    if i % 2 == 1 {
+                        run += 1;
+                    }
+                }
+                tiles
+            }
+            TileOrder::Hilbert => {
+                // Follow the curve over the smallest power-of-two square covering the
+                // grid, skipping the points outside it
+                let side = columns.max(rows).next_power_of_two();
+                (0..side as u64 * side as u64)
+                    .map(|d| hilbert_point(side, d))
+                    .filter(|&(c, r)| c < columns && r < rows)
+                    .collect()
+            }
+        }
+    }
+}
+
+// The point at distance `d` along a Hilbert curve filling a `side` by `side` square,
+// where `side` is a power of two
+fn hilbert_point(side: u32, d: u64) -> (u32, u32) {
+    let (mut x, mut y) = (0, 0);
+    let mut t = d;
+    let mut s = 1;
+    while s < side {
+        let rx = (1 & (t / 2)) as u32;
+        let ry = (1 & (t ^ rx as u64)) as u32;
+        // Rotate the quadrant
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - x;
+                y = s - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        x += s * rx;
+        y += s * ry;
+        t /= 4;
+        s *= 2;
+    }
+    (x, y)
+}
+
+type ProgressCallback<'a> = Box<dyn FnMut(u64) + Send + 'a>;
+
+/// Shared progress sink for parallel rendering. Pixel counts reported by each
+/// worker are summed, and the callback is given the running total, so it sees
+/// a single monotonic count rather than each worker's own slice.
+pub struct ProgressAggregator<'a> {
+    state: Mutex<(u64, Option<ProgressCallback<'a>>)>,
+}
+
+impl<'a> ProgressAggregator<'a> {
+    pub fn new(callback: Option<ProgressCallback<'a>>) -> Self {
+        ProgressAggregator {
+            state: Mutex::new((0, callback)),
+        }
+    }
+
+    pub fn add(&self, pixels: u64) {
+        let mut state = self.state.lock().expect("should be lockable");
+        state.0 += pixels;
+        let total = state.0;
+        if let Some(f) = &mut state.1 {
+            (f)(total);
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.state.lock().expect("should be lockable").0
+    }
+}
+
+/// A flag for stopping a render early, e.g. when a preview window is closed.
+/// Clones share the same flag, so one may be given to the renderer and another
+/// kept to cancel it.
+#[derive(Debug, Default, Clone)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
 }
 
 pub struct Camera {
     resolution: Resolution,
-
-    #[allow(dead_code)]
-    field_of_view: f64, // stored, but not used
+    field_of_view: f64,
 
     transform: Matrix4,
     inverse_transform: Matrix4,
@@ -60,6 +239,11 @@ pub struct Camera {
     half_width: f64,
     half_height: f64,
     pixel_size: f64,
+
+    near: f64, // hits nearer than this along each ray are ignored
+    far: f64,  // and those farther than this
+
+    single_threaded: bool, // shade pixels on the calling thread, not in parallel
 }
 
 impl Camera {
@@ -75,6 +259,64 @@ impl Camera {
         }
     }
 
+    pub fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    pub fn field_of_view(&self) -> f64 {
+        self.field_of_view
+    }
+
+    pub fn set_field_of_view(&mut self, field_of_view: f64) {
+        let c = calc_pixel_size(self.resolution.hsize, self.resolution.vsize, field_of_view);
+        self.field_of_view = field_of_view;
+        self.half_width = c.half_width;
+        self.half_height = c.half_height;
+        self.pixel_size = c.pixel_size;
+    }
+
+    /// A camera with the same view at `factor` times the resolution, for rendering
+    /// a supersampled image to be downsampled back to this camera's resolution.
+    pub fn supersampled(&self, factor: u32) -> Camera {
+        let resolution = Resolution::new(
+            self.resolution.hsize * factor,
+            self.resolution.vsize * factor,
+        );
+        let mut c = Camera::new(resolution, self.field_of_view);
+        c.set_transform(&self.transform);
+        c.set_clip_distances(self.near, self.far);
+        c.set_single_threaded(self.single_threaded);
+        c
+    }
+
+    /// Ignore hits nearer to the camera than `near`, e.g. to see past an object
+    /// right in front of it, or farther than `far`, along each ray from the camera.
+    /// The defaults, 0 and infinity, ignore none. Reflected and refracted rays, and
+    /// shadow rays, aren't affected.
+    pub fn set_clip_distances(&mut self, near: f64, far: f64) {
+        self.near = near;
+        self.far = far;
+    }
+
+    pub fn near(&self) -> f64 {
+        self.near
+    }
+
+    pub fn far(&self) -> f64 {
+        self.far
+    }
+
+    /// Shade pixels one line after another on the calling thread, rather than lines
+    /// in parallel, in every render except `render_single_threaded`, which always
+    /// does. Useful for profiling and debugging. The image is the same either way.
+    pub fn set_single_threaded(&mut self, single_threaded: bool) {
+        self.single_threaded = single_threaded;
+    }
+
+    pub fn single_threaded(&self) -> bool {
+        self.single_threaded
+    }
+
     pub fn set_transform(&mut self, transform: &Matrix4) {
         self.transform = *transform;
         self.inverse_transform = self.transform.inverse();
@@ -88,6 +330,65 @@ impl Camera {
         &self.inverse_transform
     }
 
+    /// A view transform that frames `bounds`, e.g. from `World::bounds()`, looking
+    /// at its center along +z from far enough away that the bounding sphere fits
+    /// within the narrower half of the field of view, with a small margin.
+    pub fn fit_to_bounds(&self, bounds: &Bounds) -> Result<Matrix4> {
+        if !bounds.is_finite() {
+            bail!("Cannot frame empty or unbounded bounds");
+        }
+        const MARGIN: f64 = 1.1;
+
+        let center = bounds.min + (bounds.max - bounds.min) * 0.5;
+        let radius = (bounds.max - bounds.min).magnitude() / 2.0;
+        let half_angle = self.half_width.min(self.half_height).atan();
+        let distance = (radius * MARGIN).max(EPSILON) / half_angle.sin();
+
+        let from = center + vector(0.0, 0.0, -distance);
+        Ok(view_transform(&from, &center, &vector(0.0, 1.0, 0.0)))
+    }
+
+    /// The (x, y, width, height) region of the image whose pixels may see anything
+    /// within the world-space `bounds`, with a pixel to spare around it, or None if
+    /// it is out of view. Bounds that are unbounded, or reach behind the camera, give
+    /// the whole image.
+    pub fn project_bounds(&self, bounds: &Bounds) -> Option<(u32, u32, u32, u32)> {
+        if bounds.is_empty() {
+            return None;
+        }
+        if !bounds.is_finite() {
+            return Some(self.full_region());
+        }
+
+        // The extent of the corners on the canvas at z = -1, in pixels from the top left
+        let (mut min_x, mut min_y) = (f64::INFINITY, f64::INFINITY);
+        let (mut max_x, mut max_y) = (-f64::INFINITY, -f64::INFINITY);
+        for x in [bounds.min.x(), bounds.max.x()] {
+            for y in [bounds.min.y(), bounds.max.y()] {
+                for z in [bounds.min.z(), bounds.max.z()] {
+                    let p = self.transform * point(x, y, z);
+                    if p.z() > -EPSILON {
+                        return Some(self.full_region());
+                    }
+                    let px = (self.half_width + p.x() / p.z()) / self.pixel_size;
+                    let py = (self.half_height + p.y() / p.z()) / self.pixel_size;
+                    (min_x, max_x) = (min_x.min(px), max_x.max(px));
+                    (min_y, max_y) = (min_y.min(py), max_y.max(py));
+                }
+            }
+        }
+
+        let (hsize, vsize) = (self.resolution.hsize as f64, self.resolution.vsize as f64);
+        if max_x < 0.0 || max_y < 0.0 || min_x > hsize || min_y > vsize {
+            return None;
+        }
+        let x0 = (min_x.floor() - 1.0).max(0.0);
+        let y0 = (min_y.floor() - 1.0).max(0.0);
+        let x1 = (max_x.ceil() + 1.0).min(hsize);
+        let y1 = (max_y.ceil() + 1.0).min(vsize);
+        Some((x0 as u32, y0 as u32, (x1 - x0) as u32, (y1 - y0) as u32))
+    }
+
     pub fn ray_for_pixel(&self, px: u32, py: u32) -> Ray {
         // the offset from the edge of the canvas to the pixel's center
         let xoffset = (px as f64 + 0.5) * self.pixel_size;
@@ -105,7 +406,18 @@ impl Camera {
         let origin = self.inverse_transform * point(0.0, 0.0, 0.0);
         let direction = normalize(&(pixel - origin));
 
-        ray(origin, direction)
+        // The direction is a unit vector, so t is the distance from the camera
+        ray(origin, direction).with_range(self.near, self.far)
+    }
+
+    /// The ray through the given pixel, along with the rays through its neighbours
+    /// in x and y, for estimating the pixel's footprint on a surface.
+    pub fn ray_differential_for_pixel(&self, px: u32, py: u32) -> RayDifferential {
+        RayDifferential::new(
+            self.ray_for_pixel(px, py),
+            self.ray_for_pixel(px + 1, py),
+            self.ray_for_pixel(px, py + 1),
+        )
     }
 
     pub fn render_single_threaded(
@@ -115,6 +427,7 @@ impl Camera {
         mut progress_callback: Option<Box<dyn FnMut(u64) + '_>>,
     ) -> Canvas {
         let mut image = canvas(self.resolution.hsize, self.resolution.vsize);
+        let mut total = 0;
 
         for y in 0..self.resolution.vsize {
             for x in 0..self.resolution.hsize {
@@ -123,10 +436,10 @@ impl Camera {
                 image.write_pixel(x, y, &color);
             }
 
-            match &mut progress_callback {
-                Some(f) => (f)(self.resolution.hsize as u64),
-                None => (),
-            };
+            total += self.resolution.hsize as u64;
+            if let Some(f) = &mut progress_callback {
+                (f)(total);
+            }
         }
         image
     }
@@ -279,40 +592,131 @@ impl Camera {
     //         .expect("should be consumable")
     // }
 
-    // pub fn render_subimage(
-    //     &self,
-    //     world: &World,
-    //     start_x: u32,
-    //     end_x: u32,
-    //     start_y: u32,
-    //     end_y: u32,
-    //     max_recursive_depth: i32,
-    //     progress_callback: Option<Arc<Mutex<Box<dyn FnMut(u64) + Send + '_>>>>,
-    // ) -> Canvas {
-    //     let height = end_y - start_y;
-    //     let width = end_x - start_x;
-    //     let mut image = canvas(width, height);
-    //
-    //     for y in 0..height {
-    //         for x in 0..width {
-    //             let ray = ray_for_pixel(self, start_x + x, start_y + y);
-    //             let color = color_at(world, &ray, max_recursive_depth);
-    //             image.write_pixel(x, y, &color);
-    //         }
-    //
-    //         {
-    //             // unstable: if pb_opt.is_some_and(|x| ...)
-    //             match &progress_callback {
-    //                 Some(ref arc) => {
-    //                     let mut f = arc.lock().expect("should be lockable");
-    //                     (f)(width as u64);
-    //                 }
-    //                 None => (),
-    //             }
-    //         }
-    //     }
-    //     image
-    // }
+    /// Render only the given (x, y, width, height) region of the image, clipped to the
+    /// camera's resolution. The result is the size of the clipped region.
+    pub fn render_subimage(
+        &self,
+        world: &World,
+        max_recursive_depth: i32,
+        region: (u32, u32, u32, u32),
+        progress_callback: Option<Box<dyn FnMut(u64) + Send + '_>>,
+    ) -> Canvas {
+        self.render_subimage_with(
+            |ray| color_at(world, ray, max_recursive_depth),
+            region,
+            progress_callback,
+        )
+    }
+
+    /// As `render_subimage`, with a custom per-ray shader in place of `color_at`.
+    pub fn render_subimage_with<F>(
+        &self,
+        shader: F,
+        region: (u32, u32, u32, u32),
+        progress_callback: Option<Box<dyn FnMut(u64) + Send + '_>>,
+    ) -> Canvas
+    where
+        F: Fn(&Ray) -> Color + Sync,
+    {
+        self.render_pixels(
+            |x, y| shader(&ray_for_pixel(self, x, y)),
+            self.clip_region(region),
+            progress_callback,
+        )
+    }
+
+    /// As `render_subimage_with`, for a shader that gives `N` colors for each ray, e.g.
+    /// a color, its alpha and its depth, which are rendered into `N` canvases (layers)
+    /// in a single pass.
+    pub fn render_subimage_layers_with<F, const N: usize>(
+        &self,
+        shader: F,
+        region: (u32, u32, u32, u32),
+        progress_callback: Option<Box<dyn FnMut(u64) + Send + '_>>,
+    ) -> [Canvas; N]
+    where
+        F: Fn(&Ray) -> [Color; N] + Sync,
+    {
+        let region = self.clip_region(region);
+        let (_, _, width, height) = region;
+        let mut pixels = vec![[Color::default(); N]; (width * height) as usize];
+        self.shade_pixels(
+            &mut pixels,
+            |x, y| shader(&ray_for_pixel(self, x, y)),
+            region,
+            progress_callback,
+        );
+
+        std::array::from_fn(|layer| {
+            let mut image = canvas(width, height);
+            for (pixel, colors) in image.pixels.iter_mut().zip(&pixels) {
+                *pixel = colors[layer];
+            }
+            image
+        })
+    }
+
+    /// Render the image in square tiles of `tile_size` pixels, a row of tiles at a
+    /// time from the top left, calling `on_tile` with the position and image of each
+    /// tile as it is finished, e.g. to display the render as it progresses. Tiles at
+    /// the right and bottom edges may be smaller. Returns None if `cancel` is
+    /// cancelled, which is checked between tiles.
+    pub fn render_tiles_with<F, T>(
+        &self,
+        shader: F,
+        tile_size: u32,
+        cancel: &CancelToken,
+        on_tile: T,
+    ) -> Option<Canvas>
+    where
+        F: Fn(&Ray) -> Color + Sync,
+        T: FnMut(u32, u32, &Canvas),
+    {
+        self.render_tiles_ordered_with(shader, tile_size, TileOrder::RowMajor, cancel, on_tile)
+    }
+
+    /// As `render_tiles_with`, rendering the tiles in the given order.
+    pub fn render_tiles_ordered_with<F, T>(
+        &self,
+        shader: F,
+        tile_size: u32,
+        order: TileOrder,
+        cancel: &CancelToken,
+        mut on_tile: T,
+    ) -> Option<Canvas>
+    where
+        F: Fn(&Ray) -> Color + Sync,
+        T: FnMut(u32, u32, &Canvas),
+    {
+        assert!(tile_size > 0, "tile size must be positive");
+        let mut image = canvas(self.resolution.hsize, self.resolution.vsize);
+
+        let columns = self.resolution.hsize.div_ceil(tile_size);
+        let rows = self.resolution.vsize.div_ceil(tile_size);
+        for (column, row) in order.tiles(columns, rows) {
+            if cancel.is_cancelled() {
+                return None;
+            }
+            let (x, y) = (column * tile_size, row * tile_size);
+            let tile = self.render_subimage_with(&shader, (x, y, tile_size, tile_size), None);
+            image.blit(&tile, x, y);
+            on_tile(x, y, &tile);
+        }
+
+        Some(image)
+    }
+
+    /// The part of the (x, y, width, height) region that lies within the image.
+    pub fn clip_region(&self, (x, y, width, height): (u32, u32, u32, u32)) -> (u32, u32, u32, u32) {
+        let x = x.min(self.resolution.hsize);
+        let y = y.min(self.resolution.vsize);
+        (
+            x,
+            y,
+            width.min(self.resolution.hsize - x),
+            height.min(self.resolution.vsize - y),
+        )
+    }
 
     // Try rendering directly to array (e.g. Programming Rust: Revisiting the Mandelbrot Set)
     // https://stackoverflow.com/questions/41081240/idiomatic-callbacks-in-rust
@@ -322,35 +726,148 @@ impl Camera {
         max_recursive_depth: i32,
         progress_callback: Option<Box<dyn FnMut(u64) + Send + '_>>,
     ) -> Canvas {
-        let mut image = canvas(self.resolution.hsize, self.resolution.vsize);
+        self.render_with(
+            |ray| color_at(world, ray, max_recursive_depth),
+            progress_callback,
+        )
+    }
 
-        let pb_arc = progress_callback.map(|x| Arc::new(Mutex::new(x)));
-        let pb_opt = pb_arc.as_ref().map(Arc::clone);
+    /// Render using a custom per-ray shader in place of `color_at`, in parallel by lines
+    /// unless the camera is single-threaded. The progress callback is given the total
+    /// number of pixels rendered so far.
+    pub fn render_with<F>(
+        &self,
+        shader: F,
+        progress_callback: Option<Box<dyn FnMut(u64) + Send + '_>>,
+    ) -> Canvas
+    where
+        F: Fn(&Ray) -> Color + Sync,
+    {
+        self.render_pixels(
+            |x, y| shader(&ray_for_pixel(self, x, y)),
+            self.full_region(),
+            progress_callback,
+        )
+    }
 
-        let bands: Vec<(usize, &mut [Color])> = image
-            .pixels
-            .chunks_mut(self.resolution.hsize as usize)
-            .enumerate()
-            .collect();
+    /// As `render_with_rayon_by_lines`, but with patterns filtered over each pixel's
+    /// footprint, to reduce aliasing of fine or distant detail.
+    pub fn render_with_differentials(
+        &self,
+        world: &World,
+        max_recursive_depth: i32,
+        progress_callback: Option<Box<dyn FnMut(u64) + Send + '_>>,
+    ) -> Canvas {
+        self.render_pixels(
+            |x, y| {
+                color_at_differential(
+                    world,
+                    &self.ray_differential_for_pixel(x, y),
+                    max_recursive_depth,
+                )
+            },
+            self.full_region(),
+            progress_callback,
+        )
+    }
 
-        bands.into_par_iter().for_each(|(i, band)| {
-            let y = i;
-            for x in 0..self.resolution.hsize {
-                let ray = ray_for_pixel(self, x, y as u32);
-                let color = color_at(world, &ray, max_recursive_depth);
-                band[x as usize] = color;
-            }
+    fn full_region(&self) -> (u32, u32, u32, u32) {
+        (0, 0, self.resolution.hsize, self.resolution.vsize)
+    }
 
-            match &pb_opt {
-                Some(ref arc) => {
-                    let mut f = arc.lock().expect("should be lockable");
-                    (f)(self.resolution.hsize as u64);
-                }
-                None => (),
+    // Shade each pixel of the (x, y, width, height) region, in parallel by lines,
+    // into a canvas the size of the region.
+    fn render_pixels<F>(
+        &self,
+        shader: F,
+        region: (u32, u32, u32, u32),
+        progress_callback: Option<Box<dyn FnMut(u64) + Send + '_>>,
+    ) -> Canvas
+    where
+        F: Fn(u32, u32) -> Color + Sync,
+    {
+        let (_, _, width, height) = region;
+        let mut image = canvas(width, height);
+        self.shade_pixels(&mut image.pixels, shader, region, progress_callback);
+        image
+    }
+
+    // Shade each pixel of the (x, y, width, height) region, in parallel by lines
+    // unless single-threaded, into `pixels`, which has one entry per pixel of the
+    // region in row-major order.
+    fn shade_pixels<T, F>(
+        &self,
+        pixels: &mut [T],
+        shader: F,
+        (x0, y0, width, _): (u32, u32, u32, u32),
+        progress_callback: Option<Box<dyn FnMut(u64) + Send + '_>>,
+    ) where
+        T: Send,
+        F: Fn(u32, u32) -> T + Sync,
+    {
+        if width == 0 {
+            return;
+        }
+
+        let progress = ProgressAggregator::new(progress_callback);
+
+        let bands: Vec<(usize, &mut [T])> = pixels.chunks_mut(width as usize).enumerate().collect();
+
+        let shade_band = |(i, band): (usize, &mut [T])| {
+            let y = y0 + i as u32;
+            for x in 0..width {
+                band[x as usize] = shader(x0 + x, y);
             }
-        });
+
+            progress.add(width as u64);
+        };
+
+        if self.single_threaded {
+            bands.into_iter().for_each(shade_band);
+        } else {
+            bands.into_par_iter().for_each(shade_band);
+        }
+    }
+
+    /// Draw the edges of every object's world-space bounding box over `image`,
+    /// which should have been rendered by this camera. Group bounds are drawn
+    /// too, which helps to diagnose culling. Unbounded objects, such as planes,
+    /// are skipped.
+    pub fn render_bounds_overlay(
+        &self,
+        world: &World,
+        image: &mut Canvas,
+        options: &BoundsOverlayOptions,
+    ) {
+        let boxes: Vec<_> = world
+            .object_bounds()
+            .iter()
+            .filter(|b| b.is_finite())
+            .collect();
 
         image
+            .pixels
+            .par_chunks_mut(self.resolution.hsize as usize)
+            .enumerate()
+            .for_each(|(y, band)| {
+                for (x, pixel) in band.iter_mut().enumerate() {
+                    let ray = ray_for_pixel(self, x as u32, y as u32);
+
+                    // A pixel is on an edge if the ray enters or leaves a box
+                    // within half a line width of two of its faces.
+                    let on_edge = boxes.iter().any(|b| match b.ray_interval(&ray) {
+                        Some((t0, t1)) => [t0, t1].iter().any(|&t| {
+                            let tolerance = 0.5 * options.line_width * self.pixel_size * t;
+                            t > 0.0 && b.faces_near(&ray.position(t), tolerance) >= 2
+                        }),
+                        None => false,
+                    });
+
+                    if on_edge {
+                        *pixel = options.color;
+                    }
+                }
+            });
     }
 }
 
@@ -372,6 +889,9 @@ impl Default for Camera {
             half_width: c.half_width,
             half_height: c.half_height,
             pixel_size: c.pixel_size,
+            near: 0.0,
+            far: f64::INFINITY,
+            single_threaded: false,
         }
     }
 }
@@ -380,6 +900,10 @@ pub fn camera(resolution: Resolution, field_of_view: f64) -> Camera {
     Camera::new(resolution, field_of_view)
 }
 
+pub fn fit_to_bounds(camera: &Camera, bounds: &Bounds) -> Result<Matrix4> {
+    camera.fit_to_bounds(bounds)
+}
+
 pub fn ray_for_pixel(camera: &Camera, px: u32, py: u32) -> Ray {
     camera.ray_for_pixel(px, py)
 }
@@ -388,6 +912,15 @@ pub fn render(camera: &mut Camera, world: &World, max_recursive_depth: i32) -> C
     camera.render_single_threaded(world, max_recursive_depth, None)
 }
 
+pub fn render_bounds_overlay(
+    camera: &Camera,
+    world: &World,
+    image: &mut Canvas,
+    options: &BoundsOverlayOptions,
+) {
+    camera.render_bounds_overlay(world, image, options)
+}
+
 struct CalcPixelSizeResult {
     half_width: f64,
     half_height: f64,
@@ -413,12 +946,18 @@ fn calc_pixel_size(hsize: u32, vsize: u32, field_of_view: f64) -> CalcPixelSizeR
 mod tests {
     use super::*;
     use crate::colors::color;
+    use crate::colors::{BLACK, WHITE};
+    use crate::lights::point_light;
     use crate::matrices::identity4;
+    use crate::patterns::checkers_pattern;
+    use crate::shapes::{plane, sphere};
     use crate::transformations::{rotation_y, translation, view_transform};
     use crate::tuples::vector;
-    use crate::world::default_world;
+    use crate::world::{default_world, world};
     use approx::assert_relative_eq;
+    use rstest::rstest;
     use std::f64::consts::PI;
+    use std::sync::Arc;
 
     // Constructing a camera
     #[test]
@@ -447,6 +986,45 @@ mod tests {
         assert_relative_eq!(c.pixel_size, 0.01);
     }
 
+    // The aspect ratios of standard resolutions
+    #[test]
+    fn resolution_aspect_ratio() {
+        assert_relative_eq!(Resolution::QHD.aspect_ratio(), 16.0 / 9.0);
+        assert_relative_eq!(Resolution::VGA.aspect_ratio(), 4.0 / 3.0);
+        assert_relative_eq!(
+            Resolution::CINEMA_SCOPE.aspect_ratio(),
+            2.39,
+            epsilon = 5e-3
+        );
+    }
+
+    // A resolution with the given width and aspect ratio
+    #[test]
+    fn resolution_from_width_and_aspect_ratio() {
+        let r = Resolution::from_width_aspect(1920, 16.0 / 9.0);
+        assert_eq!(r.hsize, 1920);
+        assert_eq!(r.vsize, 1080);
+        let r = Resolution::from_width_aspect(640, 4.0 / 3.0);
+        assert_eq!(r.vsize, 480);
+    }
+
+    // Changing the field of view recalculates the pixel size
+    #[test]
+    fn changing_field_of_view_updates_pixel_size() {
+        let mut c = camera(Resolution::new(200, 125), PI / 2.0);
+        c.set_field_of_view(PI / 3.0);
+        assert_relative_eq!(c.field_of_view(), PI / 3.0);
+        let expected = calc_pixel_size(200, 125, PI / 3.0);
+        assert_relative_eq!(c.pixel_size, expected.pixel_size);
+        assert_relative_eq!(c.half_width, expected.half_width);
+        assert_relative_eq!(c.half_height, expected.half_height);
+
+        // Narrower field of view means smaller pixels
+        assert!(c.pixel_size < 0.01);
+        c.set_field_of_view(PI / 2.0);
+        assert_relative_eq!(c.pixel_size, 0.01);
+    }
+
     // Constructing a ray through the center of the canvas
     #[test]
     fn constructing_ray_through_center_of_canvas() {
@@ -480,6 +1058,120 @@ mod tests {
         assert_relative_eq!(r.direction, vector(k, 0.0, -k));
     }
 
+    // Progress from parallel workers is summed into a single total
+    #[test]
+    fn progress_aggregator_sums_workers() {
+        let reported = Arc::new(Mutex::new(vec![]));
+        let r = Arc::clone(&reported);
+        let progress = ProgressAggregator::new(Some(Box::new(move |total| {
+            r.lock().unwrap().push(total);
+        })));
+        (0..100).into_par_iter().for_each(|_| progress.add(3));
+        assert_eq!(progress.total(), 300);
+
+        let reported = reported.lock().unwrap();
+        assert_eq!(reported.len(), 100);
+        assert!(reported.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    // After a full render, the reported progress equals the number of pixels
+    #[test]
+    fn progress_after_render_equals_pixel_count() {
+        let c = camera(Resolution::new(13, 7), PI / 2.0);
+        let last = Arc::new(Mutex::new(0));
+        let l = Arc::clone(&last);
+        c.render_with(
+            |_| color(0.0, 0.0, 0.0),
+            Some(Box::new(move |total| *l.lock().unwrap() = total)),
+        );
+        assert_eq!(*last.lock().unwrap(), 13 * 7);
+
+        let mut last = 0;
+        c.render_single_threaded(&default_world(), 1, Some(Box::new(|total| last = total)));
+        assert_eq!(last, 13 * 7);
+    }
+
+    // Rendering a distant checkered plane with ray differentials averages the
+    // checkers towards grey, rather than point sampling black or white
+    #[test]
+    fn rendering_distant_checkers_with_differentials() {
+        let mut floor = plane();
+        floor
+            .material
+            .set_pattern(&checkers_pattern(&BLACK, &WHITE));
+        floor.material.ambient = 1.0;
+        floor.material.diffuse = 0.0;
+        floor.material.specular = 0.0;
+        let mut w = world();
+        w.add_light(point_light(point(0.0, 10.0, 0.0), color(1.0, 1.0, 1.0)));
+        w.add_object(floor);
+
+        let mut c = camera(Resolution::new(40, 40), PI / 3.0);
+        c.set_transform(&view_transform(
+            &point(0.0, 1.0, 0.0),
+            &point(0.0, 0.9, 10.0),
+            &vector(0.0, 1.0, 0.0),
+        ));
+        let plain = c.render_with_rayon_by_lines(&w, 1, None);
+        let filtered = c.render_with_differentials(&w, 1, None);
+
+        // Rows just below the horizon see many checkers per pixel:
+        let deviation = |image: &Canvas| {
+            let mut sum = 0.0;
+            for y in 21..25 {
+                for x in 0..40 {
+                    sum += (image.pixel_at(x, y).red() - 0.5).abs();
+                }
+            }
+            sum / (4.0 * 40.0)
+        };
+        assert_eq!(deviation(&plain), 0.5);
+        assert!(deviation(&filtered) < 0.1);
+
+        // Nearby checkers, spanning many pixels, are largely unaffected:
+        let near = (0..40).filter(|&x| {
+            (plain.pixel_at(x, 39).red() - filtered.pixel_at(x, 39).red()).abs() < 0.1
+        });
+        assert!(near.count() > 30);
+    }
+
+    // Supersampling a hard edge averages the pixels it crosses
+    #[test]
+    fn supersampling_averages_hard_edge() {
+        let c = camera(Resolution::new(5, 5), PI / 2.0);
+        let edge = |r: &Ray| {
+            if r.direction.x() > 0.0 {
+                color(1.0, 1.0, 1.0)
+            } else {
+                color(0.0, 0.0, 0.0)
+            }
+        };
+
+        let plain = c.render_with(edge, None);
+        assert_eq!(*plain.pixel_at(2, 2), color(0.0, 0.0, 0.0));
+
+        let ssaa = c.supersampled(2).render_with(edge, None).downsample(2);
+        assert_eq!((ssaa.width, ssaa.height), (5, 5));
+        assert_relative_eq!(*ssaa.pixel_at(2, 2), color(0.5, 0.5, 0.5));
+        assert_eq!(*ssaa.pixel_at(0, 2), *plain.pixel_at(0, 2));
+        assert_eq!(*ssaa.pixel_at(4, 2), *plain.pixel_at(4, 2));
+
+        // SSAA of 1 is the plain render:
+        let w = default_world();
+        let mut c = camera(Resolution::new(11, 11), PI / 2.0);
+        c.set_transform(&view_transform(
+            &point(0.0, 0.0, -5.0),
+            &point(0.0, 0.0, 0.0),
+            &vector(0.0, 1.0, 0.0),
+        ));
+        let plain = c.render_with_rayon_by_lines(&w, 1, None);
+        let ssaa = c
+            .supersampled(1)
+            .render_with_rayon_by_lines(&w, 1, None)
+            .downsample(1);
+        assert_eq!(ssaa, plain);
+    }
+
     // Rendering a world with a camera
     #[test]
     fn rendering_world_with_camera() {
@@ -496,4 +1188,363 @@ mod tests {
             epsilon = 1e-5
         );
     }
+
+    // Rendering with a custom per-ray shader in place of color_at
+    #[test]
+    fn rendering_with_custom_shader() {
+        let c = camera(Resolution::new(7, 5), PI / 2.0);
+        let image = c.render_with(|_| color(0.25, 0.5, 0.75), None);
+        assert_eq!(image.width, 7);
+        assert_eq!(image.height, 5);
+        assert!(image.pixels.iter().all(|&p| p == color(0.25, 0.5, 0.75)));
+
+        // Shade by ray direction
+        let image = c.render_with(|r| color(r.direction.x(), 0.0, 0.0), None);
+        assert!(image.pixel_at(0, 2).red() > 0.0);
+        assert_relative_eq!(image.pixel_at(3, 2).red(), 0.0);
+    }
+
+    // Rendering a region gives the same pixels as the full render, within the region
+    #[test]
+    fn rendering_a_subimage_matches_full_render() {
+        let w = default_world();
+        let mut c = camera(Resolution::new(21, 15), PI / 2.0);
+        c.set_transform(&view_transform(
+            &point(0.0, 0.0, -5.0),
+            &point(0.0, 0.0, 0.0),
+            &vector(0.0, 1.0, 0.0),
+        ));
+        let full = c.render_with_rayon_by_lines(&w, 5, None);
+
+        let (x0, y0) = (6, 4);
+        let sub = c.render_subimage(&w, 5, (x0, y0, 9, 7), None);
+        assert_eq!((sub.width, sub.height), (9, 7));
+        for y in 0..sub.height {
+            for x in 0..sub.width {
+                assert_eq!(sub.pixel_at(x, y), full.pixel_at(x0 + x, y0 + y));
+            }
+        }
+
+        // Placed over the background, everything outside the region is background
+        let mut image = Canvas::filled(21, 15, &w.ambient_background());
+        image.blit(&sub, x0, y0);
+        for y in 0..15 {
+            for x in 0..21 {
+                let inside = (x0..x0 + 9).contains(&x) && (y0..y0 + 7).contains(&y);
+                let expected = if inside {
+                    *full.pixel_at(x, y)
+                } else {
+                    w.ambient_background()
+                };
+                assert_eq!(*image.pixel_at(x, y), expected);
+            }
+        }
+    }
+
+    // A region extending past the image is clipped to it
+    #[test]
+    fn subimage_region_is_clipped_to_image() {
+        let c = camera(Resolution::new(10, 8), PI / 2.0);
+        assert_eq!(c.clip_region((6, 5, 10, 10)), (6, 5, 4, 3));
+        assert_eq!(c.clip_region((12, 9, 1, 1)), (10, 8, 0, 0));
+        let sub = c.render_subimage_with(|_| color(1.0, 0.0, 0.0), (6, 5, 10, 10), None);
+        assert_eq!((sub.width, sub.height), (4, 3));
+    }
+
+    // A single-threaded camera renders a region on the calling thread, with the
+    // same pixels
+    #[test]
+    fn single_threaded_subimage() {
+        let w = default_world();
+        let mut c = camera(Resolution::new(21, 15), PI / 2.0);
+        c.set_transform(&view_transform(
+            &point(0.0, 0.0, -5.0),
+            &point(0.0, 0.0, 0.0),
+            &vector(0.0, 1.0, 0.0),
+        ));
+        let parallel = c.render_subimage(&w, 5, (6, 4, 9, 7), None);
+
+        c.set_single_threaded(true);
+        assert!(c.supersampled(2).single_threaded());
+        let threads = std::sync::Mutex::new(std::collections::HashSet::new());
+        let sub = c.render_subimage_with(
+            |ray| {
+                threads.lock().unwrap().insert(std::thread::current().id());
+                color_at(&w, ray, 5)
+            },
+            (6, 4, 9, 7),
+            None,
+        );
+        assert_eq!(
+            threads.into_inner().unwrap(),
+            [std::thread::current().id()].into()
+        );
+        assert_eq!(sub.pixels, parallel.pixels);
+    }
+
+    // A single-threaded camera renders with a custom shader on the calling thread
+    #[test]
+    fn single_threaded_render_with() {
+        let mut c = camera(Resolution::new(11, 7), PI / 2.0);
+        c.set_single_threaded(true);
+        let threads = std::sync::Mutex::new(std::collections::HashSet::new());
+        let image = c.render_with(
+            |_| {
+                threads.lock().unwrap().insert(std::thread::current().id());
+                WHITE
+            },
+            None,
+        );
+        assert_eq!(
+            threads.into_inner().unwrap(),
+            [std::thread::current().id()].into()
+        );
+        assert!(image.pixels.iter().all(|p| *p == WHITE));
+    }
+
+    // Rendering in tiles gives the same image, reporting each tile as it's done
+    #[test]
+    fn rendering_in_tiles() {
+        let w = default_world();
+        let mut c = camera(Resolution::new(21, 15), PI / 2.0);
+        c.set_transform(&view_transform(
+            &point(0.0, 0.0, -5.0),
+            &point(0.0, 0.0, 0.0),
+            &vector(0.0, 1.0, 0.0),
+        ));
+        let shader = |ray: &Ray| color_at(&w, ray, 5);
+        let full = c.render_with(shader, None);
+
+        let mut tiles = vec![];
+        let tiled = c
+            .render_tiles_with(shader, 8, &CancelToken::new(), |x, y, tile| {
+                tiles.push((x, y, tile.width, tile.height));
+            })
+            .expect("should not be cancelled");
+        assert_eq!(tiled, full);
+        assert_eq!(
+            tiles,
+            vec![
+                (0, 0, 8, 8),
+                (8, 0, 8, 8),
+                (16, 0, 5, 8),
+                (0, 8, 8, 7),
+                (8, 8, 8, 7),
+                (16, 8, 5, 7)
+            ]
+        );
+    }
+
+    // Every tile order visits each tile exactly once
+    #[rstest]
+    fn tile_orders_cover_every_tile(
+        #[values(TileOrder::RowMajor, TileOrder::Spiral, TileOrder::Hilbert)] order: TileOrder,
+        #[values((1, 1), (3, 2), (4, 4), (5, 3), (2, 7), (0, 3))] size: (u32, u32),
+    ) {
+        let (columns, rows) = size;
+        let mut tiles = order.tiles(columns, rows);
+        assert_eq!(tiles.len(), (columns * rows) as usize);
+        tiles.sort();
+        tiles.dedup();
+        let mut expected = TileOrder::RowMajor.tiles(columns, rows);
+        expected.sort();
+        assert_eq!(tiles, expected);
+    }
+
+    // A spiral starts at the centre tile and works outwards
+    #[test]
+    fn spiral_tile_order() {
+        assert_eq!(
+            TileOrder::Spiral.tiles(3, 3),
+            vec![
+                (1, 1),
+                (2, 1),
+                (2, 2),
+                (1, 2),
+                (0, 2),
+                (0, 1),
+                (0, 0),
+                (1, 0),
+                (2, 0)
+            ]
+        );
+        assert_eq!(TileOrder::Spiral.tiles(5, 2)[0], (2, 0));
+    }
+
+    // Consecutive tiles along a Hilbert curve are neighbours
+    #[test]
+    fn hilbert_tile_order() {
+        let tiles = TileOrder::Hilbert.tiles(4, 4);
+        assert_eq!(tiles[0], (0, 0));
+        for pair in tiles.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            assert_eq!(a.0.abs_diff(b.0) + a.1.abs_diff(b.1), 1, "{a:?} to {b:?}");
+        }
+    }
+
+    // Rendering tiles in any order gives the same image, starting from the centre for
+    // a spiral
+    #[rstest]
+    fn rendering_tiles_in_order(
+        #[values(TileOrder::RowMajor, TileOrder::Spiral, TileOrder::Hilbert)] order: TileOrder,
+    ) {
+        let w = default_world();
+        let mut c = camera(Resolution::new(21, 15), PI / 2.0);
+        c.set_transform(&view_transform(
+            &point(0.0, 0.0, -5.0),
+            &point(0.0, 0.0, 0.0),
+            &vector(0.0, 1.0, 0.0),
+        ));
+        let shader = |ray: &Ray| color_at(&w, ray, 5);
+        let full = c.render_with(shader, None);
+
+        let mut tiles = vec![];
+        let tiled = c
+            .render_tiles_ordered_with(shader, 4, order, &CancelToken::new(), |x, y, _| {
+                tiles.push((x / 4, y / 4));
+            })
+            .expect("should not be cancelled");
+        assert_eq!(tiled, full);
+        assert_eq!(tiles, order.tiles(6, 4));
+        if order == TileOrder::Spiral {
+            assert_eq!(tiles[0], (2, 1));
+        }
+    }
+
+    // Cancelling a tiled render stops it after the current tile
+    #[test]
+    fn cancelling_tiled_render() {
+        let c = camera(Resolution::new(16, 16), PI / 2.0);
+        let cancel = CancelToken::new();
+        let mut count = 0;
+        let image = c.render_tiles_with(
+            |_| WHITE,
+            4,
+            &cancel.clone(),
+            |_, _, _| {
+                count += 1;
+                cancel.cancel();
+            },
+        );
+        assert!(image.is_none());
+        assert_eq!(count, 1);
+    }
+
+    // Objects nearer than the camera's near distance are skipped, and the next hit
+    // is shaded instead, while those beyond its far distance aren't seen at all
+    #[test]
+    fn rendering_with_near_and_far_distances() {
+        let w = default_world();
+        let mut c = camera(Resolution::new(11, 11), PI / 2.0);
+        let from = point(0.0, 0.0, -5.0);
+        c.set_transform(&view_transform(
+            &from,
+            &point(0.0, 0.0, 0.0),
+            &vector(0.0, 1.0, 0.0),
+        ));
+        let unclipped = render(&mut c, &w, 1);
+
+        // The outer sphere is hit at t = 4, the inner one at t = 4.5
+        c.set_clip_distances(4.2, f64::INFINITY);
+        let image = render(&mut c, &w, 1);
+        let inner = color_at(&w, &ray(point(0.0, 0.0, -0.8), vector(0.0, 0.0, 1.0)), 1);
+        assert_ne!(image.pixel_at(5, 5), unclipped.pixel_at(5, 5));
+        assert_relative_eq!(*image.pixel_at(5, 5), inner, epsilon = 1e-12);
+        assert_eq!(c.supersampled(2).near(), 4.2);
+
+        c.set_clip_distances(0.0, 3.9);
+        let image = render(&mut c, &w, 1);
+        assert_eq!(*image.pixel_at(5, 5), w.ambient_background());
+    }
+
+    // The bounds overlay outlines the projected bounding square of a sphere
+    #[test]
+    fn bounds_overlay_outlines_sphere() {
+        let mut w = world();
+        w.add_object(sphere(1));
+        let mut c = camera(Resolution::new(101, 101), PI / 2.0);
+        c.set_transform(&view_transform(
+            &point(0.0, 0.0, -5.0),
+            &point(0.0, 0.0, 0.0),
+            &vector(0.0, 1.0, 0.0),
+        ));
+        let mut image = canvas(101, 101);
+        let options = BoundsOverlayOptions::default();
+        c.render_bounds_overlay(&w, &mut image, &options);
+
+        // The front face of the box, at z = -1, projects to a square of half-width
+        // 1/4 of the image, so its edges lie around 25 pixels from the centre:
+        let marked = |x: u32, y: u32| *image.pixel_at(x, y) == options.color;
+        assert!((36..=40).any(|x| marked(x, 50)));
+        assert!((61..=65).any(|x| marked(x, 50)));
+        assert!((36..=40).any(|y| marked(50, y)));
+        assert!((61..=65).any(|y| marked(50, y)));
+
+        // Neither the interior of a face nor the area outside the box is marked:
+        assert!(!marked(50, 50));
+        assert!(!marked(45, 50));
+        assert!(!marked(5, 50));
+        assert!(!marked(5, 5));
+    }
+
+    // With the default orientation, world +x is on the left of the image
+    #[test]
+    fn default_orientation_puts_positive_x_on_the_left() {
+        let c = camera(Resolution::new(11, 11), PI / 2.0);
+        let left_edge = ray_for_pixel(&c, 0, 5);
+        let right_edge = ray_for_pixel(&c, 10, 5);
+        assert!(left_edge.direction.x() > 0.0);
+        assert!(right_edge.direction.x() < 0.0);
+        assert!(ray_for_pixel(&c, 5, 0).direction.y() > 0.0);
+    }
+
+    // Fitting the camera to the default world's bounds keeps both spheres in view
+    #[test]
+    fn fitting_camera_to_default_world() {
+        let mut w = default_world();
+        w.prepare();
+        let mut c = camera(Resolution::new(201, 101), PI / 2.0);
+        let t = c.fit_to_bounds(&w.bounds()).unwrap();
+        c.set_transform(&t);
+
+        assert_eq!(w.object_bounds().len(), 2);
+        for b in w.object_bounds() {
+            for x in [b.min.x(), b.max.x()] {
+                for y in [b.min.y(), b.max.y()] {
+                    for z in [b.min.z(), b.max.z()] {
+                        let p = t * point(x, y, z);
+                        assert!(p.z() < 0.0);
+                        assert!((p.x() / -p.z()).abs() <= c.half_width);
+                        assert!((p.y() / -p.z()).abs() <= c.half_height);
+                    }
+                }
+            }
+        }
+    }
+
+    // Projecting bounds gives the region of the image that sees them
+    #[test]
+    fn projecting_bounds_to_the_image() {
+        let c = camera(Resolution::new(100, 100), PI / 2.0);
+        let b = |min, max| Bounds::new(min, max);
+
+        // In the middle of the view, at z = -2 the canvas spans -2..2
+        let middle = b(point(-0.2, -0.2, -2.0), point(0.2, 0.2, -2.0));
+        assert_eq!(c.project_bounds(&middle), Some((44, 44, 12, 12)));
+
+        // Out of view, and behind the camera
+        let aside = b(point(10.0, 0.0, -2.0), point(11.0, 1.0, -2.0));
+        assert_eq!(c.project_bounds(&aside), None);
+        let behind = b(point(-1.0, -1.0, -1.0), point(1.0, 1.0, 1.0));
+        assert_eq!(c.project_bounds(&behind), Some((0, 0, 100, 100)));
+        assert_eq!(c.project_bounds(&Bounds::default()), None);
+    }
+
+    // Bounds that are empty or unbounded can't be framed
+    #[test]
+    fn fitting_camera_to_unbounded_scene_fails() {
+        let c = Camera::default();
+        assert!(c.fit_to_bounds(&Bounds::default()).is_err());
+        assert!(c.fit_to_bounds(&Bounds::infinite()).is_err());
+    }
 }
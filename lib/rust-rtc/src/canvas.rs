@@ -1,8 +1,19 @@
 // Chapter 2: Drawing On a Canvas
 
-use crate::colors::Color;
+use crate::colors::{linear_to_srgb, srgb_to_linear, Color, BLACK};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
 
-#[derive(Debug)]
+/// How a canvas is sampled at fractional (u, v) coordinates.
+#[derive(Debug, PartialEq, Default, Copy, Clone)]
+pub enum TextureFilter {
+    Nearest,
+    #[default]
+    Bilinear,
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct Canvas {
     pub width: u32,
     pub height: u32,
@@ -19,6 +30,12 @@ impl Canvas {
         }
     }
 
+    pub fn filled(width: u32, height: u32, color: &Color) -> Canvas {
+        let mut c = Canvas::new(width, height);
+        c.pixels.fill(*color);
+        c
+    }
+
     fn _index_of(&self, x: u32, y: u32) -> Option<usize> {
         if x < self.width && y < self.height {
             usize::try_from(x + y * self.width).ok()
@@ -49,8 +66,52 @@ impl Canvas {
         }
     }
 
+    // Convert (u, v) in [0, 1] to continuous pixel coordinates, where pixel centres
+    // lie on whole numbers. v = 0 is the bottom row. Clamped to the image edges.
+    // None for an empty image, which has no pixels to sample.
+    fn _pixel_coordinates(&self, u: f64, v: f64) -> Option<(f64, f64)> {
+        if self.width == 0 || self.height == 0 {
+            return None;
+        }
+        let x = u * self.width as f64 - 0.5;
+        let y = (1.0 - v) * self.height as f64 - 0.5;
+        Some((
+            x.clamp(0.0, (self.width - 1) as f64),
+            y.clamp(0.0, (self.height - 1) as f64),
+        ))
+    }
+
+    /// The pixel nearest to (u, v), or black for an empty canvas.
+    pub fn sample_nearest(&self, u: f64, v: f64) -> Color {
+        let Some((x, y)) = self._pixel_coordinates(u, v) else {
+            return BLACK;
+        };
+        *self.pixel_at(x.round() as u32, y.round() as u32)
+    }
+
+    /// The four pixels around (u, v), interpolated, or black for an empty canvas.
+    pub fn sample_bilinear(&self, u: f64, v: f64) -> Color {
+        let Some((x, y)) = self._pixel_coordinates(u, v) else {
+            return BLACK;
+        };
+        let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (fx, fy) = (x - x0 as f64, y - y0 as f64);
+
+        let top = *self.pixel_at(x0, y0) * (1.0 - fx) + *self.pixel_at(x1, y0) * fx;
+        let bottom = *self.pixel_at(x0, y1) * (1.0 - fx) + *self.pixel_at(x1, y1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+
+    pub fn sample(&self, u: f64, v: f64, filter: TextureFilter) -> Color {
+        match filter {
+            TextureFilter::Nearest => self.sample_nearest(u, v),
+            TextureFilter::Bilinear => self.sample_bilinear(u, v),
+        }
+    }
+
     fn _add_value(row: &mut String, value: f64) {
-        let v = f64::min(f64::max(value, 0.0), 1.0);
+        let v = value.clamp(0.0, 1.0);
         let ivalue = (v * 255.0).round() as i32;
         if !row.is_empty() {
             row.push(' ');
@@ -72,12 +133,63 @@ impl Canvas {
         lines
     }
 
+    /// Reduce the canvas in size by `factor`, averaging each factor x factor block of
+    /// pixels into one (a box filter). Partial blocks at the right and bottom edges
+    /// average only the pixels they contain.
+    pub fn downsample(&self, factor: u32) -> Canvas {
+        assert!(factor > 0, "downsample factor must be positive");
+        let mut result = Canvas::new(self.width.div_ceil(factor), self.height.div_ceil(factor));
+
+        for y in 0..result.height {
+            for x in 0..result.width {
+                let mut sum = Color::new(0.0, 0.0, 0.0);
+                let mut count = 0;
+                for sy in y * factor..((y + 1) * factor).min(self.height) {
+                    for sx in x * factor..((x + 1) * factor).min(self.width) {
+                        sum += self.pixel_at(sx, sy);
+                        count += 1;
+                    }
+                }
+                result.write_pixel(x, y, &(sum / count as f64));
+            }
+        }
+
+        result
+    }
+
+    /// A copy of this canvas, with linear colors encoded as sRGB.
+    pub fn to_srgb(&self) -> Canvas {
+        self.map_pixels(linear_to_srgb)
+    }
+
+    /// A copy of this canvas, with sRGB colors decoded to linear.
+    pub fn to_linear(&self) -> Canvas {
+        self.map_pixels(srgb_to_linear)
+    }
+
+    fn map_pixels(&self, f: fn(Color) -> Color) -> Canvas {
+        Canvas {
+            width: self.width,
+            height: self.height,
+            pixels: self.pixels.iter().map(|&p| f(p)).collect(),
+        }
+    }
+
     pub fn to_ppm(&self) -> String {
-        let header = format!("P3\n{} {}\n255\n", self.width, self.height);
-        let mut data = String::new();
+        let mut data = Vec::new();
+        self.write_ppm(&mut data)
+            .expect("writing to memory should not fail");
+        String::from_utf8(data).expect("PPM data should be ASCII")
+    }
+
+    /// Write the canvas as a plain (P3) PPM, one row at a time, so that large images
+    /// are never held in memory as text.
+    pub fn write_ppm<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write!(writer, "P3\n{} {}\n255\n", self.width, self.height)?;
 
+        let mut row = String::new();
         for y in 0..self.height {
-            let mut row = String::new();
+            row.clear();
             for x in 0..self.width {
                 let p = self.pixel_at(x, y);
                 Canvas::_add_value(&mut row, p.red());
@@ -85,20 +197,35 @@ impl Canvas {
                 Canvas::_add_value(&mut row, p.blue());
             }
 
-            let lines = Canvas::_split_line_by(&row, 70);
+            // The PPM spec limits lines to 70 characters
+            for line in Canvas::_split_line_by(&row, 70) {
+                writeln!(writer, "{line}")?;
+            }
+        }
+
+        Ok(())
+    }
 
-            for line in lines {
-                data.push_str(&line);
-                data.push('\n');
+    // Binary (P6) variant, one byte per sample
+    pub fn to_ppm_binary(&self) -> Vec<u8> {
+        let header = format!("P6\n{} {}\n255\n", self.width, self.height);
+        let mut data = header.into_bytes();
+        data.reserve(self.pixels.len() * 3);
+
+        for p in &self.pixels {
+            for value in [p.red(), p.green(), p.blue()] {
+                data.push((value.clamp(0.0, 1.0) * 255.0).round() as u8);
             }
         }
 
-        header + &data
+        data
     }
 
-    pub fn to_ppm_file(&self, filename: &str) {
-        let data = self.to_ppm();
-        std::fs::write(filename, data).expect("Unable to write file");
+    /// Write the canvas to a plain (P3) PPM file.
+    pub fn to_ppm_file<P: AsRef<Path>>(&self, filename: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(filename)?);
+        self.write_ppm(&mut writer)?;
+        writer.flush()
     }
 }
 
@@ -114,14 +241,28 @@ pub fn write_pixel(c: &mut Canvas, x: u32, y: u32, color: &Color) {
     c.write_pixel(x, y, color);
 }
 
+pub fn downsample(c: &Canvas, factor: u32) -> Canvas {
+    c.downsample(factor)
+}
+
 pub fn ppm_from_canvas(c: &Canvas) -> String {
     c.to_ppm()
 }
 
+pub fn write_ppm<W: Write>(c: &Canvas, writer: &mut W) -> io::Result<()> {
+    c.write_ppm(writer)
+}
+
+pub fn ppm_binary_from_canvas(c: &Canvas) -> Vec<u8> {
+    c.to_ppm_binary()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::colors::color;
+    use approx::assert_relative_eq;
+    use rstest::rstest;
 
     // Creating a canvas
     #[test]
@@ -205,6 +346,164 @@ mod tests {
         );
     }
 
+    // Constructing a binary PPM
+    #[test]
+    fn construct_binary_ppm() {
+        let mut c = canvas(2, 1);
+        write_pixel(&mut c, 0, 0, &color(1.5, 0.5, 0.0));
+        write_pixel(&mut c, 1, 0, &color(-0.5, 0.0, 1.0));
+        let ppm = ppm_binary_from_canvas(&c);
+        assert_eq!(&ppm[..11], b"P6\n2 1\n255\n");
+        assert_eq!(&ppm[11..], &[255, 128, 0, 0, 0, 255]);
+    }
+
+    // Converting a canvas between linear and sRGB
+    #[test]
+    fn converting_canvas_to_srgb_and_back() {
+        let mut c = canvas(2, 1);
+        write_pixel(&mut c, 0, 0, &color(0.0, 0.5, 1.0));
+        write_pixel(&mut c, 1, 0, &color(0.002, 0.2, 0.8));
+        let srgb = c.to_srgb();
+        assert_relative_eq!(
+            *srgb.pixel_at(0, 0),
+            color(0.0, 0.735357, 1.0),
+            epsilon = 1e-6
+        );
+        assert_relative_eq!(
+            *srgb.pixel_at(1, 0),
+            color(0.002 * 12.92, 0.484529, 0.906332),
+            epsilon = 1e-6
+        );
+        assert_relative_eq!(
+            *srgb.to_linear().pixel_at(1, 0),
+            *c.pixel_at(1, 0),
+            epsilon = 1e-12
+        );
+    }
+
+    // Downsampling averages blocks of pixels
+    #[test]
+    fn downsampling_averages_blocks() {
+        let c = two_by_two();
+        let d = downsample(&c, 2);
+        assert_eq!((d.width, d.height), (1, 1));
+        assert_relative_eq!(*d.pixel_at(0, 0), color(0.5, 0.5, 0.5));
+        assert_eq!(c.downsample(1), c);
+
+        // Partial blocks at the edges:
+        let mut c = canvas(3, 1);
+        write_pixel(&mut c, 2, 0, &color(1.0, 1.0, 1.0));
+        let d = c.downsample(2);
+        assert_eq!((d.width, d.height), (2, 1));
+        assert_eq!(*d.pixel_at(0, 0), color(0.0, 0.0, 0.0));
+        assert_eq!(*d.pixel_at(1, 0), color(1.0, 1.0, 1.0));
+    }
+
+    fn two_by_two() -> Canvas {
+        let mut c = canvas(2, 2);
+        write_pixel(&mut c, 0, 0, &color(1.0, 0.0, 0.0));
+        write_pixel(&mut c, 1, 0, &color(0.0, 1.0, 0.0));
+        write_pixel(&mut c, 0, 1, &color(0.0, 0.0, 1.0));
+        write_pixel(&mut c, 1, 1, &color(1.0, 1.0, 1.0));
+        c
+    }
+
+    // Sampling at pixel centres returns the stored color
+    #[rstest]
+    #[case(0.25, 0.75, color(1.0, 0.0, 0.0))]
+    #[case(0.75, 0.75, color(0.0, 1.0, 0.0))]
+    #[case(0.25, 0.25, color(0.0, 0.0, 1.0))]
+    #[case(0.75, 0.25, color(1.0, 1.0, 1.0))]
+    fn sampling_at_pixel_centres(#[case] u: f64, #[case] v: f64, #[case] expected: Color) {
+        let c = two_by_two();
+        assert_eq!(c.sample(u, v, TextureFilter::Bilinear), expected);
+        assert_eq!(c.sample(u, v, TextureFilter::Nearest), expected);
+    }
+
+    // Bilinear sampling between two pixels returns their average
+    #[test]
+    fn bilinear_sampling_between_pixels() {
+        let c = two_by_two();
+        assert_relative_eq!(c.sample_bilinear(0.5, 0.75), color(0.5, 0.5, 0.0));
+        assert_relative_eq!(c.sample_bilinear(0.25, 0.5), color(0.5, 0.0, 0.5));
+        assert_relative_eq!(c.sample_bilinear(0.5, 0.5), color(0.5, 0.5, 0.5));
+    }
+
+    // Sampling beyond the pixel centres is clamped to the image borders
+    #[rstest]
+    #[case(0.0, 1.0, color(1.0, 0.0, 0.0))]
+    #[case(-3.0, 7.0, color(1.0, 0.0, 0.0))]
+    #[case(1.0, 0.0, color(1.0, 1.0, 1.0))]
+    #[case(0.0, 0.75, color(1.0, 0.0, 0.0))]
+    #[case(1.0, 0.75, color(0.0, 1.0, 0.0))]
+    fn bilinear_sampling_clamps_at_borders(
+        #[case] u: f64,
+        #[case] v: f64,
+        #[case] expected: Color,
+    ) {
+        let c = two_by_two();
+        assert_relative_eq!(c.sample_bilinear(u, v), expected);
+    }
+
+    // An empty canvas samples as black, rather than panicking
+    #[rstest]
+    #[case(0, 0)]
+    #[case(3, 0)]
+    #[case(0, 2)]
+    fn sampling_empty_canvas(#[case] width: u32, #[case] height: u32) {
+        let c = canvas(width, height);
+        assert_eq!(c.sample(0.5, 0.5, TextureFilter::Bilinear), BLACK);
+        assert_eq!(c.sample(0.5, 0.5, TextureFilter::Nearest), BLACK);
+    }
+
+    // No PPM line is longer than 70 characters, and the values survive wrapping
+    #[test]
+    fn wide_ppm_lines_are_wrapped_between_values() {
+        let mut c = canvas(101, 3);
+        for y in 0..c.height {
+            for x in 0..c.width {
+                // Bright, so nearly every value is three digits wide
+                let v = 0.6 + 0.4 * x as f64 / c.width as f64;
+                write_pixel(&mut c, x, y, &color(v, 1.0, 1.5));
+            }
+        }
+        let ppm = ppm_from_canvas(&c);
+        assert!(ppm.lines().all(|line| line.len() <= 70));
+
+        let values = ppm
+            .lines()
+            .skip(3)
+            .flat_map(|line| line.split(' '))
+            .map(|v| v.parse::<u8>().expect("should be a sample value"))
+            .collect::<Vec<_>>();
+        let expected = c
+            .pixels
+            .iter()
+            .flat_map(|p| [p.red(), p.green(), p.blue()])
+            .map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8)
+            .collect::<Vec<_>>();
+        assert_eq!(values, expected);
+    }
+
+    // Streaming a PPM gives the same bytes as building it in memory
+    #[test]
+    fn streamed_ppm_matches_in_memory_ppm() {
+        let mut c = canvas(13, 4);
+        for y in 0..c.height {
+            for x in 0..c.width {
+                let v = (x + y * c.width) as f64 / 52.0;
+                write_pixel(&mut c, x, y, &color(v, 1.0 - v, 0.5));
+            }
+        }
+
+        let mut streamed = Vec::new();
+        write_ppm(&c, &mut streamed).expect("should write to memory");
+        assert_eq!(streamed, ppm_from_canvas(&c).into_bytes());
+
+        let text = String::from_utf8(streamed).expect("should be ASCII");
+        assert!(text.lines().all(|line| line.len() <= 70));
+    }
+
     // PPM files are terminated by a newline character
     #[test]
     fn ppm_terminated_by_newline() {
@@ -213,4 +512,27 @@ mod tests {
 
         assert!(ppm.ends_with('\n'));
     }
+
+    // Writing a PPM file to a valid path succeeds
+    #[test]
+    fn writing_ppm_file() {
+        let mut c = canvas(3, 2);
+        write_pixel(&mut c, 1, 1, &color(1.0, 0.5, 0.0));
+        let path = std::env::temp_dir().join(format!("rtc_canvas_{}.ppm", std::process::id()));
+
+        c.to_ppm_file(&path).expect("should write to the temp dir");
+        let written = std::fs::read_to_string(&path).expect("file should exist");
+        std::fs::remove_file(&path).expect("should remove the file");
+        assert_eq!(written, ppm_from_canvas(&c));
+    }
+
+    // Writing a PPM file to an unwritable path is an error, not a panic
+    #[test]
+    fn writing_ppm_file_to_unwritable_path_fails() {
+        let c = canvas(3, 2);
+        let path = std::env::temp_dir()
+            .join("rtc_no_such_directory")
+            .join("image.ppm");
+        assert!(c.to_ppm_file(path).is_err());
+    }
 }
@@ -0,0 +1,188 @@
+// Capsule: a finite cylinder with hemispherical end caps
+
+use crate::intersections::{Intersection, Intersections};
+use crate::math::solve_quadratic;
+use crate::rays::Ray;
+use crate::tuples::{vector, Point, Vector};
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Capsule {
+    pub minimum_y: f64, // centre of the lower hemisphere, in object space
+    pub maximum_y: f64, // centre of the upper hemisphere, must be >= minimum_y
+    pub radius: f64,
+}
+
+impl Default for Capsule {
+    fn default() -> Self {
+        Capsule {
+            minimum_y: -1.0,
+            maximum_y: 1.0,
+            radius: 1.0,
+        }
+    }
+}
+
+impl Capsule {
+    pub fn new(minimum_y: f64, maximum_y: f64, radius: f64) -> Self {
+        Capsule {
+            minimum_y,
+            maximum_y,
+            radius,
+        }
+    }
+
+    pub fn local_normal_at(&self, local_point: &Point) -> Vector {
+        // Above or below the body, the normal points away from the hemisphere's centre.
+        // At the joints both cases reduce to the radial body normal, so it is smooth.
+        let y = if local_point.y() > self.maximum_y {
+            local_point.y() - self.maximum_y
+        } else if local_point.y() < self.minimum_y {
+            local_point.y() - self.minimum_y
+        } else {
+            0.0
+        };
+        vector(local_point.x(), y, local_point.z()) / self.radius
+    }
+
+    pub fn local_intersect(&self, local_ray: &Ray) -> Intersections<'_> {
+        let mut xs: Intersections = vec![];
+        self.intersect_body(local_ray, &mut xs);
+        self.intersect_cap(local_ray, self.minimum_y, |y| y <= self.minimum_y, &mut xs);
+        self.intersect_cap(local_ray, self.maximum_y, |y| y >= self.maximum_y, &mut xs);
+        xs.sort_by(|a, b| a.t.total_cmp(&b.t));
+        xs
+    }
+
+    fn intersect_body(&self, ray: &Ray, xs: &mut Intersections) {
+        let a = ray.direction.x() * ray.direction.x() + ray.direction.z() * ray.direction.z();
+        let b = 2.0 * ray.origin.x() * ray.direction.x() + 2.0 * ray.origin.z() * ray.direction.z();
+        let c = ray.origin.x() * ray.origin.x() + ray.origin.z() * ray.origin.z()
+            - self.radius * self.radius;
+
+        // A ray parallel to the Y axis has no roots, and can only hit the caps
+        if let Some((t0, t1)) = solve_quadratic(a, b, c) {
+            for t in [t0, t1] {
+                let y = ray.origin.y() + t * ray.direction.y();
+                if self.minimum_y < y && y < self.maximum_y {
+                    xs.push(Intersection::new(t, None));
+                }
+            }
+        }
+    }
+
+    // Intersect the sphere centred at (0, centre_y, 0), keeping only the hits on the
+    // hemisphere selected by `keep`.
+    fn intersect_cap(
+        &self,
+        ray: &Ray,
+        centre_y: f64,
+        keep: impl Fn(f64) -> bool,
+        xs: &mut Intersections,
+    ) {
+        let oy = ray.origin.y() - centre_y;
+        let a = ray.direction.dot(&ray.direction);
+        let b = 2.0
+            * (ray.origin.x() * ray.direction.x()
+                + oy * ray.direction.y()
+                + ray.origin.z() * ray.direction.z());
+        let c = ray.origin.x() * ray.origin.x() + oy * oy + ray.origin.z() * ray.origin.z()
+            - self.radius * self.radius;
+
+        if let Some((t0, t1)) = solve_quadratic(a, b, c) {
+            for t in [t0, t1] {
+                let y = ray.origin.y() + t * ray.direction.y();
+                if keep(y) {
+                    xs.push(Intersection::new(t, None));
+                }
+            }
+        }
+    }
+}
+
+pub fn local_normal_at(c: &Capsule, local_point: &Point) -> Vector {
+    c.local_normal_at(local_point)
+}
+
+pub fn local_intersect<'a>(c: &'a Capsule, local_ray: &Ray) -> Intersections<'a> {
+    c.local_intersect(local_ray)
+}
+
+pub fn capsule(minimum_y: f64, maximum_y: f64, radius: f64) -> Capsule {
+    Capsule::new(minimum_y, maximum_y, radius)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rays::ray;
+    use crate::tuples::{point, vector};
+    use approx::assert_relative_eq;
+    use rstest::rstest;
+
+    // A ray strikes the cylindrical body of a capsule
+    #[test]
+    fn ray_strikes_capsule_body() {
+        let c = capsule(-1.0, 1.0, 0.5);
+        let r = ray(point(0.0, 0.5, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = local_intersect(&c, &r);
+        assert_eq!(xs.len(), 2);
+        assert_relative_eq!(xs[0].t, 4.5, epsilon = 1e-9);
+        assert_relative_eq!(xs[1].t, 5.5, epsilon = 1e-9);
+    }
+
+    // A ray strikes an end hemisphere of a capsule
+    #[test]
+    fn ray_strikes_capsule_end_hemisphere() {
+        let c = capsule(-1.0, 1.0, 0.5);
+
+        // Horizontally, through the upper hemisphere's centre:
+        let r = ray(point(0.0, 1.25, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = local_intersect(&c, &r);
+        assert_eq!(xs.len(), 2);
+        let half_chord = f64::sqrt(0.5 * 0.5 - 0.25 * 0.25);
+        assert_relative_eq!(xs[0].t, 5.0 - half_chord, epsilon = 1e-9);
+        assert_relative_eq!(xs[1].t, 5.0 + half_chord, epsilon = 1e-9);
+
+        // Vertically, along the axis, through both hemispheres:
+        let r = ray(point(0.0, 5.0, 0.0), vector(0.0, -1.0, 0.0));
+        let xs = local_intersect(&c, &r);
+        assert_eq!(xs.len(), 2);
+        assert_relative_eq!(xs[0].t, 3.5, epsilon = 1e-9);
+        assert_relative_eq!(xs[1].t, 6.5, epsilon = 1e-9);
+    }
+
+    // A ray misses a capsule
+    #[rstest]
+    #[case(point(0.0, 2.0, -5.0), vector(0.0, 0.0, 1.0))]
+    #[case(point(0.6, 0.0, -5.0), vector(0.0, 0.0, 1.0))]
+    #[case(point(0.6, 5.0, 0.0), vector(0.0, -1.0, 0.0))]
+    fn ray_misses_capsule(#[case] origin: Point, #[case] direction: Vector) {
+        let c = capsule(-1.0, 1.0, 0.5);
+        let xs = local_intersect(&c, &ray(origin, direction));
+        assert!(xs.is_empty());
+    }
+
+    // Normal vector on a capsule
+    #[rstest]
+    #[case(point(0.5, 0.0, 0.0), vector(1.0, 0.0, 0.0))]
+    #[case(point(0.0, 1.5, 0.0), vector(0.0, 1.0, 0.0))]
+    #[case(point(0.0, -1.5, 0.0), vector(0.0, -1.0, 0.0))]
+    #[case(point(0.0, -1.0 - f64::sqrt(0.125), f64::sqrt(0.125)), vector(0.0, -f64::sqrt(0.5), f64::sqrt(0.5)))]
+    fn normal_vector_on_capsule(#[case] p: Point, #[case] expected: Vector) {
+        let c = capsule(-1.0, 1.0, 0.5);
+        assert_relative_eq!(local_normal_at(&c, &p), expected, epsilon = 1e-9);
+    }
+
+    // The normal is continuous across the body/hemisphere joint
+    #[test]
+    fn normal_is_continuous_at_joint() {
+        let c = capsule(-1.0, 1.0, 0.5);
+        let delta = 1e-7;
+        for y in [c.minimum_y, c.maximum_y] {
+            let below = local_normal_at(&c, &point(0.0, y - delta, -0.5));
+            let above = local_normal_at(&c, &point(0.0, y + delta, -0.5));
+            assert_relative_eq!(below, above, epsilon = 1e-6);
+            assert_relative_eq!(below, vector(0.0, 0.0, -1.0), epsilon = 1e-6);
+        }
+    }
+}
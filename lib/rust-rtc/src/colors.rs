@@ -1,4 +1,5 @@
 use crate::tuples::Tuple;
+use approx::{AbsDiffEq, RelativeEq};
 use derive_more::{Mul, Neg};
 
 #[derive(Debug, Default, PartialEq, Copy, Clone, Neg, Mul)]
@@ -18,6 +19,20 @@ impl Color {
     pub fn blue(&self) -> f64 {
         self.0.z()
     }
+
+    /// Relative luminance of a linear color, with Rec. 709 weights.
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.red() + 0.7152 * self.green() + 0.0722 * self.blue()
+    }
+
+    /// This color with each channel limited to the displayable range, 0.0 to 1.0.
+    pub fn clamped(&self) -> Color {
+        Color::new(
+            self.red().clamp(0.0, 1.0),
+            self.green().clamp(0.0, 1.0),
+            self.blue().clamp(0.0, 1.0),
+        )
+    }
 }
 
 pub const BLACK: Color = Color::new(0.0, 0.0, 0.0);
@@ -109,6 +124,37 @@ color_sub!(Color, &Color);
 color_sub!(&Color, Color);
 color_sub!(&Color, &Color);
 
+/// Approximate equality, for `assert_relative_eq!` and friends:
+///
+/// ```
+/// use approx::assert_relative_eq;
+/// use rust_rtc::colors::color;
+///
+/// assert_relative_eq!(color(0.1, 0.2, 0.3) * 3.0, color(0.3, 0.6, 0.9));
+/// assert_relative_eq!(color(0.5, 0.5, 0.5), color(0.5, 0.5, 0.501), epsilon = 0.01);
+/// ```
+impl AbsDiffEq for Color {
+    type Epsilon = f64;
+
+    fn default_epsilon() -> f64 {
+        f64::default_epsilon()
+    }
+
+    fn abs_diff_eq(&self, other: &Self, epsilon: f64) -> bool {
+        self.0.abs_diff_eq(&other.0, epsilon)
+    }
+}
+
+impl RelativeEq for Color {
+    fn default_max_relative() -> f64 {
+        f64::default_max_relative()
+    }
+
+    fn relative_eq(&self, other: &Self, epsilon: f64, max_relative: f64) -> bool {
+        self.0.relative_eq(&other.0, epsilon, max_relative)
+    }
+}
+
 pub fn color(r: f64, g: f64, b: f64) -> Color {
     Color::new(r, g, b)
 }
@@ -122,6 +168,10 @@ pub fn hadamard(lhs: &Color, rhs: &Color) -> Color {
     lhs * rhs
 }
 
+pub fn luminance(c: &Color) -> f64 {
+    c.luminance()
+}
+
 // Blending function (linear interpolation)
 pub fn linear_blend(t: f64, a: &Color, b: &Color) -> Color {
     let distance = b - a;
@@ -129,35 +179,46 @@ pub fn linear_blend(t: f64, a: &Color, b: &Color) -> Color {
     a + distance * fraction
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use approx::{assert_relative_eq, AbsDiffEq, RelativeEq};
-
-    impl AbsDiffEq for Color {
-        type Epsilon = f64;
-
-        fn default_epsilon() -> f64 {
-            f64::default_epsilon()
-        }
+// sRGB transfer function, per channel
+// https://en.wikipedia.org/wiki/SRGB#Transformation
+fn encode_srgb(x: f64) -> f64 {
+    if x <= 0.0031308 {
+        12.92 * x
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    }
+}
 
-        fn abs_diff_eq(&self, other: &Self, epsilon: f64) -> bool {
-            self.0 .0.abs_diff_eq(other.0 .0, epsilon)
-        }
+fn decode_srgb(x: f64) -> f64 {
+    if x <= 0.04045 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
     }
+}
 
-    impl RelativeEq for Color {
-        fn default_max_relative() -> f64 {
-            f64::default_max_relative()
-        }
+/// Encode a linear color as sRGB, e.g. for display or output.
+pub fn linear_to_srgb(c: Color) -> Color {
+    Color::new(
+        encode_srgb(c.red()),
+        encode_srgb(c.green()),
+        encode_srgb(c.blue()),
+    )
+}
 
-        fn relative_eq(&self, other: &Self, epsilon: f64, max_relative: f64) -> bool {
-            f64::relative_eq(&self.0 .0.x, &other.0 .0.x, epsilon, max_relative)
-                && f64::relative_eq(&self.0 .0.y, &other.0 .0.y, epsilon, max_relative)
-                && f64::relative_eq(&self.0 .0.z, &other.0 .0.z, epsilon, max_relative)
-                && f64::relative_eq(&self.0 .0.w, &other.0 .0.w, epsilon, max_relative)
-        }
-    }
+/// Decode an sRGB color to linear, e.g. for an image texture authored in sRGB.
+pub fn srgb_to_linear(c: Color) -> Color {
+    Color::new(
+        decode_srgb(c.red()),
+        decode_srgb(c.green()),
+        decode_srgb(c.blue()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
 
     // Colors are (red, green, blue) tuples
     #[test]
@@ -199,4 +260,35 @@ mod tests {
         assert_relative_eq!(c1 * c2, color(0.9, 0.2, 0.04));
         assert_relative_eq!(hadamard(&c1, &c2), color(0.9, 0.2, 0.04));
     }
+
+    // The sRGB transfer function is linear below the breakpoint
+    #[test]
+    fn srgb_breakpoint() {
+        let k = 0.04045;
+        assert_relative_eq!(
+            srgb_to_linear(color(k, k, k)),
+            color(k / 12.92, k / 12.92, k / 12.92)
+        );
+        // Both pieces meet at the breakpoint:
+        let above = srgb_to_linear(color(k + 1e-9, 0.0, 0.0)).red();
+        assert_relative_eq!(above, k / 12.92, epsilon = 1e-6);
+        assert_eq!(linear_to_srgb(color(0.0, 1.0, 0.5)).red(), 0.0);
+        assert_relative_eq!(linear_to_srgb(color(0.0, 1.0, 0.5)).green(), 1.0);
+        assert_relative_eq!(
+            linear_to_srgb(color(0.0, 1.0, 0.5)).blue(),
+            0.735357,
+            epsilon = 1e-6
+        );
+    }
+
+    // Linear to sRGB and back are inverses
+    #[test]
+    fn srgb_conversions_are_inverses() {
+        for i in 0..=100 {
+            let x = i as f64 / 100.0;
+            let c = color(x, 1.0 - x, x * x);
+            assert_relative_eq!(srgb_to_linear(linear_to_srgb(c)), c, epsilon = 1e-12);
+            assert_relative_eq!(linear_to_srgb(srgb_to_linear(c)), c, epsilon = 1e-12);
+        }
+    }
 }
@@ -1,7 +1,7 @@
 // Chapter 13 - Double-Napped Cone
 
 use crate::intersections::{Intersection, Intersections};
-use crate::math::EPSILON;
+use crate::math::{solve_quadratic, EPSILON};
 use crate::rays::Ray;
 use crate::tuples::{vector, Point, Vector};
 
@@ -11,6 +11,7 @@ pub struct Cone {
     pub maximum_y: f64,   // must be > minimum_y for anything to render
     pub closed_min: bool, // if true, cap is rendered
     pub closed_max: bool, // if true, cap is rendered
+    pub radius: f64,      // radius at y = ±1, in object space
 }
 
 impl Default for Cone {
@@ -20,6 +21,7 @@ impl Default for Cone {
             maximum_y: f64::INFINITY,
             closed_min: false,
             closed_max: false,
+            radius: 1.0,
         }
     }
 }
@@ -33,66 +35,62 @@ impl Cone {
         // Distances
         let x2 = local_point.x() * local_point.x();
         let z2 = local_point.z() * local_point.z();
-        let y2 = local_point.y() * local_point.y();
+        let r2 = self.radius * self.radius;
 
-        // End caps are the same as for a cylinder, except the radius depends on y:
+        // End caps are the same as for a cylinder, except the radius depends on y.
+        // Measure against the cap's own radius, not the radius at the hit point's y,
+        // so points on the rim that land just inside the cap plane still count as cap:
         let dist = x2 + z2;
-        if self.closed_max && dist < y2 && local_point.y() >= self.maximum_y - EPSILON {
+        let cap_radius2 = |y: f64| r2 * y * y;
+        if self.closed_max
+            && dist <= cap_radius2(self.maximum_y)
+            && local_point.y() >= self.maximum_y - EPSILON
+        {
             vector(0.0, 1.0, 0.0)
-        } else if self.closed_min && dist < y2 && local_point.y() <= self.minimum_y + EPSILON {
+        } else if self.closed_min
+            && dist <= cap_radius2(self.minimum_y)
+            && local_point.y() <= self.minimum_y + EPSILON
+        {
             vector(0.0, -1.0, 0.0)
         } else {
             let y = if local_point.y() > 0.0 {
-                -dist.sqrt()
+                -dist.sqrt() * self.radius
             } else {
-                dist.sqrt()
+                dist.sqrt() * self.radius
             };
             vector(local_point.x(), y, local_point.z())
         }
     }
 
-    pub fn local_intersect(&self, local_ray: &Ray) -> Intersections {
+    pub fn local_intersect(&self, local_ray: &Ray) -> Intersections<'_> {
+        // x^2 + z^2 = (radius * y)^2
+        let r2 = self.radius * self.radius;
         let a = local_ray.direction.x() * local_ray.direction.x()
-            - local_ray.direction.y() * local_ray.direction.y()
+            - r2 * local_ray.direction.y() * local_ray.direction.y()
             + local_ray.direction.z() * local_ray.direction.z();
         let b = 2.0 * local_ray.origin.x() * local_ray.direction.x()
-            - 2.0 * local_ray.origin.y() * local_ray.direction.y()
+            - 2.0 * r2 * local_ray.origin.y() * local_ray.direction.y()
             + 2.0 * local_ray.origin.z() * local_ray.direction.z();
 
-        let mut xs: Intersections = vec![];
+        let c = local_ray.origin.x() * local_ray.origin.x()
+            - r2 * local_ray.origin.y() * local_ray.origin.y()
+            + local_ray.origin.z() * local_ray.origin.z();
 
-        let a_is_zero = a.abs() < f64::EPSILON;
+        let mut xs: Intersections = vec![];
 
-        if a_is_zero && b.abs() < f64::EPSILON {
-            // miss, but might still hit cap...
-        } else {
-            let c = local_ray.origin.x() * local_ray.origin.x()
-                - local_ray.origin.y() * local_ray.origin.y()
-                + local_ray.origin.z() * local_ray.origin.z();
+        // A ray parallel to one of the cone's halves has a single root (the other is
+        // infinite, so never within the cone's extent), and one through the apex
+        // parallel to a half has none, but might still hit a cap
+        if let Some((t0, t1)) = solve_quadratic(a, b, c) {
+            // Check for truncation:
+            let y0 = local_ray.origin.y() + t0 * local_ray.direction.y();
+            if self.minimum_y < y0 && y0 < self.maximum_y {
+                xs.push(Intersection::new(t0, None));
+            }
 
-            if a_is_zero {
-                // b isn't zero
-                let t = -c / (2.0 * b);
-                xs.push(Intersection::new(t, None));
-            } else {
-                let disc = b * b - 4.0 * a * c;
-
-                // Ray intersects:
-                if disc >= 0.0 {
-                    let t0 = (-b - disc.sqrt()) / (2.0 * a);
-                    let t1 = (-b + disc.sqrt()) / (2.0 * a);
-
-                    // Check for truncation:
-                    let y0 = local_ray.origin.y() + t0 * local_ray.direction.y();
-                    if self.minimum_y < y0 && y0 < self.maximum_y {
-                        xs.push(Intersection::new(t0, None));
-                    }
-
-                    let y1 = local_ray.origin.y() + t1 * local_ray.direction.y();
-                    if self.minimum_y < y1 && y1 < self.maximum_y {
-                        xs.push(Intersection::new(t1, None));
-                    }
-                }
+            let y1 = local_ray.origin.y() + t1 * local_ray.direction.y();
+            if self.minimum_y < y1 && y1 < self.maximum_y {
+                xs.push(Intersection::new(t1, None));
             }
         }
         self.intersect_caps(local_ray, &mut xs);
@@ -109,7 +107,7 @@ impl Cone {
             // Check for an intersection with the lower end cap by intersecting with
             // the plane at y = self.minimum_y
             let t = (self.minimum_y - ray.origin.y()) / ray.direction.y();
-            if check_cap(ray, t, self.minimum_y.abs()) {
+            if check_cap(ray, t, self.radius * self.minimum_y.abs()) {
                 xs.push(Intersection::new(t, None));
             }
         }
@@ -118,7 +116,7 @@ impl Cone {
             // Check for an intersection with the upper end cap by intersecting with
             // the plane at y = self.maximum_y
             let t = (self.maximum_y - ray.origin.y()) / ray.direction.y();
-            if check_cap(ray, t, self.maximum_y.abs()) {
+            if check_cap(ray, t, self.radius * self.maximum_y.abs()) {
                 xs.push(Intersection::new(t, None));
             }
         }
@@ -192,7 +190,9 @@ mod tests {
         let r = ray(point(0.0, 0.0, -1.0), direction);
         let xs = local_intersect(&c, &r);
         assert_eq!(xs.len(), 1);
-        assert_relative_eq!(xs[0].t, 0.35355, epsilon = 1e-5);
+        // The book gives 0.35355, from solving the linear equation as t = -c/2b rather
+        // than -c/b, but the hit is at (0, 0.5, -0.5), a distance of sqrt(0.5) away:
+        assert_relative_eq!(xs[0].t, std::f64::consts::FRAC_1_SQRT_2, epsilon = 1e-12);
     }
 
     struct TestItem2 {
@@ -238,4 +238,58 @@ mod tests {
         let n = local_normal_at(&c, &item.origin);
         assert_eq!(n, item.direction);
     }
+
+    // Intersecting a cone with a non-unit radius
+    #[test]
+    fn intersecting_cone_with_radius() {
+        let mut c = cone();
+        c.radius = 2.0;
+        // At y = 1 the cone surface is at x = ±2
+        let r = ray(point(-5.0, 1.0, 0.0), vector(1.0, 0.0, 0.0));
+        let xs = local_intersect(&c, &r);
+        assert_eq!(xs.len(), 2);
+        assert_relative_eq!(xs[0].t, 3.0, epsilon = 1e-5);
+        assert_relative_eq!(xs[1].t, 7.0, epsilon = 1e-5);
+    }
+
+    // Computing the normal vector on a cone with a non-unit radius
+    #[test]
+    fn compute_normal_vector_on_cone_with_radius() {
+        let mut c = cone();
+        c.radius = 2.0;
+        let n = local_normal_at(&c, &point(2.0, 1.0, 0.0)).normalize();
+        // Surface slope is dy/dx = 1/2, so the normal is perpendicular to (2, 1, 0):
+        assert_relative_eq!(n, vector(1.0, -2.0, 0.0).normalize(), epsilon = 1e-9);
+    }
+
+    // The normal vector on a closed cone's end caps
+    #[rstest]
+    #[case(point(0.0, 2.0, 0.0), vector(0.0, 1.0, 0.0))]
+    #[case(point(1.5, 2.0, 0.0), vector(0.0, 1.0, 0.0))]
+    #[case(point(0.0, 2.0, 2.0), vector(0.0, 1.0, 0.0))]
+    #[case(point(2.0 - 1e-13, 2.0 - 1e-12, 0.0), vector(0.0, 1.0, 0.0))]
+    #[case(point(0.0, -1.0, 0.5), vector(0.0, -1.0, 0.0))]
+    #[case(point(1.0 - 1e-13, -1.0 + 1e-12, 0.0), vector(0.0, -1.0, 0.0))]
+    fn normal_vector_on_cone_end_caps(#[case] p: Point, #[case] normal: Vector) {
+        let mut c = cone();
+        c.minimum_y = -1.0;
+        c.maximum_y = 2.0;
+        c.closed_min = true;
+        c.closed_max = true;
+        assert_eq!(local_normal_at(&c, &p), normal);
+    }
+
+    // The normal vector on a closed cone's sloped side, away from the caps
+    #[rstest]
+    #[case(point(1.0, 1.0, 0.0), vector(1.0, -1.0, 0.0))]
+    #[case(point(0.0, 1.5, -1.5), vector(0.0, -1.5, -1.5))]
+    #[case(point(0.5, -0.5, 0.0), vector(0.5, 0.5, 0.0))]
+    fn normal_vector_on_closed_cone_side(#[case] p: Point, #[case] normal: Vector) {
+        let mut c = cone();
+        c.minimum_y = -1.0;
+        c.maximum_y = 2.0;
+        c.closed_min = true;
+        c.closed_max = true;
+        assert_relative_eq!(local_normal_at(&c, &p), normal, epsilon = 1e-9);
+    }
 }
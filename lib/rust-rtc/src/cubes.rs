@@ -1,13 +1,40 @@
 use crate::intersections::{intersections, Intersection, Intersections};
+use crate::materials::Material;
 use crate::rays::Ray;
 use crate::tuples::{vector, Point, Vector};
 
-#[derive(Debug, PartialEq, Default, Copy, Clone)]
-pub struct Cube {}
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct Cube {
+    // A material for each face, e.g. for dice, in place of the shape's own material
+    face_materials: Option<Box<[Material; 6]>>,
+}
 
 impl Cube {
     pub fn new() -> Self {
-        Cube {}
+        Cube::default()
+    }
+
+    /// A cube with its own material on each face, given in the order +x, -x, +y,
+    /// -y, +z, -z.
+    pub fn with_faces(materials: [Material; 6]) -> Self {
+        Cube {
+            face_materials: Some(Box::new(materials)),
+        }
+    }
+
+    /// The material of the face that `local_point` lies on, if the cube has a
+    /// material for each face.
+    pub fn face_material(&self, local_point: &Point) -> Option<&Material> {
+        let materials = self.face_materials.as_ref()?;
+        let index = match cube_face(local_point) {
+            CubeFace::Right => 0,
+            CubeFace::Left => 1,
+            CubeFace::Up => 2,
+            CubeFace::Down => 3,
+            CubeFace::Front => 4,
+            CubeFace::Back => 5,
+        };
+        Some(&materials[index])
     }
 
     pub fn local_normal_at(&self, local_point: &Point) -> Vector {
@@ -27,7 +54,7 @@ impl Cube {
         }
     }
 
-    pub fn local_intersect(&self, local_ray: &Ray) -> Intersections {
+    pub fn local_intersect(&self, local_ray: &Ray) -> Intersections<'_> {
         let (xtmin, xtmax) = check_axis(local_ray.origin.x(), local_ray.direction.x());
         let (ytmin, ytmax) = check_axis(local_ray.origin.y(), local_ray.direction.y());
 
@@ -49,16 +76,7 @@ impl Cube {
         if tmin > tmax {
             intersections![]
         } else {
-            intersections!(
-                Intersection {
-                    t: tmin,
-                    object: None
-                },
-                Intersection {
-                    t: tmax,
-                    object: None
-                }
-            )
+            intersections!(Intersection::new(tmin, None), Intersection::new(tmax, None))
         }
     }
 }
@@ -87,6 +105,75 @@ pub fn cube() -> Cube {
     Cube::new()
 }
 
+/// Faces of a cube, in the order of the cube-mapping convention used by
+/// `cube_uv` and `CubeMapPattern`.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum CubeFace {
+    Left,
+    Front,
+    Right,
+    Back,
+    Up,
+    Down,
+}
+
+impl CubeFace {
+    pub const ALL: [CubeFace; 6] = [
+        CubeFace::Left,
+        CubeFace::Front,
+        CubeFace::Right,
+        CubeFace::Back,
+        CubeFace::Up,
+        CubeFace::Down,
+    ];
+
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// The face of the cube that `local_point` lies on, found from its largest
+/// component. Points on an edge or corner are on the x faces, then the y faces.
+pub fn cube_face(local_point: &Point) -> CubeFace {
+    let (x, y, z) = (local_point.x(), local_point.y(), local_point.z());
+    let coord = f64::max(f64::max(x.abs(), y.abs()), z.abs());
+
+    if coord == x {
+        CubeFace::Right
+    } else if coord == -x {
+        CubeFace::Left
+    } else if coord == y {
+        CubeFace::Up
+    } else if coord == -y {
+        CubeFace::Down
+    } else if coord == z {
+        CubeFace::Front
+    } else {
+        CubeFace::Back
+    }
+}
+
+/// The face of the cube that `local_point` lies on, as for `cube_face`, along
+/// with the planar (u, v) coordinates on that face, each in [0, 1). Each face is
+/// seen unfolded from outside the cube, with v increasing upwards (towards +y on
+/// the side faces).
+pub fn cube_uv(local_point: &Point) -> (CubeFace, f64, f64) {
+    let (x, y, z) = (local_point.x(), local_point.y(), local_point.z());
+    let face = cube_face(local_point);
+
+    let wrap = |a: f64| a.rem_euclid(2.0) / 2.0;
+    let (u, v) = match face {
+        CubeFace::Front => (wrap(x + 1.0), wrap(y + 1.0)),
+        CubeFace::Back => (wrap(1.0 - x), wrap(y + 1.0)),
+        CubeFace::Left => (wrap(z + 1.0), wrap(y + 1.0)),
+        CubeFace::Right => (wrap(1.0 - z), wrap(y + 1.0)),
+        CubeFace::Up => (wrap(x + 1.0), wrap(1.0 - z)),
+        CubeFace::Down => (wrap(x + 1.0), wrap(z + 1.0)),
+    };
+
+    (face, u, v)
+}
+
 pub fn local_intersect<'a>(c: &'a Cube, local_ray: &Ray) -> Intersections<'a> {
     c.local_intersect(local_ray)
 }
@@ -138,6 +225,41 @@ mod test {
         assert_eq!(xs[1].t, item.t2);
     }
 
+    // Identifying the face of a cube from a point
+    #[rstest]
+    #[case(point(-1.0, 0.5, -0.25), CubeFace::Left)]
+    #[case(point(1.1, -0.75, 0.8), CubeFace::Right)]
+    #[case(point(0.1, 0.6, 0.9), CubeFace::Front)]
+    #[case(point(-0.7, 0.0, -2.0), CubeFace::Back)]
+    #[case(point(0.5, 1.0, 0.9), CubeFace::Up)]
+    #[case(point(-0.2, -1.3, 1.1), CubeFace::Down)]
+    fn face_of_cube_from_point(#[case] p: Point, #[case] expected: CubeFace) {
+        assert_eq!(cube_uv(&p).0, expected);
+    }
+
+    // UV mapping each face of a cube
+    #[rstest]
+    #[case(point(-0.5, 0.5, 1.0), CubeFace::Front, 0.25, 0.75)]
+    #[case(point(0.5, -0.5, 1.0), CubeFace::Front, 0.75, 0.25)]
+    #[case(point(0.5, 0.5, -1.0), CubeFace::Back, 0.25, 0.75)]
+    #[case(point(-0.5, -0.5, -1.0), CubeFace::Back, 0.75, 0.25)]
+    #[case(point(-1.0, 0.5, -0.5), CubeFace::Left, 0.25, 0.75)]
+    #[case(point(-1.0, -0.5, 0.5), CubeFace::Left, 0.75, 0.25)]
+    #[case(point(1.0, 0.5, 0.5), CubeFace::Right, 0.25, 0.75)]
+    #[case(point(1.0, -0.5, -0.5), CubeFace::Right, 0.75, 0.25)]
+    #[case(point(-0.5, 1.0, -0.5), CubeFace::Up, 0.25, 0.75)]
+    #[case(point(0.5, 1.0, 0.5), CubeFace::Up, 0.75, 0.25)]
+    #[case(point(-0.5, -1.0, 0.5), CubeFace::Down, 0.25, 0.75)]
+    #[case(point(0.5, -1.0, -0.5), CubeFace::Down, 0.75, 0.25)]
+    fn uv_mapping_cube_faces(
+        #[case] p: Point,
+        #[case] face: CubeFace,
+        #[case] u: f64,
+        #[case] v: f64,
+    ) {
+        assert_eq!(cube_uv(&p), (face, u, v));
+    }
+
     // A ray misses a cube
     #[rstest]
     #[case(TestItem::new(point(-2.0, 0.0, 0.0), vector(0.2673, 0.5345, 0.8018), 0.0, 0.0))]
@@ -1,5 +1,5 @@
 use crate::intersections::{Intersection, Intersections};
-use crate::math::EPSILON;
+use crate::math::{solve_quadratic, EPSILON};
 use crate::rays::Ray;
 use crate::tuples::{vector, Point, Vector};
 
@@ -9,6 +9,7 @@ pub struct Cylinder {
     pub maximum_y: f64,   // must be > minimum_y for anything to render
     pub closed_min: bool, // if true, cap is rendered
     pub closed_max: bool, // if true, cap is rendered
+    pub radius: f64,      // in object space, default 1.0
 }
 
 impl Default for Cylinder {
@@ -18,6 +19,7 @@ impl Default for Cylinder {
             maximum_y: f64::INFINITY,
             closed_min: false,
             closed_max: false,
+            radius: 1.0,
         }
     }
 }
@@ -31,53 +33,44 @@ impl Cylinder {
         // Normal on a cylinder surface point is equal to the vector to the point
         // projected onto the XZ plane, provided the cylinder is not truncated at that point.
         let dist = local_point.x() * local_point.x() + local_point.z() * local_point.z();
+        let r2 = self.radius * self.radius;
 
-        if self.closed_max && dist < 1.0 && local_point.y() >= self.maximum_y - EPSILON {
+        if self.closed_max && dist < r2 && local_point.y() >= self.maximum_y - EPSILON {
             vector(0.0, 1.0, 0.0)
-        } else if self.closed_min && dist < 1.0 && local_point.y() <= self.minimum_y + EPSILON {
+        } else if self.closed_min && dist < r2 && local_point.y() <= self.minimum_y + EPSILON {
             vector(0.0, -1.0, 0.0)
         } else {
-            vector(local_point.x(), 0.0, local_point.z())
+            vector(
+                local_point.x() / self.radius,
+                0.0,
+                local_point.z() / self.radius,
+            )
         }
     }
 
-    pub fn local_intersect(&self, local_ray: &Ray) -> Intersections {
+    pub fn local_intersect(&self, local_ray: &Ray) -> Intersections<'_> {
         let a = local_ray.direction.x() * local_ray.direction.x()
             + local_ray.direction.z() * local_ray.direction.z();
 
+        let b = 2.0 * local_ray.origin.x() * local_ray.direction.x()
+            + 2.0 * local_ray.origin.z() * local_ray.direction.z();
+        let c = local_ray.origin.x() * local_ray.origin.x()
+            + local_ray.origin.z() * local_ray.origin.z()
+            - self.radius * self.radius;
+
         let mut xs: Intersections = vec![];
 
-        // Ray is parallel to the Y axis:
-        if a >= f64::EPSILON {
-            let b = 2.0 * local_ray.origin.x() * local_ray.direction.x()
-                + 2.0 * local_ray.origin.z() * local_ray.direction.z();
-            let c = local_ray.origin.x() * local_ray.origin.x()
-                + local_ray.origin.z() * local_ray.origin.z()
-                - 1.0;
-            let disc = b * b - 4.0 * a * c;
-
-            // Ray intersects:
-            if disc >= 0.0 {
-                let t0 = (-b - disc.sqrt()) / (2.0 * a);
-                let t1 = (-b + disc.sqrt()) / (2.0 * a);
-
-                // Book (page 184) shows swap(t0, t1) but it breaks tests:
-                // let (t0, t1) = if t0 > 1.0 {
-                //     (t1, t0)
-                // } else {
-                //     (t0, t1)
-                // };
-
-                // Check for truncation:
-                let y0 = local_ray.origin.y() + t0 * local_ray.direction.y();
-                if self.minimum_y < y0 && y0 < self.maximum_y {
-                    xs.push(Intersection::new(t0, None));
-                }
-
-                let y1 = local_ray.origin.y() + t1 * local_ray.direction.y();
-                if self.minimum_y < y1 && y1 < self.maximum_y {
-                    xs.push(Intersection::new(t1, None));
-                }
+        // A ray parallel to the Y axis has no roots, but might still hit a cap
+        if let Some((t0, t1)) = solve_quadratic(a, b, c) {
+            // Check for truncation:
+            let y0 = local_ray.origin.y() + t0 * local_ray.direction.y();
+            if self.minimum_y < y0 && y0 < self.maximum_y {
+                xs.push(Intersection::new(t0, None));
+            }
+
+            let y1 = local_ray.origin.y() + t1 * local_ray.direction.y();
+            if self.minimum_y < y1 && y1 < self.maximum_y {
+                xs.push(Intersection::new(t1, None));
             }
         }
         self.intersect_caps(local_ray, &mut xs);
@@ -94,7 +87,7 @@ impl Cylinder {
             // Check for an intersection with the lower end cap by intersecting with
             // the plane at y = self.minimum_y
             let t = (self.minimum_y - ray.origin.y()) / ray.direction.y();
-            if check_cap(ray, t) {
+            if check_cap(ray, t, self.radius) {
                 xs.push(Intersection::new(t, None));
             }
         }
@@ -103,17 +96,17 @@ impl Cylinder {
             // Check for an intersection with the upper end cap by intersecting with
             // the plane at y = self.maximum_y
             let t = (self.maximum_y - ray.origin.y()) / ray.direction.y();
-            if check_cap(ray, t) {
+            if check_cap(ray, t, self.radius) {
                 xs.push(Intersection::new(t, None));
             }
         }
     }
 }
 
-fn check_cap(ray: &Ray, t: f64) -> bool {
+fn check_cap(ray: &Ray, t: f64, radius: f64) -> bool {
     let x = ray.origin.x() + t * ray.direction.x();
     let z = ray.origin.z() + t * ray.direction.z();
-    x * x + z * z <= 1.0
+    x * x + z * z <= radius * radius
 }
 
 pub fn local_normal_at(c: &Cylinder, local_point: &Point) -> Vector {
@@ -136,6 +129,7 @@ mod tests {
     use crate::tuples::{normalize, point, vector, Point, Vector};
     use approx::assert_relative_eq;
     use rstest::rstest;
+    use std::f64::consts::FRAC_1_SQRT_2;
 
     struct TestItem {
         origin: Point,
@@ -281,4 +275,53 @@ mod tests {
         let n = local_normal_at(&cyl, &item.point);
         assert_eq!(n, item.direction);
     }
+
+    // A ray strikes a cylinder with a non-unit radius
+    #[test]
+    fn ray_strikes_cylinder_with_radius() {
+        let mut cyl = cylinder();
+        cyl.radius = 2.0;
+        let r = ray(point(2.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = local_intersect(&cyl, &r);
+        assert_eq!(xs.len(), 2);
+        assert_relative_eq!(xs[0].t, 5.0, epsilon = 1e-5);
+        assert_relative_eq!(xs[1].t, 5.0, epsilon = 1e-5);
+
+        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = local_intersect(&cyl, &r);
+        assert_eq!(xs.len(), 2);
+        assert_relative_eq!(xs[0].t, 3.0, epsilon = 1e-5);
+        assert_relative_eq!(xs[1].t, 7.0, epsilon = 1e-5);
+    }
+
+    // Normal vector on a cylinder with a non-unit radius
+    #[rstest]
+    #[case(point(2.0, 0.0, 0.0), vector(1.0, 0.0, 0.0))]
+    #[case(point(0.0, 5.0, -2.0), vector(0.0, 0.0, -1.0))]
+    #[case(point(f64::sqrt(2.0), -2.0, f64::sqrt(2.0)), vector(FRAC_1_SQRT_2, 0.0, FRAC_1_SQRT_2))]
+    fn normal_vector_on_cylinder_with_radius(#[case] p: Point, #[case] expected: Vector) {
+        let mut cyl = cylinder();
+        cyl.radius = 2.0;
+        let n = local_normal_at(&cyl, &p);
+        assert_relative_eq!(n, expected, epsilon = 1e-9);
+        assert_relative_eq!(n.magnitude(), 1.0, epsilon = 1e-9);
+    }
+
+    // Caps of a cylinder with a non-unit radius extend to that radius
+    #[test]
+    fn caps_of_cylinder_with_radius() {
+        let mut cyl = cylinder();
+        cyl.minimum_y = 1.0;
+        cyl.maximum_y = 2.0;
+        cyl.closed_min = true;
+        cyl.closed_max = true;
+        cyl.radius = 2.0;
+        let r = ray(point(1.5, 3.0, 0.0), vector(0.0, -1.0, 0.0));
+        let xs = local_intersect(&cyl, &r);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(
+            local_normal_at(&cyl, &point(1.5, 2.0, 0.0)),
+            vector(0.0, 1.0, 0.0)
+        );
+    }
 }
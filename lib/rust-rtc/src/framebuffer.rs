@@ -0,0 +1,154 @@
+// Progressive rendering: accumulate any number of samples per pixel, and resolve
+// their average at any time, e.g. to refine an interactive preview.
+
+use crate::canvas::{canvas, Canvas};
+use crate::colors::Color;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Framebuffer {
+    sums: Canvas,
+    counts: Vec<u32>,
+    background: Color, // resolved for pixels without samples
+}
+
+impl Framebuffer {
+    pub fn new(width: u32, height: u32, background: &Color) -> Framebuffer {
+        Framebuffer {
+            sums: canvas(width, height),
+            counts: vec![0; (width * height) as usize],
+            background: *background,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.sums.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.sums.height
+    }
+
+    // The index of pixel (x, y) in `counts`, which must be within the framebuffer;
+    // otherwise an x beyond the width would wrap onto the next row
+    fn index(&self, x: u32, y: u32) -> usize {
+        assert!(
+            x < self.width() && y < self.height(),
+            "pixel ({x}, {y}) is outside the {}x{} framebuffer",
+            self.width(),
+            self.height()
+        );
+        (x + y * self.width()) as usize
+    }
+
+    pub fn accumulate_sample(&mut self, x: u32, y: u32, color: &Color) {
+        let index = self.index(x, y);
+        let sum = self.sums.pixel_at(x, y) + color;
+        self.sums.write_pixel(x, y, &sum);
+        self.counts[index] += 1;
+    }
+
+    /// Add one sample to every pixel, e.g. a whole rendering pass.
+    pub fn accumulate_canvas(&mut self, pass: &Canvas) {
+        assert_eq!(
+            (pass.width, pass.height),
+            (self.width(), self.height()),
+            "pass size must match the framebuffer"
+        );
+        for (sum, sample) in self.sums.pixels.iter_mut().zip(&pass.pixels) {
+            *sum += sample;
+        }
+        for count in &mut self.counts {
+            *count += 1;
+        }
+    }
+
+    pub fn samples(&self, x: u32, y: u32) -> u32 {
+        self.counts[self.index(x, y)]
+    }
+
+    /// The average of the samples at one pixel, or the background if there are none.
+    pub fn resolve_pixel(&self, x: u32, y: u32) -> Color {
+        match self.samples(x, y) {
+            0 => self.background,
+            count => self.sums.pixel_at(x, y) / count as f64,
+        }
+    }
+
+    /// The average of the samples at each pixel, or the background where there are none.
+    pub fn resolve(&self) -> Canvas {
+        let mut image = canvas(self.width(), self.height());
+        for ((pixel, sum), &count) in image
+            .pixels
+            .iter_mut()
+            .zip(&self.sums.pixels)
+            .zip(&self.counts)
+        {
+            *pixel = if count == 0 {
+                self.background
+            } else {
+                sum / count as f64
+            };
+        }
+        image
+    }
+}
+
+pub fn framebuffer(width: u32, height: u32, background: &Color) -> Framebuffer {
+    Framebuffer::new(width, height, background)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colors::{color, BLACK};
+    use approx::assert_relative_eq;
+
+    // Resolving accumulated samples gives their average
+    #[test]
+    fn resolving_averages_samples() {
+        let mut fb = framebuffer(3, 2, &BLACK);
+        fb.accumulate_sample(1, 1, &color(1.0, 0.5, 0.0));
+        fb.accumulate_sample(1, 1, &color(0.0, 0.5, 1.0));
+        assert_eq!(fb.samples(1, 1), 2);
+        assert_eq!(fb.samples(0, 0), 0);
+
+        let image = fb.resolve();
+        assert_relative_eq!(*image.pixel_at(1, 1), color(0.5, 0.5, 0.5));
+    }
+
+    // Resolving an empty framebuffer gives the background
+    #[test]
+    fn resolving_empty_framebuffer_gives_background() {
+        let background = color(0.1, 0.2, 0.3);
+        let fb = framebuffer(4, 3, &background);
+        let image = fb.resolve();
+        assert_eq!((image.width, image.height), (4, 3));
+        assert!(image.pixels.iter().all(|&p| p == background));
+    }
+
+    // Accumulating whole passes adds a sample to every pixel
+    #[test]
+    fn accumulating_passes() {
+        let mut fb = framebuffer(2, 2, &BLACK);
+        let mut pass = canvas(2, 2);
+        pass.pixels.fill(color(1.0, 1.0, 1.0));
+        fb.accumulate_canvas(&pass);
+        fb.accumulate_canvas(&canvas(2, 2));
+        fb.accumulate_sample(0, 0, &color(1.0, 1.0, 1.0));
+
+        let image = fb.resolve();
+        assert_relative_eq!(*image.pixel_at(1, 0), color(0.5, 0.5, 0.5));
+        assert_relative_eq!(
+            *image.pixel_at(0, 0),
+            color(2.0 / 3.0, 2.0 / 3.0, 2.0 / 3.0)
+        );
+    }
+
+    // A pixel beyond the width is out of range, rather than one on the next row
+    #[test]
+    #[should_panic(expected = "outside the 3x2 framebuffer")]
+    fn pixel_beyond_width_is_out_of_range() {
+        let fb = framebuffer(3, 2, &BLACK);
+        fb.samples(3, 0);
+    }
+}
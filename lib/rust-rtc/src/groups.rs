@@ -0,0 +1,128 @@
+// Chapter 14: Groups
+
+use crate::intersections::Intersections;
+use crate::matrices::Matrix4;
+use crate::rays::Ray;
+use crate::tuples::{Point, Vector};
+use crate::world::ObjectIndex;
+
+// A group has no geometry of its own. Its members are stored in the World,
+// and resolved by the World during intersection.
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct Group {
+    pub(crate) members: Vec<ObjectIndex>,
+    keyframes: Vec<(f64, Matrix4)>, // sorted by time
+}
+
+impl Group {
+    pub fn new() -> Self {
+        Group::default()
+    }
+
+    pub fn members(&self) -> &[ObjectIndex] {
+        &self.members
+    }
+
+    /// Animate the group, and so its whole subtree: at `time`, the group's
+    /// transform is `transform`. A keyframe at an existing time replaces it.
+    pub fn add_keyframe(&mut self, time: f64, transform: &Matrix4) {
+        let i = self.keyframes.partition_point(|(t, _)| *t < time);
+        match self.keyframes.get_mut(i) {
+            Some(key) if key.0 == time => key.1 = *transform,
+            _ => self.keyframes.insert(i, (time, *transform)),
+        }
+    }
+
+    pub fn keyframes(&self) -> &[(f64, Matrix4)] {
+        &self.keyframes
+    }
+
+    /// The animated transform at `time`, interpolated between the surrounding
+    /// keyframes, or None if the group has no keyframes. Before the first or after
+    /// the last keyframe, that keyframe's transform holds.
+    pub fn transform_at(&self, time: f64) -> Option<Matrix4> {
+        let i = self.keyframes.partition_point(|(t, _)| *t <= time);
+        let before = i.checked_sub(1).map(|i| self.keyframes[i]);
+        match (before, self.keyframes.get(i).copied()) {
+            (Some((t0, m0)), Some((t1, m1))) => Some(m0.interpolate(&m1, (time - t0) / (t1 - t0))),
+            (Some((_, m)), None) | (None, Some((_, m))) => Some(m),
+            (None, None) => None,
+        }
+    }
+
+    pub fn local_normal_at(&self, _local_point: &Point) -> Vector {
+        panic!("a group has no surface, so has no normal");
+    }
+
+    pub fn local_intersect(&self, _local_ray: &Ray) -> Intersections<'_> {
+        // Members can only be resolved within a World
+        vec![]
+    }
+}
+
+pub fn group() -> Group {
+    Group::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rays::ray;
+    use crate::transformations::{rotation_y, scaling, translation};
+    use crate::tuples::{point, vector};
+    use approx::assert_relative_eq;
+    use std::f64::consts::PI;
+
+    // Creating a new group
+    #[test]
+    fn creating_a_new_group() {
+        let g = group();
+        assert!(g.members().is_empty());
+    }
+
+    // Intersecting a ray with an empty group
+    #[test]
+    fn intersecting_ray_with_empty_group() {
+        let g = group();
+        let r = ray(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 1.0));
+        let xs = g.local_intersect(&r);
+        assert!(xs.is_empty());
+    }
+
+    // A group without keyframes is not animated
+    #[test]
+    fn group_without_keyframes_has_no_animated_transform() {
+        let g = group();
+        assert!(g.transform_at(0.5).is_none());
+    }
+
+    // Keyframes interpolate translation and scale linearly, and rotation by slerp
+    #[test]
+    fn interpolating_between_keyframes() {
+        let mut g = group();
+        g.add_keyframe(
+            1.0,
+            &(translation(2.0, 0.0, 4.0) * rotation_y(PI / 2.0) * scaling(3.0, 3.0, 3.0)),
+        );
+        g.add_keyframe(0.0, &Matrix4::default());
+
+        let expected = translation(1.0, 0.0, 2.0) * rotation_y(PI / 4.0) * scaling(2.0, 2.0, 2.0);
+        assert_relative_eq!(g.transform_at(0.5).unwrap(), expected, epsilon = 1e-9);
+        assert_relative_eq!(
+            g.transform_at(0.0).unwrap(),
+            Matrix4::default(),
+            epsilon = 1e-9
+        );
+    }
+
+    // A single keyframe is static
+    #[test]
+    fn single_keyframe_is_static() {
+        let mut g = group();
+        let m = translation(1.0, 2.0, 3.0) * rotation_y(0.3);
+        g.add_keyframe(0.5, &m);
+        for time in [0.0, 0.5, 1.0] {
+            assert_relative_eq!(g.transform_at(time).unwrap(), m, epsilon = 1e-9);
+        }
+    }
+}
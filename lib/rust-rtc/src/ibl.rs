@@ -0,0 +1,130 @@
+// Image-based lighting: an environment image surrounding the scene, seen by rays
+// that miss everything, and lighting surfaces as ambient light.
+
+use crate::canvas::Canvas;
+use crate::colors::{Color, BLACK};
+use crate::ppm_reader::read_ppm_texture;
+use crate::tuples::Vector;
+use anyhow::Result;
+use std::f64::consts::PI;
+use std::path::Path;
+use std::sync::Arc;
+
+// Ambient light is averaged over SAMPLES x SAMPLES directions
+const SAMPLES: usize = 4;
+
+/// A spherical light probe: an equirectangular (latitude/longitude) image of the
+/// light arriving from every direction. The top row is straight up (+y), the middle
+/// row the horizon, and the middle column looks along +z.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Environment {
+    image: Arc<Canvas>,
+    intensity: f64, // scales the image, as PPM can't hold values above 1.0
+}
+
+impl Environment {
+    pub fn new(image: Canvas, intensity: f64) -> Environment {
+        Environment {
+            image: Arc::new(image),
+            intensity,
+        }
+    }
+
+    /// Load an sRGB encoded PPM image.
+    pub fn load<P: AsRef<Path>>(path: P, intensity: f64) -> Result<Environment> {
+        Ok(Environment::new(read_ppm_texture(path)?, intensity))
+    }
+
+    /// Light arriving from `direction` (which needn't be normalized).
+    pub fn radiance(&self, direction: &Vector) -> Color {
+        let d = direction.normalize();
+        let u = 0.5 + d.x().atan2(d.z()) / (2.0 * PI);
+        let v = 1.0 - d.y().clamp(-1.0, 1.0).acos() / PI;
+        self.image.sample_bilinear(u, v) * self.intensity
+    }
+
+    /// Ambient light falling on a surface with unit normal `normalv`: the
+    /// environment averaged over the hemisphere above the surface, weighted by the
+    /// cosine of the angle to the normal, as for a diffuse surface. A uniform
+    /// environment gives its own color.
+    pub fn irradiance(&self, normalv: &Vector) -> Color {
+        let (u, v) = normalv.tangent_basis();
+
+        // Stratified: one sample in each cell of a grid over the unit square,
+        // mapped onto the hemisphere so more samples lie near the normal
+        let mut sum = BLACK;
+        for i in 0..SAMPLES {
+            for j in 0..SAMPLES {
+                let r2 = (i as f64 + 0.5) / SAMPLES as f64;
+                let phi = 2.0 * PI * (j as f64 + 0.5) / SAMPLES as f64;
+                let r = r2.sqrt();
+                let direction =
+                    u * (r * phi.cos()) + v * (r * phi.sin()) + normalv * (1.0 - r2).sqrt();
+                sum += self.radiance(&direction);
+            }
+        }
+        sum / (SAMPLES * SAMPLES) as f64
+    }
+}
+
+pub fn environment(image: Canvas, intensity: f64) -> Environment {
+    Environment::new(image, intensity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::canvas;
+    use crate::colors::{color, WHITE};
+    use crate::tuples::vector;
+    use approx::assert_relative_eq;
+    use rstest::rstest;
+
+    // White above the horizon, black below
+    fn half_black_environment() -> Environment {
+        let mut image = canvas(16, 8);
+        for y in 0..4 {
+            for x in 0..16 {
+                image.write_pixel(x, y, &WHITE);
+            }
+        }
+        environment(image, 1.0)
+    }
+
+    // The environment is looked up by direction
+    #[rstest]
+    #[case(vector(0.0, 1.0, 0.0), WHITE)]
+    #[case(vector(0.0, -1.0, 0.0), BLACK)]
+    #[case(vector(3.0, 2.0, 1.0), WHITE)]
+    #[case(vector(-1.0, -0.5, 0.0), BLACK)]
+    fn radiance_by_direction(#[case] direction: Vector, #[case] expected: Color) {
+        let env = half_black_environment();
+        assert_eq!(env.radiance(&direction), expected);
+    }
+
+    // A uniform environment lights every surface the same
+    #[rstest]
+    #[case(vector(0.0, 1.0, 0.0))]
+    #[case(vector(0.0, -1.0, 0.0))]
+    #[case(vector(1.0, 0.0, 0.0))]
+    #[case(vector(1.0, 1.0, -1.0).normalize())]
+    fn uniform_environment_irradiance(#[case] normal: Vector) {
+        let mut image = canvas(8, 4);
+        image.pixels.fill(color(0.5, 0.5, 0.5));
+        let env = environment(image, 2.0);
+        assert_relative_eq!(env.irradiance(&normal), WHITE, epsilon = 1e-9);
+    }
+
+    // Surfaces facing the bright half of the environment are lit more
+    #[test]
+    fn half_black_environment_irradiance() {
+        let env = half_black_environment();
+        let up = env.irradiance(&vector(0.0, 1.0, 0.0));
+        let side = env.irradiance(&vector(1.0, 0.0, 0.0));
+        let down = env.irradiance(&vector(0.0, -1.0, 0.0));
+
+        assert_relative_eq!(up, WHITE, epsilon = 1e-9);
+        assert_relative_eq!(down, BLACK, epsilon = 1e-9);
+        assert!(side.red() > 0.2 && side.red() < 0.8, "{side:?}");
+    }
+}
@@ -0,0 +1,37 @@
+// Instancing: many copies of one prototype object, each placed by its own
+// transform, sharing the prototype's geometry and material.
+
+use crate::intersections::Intersections;
+use crate::rays::Ray;
+use crate::tuples::{Point, Vector};
+use crate::world::ObjectIndex;
+
+// An instance has no geometry of its own. Its prototype is stored in the World,
+// and resolved by the World during intersection, in the instance's object space.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Instance {
+    pub(crate) prototype: ObjectIndex,
+}
+
+impl Instance {
+    pub fn new(prototype: ObjectIndex) -> Self {
+        Instance { prototype }
+    }
+
+    pub fn prototype(&self) -> ObjectIndex {
+        self.prototype
+    }
+
+    pub fn local_normal_at(&self, _local_point: &Point) -> Vector {
+        panic!("an instance has no surface of its own, so has no normal");
+    }
+
+    pub fn local_intersect(&self, _local_ray: &Ray) -> Intersections<'_> {
+        // The prototype can only be resolved within a World
+        vec![]
+    }
+}
+
+pub fn instance(prototype: ObjectIndex) -> Instance {
+    Instance::new(prototype)
+}
@@ -1,9 +1,11 @@
 // Chapter 5: Ray-Sphere Intersections
 
 use crate::math::EPSILON;
-use crate::rays::Ray;
-use crate::shapes::{normal_at, Shape, ShapeTrait};
-use crate::tuples::{dot, reflect, Point, Vector};
+use crate::matrices::transpose;
+use crate::rays::{Footprint, Ray, RayPath};
+use crate::shapes::{normal_at_uv, Shape, ShapeTrait};
+use crate::tuples::{dot, normalize, reflect, vector, Point, Vector};
+use crate::world::{ObjectIndex, World};
 
 use crate::materials::RefractiveIndex;
 pub use std::vec as intersections;
@@ -12,23 +14,53 @@ pub use std::vec as intersections;
 pub struct Intersection<'a> {
     pub t: f64,
     pub object: Option<&'a Shape>,
+    // Index of the object within the world, set by World::intersect.
+    // Unlike `object`, this can outlive the borrow of the world.
+    pub object_index: Option<ObjectIndex>,
+    // Barycentric coordinates of the hit, for shapes that provide them (triangles)
+    pub u: Option<f64>,
+    pub v: Option<f64>,
+    // The instance placing `object` (its prototype, or a member of it) in the world,
+    // if the hit is on an instance
+    pub instance: Option<&'a Shape>,
 }
 
 impl Intersection<'_> {
-    pub fn new(t: f64, object: Option<&Shape>) -> Intersection {
-        Intersection { t, object }
+    pub fn new(t: f64, object: Option<&Shape>) -> Intersection<'_> {
+        Intersection {
+            t,
+            object,
+            object_index: None,
+            u: None,
+            v: None,
+            instance: None,
+        }
+    }
+
+    pub fn with_uv(t: f64, object: Option<&Shape>, u: f64, v: f64) -> Intersection<'_> {
+        Intersection {
+            u: Some(u),
+            v: Some(v),
+            ..Intersection::new(t, object)
+        }
     }
 }
 
-pub fn intersection(t: f64, object: Option<&Shape>) -> Intersection {
+pub fn intersection(t: f64, object: Option<&Shape>) -> Intersection<'_> {
     Intersection::new(t, object)
 }
 
+pub fn intersection_with_uv(t: f64, object: Option<&Shape>, u: f64, v: f64) -> Intersection<'_> {
+    Intersection::with_uv(t, object, u, v)
+}
+
 pub type Intersections<'a> = Vec<Intersection<'a>>;
 
+/// Intersect a ray with a single shape, in isolation. A group's members live in the
+/// World, so intersecting a group this way always misses: use `intersect_in_world`.
 pub fn intersect<'a>(object: &'a Shape, ray: &Ray) -> Intersections<'a> {
     // Apply the inverse of the shape's transformation
-    let local_ray = ray.transform(object.inverse_transform());
+    let local_ray = ray.transform(object.world_inverse_transform());
     let mut intersections = object.local_intersect(&local_ray);
     for intersection in &mut intersections {
         intersection.object = Some(object);
@@ -36,10 +68,26 @@ pub fn intersect<'a>(object: &'a Shape, ray: &Ray) -> Intersections<'a> {
     intersections
 }
 
+/// Intersect a ray with a shape belonging to `world`, resolving the members of groups.
+/// The intersections are sorted.
+pub fn intersect_in_world<'a>(object: &'a Shape, ray: &Ray, world: &'a World) -> Intersections<'a> {
+    world.intersect_shape(object, ray)
+}
+
+/// Sort intersections in ascending order by parameter t. Intersections with equal t
+/// are ordered by object id, so that coincident surfaces are always visited in the
+/// same order, regardless of the order the objects were added to the world.
+pub fn sort_intersections(intersections: &mut Intersections) {
+    intersections.sort_by(|a, b| {
+        a.t.total_cmp(&b.t)
+            .then_with(|| a.object.map(Shape::id).cmp(&b.object.map(Shape::id)))
+    });
+}
+
 /// Given a vector of ray intersections, sort in ascending order by parameter t, and then
 /// return the intersection with the lowest positive t.
 pub fn hit<'a>(intersections: &'a mut Intersections<'a>) -> Option<&'a Intersection<'a>> {
-    intersections.sort_by(|a, b| a.t.total_cmp(&b.t));
+    sort_intersections(intersections);
 
     let hit = intersections.iter().find(|&x| x.t > 0.0);
     hit
@@ -56,15 +104,22 @@ pub struct IntersectionComputation<'a> {
     pub normalv: Vector,
     pub inside: bool,
     pub reflectv: Vector,
-    pub n1: f64, // refractive index of material being exited
-    pub n2: f64, // refractive index of material being entered
+    pub n1: f64,          // refractive index of material being exited
+    pub n2: f64,          // refractive index of material being entered
+    pub n1_rgb: [f64; 3], // n1 for red, green and blue light, for dispersion
+    pub n2_rgb: [f64; 3],
+    pub u: Option<f64>, // from the intersection, if the shape provides them
+    pub v: Option<f64>,
+    pub footprint: Option<Footprint>, // of a camera pixel, for texture filtering
+    pub instance: Option<&'a Shape>,  // placing the object in the world, if any
+    pub path: RayPath,                // of the ray that hit the object
 }
 
 // Note to self: cannot implement Default for IntersectionComputation
 // because it contains a reference field.
 
 impl IntersectionComputation<'_> {
-    pub fn new(object: &Shape) -> IntersectionComputation {
+    pub fn new(object: &Shape) -> IntersectionComputation<'_> {
         IntersectionComputation {
             t: 0.0,
             object,
@@ -77,28 +132,94 @@ impl IntersectionComputation<'_> {
             reflectv: Vector::default(),
             n1: RefractiveIndex::VACUUM,
             n2: RefractiveIndex::VACUUM,
+            n1_rgb: [RefractiveIndex::VACUUM; 3],
+            n2_rgb: [RefractiveIndex::VACUUM; 3],
+            u: None,
+            v: None,
+            footprint: None,
+            instance: None,
+            path: RayPath::default(),
         }
     }
 }
 
+impl IntersectionComputation<'_> {
+    /// A world-space point as the object's material sees it. For a hit on an
+    /// instance, that's where the point would be on the prototype itself, so the
+    /// instance's patterns move with it.
+    pub fn material_point(&self, world_point: &Point) -> Point {
+        match self.instance {
+            Some(instance) => instance.world_inverse_transform() * world_point,
+            None => *world_point,
+        }
+    }
+
+    /// The pixel's footprint, if known, as the object's material sees it.
+    pub fn material_footprint(&self) -> Option<Footprint> {
+        match (self.instance, &self.footprint) {
+            (Some(instance), Some(footprint)) => {
+                Some(footprint.transform(instance.world_inverse_transform()))
+            }
+            _ => self.footprint,
+        }
+    }
+}
+
+// The normal at a world-space point on a hit object, placed by `instance`, if any
+fn hit_normal(
+    object: &Shape,
+    instance: Option<&Shape>,
+    world_point: &Point,
+    uv: Option<(f64, f64)>,
+) -> Vector {
+    match instance {
+        Some(instance) => {
+            let inverse = instance.world_inverse_transform();
+            let prototype_normal = normal_at_uv(object, &(inverse * world_point), uv);
+            let mut world_normal = transpose(inverse) * prototype_normal;
+            world_normal.set_w(0.0);
+            normalize(&world_normal)
+        }
+        None => normal_at_uv(object, world_point, uv),
+    }
+}
+
+/// How far to offset a point above or below the surface at `point`, to avoid
+/// "acne" from rays starting there hitting the surface again. Rounding errors grow
+/// with the coordinates, so the offset does too, but it's never less than `EPSILON`.
+pub fn surface_offset(point: &Point) -> f64 {
+    let distance = vector(point.x(), point.y(), point.z()).magnitude();
+    EPSILON * distance.max(1.0)
+}
+
 pub fn prepare_computations<'a>(
     intersection: &'a Intersection,
     ray: &Ray,
 ) -> IntersectionComputation<'a> {
     let mut comps = IntersectionComputation::new(intersection.object.expect("no shape ref"));
     comps.t = intersection.t;
+    comps.u = intersection.u;
+    comps.v = intersection.v;
+    comps.instance = intersection.instance;
+    comps.path = ray.path;
 
     comps.point = ray.position(comps.t);
     comps.eyev = -ray.direction;
-    comps.normalv = normal_at(comps.object, &comps.point);
+    comps.normalv = hit_normal(
+        comps.object,
+        comps.instance,
+        &comps.point,
+        comps.u.zip(comps.v),
+    );
 
     if dot(&comps.normalv, &comps.eyev) < 0.0 {
         comps.inside = true;
         comps.normalv = -comps.normalv;
     }
 
-    comps.under_point = comps.point - comps.normalv * EPSILON;
-    comps.over_point = comps.point + comps.normalv * EPSILON;
+    let offset = surface_offset(&comps.point);
+    comps.under_point = comps.point - comps.normalv * offset;
+    comps.over_point = comps.point + comps.normalv * offset;
 
     comps.reflectv = reflect(&ray.direction, &comps.normalv);
 
@@ -109,50 +230,75 @@ pub fn prepare_computations_for_refraction<'a>(
     intersection: &'a Intersection,
     ray: &Ray,
     intersections: &[Intersection],
+) -> IntersectionComputation<'a> {
+    prepare_computations_in_medium(intersection, ray, intersections, RefractiveIndex::VACUUM)
+}
+
+/// As `prepare_computations_for_refraction`, for a ray that starts out in a medium
+/// of refractive index `outer`, e.g. a camera under water, rather than a vacuum.
+pub fn prepare_computations_in_medium<'a>(
+    intersection: &'a Intersection,
+    ray: &Ray,
+    intersections: &[Intersection],
+    outer: f64,
 ) -> IntersectionComputation<'a> {
     let mut comps = prepare_computations(intersection, ray);
 
+    // A thin surface is its own interface, regardless of what else the ray is inside.
+    // The normal has been flipped towards the eye, so an inside hit came from below.
+    let material = &comps.object.material;
+    if material.thin {
+        (comps.n1, comps.n2) = if comps.inside {
+            (
+                material.refractive_index_below,
+                material.refractive_index_above,
+            )
+        } else {
+            (
+                material.refractive_index_above,
+                material.refractive_index_below,
+            )
+        };
+        (comps.n1_rgb, comps.n2_rgb) = ([comps.n1; 3], [comps.n2; 3]);
+        return comps;
+    }
+
     // Determine n1 (refractive index of material being exited),
     // and n2 (refractive index of material being entered):
-    let mut containers: Vec<&Shape> = vec![];
+    let mut containers = Containers::default();
     for i in intersections {
-        let object = &i.object.expect("object should exist");
+        let object = i.object.expect("object should exist");
 
         if std::ptr::eq(i, intersection) {
             // If the intersection is the hit, set n1 to the refractive index of the last object
-            // in the containers list. If the list is empty, set it to 1.0 (vacuum).
-            comps.n1 = match containers.last() {
-                Some(object) => object.material.refractive_index,
-                None => RefractiveIndex::VACUUM,
+            // in the containers list. If the list is empty, use the outer medium.
+            (comps.n1, comps.n1_rgb) = match containers.last() {
+                Some(object) => (
+                    object.material.refractive_index,
+                    object.material.refractive_index_rgb(),
+                ),
+                None => (outer, [outer; 3]),
             };
         }
 
         // If the intersection's object is already in the containers list, then the hit intersection
         // must be exiting the object. Remove it from the containers list.
         // Otherwise, the intersection is entering the object, so add to the containers list.
-        let mut index = None;
-        for (j, x) in containers.iter().enumerate() {
-            if std::ptr::eq(*x, *object) {
-                index = Some(j);
-                break;
-            }
-        }
-        match index {
-            Some(n) => {
-                containers.remove(n);
-            }
-            None => {
-                containers.push(object);
-            }
+        // Thin surfaces enclose nothing, so are never containers.
+        if !object.material.thin {
+            containers.toggle(object, i.instance);
         }
 
         // If the intersection is the hit, set n2 to the refractive index of the last object
         // in the containers list. If the list is empty, then there is no containing object
-        // and n2 should be set 1.0 (vacuum).
+        // and n2 should be set to that of the outer medium.
         if std::ptr::eq(i, intersection) {
-            comps.n2 = match containers.last() {
-                Some(object) => object.material.refractive_index,
-                None => RefractiveIndex::VACUUM,
+            (comps.n2, comps.n2_rgb) = match containers.last() {
+                Some(object) => (
+                    object.material.refractive_index,
+                    object.material.refractive_index_rgb(),
+                ),
+                None => (outer, [outer; 3]),
             };
 
             break;
@@ -162,6 +308,80 @@ pub fn prepare_computations_for_refraction<'a>(
     comps
 }
 
+/// The (n1, n2) refractive indices at every one of a ray's sorted intersections, as
+/// `prepare_computations_for_refraction` would find them, in a single pass.
+pub fn refractive_indices(ray: &Ray, intersections: &[Intersection]) -> Vec<(f64, f64)> {
+    refractive_indices_in_medium(ray, intersections, RefractiveIndex::VACUUM)
+}
+
+/// As `refractive_indices`, for a ray that starts out in a medium of refractive index
+/// `outer`, as `prepare_computations_in_medium` would find them.
+pub fn refractive_indices_in_medium(
+    ray: &Ray,
+    intersections: &[Intersection],
+    outer: f64,
+) -> Vec<(f64, f64)> {
+    let mut containers = Containers::default();
+    let current = |containers: &Containers| {
+        containers
+            .last()
+            .map_or(outer, |o| o.material.refractive_index)
+    };
+
+    intersections
+        .iter()
+        .map(|i| {
+            let object = i.object.expect("object should exist");
+            let material = &object.material;
+            if material.thin {
+                let normalv = hit_normal(object, i.instance, &ray.position(i.t), i.u.zip(i.v));
+                if dot(&normalv, &ray.direction) > 0.0 {
+                    (
+                        material.refractive_index_below,
+                        material.refractive_index_above,
+                    )
+                } else {
+                    (
+                        material.refractive_index_above,
+                        material.refractive_index_below,
+                    )
+                }
+            } else {
+                let n1 = current(&containers);
+                containers.toggle(object, i.instance);
+                (n1, current(&containers))
+            }
+        })
+        .collect()
+}
+
+// The objects a ray is inside, in the order it entered them. A ray is rarely
+// inside more than a few objects, so the list is searched from the most recently
+// entered object, which is usually the one being exited. Objects are identified
+// by id rather than address, so that a clone of an object (e.g. in a test's
+// intersection list) is the same object. Each instance of an object is a separate
+// object, so is identified by the instance's id too.
+#[derive(Default)]
+struct Containers<'a> {
+    entered: Vec<((u64, Option<u64>), &'a Shape)>,
+}
+
+impl<'a> Containers<'a> {
+    fn toggle(&mut self, object: &'a Shape, instance: Option<&Shape>) {
+        let key = (object.id(), instance.map(Shape::id));
+        match self.entered.iter().rposition(|(k, _)| *k == key) {
+            Some(n) => {
+                self.entered.remove(n);
+            }
+            None => self.entered.push((key, object)),
+        }
+    }
+
+    fn last(&self) -> Option<&'a Shape> {
+        self.entered.last().map(|(_, object)| *object)
+    }
+}
+
 // https://graphics.stanford.edu/courses/cs148-10-summer/docs/2006--degreve--reflection_refraction.pdf
 pub fn schlick(comps: &IntersectionComputation) -> f64 {
     // Cosine of angle between eye and normal vector:
@@ -182,7 +402,14 @@ pub fn schlick(comps: &IntersectionComputation) -> f64 {
         cos = cos_t;
     }
 
-    let k = (comps.n1 - comps.n2) / (comps.n1 + comps.n2);
+    schlick_reflectance(cos, comps.n1, comps.n2)
+}
+
+/// Schlick's approximation of the fraction of light reflected at a boundary from
+/// refractive index `n1` to `n2`, seen at `cos` (the cosine of the angle between
+/// eye and normal). Total internal reflection is not considered.
+pub fn schlick_reflectance(cos: f64, n1: f64, n2: f64) -> f64 {
+    let k = (n1 - n2) / (n1 + n2);
     let r0 = k * k;
 
     let w = 1.0 - cos;
@@ -192,10 +419,13 @@ pub fn schlick(comps: &IntersectionComputation) -> f64 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::colors::color;
+    use crate::lights::point_light;
     use crate::rays::ray;
-    use crate::shapes::{glass_sphere, plane, sphere};
+    use crate::shapes::{glass_sphere, plane, smooth_triangle, sphere};
     use crate::transformations::{scaling, translation};
     use crate::tuples::{point, vector};
+    use crate::world::{intersect_world, is_shadowed, world};
     use approx::assert_relative_eq;
     use rstest::rstest;
 
@@ -315,6 +545,73 @@ mod tests {
         assert!(comps.point.z() > comps.over_point.z());
     }
 
+    // At a unit sphere, the hit is offset by EPSILON along the normal
+    #[test]
+    fn hit_on_unit_sphere_is_offset_by_epsilon() {
+        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let shape = sphere(1);
+        let i = intersection(4.0, Some(&shape));
+        let comps = prepare_computations(&i, &r);
+        assert_eq!(comps.point, point(0.0, 0.0, -1.0));
+        assert_relative_eq!(comps.over_point, point(0.0, 0.0, -1.0 - EPSILON));
+        assert_relative_eq!(comps.under_point, point(0.0, 0.0, -1.0 + EPSILON));
+    }
+
+    // The offset scales with the hit point, so a large, distant sphere doesn't
+    // shadow itself
+    #[test]
+    fn large_distant_sphere_does_not_shadow_itself() {
+        let mut w = world();
+        let mut s = sphere(1);
+        s.set_transform(&(translation(0.0, 0.0, 1.0e8) * scaling(1.0e6, 1.0e6, 1.0e6)));
+        w.add_object(s);
+        let light = point_light(point(0.0, 0.0, 0.0), color(1.0, 1.0, 1.0));
+        w.add_light(light);
+
+        let mut shadowed = 0;
+        for i in 0..100 {
+            let x = (i as f64 - 50.0) * 1.0e-4;
+            let r = ray(point(0.0, 0.0, 0.0), normalize(&vector(x, 0.5 * x, 1.0)));
+            let xs = intersect_world(&w, &r);
+            let comps = prepare_computations(&xs[0], &r);
+            if is_shadowed(&w, &comps.over_point, &light) {
+                shadowed += 1;
+            }
+        }
+        assert_eq!(shadowed, 0);
+    }
+
+    // Preparing the normal on a smooth triangle
+    #[test]
+    fn preparing_normal_on_smooth_triangle() {
+        let tri = smooth_triangle(
+            point(0.0, 1.0, 0.0),
+            point(-1.0, 0.0, 0.0),
+            point(1.0, 0.0, 0.0),
+            vector(0.0, 1.0, 0.0),
+            vector(-1.0, 0.0, 0.0),
+            vector(1.0, 0.0, 0.0),
+        );
+        let i = intersection_with_uv(1.0, Some(&tri), 0.45, 0.25);
+        let r = ray(point(-0.2, 0.3, -2.0), vector(0.0, 0.0, 1.0));
+        let comps = prepare_computations(&i, &r);
+        assert_eq!(comps.u, Some(0.45));
+        assert_eq!(comps.v, Some(0.25));
+        assert_relative_eq!(comps.normalv, vector(-0.5547, 0.83205, 0.0), epsilon = 1e-5);
+    }
+
+    // Intersections with shapes other than triangles carry no u/v
+    #[test]
+    fn non_triangle_hits_carry_no_uv() {
+        let shape = sphere(1);
+        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = intersect(&shape, &r);
+        assert!(xs.iter().all(|i| i.u.is_none() && i.v.is_none()));
+        let comps = prepare_computations(&xs[0], &r);
+        assert_eq!(comps.u, None);
+        assert_eq!(comps.v, None);
+    }
+
     // Chapter 11: Reflections
 
     // Precomputing the reflection vector
@@ -364,6 +661,136 @@ mod tests {
         assert_eq!(comps.n2, n2);
     }
 
+    // The book's containers algorithm, with a linear search, for comparison
+    fn reference_n1_n2(hit: usize, xs: &[Intersection]) -> (f64, f64) {
+        let mut containers: Vec<&Shape> = vec![];
+        let mut n1 = RefractiveIndex::VACUUM;
+        for (k, i) in xs.iter().enumerate() {
+            let object = i.object.unwrap();
+            if k == hit {
+                n1 = containers
+                    .last()
+                    .map_or(1.0, |o| o.material.refractive_index);
+            }
+            match containers.iter().position(|x| std::ptr::eq(*x, object)) {
+                Some(n) => {
+                    containers.remove(n);
+                }
+                None => containers.push(object),
+            }
+            if k == hit {
+                return (
+                    n1,
+                    containers
+                        .last()
+                        .map_or(1.0, |o| o.material.refractive_index),
+                );
+            }
+        }
+        unreachable!()
+    }
+
+    // Finding n1 and n2 through many overlapping objects
+    #[test]
+    fn finding_n1_and_n2_through_many_overlapping_objects() {
+        let spheres: Vec<Shape> = (0..200)
+            .map(|k| {
+                let mut s = glass_sphere();
+                let k = k as f64;
+                // Spread along the ray, with varying sizes so entries and exits interleave
+                s.set_transform(
+                    &(translation(0.0, 0.0, (k * 7.3) % 50.0)
+                        * scaling(1.0, 1.0, 1.0 + (k * 3.1) % 9.0)),
+                );
+                s.material.refractive_index = 1.0 + k / 100.0;
+                s
+            })
+            .collect();
+
+        let r = ray(point(0.0, 0.0, -20.0), vector(0.0, 0.0, 1.0));
+        let mut xs: Intersections = spheres.iter().flat_map(|s| intersect(s, &r)).collect();
+        sort_intersections(&mut xs);
+        assert_eq!(xs.len(), 400);
+
+        let all = refractive_indices(&r, &xs);
+        for hit in 0..xs.len() {
+            let comps = prepare_computations_for_refraction(&xs[hit], &r, &xs);
+            assert_eq!((comps.n1, comps.n2), reference_n1_n2(hit, &xs), "hit {hit}");
+            assert_eq!(all[hit], (comps.n1, comps.n2), "hit {hit}");
+        }
+    }
+
+    // A ray crossing a thin water plane refracts with the water index in both directions
+    #[rstest]
+    #[case(point(0.0, 1.0, 0.0), vector(0.0, -1.0, 0.0), RefractiveIndex::AIR, RefractiveIndex::WATER)]
+    #[case(point(0.0, -1.0, 0.0), vector(0.0, 1.0, 0.0), RefractiveIndex::WATER, RefractiveIndex::AIR)]
+    fn thin_surface_uses_refractive_index_above_and_below(
+        #[case] origin: Point,
+        #[case] direction: Vector,
+        #[case] n1: f64,
+        #[case] n2: f64,
+    ) {
+        let mut water = plane();
+        water.material.transparency = 1.0;
+        water.material.thin = true;
+        water.material.refractive_index_above = RefractiveIndex::AIR;
+        water.material.refractive_index_below = RefractiveIndex::WATER;
+
+        let r = ray(origin, direction);
+        let xs = intersect(&water, &r);
+        let comps = prepare_computations_for_refraction(&xs[0], &r, &xs);
+        assert_eq!(comps.n1, n1);
+        assert_eq!(comps.n2, n2);
+        assert_eq!(comps.normalv, -direction);
+    }
+
+    // Coincident surfaces at identical t are ordered deterministically
+    #[test]
+    fn coincident_surfaces_have_deterministic_n1_and_n2() {
+        let mut a = glass_sphere();
+        a.material.refractive_index = 1.5;
+        let mut b = glass_sphere();
+        b.material.refractive_index = 2.0;
+        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+
+        let mut expected = None;
+        for run in 0..10 {
+            // Alternate the order the intersections are produced in
+            let mut xs = if run % 2 == 0 {
+                intersections!(
+                    Intersection::new(4.0, Some(&a)),
+                    Intersection::new(4.0, Some(&b)),
+                    Intersection::new(6.0, Some(&a)),
+                    Intersection::new(6.0, Some(&b))
+                )
+            } else {
+                intersections!(
+                    Intersection::new(6.0, Some(&b)),
+                    Intersection::new(6.0, Some(&a)),
+                    Intersection::new(4.0, Some(&b)),
+                    Intersection::new(4.0, Some(&a))
+                )
+            };
+            sort_intersections(&mut xs);
+
+            let n: Vec<(f64, f64)> = (0..xs.len())
+                .map(|i| {
+                    let comps = prepare_computations_for_refraction(&xs[i], &r, &xs);
+                    (comps.n1, comps.n2)
+                })
+                .collect();
+
+            match &expected {
+                None => expected = Some(n),
+                Some(e) => assert_eq!(&n, e),
+            }
+        }
+        assert_eq!(
+            expected.unwrap(),
+            vec![(1.0, 1.5), (1.5, 2.0), (2.0, 2.0), (2.0, 1.0)]
+        );
+    }
+
     // The under point is offset below the surface
     #[test]
     fn under_point_is_offset_below_surface() {
@@ -1,17 +1,35 @@
 use crate::materials::RefractiveIndex;
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
+use flate2::read::GzDecoder;
 use serde::de::DeserializeOwned;
 use serde::Deserialize;
+use std::io::Read;
 use std::path::Path;
 
 #[derive(Deserialize, Debug, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct Scene {
+    pub(crate) world: Option<WorldSettings>,
     pub(crate) lights: Option<Vec<Light>>,
     pub(crate) bodies: Option<Vec<Body>>,
     pub(crate) cameras: Option<Vec<Camera>>,
 }
 
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct WorldSettings {
+    pub(crate) background: Option<Color>,
+    pub(crate) ambient_medium: Option<f64>,
+    pub(crate) environment: Option<Environment>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Environment {
+    pub(crate) filename: String, // relative to the scene file
+    pub(crate) intensity: Option<f64>,
+}
+
 #[derive(Deserialize, Debug, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub(crate) enum Light {
@@ -19,6 +37,8 @@ pub(crate) enum Light {
     PointLight {
         position: [f64; 3],
         intensity: [f64; 3],
+        affects_diffuse: Option<bool>,
+        affects_specular: Option<bool>,
     },
 }
 
@@ -46,6 +66,7 @@ pub(crate) struct Cone {
     pub(crate) common: BodyCommon,
     pub(crate) minimum_y: Option<f64>,
     pub(crate) maximum_y: Option<f64>,
+    pub(crate) radius: Option<f64>,
 }
 
 #[derive(Deserialize, Debug, PartialEq)]
@@ -56,6 +77,16 @@ pub(crate) struct Cylinder {
     pub(crate) maximum_y: Option<f64>,
     pub(crate) closed_min: Option<bool>,
     pub(crate) closed_max: Option<bool>,
+    pub(crate) radius: Option<f64>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+pub(crate) struct Capsule {
+    #[serde(flatten)]
+    pub(crate) common: BodyCommon,
+    pub(crate) minimum_y: Option<f64>,
+    pub(crate) maximum_y: Option<f64>,
+    pub(crate) radius: Option<f64>,
 }
 
 #[derive(Deserialize, Debug, PartialEq)]
@@ -76,6 +107,8 @@ pub(crate) enum Body {
     Cylinder(Cylinder),
     #[serde(rename = "cube")]
     Cube(Cube),
+    #[serde(rename = "capsule")]
+    Capsule(Capsule),
 }
 
 #[derive(Deserialize, Debug, PartialEq)]
@@ -87,11 +120,16 @@ pub(crate) struct Material {
     pub(crate) diffuse: f64,
     pub(crate) specular: f64,
     pub(crate) shininess: f64,
+    pub(crate) specular_color: Option<Color>,
     pub(crate) reflective: f64,
+    pub(crate) fresnel: bool,
     pub(crate) transparency: f64,
     pub(crate) refractive_index: f64,
+    pub(crate) dispersion: Option<[f64; 3]>,
     pub(crate) casts_shadow: bool,
     pub(crate) receives_shadow: bool,
+    pub(crate) visible_in_reflections: bool,
+    pub(crate) visible_in_refractions: bool,
     pub(crate) pattern: Option<Pattern>,
 }
 
@@ -113,11 +151,16 @@ impl Default for Material {
             diffuse: 0.9,
             specular: 0.9,
             shininess: 200.0,
+            specular_color: None,
             reflective: 0.0,
+            fresnel: false,
             transparency: 0.0,
             refractive_index: RefractiveIndex::AIR,
+            dispersion: None,
             casts_shadow: true,
             receives_shadow: true,
+            visible_in_reflections: true,
+            visible_in_refractions: true,
             pattern: None,
         }
     }
@@ -183,6 +226,19 @@ pub(crate) struct Camera {
     pub(crate) name: String,
     pub(crate) resolution: Option<Resolution>,
     pub(crate) field_of_view: Option<f64>,
+    pub(crate) ssaa: Option<u32>,
+    pub(crate) fog: Option<Fog>,
+    pub(crate) clamp_indirect: Option<f64>,
+    pub(crate) region: Option<(u32, u32, u32, u32)>,
+    pub(crate) russian_roulette: Option<bool>,
+    pub(crate) shadow_samples: Option<u32>,
+    pub(crate) shadow_softness: Option<f64>,
+    pub(crate) seed: Option<u64>,
+    pub(crate) image_based_lighting: Option<bool>,
+    pub(crate) dispersion: Option<bool>,
+    pub(crate) light_sampling: Option<u32>,
+    pub(crate) near: Option<f64>,
+    pub(crate) far: Option<f64>,
     pub(crate) from: [f64; 3],
     pub(crate) to: [f64; 3],
     pub(crate) up: [f64; 3],
@@ -195,6 +251,19 @@ impl Default for Camera {
             name: "main".to_string(),
             resolution: None,
             field_of_view: None,
+            ssaa: None,
+            fog: None,
+            clamp_indirect: None,
+            region: None,
+            russian_roulette: None,
+            shadow_samples: None,
+            shadow_softness: None,
+            seed: None,
+            image_based_lighting: None,
+            dispersion: None,
+            light_sampling: None,
+            near: None,
+            far: None,
             from: [0.0, 0.0, -10.0],
             to: [0.0, 1.0, 0.0],
             up: [0.0, 1.0, 0.0],
@@ -203,6 +272,13 @@ impl Default for Camera {
     }
 }
 
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Fog {
+    pub(crate) color: Color,
+    pub(crate) density: f64,
+}
+
 #[derive(Deserialize, Debug, PartialEq)]
 #[allow(clippy::upper_case_acronyms)]
 #[serde(deny_unknown_fields)]
@@ -231,11 +307,41 @@ impl Default for Resolution {
     }
 }
 
-fn load_json5<T>(filename: &Path) -> Result<T>
+// Every gzip file starts with these bytes
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+// A gzipped file that expands to more than this is refused, rather than
+// exhausting memory
+const MAX_DECOMPRESSED_SIZE: u64 = 1 << 30;
+
+/// The text in a file, which may be gzipped (e.g. `scene.json5.gz`). Compression
+/// is recognised by the file's first bytes, whatever its name.
+pub(crate) fn read_text(filename: &Path) -> Result<String> {
+    let data = std::fs::read(filename)?;
+    if data.starts_with(&GZIP_MAGIC) {
+        decompress(&data, MAX_DECOMPRESSED_SIZE)
+            .with_context(|| format!("reading {}", filename.display()))
+    } else {
+        Ok(String::from_utf8(data)?)
+    }
+}
+
+fn decompress(data: &[u8], limit: u64) -> Result<String> {
+    let mut text = String::new();
+    GzDecoder::new(data)
+        .take(limit + 1)
+        .read_to_string(&mut text)?;
+    if text.len() as u64 > limit {
+        bail!("decompresses to more than {limit} bytes");
+    }
+    Ok(text)
+}
+
+pub(crate) fn load_json5<T>(filename: &Path) -> Result<T>
 where
     T: DeserializeOwned,
 {
-    let data = std::fs::read_to_string(filename)?;
+    let data = read_text(filename)?;
     let t: T = json5::from_str(&data)?;
 
     //let deserializer = &mut json5::from_str(&data);
@@ -255,3 +361,29 @@ where
 pub fn load_scene(filename: &Path) -> Result<Scene> {
     load_json5::<Scene>(filename)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn gzip(text: &str) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(text.as_bytes()).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    // Gzipped text up to the limit decompresses
+    #[test]
+    fn decompress_within_limit() {
+        assert_eq!(decompress(&gzip("abcd"), 4).unwrap(), "abcd");
+    }
+
+    // Gzipped text that expands beyond the limit is refused
+    #[test]
+    fn decompress_beyond_limit() {
+        assert!(decompress(&gzip("abcde"), 4).is_err());
+    }
+}
@@ -1,9 +1,15 @@
+pub mod bounds;
 pub mod camera;
 pub mod canvas;
+pub mod capsules;
 pub mod colors;
 pub mod cones;
 pub mod cubes;
 pub mod cylinders;
+pub mod framebuffer;
+pub mod groups;
+pub mod ibl;
+pub mod instances;
 pub mod intersections;
 mod json;
 pub mod lights;
@@ -13,10 +19,14 @@ pub mod matrices;
 pub mod patterns;
 pub mod perlin_noise;
 pub mod planes;
+pub mod ppm_reader;
+pub mod preview;
 pub mod rays;
+pub mod scenes;
 pub mod shapes;
 pub mod spheres;
 pub mod transformations;
+pub mod triangles;
 pub mod tuples;
 pub mod utils;
 pub mod world;
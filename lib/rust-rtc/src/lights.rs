@@ -7,6 +7,10 @@ use crate::tuples::Point;
 pub struct PointLight {
     pub position: Point,
     pub intensity: Color,
+    // For artistic control, a light can be limited to diffuse lighting (a fill
+    // light) or to specular highlights (a rim light). Both are on by default.
+    pub affects_diffuse: bool,
+    pub affects_specular: bool,
 }
 
 impl PointLight {
@@ -15,6 +19,8 @@ impl PointLight {
         PointLight {
             position,
             intensity,
+            affects_diffuse: true,
+            affects_specular: true,
         }
     }
 }
@@ -38,5 +44,7 @@ mod tests {
         let light = point_light(position, intensity);
         assert_eq!(light.position, position);
         assert_eq!(light.intensity, intensity);
+        assert!(light.affects_diffuse);
+        assert!(light.affects_specular);
     }
 }
@@ -1,8 +1,10 @@
 // Chapter 6: Lights and Shading
 
-use crate::colors::{color, Color};
+use crate::colors::{color, Color, BLACK, WHITE};
+use crate::intersections::schlick_reflectance;
 use crate::lights::PointLight;
-use crate::patterns::Pattern;
+use crate::patterns::{checkers_pattern, Pattern};
+use crate::rays::{Footprint, RayKind, RayPath};
 use crate::shapes::Shape;
 use crate::tuples::{dot, normalize, reflect, Point, Vector};
 
@@ -17,6 +19,21 @@ impl RefractiveIndex {
     pub const DIAMOND: f64 = 2.417;
 }
 
+#[derive(Debug, PartialEq, Clone, Default)]
+pub enum MaterialKind {
+    #[default]
+    Plain,
+    // A different material at each point on the surface: the mask's luminance
+    // blends from `a` (black) to `b` (white). The blended material's other fields
+    // are only used for things decided per object, such as shadows and containment,
+    // and are derived from `a` and `b` by Material::blend.
+    Blend {
+        a: Box<Material>,
+        b: Box<Material>,
+        mask: Box<Pattern>,
+    },
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Material {
     pub color: Color,
@@ -24,11 +41,38 @@ pub struct Material {
     pub diffuse: f64,
     pub specular: f64,
     pub shininess: f64,
+    // Tints the specular highlight, e.g. gold for a metal. When None, highlights
+    // take the color of the light.
+    pub specular_color: Option<Color>,
     pub reflective: f64,
+    // Weight reflection by the Fresnel effect, using `refractive_index`: weak when
+    // viewed head-on, up to `reflective` at grazing angles. Transparent materials
+    // already balance reflection and refraction this way, so this is for opaque ones.
+    pub fresnel: bool,
     pub transparency: f64,
     pub refractive_index: f64,
+    // Separate refractive indices for red, green and blue light, which are bent by
+    // different amounts when rendering with dispersion. Otherwise (and when None)
+    // `refractive_index` is used for all three.
+    pub dispersion: Option<[f64; 3]>,
+    // A thin surface (e.g. a water plane) doesn't enclose a volume, but separates
+    // two media: `refractive_index_above` on the side its normal points to, and
+    // `refractive_index_below` on the other. `refractive_index` is then unused.
+    pub thin: bool,
+    pub refractive_index_above: f64,
+    pub refractive_index_below: f64,
     pub casts_shadow: bool,
     pub receives_shadow: bool,
+    // Hide the object from reflected or refracted rays, e.g. for a helper gizmo,
+    // while it stays visible to the camera.
+    pub visible_in_reflections: bool,
+    pub visible_in_refractions: bool,
+    pub emission: Color, // light given off by the surface itself, regardless of lights
+    // Lighting can be brighter than 1.0 per channel, e.g. at a specular highlight,
+    // which is kept by default for tone mapping. When set, each light's contribution
+    // is clamped to 0.0 to 1.0 per channel instead, for direct (LDR) output.
+    pub clamp_output: bool,
+    pub kind: MaterialKind,
     pattern: Option<Box<Pattern>>,
 }
 
@@ -45,10 +89,174 @@ impl Material {
         }
     }
 
+    /// A perfect mirror: no ambient or diffuse light, a sharp specular highlight
+    /// (specular 1.0, shininess 300.0), and everything reflected (reflective 1.0).
+    pub fn mirror() -> Self {
+        Material {
+            ambient: 0.0,
+            diffuse: 0.0,
+            specular: 1.0,
+            shininess: 300.0,
+            reflective: 1.0,
+            ..Default::default()
+        }
+    }
+
+    /// A dull surface of the given color: the default ambient (0.1) and diffuse
+    /// (0.9) light, with no specular highlight (specular 0.0) or reflection.
+    pub fn matte(color: Color) -> Self {
+        Material {
+            color,
+            specular: 0.0,
+            ..Default::default()
+        }
+    }
+
+    /// A metal of the given color: little diffuse light (0.3), a bright highlight
+    /// (specular 0.9), and a reflection. `roughness`, from 0.0 (polished) to 1.0,
+    /// broadens the highlight, with shininess from 300.0 down to 10.0, and dims the
+    /// reflection, with reflective from 0.8 down to 0.0.
+    pub fn metal(color: Color, roughness: f64) -> Self {
+        let smoothness = 1.0 - roughness.clamp(0.0, 1.0);
+        Material {
+            color,
+            diffuse: 0.3,
+            specular: 0.9,
+            shininess: 10.0 + 290.0 * smoothness,
+            reflective: 0.8 * smoothness,
+            ..Default::default()
+        }
+    }
+
+    /// Clear glass: no ambient light and little diffuse (0.1), a sharp highlight
+    /// (specular 1.0, shininess 300.0), fully transparent (1.0) and reflective (1.0),
+    /// with the refractive index of glass. Reflection and refraction are balanced by
+    /// the Fresnel effect.
+    pub fn glass() -> Self {
+        Material {
+            ambient: 0.0,
+            diffuse: 0.1,
+            specular: 1.0,
+            shininess: 300.0,
+            reflective: 1.0,
+            transparency: 1.0,
+            refractive_index: RefractiveIndex::GLASS,
+            ..Default::default()
+        }
+    }
+
+    /// A checkered floor of colors `a` and `b`, as in the book's chapter scenes, with
+    /// a wet look: Fresnel reflection as for water, so it barely reflects when viewed
+    /// from above but is mirror-like towards the horizon.
+    pub fn checker_floor(a: &Color, b: &Color) -> Self {
+        let mut m = Material {
+            specular: 0.3,
+            reflective: 1.0,
+            fresnel: true,
+            refractive_index: RefractiveIndex::WATER,
+            ..Default::default()
+        };
+        m.set_pattern(&checkers_pattern(a, b));
+        m
+    }
+
+    /// The fraction of light reflected when viewed at `cos_theta`, the cosine of the
+    /// angle between the eye and normal vectors. This is `reflective`, weighted by
+    /// the Fresnel effect for a `fresnel` material.
+    pub fn reflectivity_at(&self, cos_theta: f64) -> f64 {
+        if self.fresnel && self.transparency == 0.0 {
+            self.reflective
+                * schlick_reflectance(cos_theta, RefractiveIndex::VACUUM, self.refractive_index)
+        } else {
+            self.reflective
+        }
+    }
+
+    /// Whether an object with this material can be seen by a ray at the end of
+    /// `path`, e.g. one hidden from reflections can't be seen in a mirror through glass.
+    pub fn is_visible_to(&self, path: RayPath) -> bool {
+        (self.visible_in_reflections || !path.includes(RayKind::Reflection))
+            && (self.visible_in_refractions || !path.includes(RayKind::Refraction))
+    }
+
+    /// A material that blends between `a` and `b` by the luminance of `mask`. What is
+    /// decided per object rather than per point comes from both: the blend casts (or
+    /// receives) shadows if either does, is only as transparent as the more opaque of
+    /// them, and has the refractive index of the more transparent.
+    pub fn blend(a: Material, b: Material, mask: Pattern) -> Self {
+        let clearer = if b.transparency > a.transparency {
+            &b
+        } else {
+            &a
+        };
+        Material {
+            casts_shadow: a.casts_shadow || b.casts_shadow,
+            receives_shadow: a.receives_shadow || b.receives_shadow,
+            transparency: a.transparency.min(b.transparency),
+            refractive_index: clearer.refractive_index,
+            kind: MaterialKind::Blend {
+                a: Box::new(a),
+                b: Box::new(b),
+                mask: Box::new(mask),
+            },
+            ..Default::default()
+        }
+    }
+
+    /// For a blended material, the two materials and how far (0.0 to 1.0) to blend
+    /// from the first to the second at `point` on `object`.
+    pub fn blend_at(
+        &self,
+        object: &Shape,
+        point: &Point,
+        footprint: Option<&Footprint>,
+    ) -> Option<(&Material, &Material, f64)> {
+        match &self.kind {
+            MaterialKind::Plain => None,
+            MaterialKind::Blend { a, b, mask } => {
+                let t = mask.pattern_at_shape(object, point, footprint).luminance();
+                Some((a, b, t.clamp(0.0, 1.0)))
+            }
+        }
+    }
+
+    /// Refractive indices for red, green and blue light.
+    pub fn refractive_index_rgb(&self) -> [f64; 3] {
+        self.dispersion.unwrap_or([self.refractive_index; 3])
+    }
+
     pub fn set_pattern(&mut self, pattern: &Pattern) {
         self.pattern = Some(Box::new(pattern.clone()));
     }
 
+    /// The surface color (pattern-aware) at `point` on `object`.
+    pub fn color_at(&self, object: &Shape, point: &Point) -> Color {
+        match &self.pattern {
+            Some(inner) => inner.pattern_at_shape(object, point, None),
+            None => self.color,
+        }
+    }
+
+    /// As `color_at`, with the pattern filtered over the pixel's footprint on the
+    /// surface, if known.
+    pub fn color_at_filtered(
+        &self,
+        object: &Shape,
+        point: &Point,
+        footprint: Option<&Footprint>,
+    ) -> Color {
+        match &self.pattern {
+            Some(inner) => inner.pattern_at_shape(object, point, footprint),
+            None => self.color,
+        }
+    }
+
+    /// Just the ambient term of `lighting`, under white light, e.g. for fast
+    /// flat-shaded previews without casting any shadow rays.
+    pub fn ambient_color(&self, object: &Shape, point: &Point) -> Color {
+        self.color_at(object, point) * self.ambient
+    }
+
     pub fn lighting(
         &self,
         object: &Shape,
@@ -58,24 +266,104 @@ impl Material {
         normalv: &Vector,
         in_shadow: bool,
     ) -> Color {
-        let material_color = match &self.pattern {
-            Some(inner) => inner.pattern_at_shape(object, point),
-            None => self.color,
-        };
+        let light_attenuation = if in_shadow { BLACK } else { WHITE };
+        self.lighting_attenuated(object, light, point, eyev, normalv, &light_attenuation)
+    }
+
+    /// As `lighting`, but rather than being either shadowed or not, the light reaching
+    /// the surface is filtered by `light_attenuation`, e.g. after passing through
+    /// colored glass. Ambient light is unaffected.
+    pub fn lighting_attenuated(
+        &self,
+        object: &Shape,
+        light: &Option<PointLight>,
+        point: &Point,
+        eyev: &Vector,
+        normalv: &Vector,
+        light_attenuation: &Color,
+    ) -> Color {
+        self.lighting_filtered(object, light, point, eyev, normalv, light_attenuation, None)
+    }
+
+    /// As `lighting_attenuated`, with the pattern filtered over the pixel's footprint
+    /// on the surface, if known.
+    #[allow(clippy::too_many_arguments)]
+    pub fn lighting_filtered(
+        &self,
+        object: &Shape,
+        light: &Option<PointLight>,
+        point: &Point,
+        eyev: &Vector,
+        normalv: &Vector,
+        light_attenuation: &Color,
+        footprint: Option<&Footprint>,
+    ) -> Color {
+        let material_color = self.color_at_filtered(object, point, footprint);
+        self.lighting_colored(
+            &material_color,
+            light,
+            point,
+            eyev,
+            normalv,
+            light_attenuation,
+        )
+    }
+
+    /// As `lighting_attenuated`, for a surface whose color (pattern-aware) is already
+    /// known, e.g. to evaluate the pattern once for many lights.
+    pub fn lighting_colored(
+        &self,
+        material_color: &Color,
+        light: &Option<PointLight>,
+        point: &Point,
+        eyev: &Vector,
+        normalv: &Vector,
+        light_attenuation: &Color,
+    ) -> Color {
+        let c = self.lighting_hdr(
+            material_color,
+            light,
+            point,
+            eyev,
+            normalv,
+            light_attenuation,
+        );
+        if self.clamp_output {
+            c.clamped()
+        } else {
+            c
+        }
+    }
 
+    // The Phong reflection model, without any clamping
+    fn lighting_hdr(
+        &self,
+        material_color: &Color,
+        light: &Option<PointLight>,
+        point: &Point,
+        eyev: &Vector,
+        normalv: &Vector,
+        light_attenuation: &Color,
+    ) -> Color {
         // Light is optional
         let light_intensity: Color;
         let light_position: Point;
+        let affects_diffuse: bool;
+        let affects_specular: bool;
         if let Some(light) = light {
             light_intensity = light.intensity;
             light_position = light.position;
+            affects_diffuse = light.affects_diffuse;
+            affects_specular = light.affects_specular;
         } else {
             light_intensity = color(0.0, 0.0, 0.0);
             light_position = crate::tuples::point(0.0, 0.0, 0.0);
+            affects_diffuse = true;
+            affects_specular = true;
         }
 
         // Combine the surface color with the light's color/intensity
-        let effective_color = material_color * light_intensity;
+        let effective_color = *material_color * light_intensity;
 
         // Find the direction to the light source
         let lightv = normalize(&(light_position - point));
@@ -83,7 +371,7 @@ impl Material {
         // Compute the ambient contribution
         let ambient = effective_color * self.ambient;
 
-        if in_shadow {
+        if *light_attenuation == BLACK {
             return ambient;
         }
 
@@ -99,7 +387,11 @@ impl Material {
             specular = color(0.0, 0.0, 0.0); // black
         } else {
             // Compute the diffuse contribution
-            diffuse = effective_color * self.diffuse * light_dot_normal;
+            diffuse = if affects_diffuse {
+                effective_color * self.diffuse * light_dot_normal
+            } else {
+                BLACK
+            };
 
             // reflect_dot_eye represents the cosine of the angle between the
             // reflection vector and the eye vector. A negative number means the
@@ -107,16 +399,17 @@ impl Material {
             let reflectv = reflect(&(-lightv), normalv);
             let reflect_dot_eye = dot(&reflectv, eyev);
 
-            if reflect_dot_eye <= 0.0 {
+            if reflect_dot_eye <= 0.0 || !affects_specular {
                 specular = color(0.0, 0.0, 0.0);
             } else {
                 // Compute the specular contribution
                 let factor = f64::powf(reflect_dot_eye, self.shininess);
-                specular = light_intensity * self.specular * factor;
+                specular =
+                    light_intensity * self.specular_color.unwrap_or(WHITE) * self.specular * factor;
             }
         }
 
-        ambient + diffuse + specular
+        ambient + diffuse * *light_attenuation + specular * *light_attenuation
     }
 }
 
@@ -128,11 +421,22 @@ impl Default for Material {
             diffuse: 0.9,
             specular: 0.9,
             shininess: 200.0,
+            specular_color: None,
             reflective: 0.0,
+            fresnel: false,
             transparency: 0.0,
             refractive_index: RefractiveIndex::AIR,
+            dispersion: None,
+            thin: false,
+            refractive_index_above: RefractiveIndex::VACUUM,
+            refractive_index_below: RefractiveIndex::VACUUM,
             casts_shadow: true,
             receives_shadow: true,
+            visible_in_reflections: true,
+            visible_in_refractions: true,
+            emission: BLACK,
+            clamp_output: false,
+            kind: MaterialKind::Plain,
             pattern: None,
         }
     }
@@ -153,6 +457,34 @@ pub fn material(
     Material::new(color, ambient, diffuse, specular, shininess)
 }
 
+pub fn blend_material(a: Material, b: Material, mask: Pattern) -> Material {
+    Material::blend(a, b, mask)
+}
+
+pub fn mirror_material() -> Material {
+    Material::mirror()
+}
+
+pub fn matte_material(color: Color) -> Material {
+    Material::matte(color)
+}
+
+pub fn metal_material(color: Color, roughness: f64) -> Material {
+    Material::metal(color, roughness)
+}
+
+pub fn glass_material() -> Material {
+    Material::glass()
+}
+
+pub fn checker_floor_material(a: &Color, b: &Color) -> Material {
+    Material::checker_floor(a, b)
+}
+
+pub fn ambient_color(material: &Material, object: &Shape, point: &Point) -> Color {
+    material.ambient_color(object, point)
+}
+
 pub fn lighting(
     material: &Material,
     object: &Shape,
@@ -186,6 +518,99 @@ mod tests {
         assert_eq!(m.shininess, 200.0);
     }
 
+    // A mirror reflects everything, with a sharp highlight
+    #[test]
+    fn mirror_material_preset() {
+        let m = mirror_material();
+        assert_eq!(m.ambient, 0.0);
+        assert_eq!(m.diffuse, 0.0);
+        assert_eq!(m.specular, 1.0);
+        assert_eq!(m.shininess, 300.0);
+        assert_eq!(m.reflective, 1.0);
+        assert_eq!(m.transparency, 0.0);
+    }
+
+    // A matte material has its color, and no highlight or reflection
+    #[test]
+    fn matte_material_preset() {
+        let m = matte_material(color(0.2, 0.4, 0.6));
+        assert_eq!(m.color, color(0.2, 0.4, 0.6));
+        assert_eq!(m.ambient, 0.1);
+        assert_eq!(m.diffuse, 0.9);
+        assert_eq!(m.specular, 0.0);
+        assert_eq!(m.reflective, 0.0);
+    }
+
+    // Roughness broadens a metal's highlight and dims its reflection
+    #[rstest]
+    #[case(0.0, 300.0, 0.8)]
+    #[case(0.5, 155.0, 0.4)]
+    #[case(1.0, 10.0, 0.0)]
+    #[case(2.0, 10.0, 0.0)]
+    fn metal_material_preset(
+        #[case] roughness: f64,
+        #[case] shininess: f64,
+        #[case] reflective: f64,
+    ) {
+        let m = metal_material(color(0.9, 0.6, 0.2), roughness);
+        assert_eq!(m.color, color(0.9, 0.6, 0.2));
+        assert_eq!(m.diffuse, 0.3);
+        assert_eq!(m.specular, 0.9);
+        assert_relative_eq!(m.shininess, shininess);
+        assert_relative_eq!(m.reflective, reflective);
+    }
+
+    // Glass is transparent and reflective, with the refractive index of glass
+    #[test]
+    fn glass_material_preset() {
+        let m = glass_material();
+        assert_eq!(m.ambient, 0.0);
+        assert_eq!(m.diffuse, 0.1);
+        assert_eq!(m.specular, 1.0);
+        assert_eq!(m.shininess, 300.0);
+        assert_eq!(m.reflective, 1.0);
+        assert_eq!(m.transparency, 1.0);
+        assert_eq!(m.refractive_index, RefractiveIndex::GLASS);
+    }
+
+    // A checker floor reflects more at a grazing angle than when viewed head-on
+    #[test]
+    fn checker_floor_material_preset() {
+        let m = checker_floor_material(&BLACK, &WHITE);
+        assert!(m.pattern.is_some());
+        assert!(m.fresnel);
+
+        let head_on = m.reflectivity_at(1.0);
+        let grazing = m.reflectivity_at(0.1);
+        assert!(grazing > head_on);
+        assert_relative_eq!(head_on, 0.02, epsilon = 0.001);
+        assert_relative_eq!(m.reflectivity_at(0.0), 1.0);
+    }
+
+    // A blend casts and receives shadows if either part does, is as transparent as
+    // its more opaque part, and refracts like its more transparent part
+    #[test]
+    fn blend_derives_per_object_fields_from_parts() {
+        let mut a = glass_material();
+        a.casts_shadow = false;
+        let mut b = matte_material(color(1.0, 0.0, 0.0));
+        b.receives_shadow = false;
+        let mask = stripe_pattern(&color(0.0, 0.0, 0.0), &color(1.0, 1.0, 1.0));
+
+        let m = blend_material(a.clone(), b.clone(), mask.clone());
+        assert!(m.casts_shadow);
+        assert!(m.receives_shadow);
+        assert_eq!(m.transparency, 0.0);
+        assert_eq!(m.refractive_index, RefractiveIndex::GLASS);
+
+        b.casts_shadow = false;
+        b.transparency = 0.5;
+        let m = blend_material(b, a, mask);
+        assert!(!m.casts_shadow);
+        assert_eq!(m.transparency, 0.5);
+        assert_eq!(m.refractive_index, RefractiveIndex::GLASS);
+    }
+
     struct MaterialFixture {
         m: Material,
         position: Point,
@@ -220,6 +645,86 @@ mod tests {
         assert_eq!(result, color(1.9, 1.9, 1.9));
     }
 
+    // Clamping the output caps a bright highlight at 1.0 per channel
+    #[rstest]
+    fn lighting_with_clamped_output(mut fix: MaterialFixture) {
+        let eyev = vector(0.0, 0.0, -1.0);
+        let normalv = vector(0.0, 0.0, -1.0);
+        let light = point_light(point(0.0, 0.0, -10.0), color(1.0, 0.5, 2.0));
+        fix.m.clamp_output = true;
+        let result = lighting(
+            &fix.m,
+            &sphere(1),
+            &Some(light),
+            &fix.position,
+            &eyev,
+            &normalv,
+            false,
+        );
+
+        // Unclamped, this would be (1.9, 0.95, 3.8)
+        assert_relative_eq!(result, color(1.0, 0.95, 1.0));
+    }
+
+    // A specular color tints the highlight, e.g. gold rather than white
+    #[rstest]
+    fn lighting_with_specular_color(mut fix: MaterialFixture) {
+        let eyev = vector(0.0, 0.0, -1.0);
+        let normalv = vector(0.0, 0.0, -1.0);
+        let light = point_light(point(0.0, 0.0, -10.0), color(1.0, 1.0, 1.0));
+        let gold = color(1.0, 0.78, 0.34);
+        fix.m.color = gold;
+        fix.m.ambient = 0.0;
+        fix.m.diffuse = 0.0;
+        let mut highlight = |specular_color| {
+            fix.m.specular_color = specular_color;
+            lighting(
+                &fix.m,
+                &sphere(1),
+                &Some(light),
+                &fix.position,
+                &eyev,
+                &normalv,
+                false,
+            )
+        };
+
+        assert_relative_eq!(highlight(None), color(0.9, 0.9, 0.9));
+        assert_relative_eq!(highlight(Some(gold)), gold * 0.9);
+    }
+
+    // A specular-only light adds a highlight without diffuse lighting, and a
+    // diffuse-only light does the reverse
+    #[rstest]
+    #[case(true, true, color(1.9, 1.9, 1.9))]
+    #[case(false, true, color(1.0, 1.0, 1.0))]
+    #[case(true, false, color(1.0, 1.0, 1.0))]
+    #[case(false, false, color(0.1, 0.1, 0.1))]
+    fn lighting_with_diffuse_or_specular_only_light(
+        fix: MaterialFixture,
+        #[case] affects_diffuse: bool,
+        #[case] affects_specular: bool,
+        #[case] expected: Color,
+    ) {
+        let eyev = vector(0.0, 0.0, -1.0);
+        let normalv = vector(0.0, 0.0, -1.0);
+        let mut light = point_light(point(0.0, 0.0, -10.0), color(1.0, 1.0, 1.0));
+        light.affects_diffuse = affects_diffuse;
+        light.affects_specular = affects_specular;
+        let result = lighting(
+            &fix.m,
+            &sphere(1),
+            &Some(light),
+            &fix.position,
+            &eyev,
+            &normalv,
+            false,
+        );
+
+        // ambient 0.1, diffuse 0.9, specular 0.9
+        assert_relative_eq!(result, expected);
+    }
+
     // Lighting with the eye between light and surface, eye offset 45 degrees
     #[rstest]
     fn lighting_with_eye_between_light_and_surface_eye_offset_45_degrees(fix: MaterialFixture) {
@@ -361,6 +866,35 @@ mod tests {
         assert_eq!(c2, color(0.0, 0.0, 0.0));
     }
 
+    // The ambient color is the ambient term of lighting, with or without a pattern
+    #[rstest]
+    fn ambient_color_matches_lighting_with_light_behind_surface(mut fix: MaterialFixture) {
+        let eyev = vector(0.0, 0.0, -1.0);
+        let normalv = vector(0.0, 0.0, -1.0);
+        let light = point_light(point(0.0, 0.0, 10.0), color(1.0, 1.0, 1.0));
+        let object = sphere(1);
+        let full =
+            |m: &Material, p: &Point| lighting(m, &object, &Some(light), p, &eyev, &normalv, false);
+
+        assert_eq!(
+            ambient_color(&fix.m, &object, &fix.position),
+            full(&fix.m, &fix.position)
+        );
+
+        fix.m.set_pattern(&stripe_pattern(
+            &color(1.0, 0.5, 0.0),
+            &color(0.0, 0.5, 1.0),
+        ));
+        fix.m.ambient = 0.3;
+        for p in [point(0.5, 0.0, 0.0), point(1.5, 0.0, 0.0)] {
+            assert_eq!(ambient_color(&fix.m, &object, &p), full(&fix.m, &p));
+        }
+        assert_ne!(
+            ambient_color(&fix.m, &object, &point(0.5, 0.0, 0.0)),
+            ambient_color(&fix.m, &object, &point(1.5, 0.0, 0.0))
+        );
+    }
+
     // Chapter 11: Reflection
 
     // Reflectivity for the default material
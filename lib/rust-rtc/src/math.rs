@@ -1,2 +1,83 @@
 pub const EPSILON: f64 = 1e-9;
 pub const MAX_RECURSIVE_DEPTH: i32 = 5;
+
+/// A pseudo-random number in [0, 1), determined entirely by `key`. Hashing (with the
+/// SplitMix64 finalizer) rather than a stateful generator means results don't depend
+/// on the order threads happen to render pixels in.
+pub fn hash_unit(key: u64) -> f64 {
+    let mut z = key.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+    // The top 53 bits fill an f64 mantissa
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// The real roots of `a t² + b t + c = 0`, smaller first, or `None` if there are
+/// none. A double root is returned twice. When `a` is (nearly) zero the equation is
+/// linear, with one root, and the other, which has gone off to infinity, is returned
+/// as `f64::INFINITY`; if `b` is (nearly) zero too, there are no roots.
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Option<(f64, f64)> {
+    if a.abs() < f64::EPSILON {
+        if b.abs() < f64::EPSILON {
+            return None;
+        }
+        return Some((-c / b, f64::INFINITY));
+    }
+
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let t0 = (-b - discriminant.sqrt()) / (2.0 * a);
+    let t1 = (-b + discriminant.sqrt()) / (2.0 * a);
+    // A negative a reverses their order
+    Some((t0.min(t1), t0.max(t1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use rstest::rstest;
+
+    // A quadratic with two real roots
+    #[rstest]
+    #[case(1.0, -3.0, 2.0, (1.0, 2.0))]
+    #[case(-1.0, 3.0, -2.0, (1.0, 2.0))]
+    #[case(2.0, 0.0, -8.0, (-2.0, 2.0))]
+    fn quadratic_with_two_roots(
+        #[case] a: f64,
+        #[case] b: f64,
+        #[case] c: f64,
+        #[case] roots: (f64, f64),
+    ) {
+        let (t0, t1) = solve_quadratic(a, b, c).unwrap();
+        assert_relative_eq!(t0, roots.0, epsilon = 1e-12);
+        assert_relative_eq!(t1, roots.1, epsilon = 1e-12);
+    }
+
+    // A quadratic with a double root returns it twice
+    #[test]
+    fn quadratic_with_double_root() {
+        assert_eq!(solve_quadratic(1.0, -4.0, 4.0), Some((2.0, 2.0)));
+    }
+
+    // A quadratic with no real roots
+    #[test]
+    fn quadratic_with_no_real_roots() {
+        assert_eq!(solve_quadratic(1.0, 0.0, 1.0), None);
+    }
+
+    // With a near-zero a, the equation is solved as linear
+    #[rstest]
+    #[case(0.0)]
+    #[case(1e-17)]
+    #[case(-1e-17)]
+    fn quadratic_with_near_zero_a(#[case] a: f64) {
+        let (t0, t1) = solve_quadratic(a, 2.0, -3.0).unwrap();
+        assert_relative_eq!(t0, 1.5, epsilon = 1e-12);
+        assert_eq!(t1, f64::INFINITY);
+        assert_eq!(solve_quadratic(a, 0.0, -3.0), None);
+    }
+}
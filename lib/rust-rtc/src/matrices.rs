@@ -1,7 +1,31 @@
 // Chapter 3: Matrices
 
 use crate::tuples::Tuple;
+use approx::{AbsDiffEq, RelativeEq};
 use glam::f64::{DMat2, DMat3, DMat4};
+use std::fmt;
+
+// Precision when none is given, e.g. by `{}` rather than `{:.5}`
+const DEFAULT_PRECISION: usize = 3;
+
+// One row per line, with values right-aligned in columns of equal width
+fn format_rows(size: usize, at: impl Fn(usize, usize) -> f64, precision: usize) -> String {
+    // Adding 0.0 turns -0.0 into 0.0
+    let cells: Vec<String> = (0..size * size)
+        .map(|i| format!("{:.*}", precision, at(i / size, i % size) + 0.0))
+        .collect();
+    let width = cells.iter().map(String::len).max().unwrap_or(0);
+    cells
+        .chunks(size)
+        .map(|row| {
+            row.iter()
+                .map(|cell| format!("{cell:>width$}"))
+                .collect::<Vec<_>>()
+                .join("  ")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
 
 #[derive(Debug, PartialEq)]
 pub struct Matrix2(DMat2);
@@ -38,6 +62,13 @@ impl Default for Matrix2 {
     }
 }
 
+impl fmt::Display for Matrix2 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let precision = f.precision().unwrap_or(DEFAULT_PRECISION);
+        f.write_str(&format_rows(2, |row, col| self.at(row, col), precision))
+    }
+}
+
 pub fn matrix2(m: &[[f64; 2]; 2]) -> Matrix2 {
     Matrix2::from_rows_array(m)
 }
@@ -69,6 +100,13 @@ impl Default for Matrix3 {
     }
 }
 
+impl fmt::Display for Matrix3 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let precision = f.precision().unwrap_or(DEFAULT_PRECISION);
+        f.write_str(&format_rows(3, |row, col| self.at(row, col), precision))
+    }
+}
+
 pub fn matrix3(m: &[[f64; 3]; 3]) -> Matrix3 {
     Matrix3::from_rows_array(m)
 }
@@ -106,6 +144,26 @@ impl Matrix4 {
         Self(self.0.inverse())
     }
 
+    /// Interpolate towards `other` by `t` (0.0 gives self, 1.0 gives other). Both
+    /// are decomposed into scale, rotation and translation: scale and translation
+    /// are interpolated linearly, and rotation by slerp, so a rotating object keeps
+    /// its size. Shearing is not preserved.
+    pub fn interpolate(&self, other: &Matrix4, t: f64) -> Matrix4 {
+        let (s0, r0, t0) = self.0.to_scale_rotation_translation();
+        let (s1, r1, t1) = other.0.to_scale_rotation_translation();
+        Self(DMat4::from_scale_rotation_translation(
+            s0.lerp(s1, t),
+            r0.slerp(r1, t),
+            t0.lerp(t1, t),
+        ))
+    }
+
+    /// For debugging: one row per line, with `precision` decimal places and the
+    /// columns aligned. `{}` gives the same with 3 decimal places, and `{:.N}` with N.
+    pub fn pretty(&self, precision: usize) -> String {
+        format_rows(4, |row, col| self.at(row, col), precision)
+    }
+
     // Fluent API support:
     pub fn then(&mut self, m: &Matrix4) -> Matrix4 {
         *self = m * *self;
@@ -125,12 +183,50 @@ pub fn inverse(m: &Matrix4) -> Matrix4 {
     m.inverse()
 }
 
+pub fn interpolate(a: &Matrix4, b: &Matrix4, t: f64) -> Matrix4 {
+    a.interpolate(b, t)
+}
+
+impl fmt::Display for Matrix4 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let precision = f.precision().unwrap_or(DEFAULT_PRECISION);
+        f.write_str(&self.pretty(precision))
+    }
+}
+
 impl Default for Matrix4 {
     fn default() -> Self {
         Self(DMat4::IDENTITY)
     }
 }
 
+// Approximate equality, for assert_relative_eq! and friends
+impl AbsDiffEq for Matrix4 {
+    type Epsilon = f64;
+
+    fn default_epsilon() -> f64 {
+        f64::default_epsilon()
+    }
+
+    fn abs_diff_eq(&self, other: &Self, epsilon: f64) -> bool {
+        self.0.abs_diff_eq(other.0, epsilon)
+    }
+}
+
+impl RelativeEq for Matrix4 {
+    fn default_max_relative() -> f64 {
+        f64::default_max_relative()
+    }
+
+    fn relative_eq(&self, other: &Self, epsilon: f64, max_relative: f64) -> bool {
+        self.0
+            .to_cols_array()
+            .iter()
+            .zip(other.0.to_cols_array().iter())
+            .all(|(a, b)| f64::relative_eq(a, b, epsilon, max_relative))
+    }
+}
+
 macro_rules! matrix4_mul {
     ( $lhs:ty , $rhs:ty ) => {
         impl std::ops::Mul<$rhs> for $lhs {
@@ -177,73 +273,7 @@ mod tests {
 
     use super::*;
     use crate::tuples::tuple;
-    use approx::{assert_relative_eq, AbsDiffEq, RelativeEq};
-
-    // wrap DVec4 so that we can define f64::relative_eq! for it
-    #[derive(PartialEq)]
-    struct DVec4Wrap(glam::f64::DVec4);
-
-    impl AbsDiffEq for DVec4Wrap {
-        type Epsilon = f64;
-
-        fn default_epsilon() -> f64 {
-            f64::default_epsilon()
-        }
-        fn abs_diff_eq(&self, other: &Self, epsilon: f64) -> bool {
-            self.0.abs_diff_eq(other.0, epsilon)
-        }
-    }
-    impl RelativeEq for DVec4Wrap {
-        fn default_max_relative() -> f64 {
-            f64::default_max_relative()
-        }
-
-        fn relative_eq(&self, other: &Self, epsilon: f64, max_relative: f64) -> bool {
-            f64::relative_eq(&self.0.x, &other.0.x, epsilon, max_relative)
-        }
-    }
-
-    impl AbsDiffEq for Matrix4 {
-        type Epsilon = f64;
-
-        fn default_epsilon() -> f64 {
-            f64::default_epsilon()
-        }
-
-        fn abs_diff_eq(&self, other: &Self, epsilon: f64) -> bool {
-            self.0.abs_diff_eq(other.0, epsilon)
-        }
-    }
-
-    impl RelativeEq for Matrix4 {
-        fn default_max_relative() -> f64 {
-            f64::default_max_relative()
-        }
-
-        fn relative_eq(&self, other: &Self, epsilon: f64, max_relative: f64) -> bool {
-            DVec4Wrap::relative_eq(
-                &DVec4Wrap(self.0.x_axis),
-                &DVec4Wrap(other.0.x_axis),
-                epsilon,
-                max_relative,
-            ) && DVec4Wrap::relative_eq(
-                &DVec4Wrap(self.0.y_axis),
-                &DVec4Wrap(other.0.y_axis),
-                epsilon,
-                max_relative,
-            ) && DVec4Wrap::relative_eq(
-                &DVec4Wrap(self.0.z_axis),
-                &DVec4Wrap(other.0.z_axis),
-                epsilon,
-                max_relative,
-            ) && DVec4Wrap::relative_eq(
-                &DVec4Wrap(self.0.w_axis),
-                &DVec4Wrap(other.0.w_axis),
-                epsilon,
-                max_relative,
-            )
-        }
-    }
+    use approx::assert_relative_eq;
 
     #[test]
     fn default_matrix2_is_identity() {
@@ -720,4 +750,29 @@ mod tests {
         let C = A * B;
         assert_relative_eq!(C * B.inverse(), A, epsilon=1e-5);
     }
+
+    // Displaying a matrix aligns its columns
+    #[test]
+    fn displaying_identity_matrix() {
+        let expected = "1.000  0.000  0.000  0.000\n\
+                        0.000  1.000  0.000  0.000\n\
+                        0.000  0.000  1.000  0.000\n\
+                        0.000  0.000  0.000  1.000";
+        assert_eq!(identity4().to_string(), expected);
+        assert_eq!(identity4().pretty(3), expected);
+    }
+
+    // Displaying a matrix with a given precision, aligning negative values
+    #[test]
+    fn displaying_matrix_with_precision() {
+        let A = matrix3(&[[1.0, -2.5, 0.0], [-0.0, 10.0, 3.25], [0.5, 0.0, -100.0]]);
+        assert_eq!(
+            format!("{A:.1}"),
+            "   1.0    -2.5     0.0\n   0.0    10.0     3.2\n   0.5     0.0  -100.0"
+        );
+        assert_eq!(
+            matrix2(&[[1.0, 2.0], [3.0, 4.0]]).to_string(),
+            "1.000  2.000\n3.000  4.000"
+        );
+    }
 }
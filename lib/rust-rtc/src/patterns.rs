@@ -1,16 +1,32 @@
 // Chapter 10 - Patterns
 
+use crate::canvas::{Canvas, TextureFilter};
 use crate::colors::{linear_blend, Color, WHITE};
+use crate::cubes::{cube_uv, CubeFace};
 use crate::matrices::Matrix4;
 use crate::perlin_noise;
+use crate::rays::Footprint;
 use crate::shapes::Shape;
-use crate::tuples::{point, Point};
+use crate::tuples::{dot, magnitude, normalize, point, Point, Vector};
+use std::sync::Arc;
+
+/// The space a pattern is fixed in, before the pattern's own transform is applied.
+#[derive(Debug, PartialEq, Default, Copy, Clone)]
+pub enum PatternSpace {
+    /// The pattern moves with the object it's applied to.
+    #[default]
+    Object,
+    /// The pattern stays put, and moving an object reveals a different part of it.
+    World,
+}
 
 #[derive(Debug, PartialEq, Default, Clone)]
 pub struct Pattern {
     pattern: PatternEnum,
     transform: Matrix4,
     inverse_transform: Matrix4,
+    offset: Vector, // phase shift, in pattern space
+    space: PatternSpace,
 }
 
 impl Pattern {
@@ -23,30 +39,143 @@ impl Pattern {
         &self.inverse_transform
     }
 
+    /// Shift the pattern by `offset`, measured in pattern space (i.e. in tiles),
+    /// after the pattern transform has been applied. Useful to move a tile
+    /// boundary away from an object seam without composing a translation.
+    pub fn set_offset(&mut self, offset: &Vector) {
+        self.offset = *offset;
+    }
+
+    pub fn offset(&self) -> &Vector {
+        &self.offset
+    }
+
+    pub fn set_space(&mut self, space: PatternSpace) {
+        self.space = space;
+    }
+
+    pub fn space(&self) -> PatternSpace {
+        self.space
+    }
+
+    // Fluent API support:
+    pub fn with_offset(mut self, offset: &Vector) -> Pattern {
+        self.set_offset(offset);
+        self
+    }
+
+    pub fn with_space(mut self, space: PatternSpace) -> Pattern {
+        self.set_space(space);
+        self
+    }
+
+    pub fn kind(&self) -> PatternKind {
+        match self.pattern {
+            PatternEnum::TestPattern(_) => PatternKind::Test,
+            PatternEnum::SolidPattern(_) => PatternKind::Solid,
+            PatternEnum::StripePattern(_) => PatternKind::Stripe,
+            PatternEnum::GradientPattern(_) => PatternKind::Gradient,
+            PatternEnum::LinearGradientPattern(_) => PatternKind::LinearGradient,
+            PatternEnum::RingPattern(_) => PatternKind::Ring,
+            PatternEnum::CheckersPattern(_) => PatternKind::Checkers,
+            PatternEnum::RadialGradientPattern(_) => PatternKind::RadialGradient,
+            PatternEnum::BlendedPattern(_) => PatternKind::Blended,
+            PatternEnum::PerturbedPattern(_) => PatternKind::Perturbed,
+            PatternEnum::ImagePattern(_) => PatternKind::Image,
+            PatternEnum::CubeMapPattern(_) => PatternKind::CubeMap,
+            PatternEnum::HorizonFadePattern(_) => PatternKind::HorizonFade,
+        }
+    }
+
+    /// The two sub-patterns of a pattern that alternates or mixes between them,
+    /// e.g. stripes, or None for other kinds of pattern.
+    pub fn colors(&self) -> Option<(&Pattern, &Pattern)> {
+        match &self.pattern {
+            PatternEnum::StripePattern(pattern) => Some(pattern.colors()),
+            PatternEnum::GradientPattern(pattern) => Some(pattern.colors()),
+            PatternEnum::LinearGradientPattern(pattern) => Some(pattern.colors()),
+            PatternEnum::RingPattern(pattern) => Some(pattern.colors()),
+            PatternEnum::CheckersPattern(pattern) => Some(pattern.colors()),
+            PatternEnum::RadialGradientPattern(pattern) => Some(pattern.colors()),
+            PatternEnum::BlendedPattern(pattern) => Some(pattern.colors()),
+            _ => None,
+        }
+    }
+
     pub fn pattern_at(&self, object_point: &Point) -> Color {
         // Convert object-space point to pattern-space point:
-        let pattern_point = self.inverse_transform * object_point;
+        let pattern_point = self.inverse_transform * object_point - self.offset;
         self.pattern.pattern_at(&pattern_point)
     }
 
-    pub fn pattern_at_shape(&self, shape: &Shape, world_point: &Point) -> Color {
-        // Convert world-space point to object-space point:
-        let object_point = shape.inverse_transform() * world_point;
-        self.pattern_at(&object_point)
+    /// As `pattern_at`, but averaged over the pixel's footprint on the surface, in
+    /// object space, by patterns that support filtering.
+    pub fn pattern_at_filtered(
+        &self,
+        object_point: &Point,
+        footprint: Option<&Footprint>,
+    ) -> Color {
+        match footprint {
+            Some(footprint) => {
+                let pattern_point = self.inverse_transform * object_point - self.offset;
+                let footprint = footprint.transform(&self.inverse_transform);
+                self.pattern.pattern_at_filtered(&pattern_point, &footprint)
+            }
+            None => self.pattern_at(object_point),
+        }
+    }
+
+    pub fn pattern_at_shape(
+        &self,
+        shape: &Shape,
+        world_point: &Point,
+        footprint: Option<&Footprint>,
+    ) -> Color {
+        match self.space {
+            PatternSpace::Object => {
+                // Convert world-space point to object-space point:
+                let object_point = shape.world_inverse_transform() * world_point;
+                let footprint = footprint.map(|f| f.transform(shape.world_inverse_transform()));
+                self.pattern_at_filtered(&object_point, footprint.as_ref())
+            }
+            PatternSpace::World => self.pattern_at_filtered(world_point, footprint),
+        }
     }
 }
 
+/// Which kind of pattern a `Pattern` is, without its parameters.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum PatternKind {
+    Test,
+    Solid,
+    Stripe,
+    Gradient,
+    LinearGradient,
+    Ring,
+    Checkers,
+    RadialGradient,
+    Blended,
+    Perturbed,
+    Image,
+    CubeMap,
+    HorizonFade,
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum PatternEnum {
     TestPattern(TestPattern),
     SolidPattern(SolidPattern),
     StripePattern(StripePattern),
     GradientPattern(GradientPattern),
+    LinearGradientPattern(LinearGradientPattern),
     RingPattern(RingPattern),
     CheckersPattern(CheckersPattern),
     RadialGradientPattern(RadialGradientPattern),
     BlendedPattern(BlendedPattern),
     PerturbedPattern(PerturbedPattern),
+    ImagePattern(ImagePattern),
+    CubeMapPattern(CubeMapPattern),
+    HorizonFadePattern(HorizonFadePattern),
 }
 
 impl Default for PatternEnum {
@@ -57,6 +186,11 @@ impl Default for PatternEnum {
 
 trait PatternTrait {
     fn pattern_at(&self, local_point: &Point) -> Color;
+
+    // Patterns without detail to filter are simply point-sampled
+    fn pattern_at_filtered(&self, local_point: &Point, _footprint: &Footprint) -> Color {
+        self.pattern_at(local_point)
+    }
 }
 
 impl PatternTrait for PatternEnum {
@@ -66,11 +200,30 @@ impl PatternTrait for PatternEnum {
             PatternEnum::SolidPattern(pattern) => pattern.pattern_at(local_point),
             PatternEnum::StripePattern(pattern) => pattern.pattern_at(local_point),
             PatternEnum::GradientPattern(pattern) => pattern.pattern_at(local_point),
+            PatternEnum::LinearGradientPattern(pattern) => pattern.pattern_at(local_point),
             PatternEnum::RingPattern(pattern) => pattern.pattern_at(local_point),
             PatternEnum::CheckersPattern(pattern) => pattern.pattern_at(local_point),
             PatternEnum::RadialGradientPattern(pattern) => pattern.pattern_at(local_point),
             PatternEnum::BlendedPattern(pattern) => pattern.pattern_at(local_point),
             PatternEnum::PerturbedPattern(pattern) => pattern.pattern_at(local_point),
+            PatternEnum::ImagePattern(pattern) => pattern.pattern_at(local_point),
+            PatternEnum::CubeMapPattern(pattern) => pattern.pattern_at(local_point),
+            PatternEnum::HorizonFadePattern(pattern) => pattern.pattern_at(local_point),
+        }
+    }
+
+    fn pattern_at_filtered(&self, local_point: &Point, footprint: &Footprint) -> Color {
+        match self {
+            PatternEnum::CheckersPattern(pattern) => {
+                pattern.pattern_at_filtered(local_point, footprint)
+            }
+            PatternEnum::ImagePattern(pattern) => {
+                pattern.pattern_at_filtered(local_point, footprint)
+            }
+            PatternEnum::HorizonFadePattern(pattern) => {
+                pattern.pattern_at_filtered(local_point, footprint)
+            }
+            _ => self.pattern_at(local_point),
         }
     }
 }
@@ -79,8 +232,13 @@ pub fn pattern_at(pattern: &Pattern, object_point: &Point) -> Color {
     pattern.pattern_at(object_point)
 }
 
-pub fn pattern_at_shape(pattern: &Pattern, shape: &Shape, world_point: &Point) -> Color {
-    pattern.pattern_at_shape(shape, world_point)
+pub fn pattern_at_shape(
+    pattern: &Pattern,
+    shape: &Shape,
+    world_point: &Point,
+    footprint: Option<&Footprint>,
+) -> Color {
+    pattern.pattern_at_shape(shape, world_point, footprint)
 }
 
 impl From<&Color> for Pattern {
@@ -173,16 +331,18 @@ impl StripePattern {
             b: Box::new(b.into()),
         }
     }
+
+    pub fn colors(&self) -> (&Pattern, &Pattern) {
+        (&self.a, &self.b)
+    }
 }
 
 impl PatternTrait for StripePattern {
     fn pattern_at(&self, local_point: &Point) -> Color {
         if local_point.x().floor() as i32 % 2 == 0 {
-            let pattern_point = self.a.inverse_transform() * local_point;
-            self.a.pattern.pattern_at(&pattern_point)
+            self.a.pattern_at(local_point)
         } else {
-            let pattern_point = self.b.inverse_transform() * local_point;
-            self.b.pattern.pattern_at(&pattern_point)
+            self.b.pattern_at(local_point)
         }
     }
 }
@@ -194,14 +354,14 @@ impl Pattern {
         U: Into<Pattern>,
     {
         Pattern {
-            pattern: PatternEnum::StripePattern(StripePattern::new(&a.into(), &b.into())),
+            pattern: PatternEnum::StripePattern(StripePattern::new(a.into(), b.into())),
             ..Default::default()
         }
     }
 }
 
 pub fn stripe_pattern<T: Into<Pattern>, U: Into<Pattern>>(a: T, b: U) -> Pattern {
-    Pattern::stripe_pattern(&a.into(), &b.into())
+    Pattern::stripe_pattern(a.into(), b.into())
 }
 
 // ------[ GradientPattern ]------
@@ -218,16 +378,18 @@ impl GradientPattern {
             b: Box::new(b.into()),
         }
     }
+
+    pub fn colors(&self) -> (&Pattern, &Pattern) {
+        (&self.a, &self.b)
+    }
 }
 
 impl PatternTrait for GradientPattern {
     fn pattern_at(&self, local_point: &Point) -> Color {
-        let pattern_point_a = self.a.inverse_transform() * local_point;
-        let pattern_point_b = self.b.inverse_transform() * local_point;
         linear_blend(
             local_point.x(),
-            &self.a.pattern.pattern_at(&pattern_point_a),
-            &self.b.pattern.pattern_at(&pattern_point_b),
+            &self.a.pattern_at(local_point),
+            &self.b.pattern_at(local_point),
         )
     }
 }
@@ -239,14 +401,79 @@ impl Pattern {
         U: Into<Pattern>,
     {
         Pattern {
-            pattern: PatternEnum::GradientPattern(GradientPattern::new(&a.into(), &b.into())),
+            pattern: PatternEnum::GradientPattern(GradientPattern::new(a.into(), b.into())),
             ..Default::default()
         }
     }
 }
 
 pub fn gradient_pattern<T: Into<Pattern>, U: Into<Pattern>>(a: T, b: U) -> Pattern {
-    Pattern::gradient_pattern(&a.into(), &b.into())
+    Pattern::gradient_pattern(a.into(), b.into())
+}
+
+// ------[ LinearGradientPattern ]------
+// As GradientPattern, but along any axis, e.g. vertically, rather than along x.
+#[derive(Debug, PartialEq, Clone)]
+pub struct LinearGradientPattern {
+    a: Box<Pattern>,
+    b: Box<Pattern>,
+    axis: Vector, // unit length
+}
+
+impl LinearGradientPattern {
+    pub fn new<T: Into<Pattern>, U: Into<Pattern>>(
+        a: T,
+        b: U,
+        axis: &Vector,
+    ) -> LinearGradientPattern {
+        LinearGradientPattern {
+            a: Box::new(a.into()),
+            b: Box::new(b.into()),
+            axis: normalize(axis),
+        }
+    }
+
+    pub fn colors(&self) -> (&Pattern, &Pattern) {
+        (&self.a, &self.b)
+    }
+
+    pub fn axis(&self) -> &Vector {
+        &self.axis
+    }
+}
+
+impl PatternTrait for LinearGradientPattern {
+    fn pattern_at(&self, local_point: &Point) -> Color {
+        let distance = dot(&(local_point - point(0.0, 0.0, 0.0)), &self.axis);
+        linear_blend(
+            distance,
+            &self.a.pattern_at(local_point),
+            &self.b.pattern_at(local_point),
+        )
+    }
+}
+
+impl Pattern {
+    /// A gradient from `a` to `b`, repeating every unit along `axis`, which needn't
+    /// be normalized, but mustn't be zero.
+    pub fn linear_gradient_pattern<T, U>(a: T, b: U, axis: &Vector) -> Pattern
+    where
+        T: Into<Pattern>,
+        U: Into<Pattern>,
+    {
+        Pattern {
+            pattern: PatternEnum::LinearGradientPattern(LinearGradientPattern::new(a, b, axis)),
+            ..Default::default()
+        }
+    }
+}
+
+pub fn linear_gradient_pattern<T: Into<Pattern>, U: Into<Pattern>>(
+    a: T,
+    b: U,
+    axis: &Vector,
+) -> Pattern {
+    Pattern::linear_gradient_pattern(a.into(), b.into(), axis)
 }
 
 // ------[ RingPattern ]------
@@ -263,6 +490,10 @@ impl RingPattern {
             b: Box::new(b.into()),
         }
     }
+
+    pub fn colors(&self) -> (&Pattern, &Pattern) {
+        (&self.a, &self.b)
+    }
 }
 
 impl PatternTrait for RingPattern {
@@ -270,11 +501,9 @@ impl PatternTrait for RingPattern {
         let distance =
             f64::sqrt(local_point.x() * local_point.x() + local_point.z() * local_point.z());
         if distance.floor() as i32 % 2 == 0 {
-            let pattern_point_a = self.a.inverse_transform() * local_point;
-            self.a.pattern.pattern_at(&pattern_point_a)
+            self.a.pattern_at(local_point)
         } else {
-            let pattern_point_b = self.b.inverse_transform() * local_point;
-            self.b.pattern.pattern_at(&pattern_point_b)
+            self.b.pattern_at(local_point)
         }
     }
 }
@@ -293,7 +522,7 @@ impl Pattern {
 }
 
 pub fn ring_pattern<T: Into<Pattern>, U: Into<Pattern>>(a: T, b: U) -> Pattern {
-    Pattern::ring_pattern(&a.into(), &b.into())
+    Pattern::ring_pattern(a.into(), b.into())
 }
 
 // ------[ CheckersPattern ]------
@@ -310,18 +539,55 @@ impl CheckersPattern {
             b: Box::new(b.into()),
         }
     }
+
+    pub fn colors(&self) -> (&Pattern, &Pattern) {
+        (&self.a, &self.b)
+    }
 }
 
 impl PatternTrait for CheckersPattern {
     fn pattern_at(&self, local_point: &Point) -> Color {
+        // A sum of whole numbers, so its parity is exact, on either side of the origin
+        // and beyond the range of an i32
         let sum = local_point.x().floor() + local_point.y().floor() + local_point.z().floor();
-        if sum.floor() as i32 % 2 == 0 {
-            let pattern_point = self.a.inverse_transform() * local_point;
-            self.a.pattern.pattern_at(&pattern_point)
+        if sum.rem_euclid(2.0) == 0.0 {
+            self.a.pattern_at(local_point)
         } else {
-            let pattern_point = self.b.inverse_transform() * local_point;
-            self.b.pattern.pattern_at(&pattern_point)
+            self.b.pattern_at(local_point)
+        }
+    }
+
+    // Box filter the checkers over the footprint. As +1/-1 for a/b, the checkers
+    // are the product of a square wave along each axis, so the filter is separable.
+    fn pattern_at_filtered(&self, local_point: &Point, footprint: &Footprint) -> Color {
+        // Integral of the square wave (-1)^floor(x) from 0 to x
+        let integral = |x: f64| {
+            let f = x - x.floor();
+            if x.floor().rem_euclid(2.0) == 0.0 {
+                f
+            } else {
+                1.0 - f
+            }
+        };
+
+        let mut weight = 1.0;
+        for axis in 0..3 {
+            let x = local_point.at(axis).expect("valid axis");
+            let w = footprint.width(axis);
+            weight *= if w < 1e-9 {
+                if x.floor().rem_euclid(2.0) == 0.0 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            } else {
+                (integral(x + w / 2.0) - integral(x - w / 2.0)) / w
+            };
         }
+
+        let a = self.a.pattern_at_filtered(local_point, Some(footprint));
+        let b = self.b.pattern_at_filtered(local_point, Some(footprint));
+        a * ((1.0 + weight) / 2.0) + b * ((1.0 - weight) / 2.0)
     }
 }
 
@@ -339,7 +605,7 @@ impl Pattern {
 }
 
 pub fn checkers_pattern<T: Into<Pattern>, U: Into<Pattern>>(a: T, b: U) -> Pattern {
-    Pattern::checkers_pattern(&a.into(), &b.into())
+    Pattern::checkers_pattern(a.into(), b.into())
 }
 
 // ------[ RadialGradientPattern ]------
@@ -362,6 +628,14 @@ impl RadialGradientPattern {
             y_factor,
         }
     }
+
+    pub fn colors(&self) -> (&Pattern, &Pattern) {
+        (&self.a, &self.b)
+    }
+
+    pub fn y_factor(&self) -> f64 {
+        self.y_factor
+    }
 }
 
 impl PatternTrait for RadialGradientPattern {
@@ -371,12 +645,10 @@ impl PatternTrait for RadialGradientPattern {
                 + self.y_factor * local_point.y() * local_point.y()
                 + local_point.z() * local_point.z(),
         );
-        let pattern_point_a = self.a.inverse_transform() * local_point;
-        let pattern_point_b = self.b.inverse_transform() * local_point;
         linear_blend(
             distance,
-            &self.a.pattern.pattern_at(&pattern_point_a),
-            &self.b.pattern.pattern_at(&pattern_point_b),
+            &self.a.pattern_at(local_point),
+            &self.b.pattern_at(local_point),
         )
     }
 }
@@ -403,7 +675,7 @@ pub fn radial_gradient_pattern<T: Into<Pattern>, U: Into<Pattern>>(
     b: U,
     y_factor: f64,
 ) -> Pattern {
-    Pattern::radial_gradient_pattern(&a.into(), &b.into(), y_factor)
+    Pattern::radial_gradient_pattern(a.into(), b.into(), y_factor)
 }
 
 // ------[ BlendedPattern ]------
@@ -421,14 +693,16 @@ impl BlendedPattern {
             b: Box::new(b.into()),
         }
     }
+
+    pub fn colors(&self) -> (&Pattern, &Pattern) {
+        (&self.a, &self.b)
+    }
 }
 
 impl PatternTrait for BlendedPattern {
     fn pattern_at(&self, local_point: &Point) -> Color {
-        let pattern_point_a = self.a.inverse_transform() * local_point;
-        let pattern_point_b = self.b.inverse_transform() * local_point;
-        let color_a = self.a.pattern.pattern_at(&pattern_point_a);
-        let color_b = self.b.pattern.pattern_at(&pattern_point_b);
+        let color_a = self.a.pattern_at(local_point);
+        let color_b = self.b.pattern_at(local_point);
         (color_a + color_b) / 2.0
     }
 }
@@ -447,7 +721,7 @@ impl Pattern {
 }
 
 pub fn blended_pattern<T: Into<Pattern>, U: Into<Pattern>>(a: T, b: U) -> Pattern {
-    Pattern::blended_pattern(&a.into(), &b.into())
+    Pattern::blended_pattern(a.into(), b.into())
 }
 
 // ------[ PerturbedPattern ]------
@@ -514,8 +788,7 @@ impl PatternTrait for PerturbedPattern {
             ) * self.scale;
         let perturbed_point = point(new_x, new_y, new_z);
 
-        let pattern_point = self.a.inverse_transform() * perturbed_point;
-        self.a.pattern.pattern_at(&pattern_point)
+        self.a.pattern_at(&perturbed_point)
     }
 }
 
@@ -542,19 +815,212 @@ pub fn perturbed_pattern<T: Into<Pattern>>(
     num_octaves: u32,
     persistence: f64,
 ) -> Pattern {
-    Pattern::perturbed_pattern(&a.into(), scale, num_octaves, persistence)
+    Pattern::perturbed_pattern(a.into(), scale, num_octaves, persistence)
+}
+
+// ------[ ImagePattern ]------
+// Planar mapping of an image onto the XZ plane, repeating every unit in X and Z.
+// The image is shared, so cloning the pattern (and its material) is cheap.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ImagePattern {
+    image: Arc<Canvas>,
+    filter: TextureFilter,
+}
+
+impl ImagePattern {
+    pub fn new(image: Canvas, filter: TextureFilter) -> ImagePattern {
+        ImagePattern {
+            image: Arc::new(image),
+            filter,
+        }
+    }
+}
+
+impl PatternTrait for ImagePattern {
+    fn pattern_at(&self, local_point: &Point) -> Color {
+        let u = local_point.x().rem_euclid(1.0);
+        let v = local_point.z().rem_euclid(1.0);
+        self.image.sample(u, v, self.filter)
+    }
+
+    // Average a grid of samples across the footprint, with roughly one sample
+    // per texel it covers
+    fn pattern_at_filtered(&self, local_point: &Point, footprint: &Footprint) -> Color {
+        let texels = f64::max(
+            footprint.width(0) * self.image.width as f64,
+            footprint.width(2) * self.image.height as f64,
+        );
+        let n = (texels.ceil() as usize).clamp(1, 8);
+        if n == 1 {
+            return self.pattern_at(local_point);
+        }
+
+        let mut sum = Color::new(0.0, 0.0, 0.0);
+        for i in 0..n {
+            for j in 0..n {
+                let a = (i as f64 + 0.5) / n as f64 - 0.5;
+                let b = (j as f64 + 0.5) / n as f64 - 0.5;
+                sum += self.pattern_at(&(local_point + footprint.dpdx * a + footprint.dpdy * b));
+            }
+        }
+        sum / (n * n) as f64
+    }
+}
+
+impl Pattern {
+    pub fn image_pattern(image: Canvas, filter: TextureFilter) -> Pattern {
+        Pattern {
+            pattern: PatternEnum::ImagePattern(ImagePattern::new(image, filter)),
+            ..Default::default()
+        }
+    }
+}
+
+pub fn image_pattern(image: Canvas, filter: TextureFilter) -> Pattern {
+    Pattern::image_pattern(image, filter)
+}
+
+// ------[ CubeMapPattern ]------
+// A separate pattern on each face of a cube, e.g. an image decal. Each face's
+// pattern is evaluated at (u, 0, v), using the face's planar mapping, so an
+// ImagePattern covers the face exactly once.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CubeMapPattern {
+    faces: [Box<Pattern>; 6], // indexed by CubeFace
+}
+
+impl CubeMapPattern {
+    pub fn new(
+        left: Pattern,
+        front: Pattern,
+        right: Pattern,
+        back: Pattern,
+        up: Pattern,
+        down: Pattern,
+    ) -> Self {
+        CubeMapPattern {
+            faces: [left, front, right, back, up, down].map(Box::new),
+        }
+    }
+
+    pub fn face(&self, face: CubeFace) -> &Pattern {
+        &self.faces[face.index()]
+    }
+}
+
+impl PatternTrait for CubeMapPattern {
+    fn pattern_at(&self, local_point: &Point) -> Color {
+        let (face, u, v) = cube_uv(local_point);
+        self.face(face).pattern_at(&point(u, 0.0, v))
+    }
+}
+
+impl Pattern {
+    pub fn cube_map_pattern(
+        left: Pattern,
+        front: Pattern,
+        right: Pattern,
+        back: Pattern,
+        up: Pattern,
+        down: Pattern,
+    ) -> Pattern {
+        Pattern {
+            pattern: PatternEnum::CubeMapPattern(CubeMapPattern::new(
+                left, front, right, back, up, down,
+            )),
+            ..Default::default()
+        }
+    }
+}
+
+pub fn cube_map_pattern(
+    left: Pattern,
+    front: Pattern,
+    right: Pattern,
+    back: Pattern,
+    up: Pattern,
+    down: Pattern,
+) -> Pattern {
+    Pattern::cube_map_pattern(left, front, right, back, up, down)
+}
+
+// ------[ HorizonFadePattern ]------
+// Fades a pattern to a solid color with distance from the origin, e.g. so a checkered
+// floor meets the horizon without a band of aliasing.
+#[derive(Debug, PartialEq, Clone)]
+pub struct HorizonFadePattern {
+    inner: Box<Pattern>,
+    color: Color,
+    distance: f64, // beyond which only the color is seen
+}
+
+impl HorizonFadePattern {
+    pub fn new<T: Into<Pattern>>(inner: T, color: &Color, distance: f64) -> HorizonFadePattern {
+        HorizonFadePattern {
+            inner: Box::new(inner.into()),
+            color: *color,
+            distance,
+        }
+    }
+
+    // Weight of the fade color: 0 up to half the distance, rising smoothly to 1
+    fn fade(&self, local_point: &Point) -> f64 {
+        let d = magnitude(&(local_point - point(0.0, 0.0, 0.0)));
+        let t = ((d / self.distance - 0.5) * 2.0).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl PatternTrait for HorizonFadePattern {
+    fn pattern_at(&self, local_point: &Point) -> Color {
+        let fade = self.fade(local_point);
+        if fade >= 1.0 {
+            return self.color;
+        }
+        self.inner.pattern_at(local_point) * (1.0 - fade) + self.color * fade
+    }
+
+    fn pattern_at_filtered(&self, local_point: &Point, footprint: &Footprint) -> Color {
+        let fade = self.fade(local_point);
+        if fade >= 1.0 {
+            return self.color;
+        }
+        let inner = self.inner.pattern_at_filtered(local_point, Some(footprint));
+        inner * (1.0 - fade) + self.color * fade
+    }
+}
+
+impl Pattern {
+    /// `inner`, fading to `color` between half `distance` and `distance` from the
+    /// pattern's origin. The distance is measured in world space, so the inner pattern
+    /// is also evaluated in world space. Translate the pattern to move the origin,
+    /// e.g. to below the camera.
+    pub fn horizon_fade<T: Into<Pattern>>(inner: T, color: &Color, distance: f64) -> Pattern {
+        Pattern {
+            pattern: PatternEnum::HorizonFadePattern(HorizonFadePattern::new(
+                inner, color, distance,
+            )),
+            space: PatternSpace::World,
+            ..Default::default()
+        }
+    }
+}
+
+pub fn horizon_fade<T: Into<Pattern>>(inner: T, color: &Color, distance: f64) -> Pattern {
+    Pattern::horizon_fade(inner.into(), color, distance)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::canvas::canvas;
+    use crate::canvas::{canvas, write_pixel};
     use crate::colors::{color, BLACK, GREEN, GREY50, RED, WHITE};
     use crate::math::EPSILON;
-    use crate::shapes::sphere;
+    use crate::shapes::{plane, sphere};
     use crate::transformations::{rotation_y, scaling, translation};
-    use crate::tuples::point;
+    use crate::tuples::{point, vector};
     use approx::assert_relative_eq;
+    use rstest::rstest;
     use std::f64::consts::PI;
 
     fn dump_pattern(pattern: &Pattern, filename: &str, size: u32, scale: f64) {
@@ -567,7 +1033,46 @@ mod tests {
                 image.write_pixel(x, y, &v);
             }
         }
-        image.to_ppm_file(filename);
+        image
+            .to_ppm_file(filename)
+            .expect("should write pattern image");
+    }
+
+    // A stripe pattern reports its sub-patterns
+    #[test]
+    fn stripe_pattern_reports_its_colors() {
+        let pattern = stripe_pattern(&WHITE, &BLACK);
+        assert_eq!(pattern.kind(), PatternKind::Stripe);
+        let (a, b) = pattern.colors().expect("stripes should have two colors");
+        assert_eq!(a, &solid_pattern(&WHITE));
+        assert_eq!(b, &solid_pattern(&BLACK));
+    }
+
+    // Sub-patterns of each kind of pattern that has them
+    #[rstest]
+    #[case(gradient_pattern(&WHITE, &BLACK), PatternKind::Gradient)]
+    #[case(
+        linear_gradient_pattern(&WHITE, &BLACK, &vector(0.0, 1.0, 0.0)),
+        PatternKind::LinearGradient
+    )]
+    #[case(ring_pattern(&WHITE, &BLACK), PatternKind::Ring)]
+    #[case(checkers_pattern(&WHITE, &BLACK), PatternKind::Checkers)]
+    #[case(radial_gradient_pattern(&WHITE, &BLACK, 0.0), PatternKind::RadialGradient)]
+    #[case(blended_pattern(&WHITE, &BLACK), PatternKind::Blended)]
+    fn patterns_report_their_colors(#[case] pattern: Pattern, #[case] kind: PatternKind) {
+        assert_eq!(pattern.kind(), kind);
+        assert_eq!(
+            pattern.colors(),
+            Some((&solid_pattern(&WHITE), &solid_pattern(&BLACK)))
+        );
+    }
+
+    // A solid pattern has no sub-patterns
+    #[test]
+    fn solid_pattern_has_no_colors() {
+        let pattern = solid_pattern(&RED);
+        assert_eq!(pattern.kind(), PatternKind::Solid);
+        assert_eq!(pattern.colors(), None);
     }
 
     // Creating a solid pattern
@@ -653,7 +1158,7 @@ mod tests {
         let mut shape = sphere(1);
         shape.set_transform(&scaling(2.0, 2.0, 2.0));
         let pattern = stripe_pattern(&WHITE, &BLACK);
-        let c = pattern_at_shape(&pattern, &shape, &point(1.5, 0.0, 0.0));
+        let c = pattern_at_shape(&pattern, &shape, &point(1.5, 0.0, 0.0), None);
         assert_eq!(c, WHITE);
     }
 
@@ -663,7 +1168,7 @@ mod tests {
         let shape = sphere(1);
         let mut pattern = stripe_pattern(&WHITE, &BLACK);
         pattern.set_transform(&scaling(2.0, 2.0, 2.0));
-        let c = pattern_at_shape(&pattern, &shape, &point(1.5, 0.0, 0.0));
+        let c = pattern_at_shape(&pattern, &shape, &point(1.5, 0.0, 0.0), None);
         assert_eq!(c, WHITE);
 
         dump_pattern(
@@ -681,10 +1186,32 @@ mod tests {
         shape.set_transform(&scaling(2.0, 2.0, 2.0));
         let mut pattern = stripe_pattern(&WHITE, &BLACK);
         pattern.set_transform(&translation(0.5, 0.0, 0.0));
-        let c = pattern_at_shape(&pattern, &shape, &point(2.5, 0.0, 0.0));
+        let c = pattern_at_shape(&pattern, &shape, &point(2.5, 0.0, 0.0), None);
         assert_eq!(c, WHITE);
     }
 
+    // Offsetting a stripe pattern swaps the color at the origin
+    #[test]
+    fn offsetting_stripe_pattern_swaps_color_at_origin() {
+        let pattern = stripe_pattern(&WHITE, &BLACK);
+        assert_eq!(pattern_at(&pattern, &point(0.0, 0.0, 0.0)), WHITE);
+
+        let pattern = pattern.with_offset(&vector(0.5, 0.0, 0.0));
+        assert_eq!(pattern_at(&pattern, &point(0.0, 0.0, 0.0)), BLACK);
+        assert_eq!(pattern_at(&pattern, &point(0.6, 0.0, 0.0)), WHITE);
+    }
+
+    // A pattern offset composes with the pattern transformation
+    #[test]
+    fn pattern_offset_composes_with_transformation() {
+        let mut pattern = stripe_pattern(&WHITE, &BLACK);
+        pattern.set_transform(&scaling(2.0, 2.0, 2.0));
+        pattern.set_offset(&vector(0.5, 0.0, 0.0));
+        // offset is in pattern space, so it moves the boundary by 0.5 * 2.0 in object space
+        assert_eq!(pattern_at(&pattern, &point(0.9, 0.0, 0.0)), BLACK);
+        assert_eq!(pattern_at(&pattern, &point(1.1, 0.0, 0.0)), WHITE);
+    }
+
     // Interfaces for creating stripe patterns
     #[test]
     fn create_stripe_pattern_in_various_ways() {
@@ -734,6 +1261,56 @@ mod tests {
         );
     }
 
+    // A linear gradient along x is the same as a gradient
+    #[test]
+    fn linear_gradient_along_x_is_gradient() {
+        let linear = linear_gradient_pattern(&WHITE, &BLACK, &vector(1.0, 0.0, 0.0));
+        let gradient = gradient_pattern(&WHITE, &BLACK);
+        for p in [
+            point(0.0, 0.0, 0.0),
+            point(0.25, 3.0, -1.0),
+            point(-0.6, 0.2, 0.7),
+            point(1.75, -2.5, 4.0),
+        ] {
+            assert_eq!(pattern_at(&linear, &p), pattern_at(&gradient, &p));
+        }
+    }
+
+    // A linear gradient along y varies vertically
+    #[test]
+    fn linear_gradient_along_y_is_vertical() {
+        let pattern = linear_gradient_pattern(&WHITE, &BLACK, &vector(0.0, 2.0, 0.0));
+        assert_eq!(
+            pattern_at(&pattern, &point(0.0, 0.25, 0.0)),
+            color(0.75, 0.75, 0.75)
+        );
+        assert_eq!(
+            pattern_at(&pattern, &point(0.6, 0.25, -0.3)),
+            color(0.75, 0.75, 0.75)
+        );
+        assert_eq!(
+            pattern_at(&pattern, &point(0.25, 0.5, 0.0)),
+            color(0.5, 0.5, 0.5)
+        );
+    }
+
+    // A diagonal gradient repeats every unit along its axis
+    #[test]
+    fn linear_gradient_along_diagonal() {
+        let pattern = linear_gradient_pattern(&WHITE, &BLACK, &vector(1.0, 1.0, 0.0));
+        let step = 0.5 / f64::sqrt(2.0);
+        assert_relative_eq!(
+            pattern_at(&pattern, &point(step, step, 0.0)),
+            color(0.5, 0.5, 0.5),
+            epsilon = EPSILON
+        );
+        assert_relative_eq!(
+            pattern_at(&pattern, &point(step - 1.0, step + 1.0, 5.0)),
+            color(0.5, 0.5, 0.5),
+            epsilon = EPSILON
+        );
+    }
+
     // Rings
 
     // A ring should extend in both x and z
@@ -779,6 +1356,83 @@ mod tests {
         assert_eq!(pattern_at(&pattern, &point(0.0, 0.0, 1.01)), BLACK);
     }
 
+    // A world-space pattern lines up across differently transformed objects
+    #[test]
+    fn world_space_pattern_lines_up_across_objects() {
+        let a = plane();
+        let mut b = plane();
+        b.set_transform(&(translation(0.5, 0.0, 0.0) * rotation_y(PI / 4.0)));
+
+        let object_space = checkers_pattern(&WHITE, &BLACK);
+        let world_space = checkers_pattern(&WHITE, &BLACK).with_space(PatternSpace::World);
+        assert_eq!(object_space.space(), PatternSpace::Object);
+
+        let points = [
+            point(0.25, 0.0, 0.25),
+            point(-0.75, 0.0, 0.25),
+            point(1.25, 0.0, -2.75),
+        ];
+        for p in &points {
+            assert_eq!(
+                pattern_at_shape(&world_space, &a, p, None),
+                pattern_at_shape(&world_space, &b, p, None)
+            );
+        }
+        assert!(points
+            .iter()
+            .any(|p| pattern_at_shape(&object_space, &a, p, None)
+                != pattern_at_shape(&object_space, &b, p, None)));
+    }
+
+    // A horizon fade shows the inner pattern nearby, and the fade color far away
+    #[test]
+    fn horizon_fade_near_and_far() {
+        let sky = color(0.5, 0.7, 1.0);
+        let mut floor = plane();
+        floor.set_transform(&scaling(3.0, 1.0, 3.0)); // ignored, as the fade is in world space
+        let pattern = horizon_fade(checkers_pattern(&WHITE, &BLACK), &sky, 100.0);
+        assert_eq!(pattern.space(), PatternSpace::World);
+
+        let at = |x: f64| pattern_at_shape(&pattern, &floor, &point(x, 0.0, 0.5), None);
+        assert_eq!(at(0.5), WHITE);
+        assert_eq!(at(1.5), BLACK);
+        assert_eq!(at(49.5), BLACK);
+        assert_eq!(at(100.5), sky);
+        assert_eq!(at(1000.5), sky);
+
+        // In between, the distance to the fade color shrinks smoothly
+        let mut previous = f64::INFINITY;
+        for x in (50..=100).step_by(2) {
+            let c = at(x as f64 + 0.5);
+            let remaining = (c - sky).red().abs() + (c - sky).blue().abs();
+            assert!(remaining <= previous + EPSILON, "at x = {x}");
+            previous = remaining;
+        }
+    }
+
+    // Cells either side of the origin alternate, on every axis
+    #[rstest]
+    #[case(point(0.5, 0.0, 0.0), point(-0.5, 0.0, 0.0))]
+    #[case(point(0.0, 0.5, 0.0), point(0.0, -0.5, 0.0))]
+    #[case(point(0.0, 0.0, 0.5), point(0.0, 0.0, -0.5))]
+    #[case(point(-1.5, 0.0, 0.0), point(-2.5, 0.0, 0.0))]
+    #[case(point(-0.5, -0.5, 0.5), point(-0.5, -0.5, -0.5))]
+    #[case(point(3.0e9 + 0.5, 0.0, 0.0), point(3.0e9 - 0.5, 0.0, 0.0))]
+    fn checkers_alternate_across_origin(#[case] p: Point, #[case] q: Point) {
+        let pattern = checkers_pattern(&WHITE, &BLACK);
+        assert_ne!(pattern_at(&pattern, &p), pattern_at(&pattern, &q));
+    }
+
+    // Checkers are symmetric about the origin, offset by one cell
+    #[test]
+    fn checkers_parity_for_negative_coordinates() {
+        let pattern = checkers_pattern(&WHITE, &BLACK);
+        assert_eq!(pattern_at(&pattern, &point(0.5, 0.5, 0.5)), WHITE);
+        assert_eq!(pattern_at(&pattern, &point(-0.5, 0.5, 0.5)), BLACK);
+        assert_eq!(pattern_at(&pattern, &point(-0.5, -0.5, 0.5)), WHITE);
+        assert_eq!(pattern_at(&pattern, &point(-0.5, -0.5, -0.5)), BLACK);
+    }
+
     // Radial Gradient in x, y, z
     #[test]
     fn radial_gradient_linearly_interpolates_between_colors() {
@@ -881,4 +1535,51 @@ mod tests {
         ); // persistence
         dump_pattern(&pattern, "perturbed_patterns.ppm", 100, 4.0);
     }
+
+    // An image pattern maps the image onto the XZ plane and repeats
+    #[rstest]
+    #[case(TextureFilter::Nearest, point(0.25, 0.0, 0.5), RED)]
+    #[case(TextureFilter::Nearest, point(0.75, 0.0, 0.5), GREEN)]
+    #[case(TextureFilter::Nearest, point(-0.75, 3.0, 2.5), RED)]
+    #[case(TextureFilter::Bilinear, point(0.25, 0.0, 0.5), RED)]
+    #[case(TextureFilter::Bilinear, point(0.5, 0.0, 0.5), color(0.5, 0.5, 0.0))]
+    fn image_pattern_maps_image_onto_xz_plane(
+        #[case] filter: TextureFilter,
+        #[case] p: Point,
+        #[case] expected: Color,
+    ) {
+        let mut image = canvas(2, 1);
+        write_pixel(&mut image, 0, 0, &RED);
+        write_pixel(&mut image, 1, 0, &GREEN);
+        let pattern = image_pattern(image, filter);
+        assert_relative_eq!(pattern_at(&pattern, &p), expected);
+    }
+
+    // A cube map pattern places a distinct pattern on each face of a cube
+    #[test]
+    fn cube_map_pattern_on_each_face() {
+        let mut decal = canvas(2, 1);
+        write_pixel(&mut decal, 0, 0, &RED);
+        write_pixel(&mut decal, 1, 0, &GREEN);
+        let solid = |r, g, b| Pattern::solid_pattern(&color(r, g, b));
+        let pattern = cube_map_pattern(
+            solid(0.1, 0.0, 0.0),
+            image_pattern(decal, TextureFilter::Nearest),
+            solid(0.3, 0.0, 0.0),
+            solid(0.4, 0.0, 0.0),
+            solid(0.5, 0.0, 0.0),
+            solid(0.6, 0.0, 0.0),
+        );
+
+        let mut shape = crate::shapes::cube();
+        shape.set_transform(&translation(0.0, 0.0, 10.0));
+        let at = |x, y, z| pattern_at_shape(&pattern, &shape, &point(x, y, z + 10.0), None);
+        assert_eq!(at(-1.0, 0.5, 0.0), color(0.1, 0.0, 0.0));
+        assert_eq!(at(-0.5, 0.5, 1.0), RED);
+        assert_eq!(at(0.5, 0.5, 1.0), GREEN);
+        assert_eq!(at(1.0, 0.5, 0.0), color(0.3, 0.0, 0.0));
+        assert_eq!(at(0.0, 0.5, -1.0), color(0.4, 0.0, 0.0));
+        assert_eq!(at(0.0, 1.0, 0.5), color(0.5, 0.0, 0.0));
+        assert_eq!(at(0.0, -1.0, 0.5), color(0.6, 0.0, 0.0));
+    }
 }
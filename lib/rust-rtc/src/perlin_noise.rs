@@ -211,7 +211,9 @@ mod tests {
                 image.write_pixel(x, y, &pixel);
             }
         }
-        image.to_ppm_file("test_perlin_noise.ppm");
+        image
+            .to_ppm_file("test_perlin_noise.ppm")
+            .expect("should write noise image");
         println!("min {}, max {}", min_vv, max_vv);
 
         assert_eq!(0.5, perlin(0.0, 0.0, 0.0));
@@ -5,12 +5,27 @@ use crate::math::EPSILON;
 use crate::rays::Ray;
 use crate::tuples::{vector, Point, Vector};
 
-#[derive(Debug, PartialEq, Copy, Clone, Default)]
-pub struct Plane {}
+/// Rays closer than this to parallel with a plane miss it. It's the sine of the
+/// angle between the ray and the plane, so it doesn't depend on the length of the
+/// ray's direction, which transforms scale.
+pub const PLANE_PARALLEL_EPSILON: f64 = EPSILON;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Plane {
+    pub parallel_epsilon: f64, // see PLANE_PARALLEL_EPSILON
+}
+
+impl Default for Plane {
+    fn default() -> Self {
+        Plane {
+            parallel_epsilon: PLANE_PARALLEL_EPSILON,
+        }
+    }
+}
 
 impl Plane {
     pub fn new() -> Plane {
-        Plane {}
+        Plane::default()
     }
 
     pub fn local_normal_at(&self, _local_point: &Point) -> Vector {
@@ -18,7 +33,7 @@ impl Plane {
         vector(0.0, 1.0, 0.0)
     }
 
-    pub fn local_intersect(&self, local_ray: &Ray) -> Intersections {
+    pub fn local_intersect(&self, local_ray: &Ray) -> Intersections<'_> {
         // The plane is at the origin, extending infinitely in both X and Z directions.
         //
         // 4 cases:
@@ -27,7 +42,8 @@ impl Plane {
         //   3. ray origin is above the plane
         //   4. ray origin is below the plane
 
-        if f64::abs(local_ray.direction.y()) < EPSILON {
+        let sine = local_ray.direction.y() / local_ray.direction.magnitude();
+        if sine.abs() < self.parallel_epsilon {
             return intersections!();
         }
 
@@ -54,6 +70,8 @@ mod tests {
     use super::*;
     use crate::rays::ray;
     use crate::tuples::point;
+    use approx::assert_relative_eq;
+    use rstest::rstest;
 
     // The normal of a plane is constant everywhere
     #[test]
@@ -85,6 +103,49 @@ mod tests {
         assert!(xs.is_empty());
     }
 
+    // Rays at ever shallower angles hit the plane until they're within the parallel
+    // epsilon of it, whatever the length of their direction
+    #[rstest]
+    #[case(1.0)]
+    #[case(1.0e-6)]
+    #[case(1.0e6)]
+    fn grazing_rays_hit_until_parallel_epsilon(#[case] length: f64) {
+        let p = plane();
+        let hits: Vec<bool> = (1..=12)
+            .map(|k| {
+                let angle = 10f64.powi(-k);
+                let direction = vector(0.0, -angle.sin(), angle.cos()) * length;
+                let r = ray(point(0.0, 1.0, 0.0), direction);
+                !local_intersect(&p, &r).is_empty()
+            })
+            .collect();
+
+        // Angles down to 1e-9 radians (the threshold) hit, and smaller ones miss
+        let expected: Vec<bool> = (1..=12).map(|k| k <= 9).collect();
+        assert_eq!(hits, expected);
+    }
+
+    // A very shallow ray still hits, at the right distance
+    #[test]
+    fn shallow_ray_hits_plane() {
+        let p = plane();
+        let angle: f64 = 1.0e-6;
+        let r = ray(point(0.0, 1.0, 0.0), vector(0.0, -angle.sin(), angle.cos()));
+        let xs = local_intersect(&p, &r);
+        assert_eq!(xs.len(), 1);
+        assert_relative_eq!(xs[0].t, 1.0 / angle.sin());
+    }
+
+    // The parallel epsilon can be changed
+    #[test]
+    fn changing_plane_parallel_epsilon() {
+        let mut p = plane();
+        assert_eq!(p.parallel_epsilon, PLANE_PARALLEL_EPSILON);
+        p.parallel_epsilon = 1.0e-3;
+        let r = ray(point(0.0, 1.0, 0.0), vector(0.0, -1.0e-4, 1.0));
+        assert!(local_intersect(&p, &r).is_empty());
+    }
+
     // A ray intersecting a plane from above
     #[test]
     fn ray_intersecting_plane_from_above() {
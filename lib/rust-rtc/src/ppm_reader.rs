@@ -0,0 +1,246 @@
+// Reading PPM images (ASCII P3 and binary P6) into a Canvas, e.g. for textures
+
+use crate::canvas::Canvas;
+use crate::colors::Color;
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data, pos: 0 }
+    }
+
+    // Skip whitespace and comments, which run from '#' to the end of the line.
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.data.len() {
+            match self.data[self.pos] {
+                b'#' => {
+                    while self.pos < self.data.len() && self.data[self.pos] != b'\n' {
+                        self.pos += 1;
+                    }
+                }
+                c if c.is_ascii_whitespace() => self.pos += 1,
+                _ => break,
+            }
+        }
+    }
+
+    fn token(&mut self) -> Result<&'a str> {
+        self.skip_whitespace();
+        let start = self.pos;
+        while self.pos < self.data.len()
+            && !self.data[self.pos].is_ascii_whitespace()
+            && self.data[self.pos] != b'#'
+        {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            bail!("unexpected end of PPM data");
+        }
+        std::str::from_utf8(&self.data[start..self.pos]).context("invalid PPM token")
+    }
+
+    fn number(&mut self, what: &str) -> Result<u32> {
+        let token = self.token().with_context(|| format!("reading {what}"))?;
+        token
+            .parse::<u32>()
+            .with_context(|| format!("invalid {what}: {token:?}"))
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        let b = *self
+            .data
+            .get(self.pos)
+            .context("unexpected end of PPM pixel data")?;
+        self.pos += 1;
+        Ok(b)
+    }
+}
+
+/// Parse an ASCII (P3) or binary (P6) PPM image. Samples are scaled by the file's
+/// maxval into the range 0.0 to 1.0.
+pub fn canvas_from_ppm(data: &[u8]) -> Result<Canvas> {
+    let mut reader = Reader::new(data);
+
+    let magic = reader.token().context("reading PPM magic number")?;
+    let binary = match magic {
+        "P3" => false,
+        "P6" => true,
+        x => bail!("unsupported PPM magic number: {x:?}"),
+    };
+
+    let width = reader.number("width")?;
+    let height = reader.number("height")?;
+    let maxval = reader.number("maxval")?;
+    if maxval == 0 || maxval > 65535 {
+        bail!("PPM maxval out of range: {maxval}");
+    }
+
+    if binary {
+        // Exactly one whitespace character separates the header from the raster
+        if !reader.byte()?.is_ascii_whitespace() {
+            bail!("expected whitespace after PPM header");
+        }
+    }
+
+    // Check the header against the data before allocating the canvas, so a bad
+    // header can't ask for a huge one. Each sample takes at least one byte.
+    let sample_bytes = if binary && maxval >= 256 { 2 } else { 1 };
+    let remaining = data.len() - reader.pos;
+    match (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|pixels| pixels.checked_mul(3 * sample_bytes))
+    {
+        Some(needed) if needed <= remaining => (),
+        _ => bail!("PPM data too short for a {width}x{height} image"),
+    }
+
+    let mut canvas = Canvas::new(width, height);
+    let scale = maxval as f64;
+
+    let sample = |reader: &mut Reader| -> Result<f64> {
+        let value = if binary {
+            if maxval < 256 {
+                reader.byte()? as u32
+            } else {
+                (reader.byte()? as u32) << 8 | reader.byte()? as u32
+            }
+        } else {
+            reader.number("sample")?
+        };
+        if value > maxval {
+            bail!("PPM sample {value} exceeds maxval {maxval}");
+        }
+        Ok(value as f64 / scale)
+    };
+
+    for y in 0..height {
+        for x in 0..width {
+            let r = sample(&mut reader)?;
+            let g = sample(&mut reader)?;
+            let b = sample(&mut reader)?;
+            canvas.write_pixel(x, y, &Color::new(r, g, b));
+        }
+    }
+
+    Ok(canvas)
+}
+
+pub fn read_ppm_file<P: AsRef<Path>>(path: P) -> Result<Canvas> {
+    let path = path.as_ref();
+    let data = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    canvas_from_ppm(&data).with_context(|| format!("parsing {}", path.display()))
+}
+
+/// Read a PPM image for use as a texture. Image files are assumed to be sRGB
+/// encoded, so the colors are linearized for shading.
+pub fn read_ppm_texture<P: AsRef<Path>>(path: P) -> Result<Canvas> {
+    Ok(read_ppm_file(path)?.to_linear())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::{canvas, pixel_at, ppm_binary_from_canvas, ppm_from_canvas, write_pixel};
+    use crate::colors::color;
+
+    fn test_canvas() -> Canvas {
+        let mut c = canvas(7, 3);
+        for y in 0..c.height {
+            for x in 0..c.width {
+                let v = (x + y * c.width) as f64 * 12.0 / 255.0;
+                write_pixel(&mut c, x, y, &color(v, 1.0 - v, 0.2));
+            }
+        }
+        c
+    }
+
+    fn assert_same_pixels(a: &Canvas, b: &Canvas) {
+        assert_eq!(a.width, b.width);
+        assert_eq!(a.height, b.height);
+        for y in 0..a.height {
+            for x in 0..a.width {
+                let (pa, pb) = (pixel_at(a, x, y), pixel_at(b, x, y));
+                // Both sides quantised to 8 bits:
+                for (ca, cb) in [
+                    (pa.red(), pb.red()),
+                    (pa.green(), pb.green()),
+                    (pa.blue(), pb.blue()),
+                ] {
+                    assert_eq!((ca * 255.0).round(), (cb * 255.0).round());
+                }
+            }
+        }
+    }
+
+    // Reading back an ASCII PPM
+    #[test]
+    fn round_trip_p3() {
+        let c = test_canvas();
+        let read = canvas_from_ppm(ppm_from_canvas(&c).as_bytes()).unwrap();
+        assert_same_pixels(&c, &read);
+    }
+
+    // Reading back a binary PPM
+    #[test]
+    fn round_trip_p6() {
+        let c = test_canvas();
+        let read = canvas_from_ppm(&ppm_binary_from_canvas(&c)).unwrap();
+        assert_same_pixels(&c, &read);
+    }
+
+    // Comments are ignored and samples are scaled by maxval
+    #[test]
+    fn parse_ppm_with_comments() {
+        let data = b"P3\n# a comment\n2 1 # trailing comment\n# another\n100\n100 50 0\n# in the raster\n0 0 25\n";
+        let c = canvas_from_ppm(data).unwrap();
+        assert_eq!(c.width, 2);
+        assert_eq!(c.height, 1);
+        assert_eq!(*pixel_at(&c, 0, 0), color(1.0, 0.5, 0.0));
+        assert_eq!(*pixel_at(&c, 1, 0), color(0.0, 0.0, 0.25));
+    }
+
+    // Binary PPMs with a maxval above 255 use two bytes per sample
+    #[test]
+    fn parse_p6_with_16_bit_samples() {
+        let mut data = b"P6 1 1 65535\n".to_vec();
+        data.extend_from_slice(&[0xff, 0xff, 0x00, 0x00, 0x80, 0x00]);
+        let c = canvas_from_ppm(&data).unwrap();
+        assert_eq!(*pixel_at(&c, 0, 0), color(1.0, 0.0, 32768.0 / 65535.0));
+    }
+
+    // Malformed input is reported as an error
+    #[test]
+    fn malformed_ppm_is_an_error() {
+        assert!(canvas_from_ppm(b"P5 1 1 255\n\0").is_err());
+        assert!(canvas_from_ppm(b"P3 2 1 255\n1 2 3").is_err());
+        assert!(canvas_from_ppm(b"P3 1 1 255\n1 2 300").is_err());
+    }
+
+    // A header giving a size larger than the data is an error, before allocating
+    #[test]
+    fn ppm_header_larger_than_data_is_an_error() {
+        let err = canvas_from_ppm(b"P6 100000 100000 255\n\0\0\0").unwrap_err();
+        assert!(err.to_string().contains("too short"), "{err}");
+        assert!(canvas_from_ppm(b"P6 4294967295 4294967295 65535\n\0").is_err());
+        assert!(canvas_from_ppm(b"P6 1 1 65535\n\0\0\0").is_err());
+    }
+
+    // Textures are linearized on load
+    #[test]
+    fn textures_are_linearized_on_load() {
+        let path = std::env::temp_dir().join(format!("rtc_texture_{}.ppm", std::process::id()));
+        std::fs::write(&path, b"P3 1 1 255\n255 188 0\n").unwrap();
+        let c = read_ppm_texture(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let p = pixel_at(&c, 0, 0);
+        assert_eq!(p.red(), 1.0);
+        assert!((p.green() - 0.5).abs() < 0.01);
+        assert_eq!(p.blue(), 0.0);
+    }
+}
@@ -0,0 +1,153 @@
+// Progressive rendering for interactive previews: a few quick, coarse images
+// first, then the final image a tile at a time, from the centre outwards. The
+// final image is the same as rendering it directly.
+
+use crate::camera::{camera, Camera, CancelToken, Resolution, TileOrder};
+use crate::canvas::Canvas;
+use crate::colors::Color;
+use crate::framebuffer::framebuffer;
+use crate::rays::Ray;
+
+// Coarse previews are rendered at these fractions of the final resolution
+const PREVIEW_DIVISORS: [u32; 3] = [8, 4, 2];
+
+// Size of the final image's tiles, in final pixels
+const TILE_SIZE: u32 = 16;
+
+/// Render the view from `cam` with `shader`, supersampled by `ssaa`, calling
+/// `on_update` with the image so far each time it improves: after each coarse
+/// preview (scaled up to full size), and after each tile of the final image (over
+/// the last preview). The final update is the finished image, which is returned.
+/// Returns None if `cancel` is cancelled first.
+pub fn progressive_render<F, U>(
+    cam: &Camera,
+    ssaa: u32,
+    shader: F,
+    cancel: &CancelToken,
+    mut on_update: U,
+) -> Option<Canvas>
+where
+    F: Fn(&Ray) -> Color + Sync,
+    U: FnMut(&Canvas),
+{
+    let resolution = cam.resolution();
+    let mut display = Canvas::new(resolution.hsize, resolution.vsize);
+
+    for divisor in PREVIEW_DIVISORS {
+        let coarse = Resolution::new(resolution.hsize / divisor, resolution.vsize / divisor);
+        if coarse.hsize == 0 || coarse.vsize == 0 {
+            continue;
+        }
+        if cancel.is_cancelled() {
+            return None;
+        }
+
+        let mut preview_cam = camera(coarse, cam.field_of_view());
+        preview_cam.set_transform(cam.transform());
+        preview_cam.set_clip_distances(cam.near(), cam.far());
+        display = upscale(&preview_cam.render_with(&shader, None), resolution);
+        on_update(&display);
+    }
+
+    // Each supersampled pixel is one sample of a final pixel, which average to the
+    // same colors as downsampling
+    let mut samples = framebuffer(resolution.hsize, resolution.vsize, &Color::default());
+    cam.supersampled(ssaa).render_tiles_ordered_with(
+        &shader,
+        TILE_SIZE * ssaa,
+        TileOrder::Spiral,
+        cancel,
+        |x0, y0, tile| {
+            for y in 0..tile.height {
+                for x in 0..tile.width {
+                    let (sx, sy) = ((x0 + x) / ssaa, (y0 + y) / ssaa);
+                    samples.accumulate_sample(sx, sy, tile.pixel_at(x, y));
+                }
+            }
+            for y in y0 / ssaa..(y0 + tile.height) / ssaa {
+                for x in x0 / ssaa..(x0 + tile.width) / ssaa {
+                    display.write_pixel(x, y, &samples.resolve_pixel(x, y));
+                }
+            }
+            on_update(&display);
+        },
+    )?;
+
+    Some(samples.resolve())
+}
+
+// Scale up by repeating pixels
+fn upscale(image: &Canvas, resolution: Resolution) -> Canvas {
+    let mut result = Canvas::new(resolution.hsize, resolution.vsize);
+    for y in 0..resolution.vsize {
+        for x in 0..resolution.hsize {
+            let sx = (x * image.width / resolution.hsize).min(image.width - 1);
+            let sy = (y * image.height / resolution.vsize).min(image.height - 1);
+            result.write_pixel(x, y, image.pixel_at(sx, sy));
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformations::view_transform;
+    use crate::tuples::{point, vector};
+    use crate::world::{color_at, default_world};
+    use std::f64::consts::PI;
+
+    fn test_camera() -> Camera {
+        let mut c = camera(Resolution::new(40, 30), PI / 3.0);
+        c.set_transform(&view_transform(
+            &point(0.0, 1.0, -5.0),
+            &point(0.0, 0.0, 0.0),
+            &vector(0.0, 1.0, 0.0),
+        ));
+        c
+    }
+
+    // A progressive render ends with the same image as a direct render
+    #[test]
+    fn progressive_render_matches_direct_render() {
+        let mut w = default_world();
+        w.set_seed(3);
+        let shader = |ray: &Ray| color_at(&w, ray, 5);
+        let c = test_camera();
+        let direct = c.supersampled(2).render_with(shader, None).downsample(2);
+
+        let mut updates = vec![];
+        let image = progressive_render(&c, 2, shader, &CancelToken::new(), |image| {
+            updates.push(image.clone())
+        })
+        .expect("should not be cancelled");
+
+        assert_eq!(image, direct);
+
+        // Three previews, then each of 3 x 2 tiles
+        assert_eq!(updates.len(), 3 + 6);
+        assert!(updates.iter().all(|u| (u.width, u.height) == (40, 30)));
+        assert_ne!(updates[0], direct);
+        assert_eq!(updates.last(), Some(&direct));
+    }
+
+    // Cancelling stops a progressive render
+    #[test]
+    fn cancelling_progressive_render() {
+        let w = default_world();
+        let cancel = CancelToken::new();
+        let mut updates = 0;
+        let image = progressive_render(
+            &test_camera(),
+            1,
+            |ray| color_at(&w, ray, 5),
+            &cancel.clone(),
+            |_| {
+                updates += 1;
+                cancel.cancel();
+            },
+        );
+        assert!(image.is_none());
+        assert_eq!(updates, 1);
+    }
+}
@@ -1,17 +1,89 @@
 // Chapter 5: Ray-Sphere Intersections
 
 use crate::matrices::Matrix4;
-use crate::tuples::{Point, Vector};
+use crate::tuples::{dot, Point, Vector};
+
+/// Why a ray was cast, so objects can be hidden from reflections or refractions.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub enum RayKind {
+    #[default]
+    Primary,
+    Reflection,
+    Refraction,
+}
+
+/// The kinds of ray cast on the way from the camera to a ray, so an object hidden
+/// from reflections stays hidden when the reflection is seen through glass.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub struct RayPath(u8);
+
+impl RayPath {
+    fn bit(kind: RayKind) -> u8 {
+        match kind {
+            RayKind::Primary => 0,
+            RayKind::Reflection => 1,
+            RayKind::Refraction => 2,
+        }
+    }
+
+    /// This path, followed by a ray of `kind`.
+    pub fn then(self, kind: RayKind) -> RayPath {
+        RayPath(self.0 | RayPath::bit(kind))
+    }
+
+    /// Whether a ray of `kind` was cast somewhere along this path. Every path starts
+    /// with a primary ray.
+    pub fn includes(self, kind: RayKind) -> bool {
+        kind == RayKind::Primary || self.0 & RayPath::bit(kind) != 0
+    }
+}
 
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub struct Ray {
     pub origin: Point,
     pub direction: Vector,
+    pub t_min: f64, // hits before this are ignored, e.g. nearer than a camera's near distance
+    pub t_max: f64, // hits after this are ignored, e.g. beyond a camera's far distance
+    pub kind: RayKind,
+    pub path: RayPath, // every kind of ray cast on the way here, including this one
 }
 
 impl Ray {
     pub fn new(origin: Point, direction: Vector) -> Ray {
-        Ray { origin, direction }
+        Ray {
+            origin,
+            direction,
+            t_min: 0.0,
+            t_max: f64::INFINITY,
+            kind: RayKind::Primary,
+            path: RayPath::default(),
+        }
+    }
+
+    /// This ray, cast for the reason given by `kind`.
+    pub fn with_kind(self, kind: RayKind) -> Ray {
+        Ray {
+            kind,
+            path: self.path.then(kind),
+            ..self
+        }
+    }
+
+    /// This ray, cast at the end of `path`, e.g. from a surface hit by a ray on it.
+    pub fn with_path(self, path: RayPath) -> Ray {
+        Ray {
+            path: path.then(self.kind),
+            ..self
+        }
+    }
+
+    /// This ray, with only hits at `t_min <= t <= t_max` to be shaded.
+    pub fn with_range(self, t_min: f64, t_max: f64) -> Ray {
+        Ray {
+            t_min,
+            t_max,
+            ..self
+        }
     }
 
     pub fn position(&self, t: f64) -> Point {
@@ -19,13 +91,74 @@ impl Ray {
     }
 
     pub fn transform(&self, m: &Matrix4) -> Ray {
+        // Transforming a ray keeps its t values, so it keeps its range too
         Ray {
             origin: m * self.origin,
             direction: m * self.direction,
+            ..*self
         }
     }
 }
 
+/// A camera ray, along with the rays through the neighbouring pixels in x and y.
+/// Together they estimate the footprint of a pixel on the surface that is hit,
+/// which patterns use to filter detail smaller than a pixel.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct RayDifferential {
+    pub ray: Ray,
+    pub rx: Ray,
+    pub ry: Ray,
+}
+
+impl RayDifferential {
+    pub fn new(ray: Ray, rx: Ray, ry: Ray) -> RayDifferential {
+        RayDifferential { ray, rx, ry }
+    }
+
+    /// Footprint of the pixel at `point`, found by intersecting the offset rays with
+    /// the plane tangent to the surface there.
+    pub fn footprint(&self, point: &Point, normal: &Vector) -> Footprint {
+        let offset = |r: &Ray| {
+            let denom = dot(normal, &r.direction);
+            let t = if denom.abs() < f64::EPSILON {
+                // Offset ray is parallel to the tangent plane, so fall back to the
+                // distance along the main ray
+                (point - self.ray.origin).magnitude()
+            } else {
+                dot(normal, &(point - r.origin)) / denom
+            };
+            r.position(t) - point
+        };
+        Footprint::new(offset(&self.rx), offset(&self.ry))
+    }
+}
+
+/// Change in the hit point from one pixel to the next, in x and y.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Footprint {
+    pub dpdx: Vector,
+    pub dpdy: Vector,
+}
+
+impl Footprint {
+    pub fn new(dpdx: Vector, dpdy: Vector) -> Footprint {
+        Footprint { dpdx, dpdy }
+    }
+
+    pub fn transform(&self, m: &Matrix4) -> Footprint {
+        Footprint {
+            dpdx: m * self.dpdx,
+            dpdy: m * self.dpdy,
+        }
+    }
+
+    /// Extent of the footprint along the given axis (0 = x, 1 = y, 2 = z).
+    pub fn width(&self, axis: usize) -> f64 {
+        self.dpdx.at(axis).expect("valid axis").abs()
+            + self.dpdy.at(axis).expect("valid axis").abs()
+    }
+}
+
 // FIXME: should these be references?
 pub fn ray(origin: Point, direction: Vector) -> Ray {
     Ray::new(origin, direction)
@@ -83,4 +216,36 @@ mod tests {
         assert_eq!(r2.origin, point(2.0, 6.0, 12.0));
         assert_eq!(r2.direction, vector(0.0, 3.0, 0.0));
     }
+
+    // The footprint of a ray differential on a surface facing the rays
+    #[test]
+    fn footprint_on_facing_surface() {
+        let rd = RayDifferential::new(
+            ray(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 1.0)),
+            ray(point(0.1, 0.0, 0.0), vector(0.0, 0.0, 1.0)),
+            ray(point(0.0, 0.2, 0.0), vector(0.0, 0.0, 1.0)),
+        );
+        let f = rd.footprint(&point(0.0, 0.0, 5.0), &vector(0.0, 0.0, -1.0));
+        assert_eq!(f.dpdx, vector(0.1, 0.0, 0.0));
+        assert_eq!(f.dpdy, vector(0.0, 0.2, 0.0));
+        assert_eq!(f.width(0), 0.1);
+        assert_eq!(f.width(2), 0.0);
+        let scaled = f.transform(&scaling(2.0, 1.0, 1.0));
+        assert_eq!(scaled.width(0), 0.2);
+    }
+
+    // The footprint grows on a surface at a grazing angle
+    #[test]
+    fn footprint_on_grazing_surface() {
+        let k = f64::sqrt(2.0) / 2.0;
+        let rd = RayDifferential::new(
+            ray(point(0.0, 1.0, 0.0), vector(0.0, -k, k)),
+            ray(point(0.0, 1.0, 0.0), vector(0.0, -k, k)),
+            ray(point(0.0, 1.1, 0.0), vector(0.0, -k, k)),
+        );
+        let f = rd.footprint(&point(0.0, 0.0, 1.0), &vector(0.0, 1.0, 0.0));
+        assert_eq!(f.dpdx, vector(0.0, 0.0, 0.0));
+        assert!((f.dpdy.z() - 0.1).abs() < 1e-9);
+        assert!(f.dpdy.y().abs() < 1e-9);
+    }
 }
@@ -0,0 +1,110 @@
+// Scenery shared by the chapter binaries, so that each need only add what's
+// particular to it, and set up the camera.
+
+use crate::colors::color;
+use crate::lights::point_light;
+use crate::materials::{default_material, Material};
+use crate::shapes::{plane, sphere};
+use crate::transformations::{rotation_x, rotation_y, scaling, translation};
+use crate::tuples::point;
+use crate::world::{world, World};
+use std::f64::consts::PI;
+
+// The material of the floor and walls in the book's scenes
+fn room_material() -> Material {
+    let mut m = default_material();
+    m.color = color(1.0, 0.9, 0.9);
+    m.specular = 0.0;
+    m
+}
+
+/// The book's room from chapter 7: a floor and two walls meeting behind the
+/// origin, made of flattened spheres.
+pub fn sphere_room(world: &mut World) {
+    let mut floor = sphere(1);
+    floor.set_transform(&scaling(10.0, 0.01, 10.0));
+    floor.material = room_material();
+    world.add_object(floor);
+
+    for (id, angle) in [(2, -PI / 4.0), (3, PI / 4.0)] {
+        let mut wall = sphere(id);
+        wall.set_transform(
+            &(translation(0.0, 0.0, 5.0)
+                * rotation_y(angle)
+                * rotation_x(PI / 2.0)
+                * scaling(10.0, 0.01, 10.0)),
+        );
+        wall.material = room_material();
+        world.add_object(wall);
+    }
+}
+
+/// A floor plane at y = 0, with the given reflectivity.
+pub fn reflective_floor(world: &mut World, reflective: f64) {
+    let mut floor = plane();
+    floor.material = room_material();
+    floor.material.reflective = reflective;
+    world.add_object(floor);
+}
+
+/// The book's three spheres, large, medium and small, resting on y = 0.
+pub fn three_spheres(world: &mut World) {
+    let spheres = [
+        (4, translation(-0.5, 1.0, 0.5), color(0.1, 1.0, 0.5)),
+        (
+            5,
+            translation(1.5, 0.5, -0.5) * scaling(0.5, 0.5, 0.5),
+            color(0.5, 1.0, 0.1),
+        ),
+        (
+            6,
+            translation(-1.5, 0.33, -0.75) * scaling(0.33, 0.33, 0.33),
+            color(1.0, 0.8, 0.1),
+        ),
+    ];
+    for (id, transform, c) in spheres {
+        let mut s = sphere(id);
+        s.set_transform(&transform);
+        s.material = default_material();
+        s.material.color = c;
+        s.material.diffuse = 0.7;
+        s.material.specular = 0.3;
+        world.add_object(s);
+    }
+}
+
+/// The book's white light, above and to the left of the camera.
+pub fn standard_light(world: &mut World) {
+    world.add_light(point_light(point(-10.0, 10.0, -10.0), color(1.0, 1.0, 1.0)));
+}
+
+/// The book's scene from chapter 7: three spheres in a room, with a white light.
+pub fn book_scene() -> World {
+    let mut w = world();
+    sphere_room(&mut w);
+    three_spheres(&mut w);
+    standard_light(&mut w);
+    w
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The three spheres are added without any lights
+    #[test]
+    fn three_spheres_adds_three_spheres() {
+        let mut w = world();
+        three_spheres(&mut w);
+        assert_eq!(w.objects().len(), 3);
+        assert!(w.lights().is_empty());
+    }
+
+    // The book's scene has a room, three spheres and one light
+    #[test]
+    fn book_scene_is_populated() {
+        let w = book_scene();
+        assert_eq!(w.objects().len(), 6);
+        assert_eq!(w.lights().len(), 1);
+    }
+}
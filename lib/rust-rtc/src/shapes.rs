@@ -1,22 +1,75 @@
 // Chapter 9: Planes
 
+use crate::bounds::Bounds;
+use crate::capsules::Capsule;
 use crate::cones::Cone;
 use crate::cubes::Cube;
 use crate::cylinders::Cylinder;
+use crate::groups::Group;
+use crate::instances::Instance;
 use crate::intersections::Intersections;
 use crate::materials::{Material, RefractiveIndex};
-use crate::matrices::{inverse, transpose, Matrix4};
+use crate::matrices::{transpose, Matrix4};
 use crate::planes::Plane;
 use crate::rays::Ray;
 use crate::spheres::Sphere;
-use crate::tuples::{normalize, Point, Vector};
+use crate::triangles::{SmoothTriangle, Triangle};
+use crate::tuples::{normalize, point, Point, Vector};
+use crate::world::ObjectIndex;
+use anyhow::{bail, Result};
+use std::sync::atomic::{AtomicU64, Ordering};
 
-#[derive(Debug, PartialEq, Default, Clone)]
+// Source of unique shape ids
+static NEXT_SHAPE_ID: AtomicU64 = AtomicU64::new(1);
+
+pub(crate) fn next_shape_id() -> u64 {
+    NEXT_SHAPE_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+#[derive(Debug, Clone)]
 pub struct Shape {
     pub shape: ShapeEnum,
-    transform: Matrix4,
+    transform: Matrix4, // relative to the parent group, if any
     inverse_transform: Matrix4,
     pub material: Material,
+    id: u64,
+    inherit_material: bool, // take the parent group's material, when prepared
+
+    // Composed transform of the parent group(s), maintained by the World
+    parent: Option<ObjectIndex>,
+    parent_transform: Matrix4,
+
+    // Object to world space, i.e. parent_transform * transform
+    world_transform: Matrix4,
+    world_inverse_transform: Matrix4,
+}
+
+impl Default for Shape {
+    fn default() -> Self {
+        Shape {
+            shape: ShapeEnum::default(),
+            transform: Matrix4::default(),
+            inverse_transform: Matrix4::default(),
+            material: Material::default(),
+            id: next_shape_id(),
+            inherit_material: false,
+            parent: None,
+            parent_transform: Matrix4::default(),
+            world_transform: Matrix4::default(),
+            world_inverse_transform: Matrix4::default(),
+        }
+    }
+}
+
+// Shapes compare equal by geometry, transform and material; the id is an identity,
+// not part of the value.
+impl PartialEq for Shape {
+    fn eq(&self, other: &Self) -> bool {
+        self.shape == other.shape
+            && self.transform == other.transform
+            && self.inverse_transform == other.inverse_transform
+            && self.material == other.material
+    }
 }
 
 impl Shape {
@@ -27,14 +80,31 @@ impl Shape {
         }
     }
 
-    pub fn glass_sphere() -> Shape {
-        let mut shape = Shape {
-            shape: ShapeEnum::Sphere(Sphere::new(0)),
+    pub fn sphere_auto() -> Shape {
+        Shape {
+            shape: ShapeEnum::Sphere(Sphere::new_auto()),
             ..Default::default()
-        };
-        shape.material.transparency = 1.0;
-        shape.material.refractive_index = RefractiveIndex::GLASS;
-        shape
+        }
+    }
+
+    pub fn glass_sphere() -> Shape {
+        Shape::sphere(0).make_glass()
+    }
+
+    pub fn glass_plane() -> Shape {
+        Shape::plane().make_glass()
+    }
+
+    pub fn glass_cube() -> Shape {
+        Shape::cube().make_glass()
+    }
+
+    /// This shape, with its material made fully transparent and given the
+    /// refractive index of glass. Its geometry and transform are unchanged.
+    pub fn make_glass(mut self) -> Shape {
+        self.material.transparency = 1.0;
+        self.material.refractive_index = RefractiveIndex::GLASS;
+        self
     }
 
     pub fn plane() -> Shape {
@@ -51,6 +121,16 @@ impl Shape {
         }
     }
 
+    /// A cube shaded with its own material on each face, e.g. for dice, given in
+    /// the order +x, -x, +y, -y, +z, -z. The shape's `material` still decides
+    /// whether it casts shadows.
+    pub fn cube_with_faces(materials: [Material; 6]) -> Shape {
+        Shape {
+            shape: ShapeEnum::Cube(Cube::with_faces(materials)),
+            ..Default::default()
+        }
+    }
+
     pub fn cylinder(minimum_y: f64, maximum_y: f64, closed_min: bool, closed_max: bool) -> Shape {
         Shape {
             //shape: ShapeEnum::Cylinder(cyl),
@@ -59,11 +139,25 @@ impl Shape {
                 maximum_y,
                 closed_min,
                 closed_max,
+                ..Default::default()
             }),
             ..Default::default()
         }
     }
 
+    /// As `cylinder`, but report an error if the y extent is empty, rather than
+    /// silently rendering nothing.
+    pub fn try_cylinder(
+        minimum_y: f64,
+        maximum_y: f64,
+        closed_min: bool,
+        closed_max: bool,
+    ) -> Result<Shape> {
+        let shape = Shape::cylinder(minimum_y, maximum_y, closed_min, closed_max);
+        shape.validate()?;
+        Ok(shape)
+    }
+
     pub fn infinite_cylinder() -> Shape {
         Shape {
             shape: ShapeEnum::Cylinder(Cylinder::new()),
@@ -78,11 +172,56 @@ impl Shape {
                 maximum_y: 0.0,
                 closed_min: true,
                 closed_max: true,
+                ..Default::default()
             }),
             ..Default::default()
         }
     }
 
+    pub fn capsule(minimum_y: f64, maximum_y: f64, radius: f64) -> Shape {
+        Shape {
+            shape: ShapeEnum::Capsule(Capsule::new(minimum_y, maximum_y, radius)),
+            ..Default::default()
+        }
+    }
+
+    pub fn triangle(p1: Point, p2: Point, p3: Point) -> Shape {
+        Shape {
+            shape: ShapeEnum::Triangle(Triangle::new(p1, p2, p3)),
+            ..Default::default()
+        }
+    }
+
+    pub fn smooth_triangle(
+        p1: Point,
+        p2: Point,
+        p3: Point,
+        n1: Vector,
+        n2: Vector,
+        n3: Vector,
+    ) -> Shape {
+        Shape {
+            shape: ShapeEnum::SmoothTriangle(SmoothTriangle::new(p1, p2, p3, n1, n2, n3)),
+            ..Default::default()
+        }
+    }
+
+    pub fn group() -> Shape {
+        Shape {
+            shape: ShapeEnum::Group(Group::new()),
+            ..Default::default()
+        }
+    }
+
+    /// An instance of the `prototype` object in a World, placed by the instance's
+    /// own transform. See `World::add_instance`.
+    pub fn instance(prototype: ObjectIndex) -> Shape {
+        Shape {
+            shape: ShapeEnum::Instance(Instance::new(prototype)),
+            ..Default::default()
+        }
+    }
+
     // Functions to extract primitive type
     pub fn as_sphere_primitive(&mut self) -> Option<&mut Sphere> {
         match self.shape {
@@ -98,6 +237,13 @@ impl Shape {
         }
     }
 
+    pub fn as_plane_primitive(&mut self) -> Option<&mut Plane> {
+        match self.shape {
+            ShapeEnum::Plane(ref mut x) => Some(x),
+            _ => None,
+        }
+    }
+
     pub fn as_cone_primitive(&mut self) -> Option<&mut Cone> {
         match self.shape {
             ShapeEnum::Cone(ref mut x) => Some(x),
@@ -105,11 +251,105 @@ impl Shape {
         }
     }
 
+    pub fn as_capsule_primitive(&mut self) -> Option<&mut Capsule> {
+        match self.shape {
+            ShapeEnum::Capsule(ref mut x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn as_group_primitive(&mut self) -> Option<&mut Group> {
+        match self.shape {
+            ShapeEnum::Group(ref mut x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// Stable identity of this shape, unique per constructed shape and
+    /// independent of the order shapes are added to a world. A clone shares the
+    /// id of the original until it is added to a world, which gives it a new one.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub(crate) fn set_id(&mut self, id: u64) {
+        self.id = id;
+    }
+
+    /// Set the transform, panicking if it is not invertible. See `try_set_transform`.
     pub fn set_transform(&mut self, m: &Matrix4) {
+        if let Err(e) = self.try_set_transform(m) {
+            panic!("{e}");
+        }
+    }
+
+    /// Set the transform, or report an error if it is not invertible (e.g. a zero
+    /// scale), rather than letting NaNs silently blacken the shape.
+    pub fn try_set_transform(&mut self, m: &Matrix4) -> Result<()> {
+        if !m.is_invertible() {
+            bail!(
+                "transform of {} (id {}) is not invertible: {:?}",
+                self.shape.kind(),
+                self.id,
+                m
+            );
+        }
         self.transform = *m;
         self.inverse_transform = self.transform.inverse();
+        self.update_world_transform();
+        Ok(())
+    }
+
+    /// A copy of the shape, with its own id, placed by `transform` instead, e.g. to
+    /// repeat an object. To share one object between many placements rather than
+    /// copying it, see `World::add_instance`.
+    pub fn clone_with_transform(&self, transform: &Matrix4) -> Shape {
+        let mut shape = Shape {
+            id: next_shape_id(),
+            parent: None,
+            parent_transform: Matrix4::default(),
+            ..self.clone()
+        };
+        shape.set_transform(transform);
+        shape
+    }
+
+    /// Check the shape's parameters describe something that can be seen: a truncated
+    /// cylinder or cone must have maximum_y greater than minimum_y.
+    pub fn validate(&self) -> Result<()> {
+        let (minimum_y, maximum_y) = match &self.shape {
+            ShapeEnum::Cylinder(c) => (c.minimum_y, c.maximum_y),
+            ShapeEnum::Cone(c) => (c.minimum_y, c.maximum_y),
+            _ => return Ok(()),
+        };
+        let kind = self.shape.kind();
+        if maximum_y == minimum_y {
+            bail!(
+                "degenerate {kind} (id {}): minimum_y and maximum_y are both {minimum_y}",
+                self.id
+            );
+        }
+        if maximum_y < minimum_y {
+            bail!(
+                "{kind} (id {}) has maximum_y ({maximum_y}) less than minimum_y ({minimum_y}); are they swapped?",
+                self.id
+            );
+        }
+        Ok(())
     }
 
+    /// The material to shade `world_point` on the surface with: the shape's own,
+    /// unless it is a cube with a material for each face.
+    pub fn material_at(&self, world_point: &Point) -> &Material {
+        match &self.shape {
+            ShapeEnum::Cube(cube) => cube
+                .face_material(&(self.world_inverse_transform * world_point))
+                .unwrap_or(&self.material),
+            _ => &self.material,
+        }
+    }
+
+    /// Transform relative to the parent group, or the world if not in a group.
     pub fn transform(&self) -> &Matrix4 {
         &self.transform
     }
@@ -118,25 +358,157 @@ impl Shape {
         &self.inverse_transform
     }
 
+    /// Transform from object space to world space, including any parent groups.
+    pub fn world_transform(&self) -> &Matrix4 {
+        &self.world_transform
+    }
+
+    pub fn world_inverse_transform(&self) -> &Matrix4 {
+        &self.world_inverse_transform
+    }
+
+    pub fn parent(&self) -> Option<ObjectIndex> {
+        self.parent
+    }
+
+    /// Leave the shape's material unset, so that in a group it has the group's
+    /// material instead (which may in turn be the group's parent's), as resolved by
+    /// `World::prepare`. Its own `material` is then overwritten, and ignored outside
+    /// a group.
+    pub fn set_inherit_material(&mut self, inherit: bool) {
+        self.inherit_material = inherit;
+    }
+
+    pub fn inherits_material(&self) -> bool {
+        self.inherit_material
+    }
+
+    pub(crate) fn set_parent(&mut self, parent: ObjectIndex, parent_world_transform: &Matrix4) {
+        self.parent = Some(parent);
+        self.parent_transform = *parent_world_transform;
+        self.update_world_transform();
+    }
+
+    // Make the world transform the shape's own transform, detaching it from its group.
+    pub(crate) fn bake_world_transform(&mut self) {
+        self.parent = None;
+        self.parent_transform = Matrix4::default();
+        self.transform = self.world_transform;
+        self.inverse_transform = self.world_inverse_transform;
+    }
+
+    fn update_world_transform(&mut self) {
+        if self.parent.is_some() {
+            self.world_transform = self.parent_transform * self.transform;
+            self.world_inverse_transform = self.world_transform.inverse();
+        } else {
+            self.world_transform = self.transform;
+            self.world_inverse_transform = self.inverse_transform;
+        }
+    }
+
+    pub fn is_group(&self) -> bool {
+        matches!(self.shape, ShapeEnum::Group(_))
+    }
+
+    pub fn is_instance(&self) -> bool {
+        matches!(self.shape, ShapeEnum::Instance(_))
+    }
+
+    /// Bounding box of the untransformed primitive, in object space.
+    pub fn bounds(&self) -> Bounds {
+        match self.shape {
+            ShapeEnum::Sphere(_) | ShapeEnum::Cube(_) => {
+                Bounds::new(point(-1.0, -1.0, -1.0), point(1.0, 1.0, 1.0))
+            }
+            ShapeEnum::Plane(_) => Bounds::new(
+                point(-f64::INFINITY, 0.0, -f64::INFINITY),
+                point(f64::INFINITY, 0.0, f64::INFINITY),
+            ),
+            ShapeEnum::Cylinder(c) => Bounds::new(
+                point(-c.radius, c.minimum_y, -c.radius),
+                point(c.radius, c.maximum_y, c.radius),
+            ),
+            ShapeEnum::Cone(c) => {
+                let r = c.radius * f64::max(c.minimum_y.abs(), c.maximum_y.abs());
+                Bounds::new(point(-r, c.minimum_y, -r), point(r, c.maximum_y, r))
+            }
+            ShapeEnum::Capsule(c) => Bounds::new(
+                point(-c.radius, c.minimum_y - c.radius, -c.radius),
+                point(c.radius, c.maximum_y + c.radius, c.radius),
+            ),
+            ShapeEnum::Triangle(t) => {
+                let mut b = Bounds::default();
+                [t.p1, t.p2, t.p3].iter().for_each(|p| b.add_point(p));
+                b
+            }
+            ShapeEnum::SmoothTriangle(t) => {
+                let mut b = Bounds::default();
+                [t.p1, t.p2, t.p3].iter().for_each(|p| b.add_point(p));
+                b
+            }
+            // Depends on the members or prototype, which only the World knows
+            ShapeEnum::Group(_) | ShapeEnum::Instance(_) => Bounds::default(),
+        }
+    }
+
+    /// Bounding box of the shape in world space.
+    pub fn world_bounds(&self) -> Bounds {
+        self.bounds().transform(&self.world_transform)
+    }
+
     pub fn normal_at(&self, world_point: &Point) -> Vector {
+        self.normal_at_uv(world_point, None)
+    }
+
+    /// As `normal_at`, but using the hit's (u, v), if known, to interpolate
+    /// the normal across a smooth triangle.
+    pub fn normal_at_uv(&self, world_point: &Point, uv: Option<(f64, f64)>) -> Vector {
         // Why multiply by the inverse transpose?
         // https://stackoverflow.com/questions/13654401/why-transform-normals-with-the-transpose-of-the-inverse-of-the-modelview-matrix
-        let inverse_transform = inverse(&self.transform);
+        let inverse_transform = &self.world_inverse_transform;
         let local_point = inverse_transform * world_point;
-        let local_normal = self.local_normal_at(&local_point);
-        let mut world_normal = transpose(&inverse_transform) * local_normal;
+        let local_normal = match (&self.shape, uv) {
+            (ShapeEnum::SmoothTriangle(t), Some((u, v))) => t.local_normal_at_uv(u, v),
+            _ => self.local_normal_at(&local_point),
+        };
+        // A mirroring transform (negative determinant) needs no special case: the
+        // inverse transpose keeps the normal on the same side of the surface.
+        let mut world_normal = transpose(inverse_transform) * local_normal;
         world_normal.set_w(0.0);
         normalize(&world_normal)
     }
 }
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum ShapeEnum {
     Sphere(Sphere),
     Plane(Plane),
     Cube(Cube),
     Cylinder(Cylinder),
     Cone(Cone),
+    Capsule(Capsule),
+    Triangle(Triangle),
+    SmoothTriangle(SmoothTriangle),
+    Group(Group),
+    Instance(Instance),
+}
+
+impl ShapeEnum {
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            ShapeEnum::Sphere(_) => "sphere",
+            ShapeEnum::Plane(_) => "plane",
+            ShapeEnum::Cube(_) => "cube",
+            ShapeEnum::Cylinder(_) => "cylinder",
+            ShapeEnum::Cone(_) => "cone",
+            ShapeEnum::Capsule(_) => "capsule",
+            ShapeEnum::Triangle(_) => "triangle",
+            ShapeEnum::SmoothTriangle(_) => "smooth triangle",
+            ShapeEnum::Group(_) => "group",
+            ShapeEnum::Instance(_) => "instance",
+        }
+    }
 }
 
 impl Default for ShapeEnum {
@@ -146,12 +518,12 @@ impl Default for ShapeEnum {
 }
 
 pub trait ShapeTrait {
-    fn local_intersect(&self, local_ray: &Ray) -> Intersections;
+    fn local_intersect(&self, local_ray: &Ray) -> Intersections<'_>;
     fn local_normal_at(&self, local_point: &Point) -> Vector;
 }
 
 impl ShapeTrait for Shape {
-    fn local_intersect(&self, local_ray: &Ray) -> Intersections {
+    fn local_intersect(&self, local_ray: &Ray) -> Intersections<'_> {
         self.shape.local_intersect(local_ray)
     }
 
@@ -161,13 +533,18 @@ impl ShapeTrait for Shape {
 }
 
 impl ShapeTrait for ShapeEnum {
-    fn local_intersect(&self, local_ray: &Ray) -> Intersections {
+    fn local_intersect(&self, local_ray: &Ray) -> Intersections<'_> {
         match self {
             ShapeEnum::Sphere(ref sphere) => sphere.local_intersect(local_ray),
             ShapeEnum::Plane(ref plane) => plane.local_intersect(local_ray),
             ShapeEnum::Cube(ref cube) => cube.local_intersect(local_ray),
             ShapeEnum::Cylinder(ref cylinder) => cylinder.local_intersect(local_ray),
             ShapeEnum::Cone(ref cone) => cone.local_intersect(local_ray),
+            ShapeEnum::Capsule(ref capsule) => capsule.local_intersect(local_ray),
+            ShapeEnum::Triangle(ref triangle) => triangle.local_intersect(local_ray),
+            ShapeEnum::SmoothTriangle(ref triangle) => triangle.local_intersect(local_ray),
+            ShapeEnum::Group(ref group) => group.local_intersect(local_ray),
+            ShapeEnum::Instance(ref instance) => instance.local_intersect(local_ray),
         }
     }
 
@@ -178,6 +555,11 @@ impl ShapeTrait for ShapeEnum {
             ShapeEnum::Cube(ref cube) => cube.local_normal_at(local_point),
             ShapeEnum::Cylinder(ref cylinder) => cylinder.local_normal_at(local_point),
             ShapeEnum::Cone(ref cone) => cone.local_normal_at(local_point),
+            ShapeEnum::Capsule(ref capsule) => capsule.local_normal_at(local_point),
+            ShapeEnum::Triangle(ref triangle) => triangle.local_normal_at(local_point),
+            ShapeEnum::SmoothTriangle(ref triangle) => triangle.local_normal_at(local_point),
+            ShapeEnum::Group(ref group) => group.local_normal_at(local_point),
+            ShapeEnum::Instance(ref instance) => instance.local_normal_at(local_point),
         }
     }
 }
@@ -186,14 +568,34 @@ pub fn normal_at(object: &Shape, world_point: &Point) -> Vector {
     object.normal_at(world_point)
 }
 
+pub fn normal_at_uv(object: &Shape, world_point: &Point, uv: Option<(f64, f64)>) -> Vector {
+    object.normal_at_uv(world_point, uv)
+}
+
 pub fn sphere(id: i32) -> Shape {
     Shape::sphere(id)
 }
 
+pub fn sphere_auto() -> Shape {
+    Shape::sphere_auto()
+}
+
 pub fn glass_sphere() -> Shape {
     Shape::glass_sphere()
 }
 
+pub fn glass_plane() -> Shape {
+    Shape::glass_plane()
+}
+
+pub fn glass_cube() -> Shape {
+    Shape::glass_cube()
+}
+
+pub fn make_glass(shape: Shape) -> Shape {
+    shape.make_glass()
+}
+
 pub fn plane() -> Shape {
     Shape::plane()
 }
@@ -202,6 +604,10 @@ pub fn cube() -> Shape {
     Shape::cube()
 }
 
+pub fn cube_with_faces(materials: [Material; 6]) -> Shape {
+    Shape::cube_with_faces(materials)
+}
+
 pub fn infinite_cylinder() -> Shape {
     Shape::infinite_cylinder()
 }
@@ -210,10 +616,41 @@ pub fn cylinder(min_y: f64, max_y: f64, closed_min: bool, closed_max: bool) -> S
     Shape::cylinder(min_y, max_y, closed_min, closed_max)
 }
 
+pub fn try_cylinder(min_y: f64, max_y: f64, closed_min: bool, closed_max: bool) -> Result<Shape> {
+    Shape::try_cylinder(min_y, max_y, closed_min, closed_max)
+}
+
 pub fn cone() -> Shape {
     Shape::cone()
 }
 
+pub fn capsule(minimum_y: f64, maximum_y: f64, radius: f64) -> Shape {
+    Shape::capsule(minimum_y, maximum_y, radius)
+}
+
+pub fn triangle(p1: Point, p2: Point, p3: Point) -> Shape {
+    Shape::triangle(p1, p2, p3)
+}
+
+pub fn smooth_triangle(
+    p1: Point,
+    p2: Point,
+    p3: Point,
+    n1: Vector,
+    n2: Vector,
+    n3: Vector,
+) -> Shape {
+    Shape::smooth_triangle(p1, p2, p3, n1, n2, n3)
+}
+
+pub fn group() -> Shape {
+    Shape::group()
+}
+
+pub fn instance(prototype: ObjectIndex) -> Shape {
+    Shape::instance(prototype)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -224,9 +661,11 @@ mod test {
     use crate::transformations::{rotation_z, scaling, translation};
     use crate::tuples::{point, vector};
     use approx::assert_relative_eq;
+    use rstest::rstest;
     use std::f64::consts::{FRAC_1_SQRT_2, PI};
 
     #[test]
+    #[allow(clippy::useless_vec)]
     fn test_vec_of_shapes() {
         let v = vec![
             Shape {
@@ -246,6 +685,34 @@ mod test {
         assert!(matches!(v[1].shape, ShapeEnum::Plane { .. }));
     }
 
+    // The glass constructors give transparent shapes with the refractive index of glass
+    #[rstest]
+    #[case(glass_sphere(), sphere(0))]
+    #[case(glass_plane(), plane())]
+    #[case(glass_cube(), cube())]
+    fn glass_constructors(#[case] glass: Shape, #[case] plain: Shape) {
+        assert_eq!(glass.material.transparency, 1.0);
+        assert_eq!(glass.material.refractive_index, RefractiveIndex::GLASS);
+        assert_eq!(glass.shape, plain.shape);
+        assert_eq!(glass.transform, identity4());
+    }
+
+    // Making a shape glass preserves its geometry and transform
+    #[test]
+    fn make_glass_preserves_geometry_and_transform() {
+        let mut c = cylinder(-1.0, 2.0, true, false);
+        c.set_transform(&translation(1.0, 2.0, 3.0));
+        c.material.reflective = 0.4;
+        let glass = make_glass(c.clone());
+
+        assert_eq!(glass.shape, c.shape);
+        assert_eq!(glass.transform, c.transform);
+        assert_eq!(glass.inverse_transform, c.inverse_transform);
+        assert_eq!(glass.material.reflective, 0.4);
+        assert_eq!(glass.material.transparency, 1.0);
+        assert_eq!(glass.material.refractive_index, RefractiveIndex::GLASS);
+    }
+
     // Moved some tests from spheres.rs as they need to
     // work with transforms and materials.
 
@@ -329,6 +796,92 @@ mod test {
         assert_eq!(s.material, m);
     }
 
+    // Bounds of primitives in object space
+    #[test]
+    fn bounds_of_primitives() {
+        assert_eq!(
+            sphere(1).bounds(),
+            Bounds::new(point(-1.0, -1.0, -1.0), point(1.0, 1.0, 1.0))
+        );
+        assert_eq!(
+            cylinder(-2.0, 3.0, true, true).bounds(),
+            Bounds::new(point(-1.0, -2.0, -1.0), point(1.0, 3.0, 1.0))
+        );
+        let mut c = cone();
+        c.as_cone_primitive().unwrap().minimum_y = -3.0;
+        assert_eq!(
+            c.bounds(),
+            Bounds::new(point(-3.0, -3.0, -3.0), point(3.0, 0.0, 3.0))
+        );
+        assert_eq!(plane().bounds().min.x(), -f64::INFINITY);
+    }
+
+    // A non-invertible transform is rejected
+    #[test]
+    fn non_invertible_transform_is_rejected() {
+        let mut s = sphere(1);
+        let err = s.try_set_transform(&scaling(0.0, 1.0, 1.0)).unwrap_err();
+        assert!(err.to_string().contains("sphere"));
+        assert_eq!(*s.transform(), identity4());
+
+        assert!(s.try_set_transform(&scaling(2.0, 1.0, 1.0)).is_ok());
+        assert_eq!(*s.transform(), scaling(2.0, 1.0, 1.0));
+    }
+
+    // A cylinder with swapped y bounds is reported
+    #[test]
+    fn cylinder_with_swapped_bounds_is_rejected() {
+        let err = try_cylinder(2.0, 1.0, true, true).unwrap_err();
+        assert!(err.to_string().contains("swapped"), "{err}");
+        assert!(try_cylinder(1.0, 2.0, true, true).is_ok());
+    }
+
+    // A cylinder or cone with equal y bounds is degenerate
+    #[test]
+    fn cylinder_with_equal_bounds_is_degenerate() {
+        let err = try_cylinder(1.0, 1.0, true, true).unwrap_err();
+        assert!(err.to_string().contains("degenerate cylinder"), "{err}");
+
+        let mut c = cone();
+        let p = c.as_cone_primitive().unwrap();
+        p.minimum_y = 0.5;
+        p.maximum_y = 0.5;
+        let err = c.validate().unwrap_err();
+        assert!(err.to_string().contains("degenerate cone"), "{err}");
+        assert!(cone().validate().is_ok());
+        assert!(sphere(1).validate().is_ok());
+    }
+
+    // The convenience setter panics on a non-invertible transform
+    #[test]
+    #[should_panic(expected = "not invertible")]
+    fn set_transform_panics_if_not_invertible() {
+        let mut s = cube();
+        s.set_transform(&scaling(1.0, 0.0, 1.0));
+    }
+
+    // Bounds of a transformed shape in world space
+    #[test]
+    fn world_bounds_of_transformed_shape() {
+        let mut s = sphere(1);
+        s.set_transform(&(translation(5.0, 0.0, 0.0) * scaling(2.0, 1.0, 1.0)));
+        assert_eq!(
+            s.world_bounds(),
+            Bounds::new(point(3.0, -1.0, -1.0), point(7.0, 1.0, 1.0))
+        );
+    }
+
+    // Spheres may be created with automatically assigned ids
+    #[test]
+    fn sphere_with_auto_id() {
+        let mut a = sphere_auto();
+        let mut b = sphere_auto();
+        let a_id = a.as_sphere_primitive().unwrap().id;
+        let b_id = b.as_sphere_primitive().unwrap().id;
+        assert!(a_id < b_id);
+        assert_eq!(sphere(5).as_sphere_primitive().unwrap().id, 5);
+    }
+
     // Get access to internal primitive type
     #[test]
     fn get_primitive_shape() {
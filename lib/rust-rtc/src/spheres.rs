@@ -1,11 +1,20 @@
 // Chapter 5: Ray-Sphere Intersections
 
 use crate::intersections::{intersections, Intersection, Intersections};
+use crate::math::solve_quadratic;
 use crate::rays::Ray;
 use crate::tuples::{dot, normalize, point, Point, Vector};
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicI32, Ordering};
+
+// Source of automatically assigned sphere ids.
+static NEXT_SPHERE_ID: AtomicI32 = AtomicI32::new(1);
 
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub struct Sphere {
+    // A label for debugging and for telling spheres apart in test output.
+    // It plays no part in rendering, and need not be unique; for a stable
+    // identity use Shape::id() instead.
     pub id: i32,
 }
 
@@ -14,6 +23,14 @@ impl Sphere {
         Sphere { id }
     }
 
+    /// A sphere with the next automatically assigned id. Auto ids are unique and
+    /// increasing among themselves, but are not coordinated with ids given explicitly.
+    pub fn new_auto() -> Sphere {
+        Sphere {
+            id: NEXT_SPHERE_ID.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
     pub fn local_normal_at(&self, local_point: &Point) -> Vector {
         // Assume the point is always on the surface of the sphere
         let mut object_normal = local_point - point(0.0, 0.0, 0.0);
@@ -21,7 +38,7 @@ impl Sphere {
         normalize(&object_normal)
     }
 
-    pub fn local_intersect(&self, local_ray: &Ray) -> Intersections {
+    pub fn local_intersect(&self, local_ray: &Ray) -> Intersections<'_> {
         // TODO: A more stable algorithm at:
         // https://www.scratchapixel.com/lessons/3d-basic-rendering/minimal-ray-tracer-rendering-simple-shapes/ray-sphere-intersection.html
 
@@ -33,26 +50,13 @@ impl Sphere {
         let b = 2.0 * dot(&local_ray.direction, &sphere_to_ray);
         let c = dot(&sphere_to_ray, &sphere_to_ray) - 1.0;
 
-        let discriminant = b * b - 4.0 * a * c;
-        if discriminant < 0.0 {
+        let Some((t1, t2)) = solve_quadratic(a, b, c) else {
             // miss
             return intersections!();
-        }
-
-        let t1 = (-b - f64::sqrt(discriminant)) / (2.0 * a);
-        let t2 = (-b + f64::sqrt(discriminant)) / (2.0 * a);
+        };
 
         //intersections!(intersection(t1, None), intersection(t2, None))
-        intersections!(
-            Intersection {
-                t: t1,
-                object: None
-            },
-            Intersection {
-                t: t2,
-                object: None
-            }
-        )
+        intersections!(Intersection::new(t1, None), Intersection::new(t2, None))
     }
 }
 
@@ -61,6 +65,10 @@ pub fn sphere(id: i32) -> Sphere {
     Sphere::new(id)
 }
 
+pub fn sphere_auto() -> Sphere {
+    Sphere::new_auto()
+}
+
 pub fn local_normal_at(s: &Sphere, local_point: &Point) -> Vector {
     s.local_normal_at(local_point)
 }
@@ -69,11 +77,75 @@ pub fn local_intersect<'a>(s: &'a Sphere, local_ray: &Ray) -> Intersections<'a>
     s.local_intersect(local_ray)
 }
 
+/// Spherical (latitude/longitude) (u, v) coordinates of `p`, a point on a sphere
+/// centred at the origin, each in [0, 1]. Seen from outside, u increases
+/// anticlockwise around +y, from the seam behind the sphere (along -z), through
+/// +x (0.25), +z (0.5) and -x (0.75). It wraps to [0, 1), so both sides of the
+/// seam meet in a repeating image. v increases from the south pole (0) to the
+/// north pole (1), where u is 0.5.
+pub fn spherical_uv(p: &Point) -> (f64, f64) {
+    // Adding 0.0 turns -0.0 into 0.0, so points on the y axis aren't on the seam
+    let theta = f64::atan2(p.x() + 0.0, p.z() + 0.0);
+    let radius = (*p - point(0.0, 0.0, 0.0)).magnitude();
+    let phi = if radius == 0.0 {
+        PI / 2.0
+    } else {
+        (p.y() / radius).clamp(-1.0, 1.0).acos()
+    };
+
+    let u = (0.5 - theta / (2.0 * PI)).rem_euclid(1.0);
+    let v = 1.0 - phi / PI;
+    (u, v)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::rays::ray;
     use crate::tuples::{point, vector};
+    use approx::assert_relative_eq;
+    use rstest::rstest;
+
+    // Using a spherical mapping on a 3D point, including the poles and the front
+    // and back of the equator
+    #[rstest]
+    #[case(point(0.0, 0.0, -1.0), 0.0, 0.5)]
+    #[case(point(1.0, 0.0, 0.0), 0.25, 0.5)]
+    #[case(point(0.0, 0.0, 1.0), 0.5, 0.5)]
+    #[case(point(-1.0, 0.0, 0.0), 0.75, 0.5)]
+    #[case(point(0.0, 1.0, 0.0), 0.5, 1.0)]
+    #[case(point(0.0, -1.0, 0.0), 0.5, 0.0)]
+    #[case(point(-0.0, 1.0, -0.0), 0.5, 1.0)]
+    #[case(
+        point(std::f64::consts::FRAC_1_SQRT_2, std::f64::consts::FRAC_1_SQRT_2, 0.0),
+        0.25,
+        0.75
+    )]
+    #[case(point(0.0, 0.0, -2.0), 0.0, 0.5)]
+    fn spherical_mapping(#[case] p: Point, #[case] u: f64, #[case] v: f64) {
+        let (actual_u, actual_v) = spherical_uv(&p);
+        assert_relative_eq!(actual_u, u);
+        assert_relative_eq!(actual_v, v);
+    }
+
+    // u is continuous across the seam, wrapping from just below 1.0 to just above 0.0
+    #[rstest]
+    #[case(0.001)]
+    #[case(-0.001)]
+    fn spherical_mapping_is_continuous_across_seam(#[case] x: f64) {
+        let (u, _) = spherical_uv(&point(x, 0.0, -1.0));
+        let (u_seam, _) = spherical_uv(&point(-0.0, 0.0, -1.0));
+        assert!((0.0..1.0).contains(&u));
+        assert_eq!(u_seam, 0.0);
+
+        let distance = (u - u_seam).rem_euclid(1.0);
+        assert!(distance.min(1.0 - distance) < 0.001);
+        if x > 0.0 {
+            assert!(u < 0.5);
+        } else {
+            assert!(u > 0.5);
+        }
+    }
 
     // A ray intersects a sphere at two points
     #[test]
@@ -184,4 +256,17 @@ mod tests {
         let n = local_normal_at(&s, &point(k, k, k));
         assert_eq!(n, normalize(&n));
     }
+
+    // Automatically assigned sphere ids are unique and increasing
+    #[test]
+    fn sphere_auto_ids_are_unique_and_increasing() {
+        let a = sphere_auto();
+        let b = sphere_auto();
+        let c = sphere_auto();
+        assert!(a.id < b.id);
+        assert!(b.id < c.id);
+
+        // Explicit ids are unaffected
+        assert_eq!(sphere(5).id, 5);
+    }
 }
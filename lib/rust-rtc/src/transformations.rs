@@ -1,7 +1,9 @@
 // Chapter 4: Transformations
 
-use crate::matrices::{matrix4, Matrix4};
-use crate::tuples::{cross, normalize, Point, Vector};
+use crate::math::EPSILON;
+use crate::matrices::{identity4, matrix4, Matrix4};
+use crate::tuples::{cross, dot, magnitude, normalize, vector, Point, Vector};
+use anyhow::{bail, Result};
 
 #[rustfmt::skip]
 pub fn translation(x: f64, y: f64, z: f64) -> Matrix4 {
@@ -85,11 +87,85 @@ pub fn shearing(xy: f64, xz: f64, yx: f64, yz: f64, zx: f64, zy: f64) -> Matrix4
     ])
 }
 
-pub fn view_transform(from: &Point, to: &Point, up: &Vector) -> Matrix4 {
+// Rotation by `radians` about the unit vector `axis`, anticlockwise when looking
+// back along it (Rodrigues' rotation formula)
+#[rustfmt::skip]
+fn rotation_about(axis: &Vector, radians: f64) -> Matrix4 {
+    let (x, y, z) = (axis.x(), axis.y(), axis.z());
+    let c = f64::cos(radians);
+    let s = f64::sin(radians);
+    let t = 1.0 - c;
+    matrix4(&[
+        [t * x * x + c,     t * x * y - s * z, t * x * z + s * y, 0.0],
+        [t * x * y + s * z, t * y * y + c,     t * y * z - s * x, 0.0],
+        [t * x * z - s * y, t * y * z + s * x, t * z * z + c,     0.0],
+        [0.0,               0.0,               0.0,               1.0],
+    ])
+}
+
+/// The smallest rotation that turns the direction of `from` into the direction of
+/// `to`, e.g. to aim a cylinder, which lies along +y, along a pipe. Opposite vectors
+/// are turned half a revolution about an axis perpendicular to both.
+pub fn rotation_between(from: &Vector, to: &Vector) -> Matrix4 {
+    let from = normalize(from);
+    let to = normalize(to);
+    let axis = cross(&from, &to);
+    let sin = magnitude(&axis);
+    let cos = dot(&from, &to);
+
+    if sin >= EPSILON {
+        rotation_about(&(axis / sin), f64::atan2(sin, cos))
+    } else if cos > 0.0 {
+        identity4()
+    } else {
+        // Any perpendicular axis will do
+        let mut axis = cross(&from, &vector(1.0, 0.0, 0.0));
+        if magnitude(&axis) < EPSILON {
+            axis = cross(&from, &vector(0.0, 1.0, 0.0));
+        }
+        rotation_about(&normalize(&axis), std::f64::consts::PI)
+    }
+}
+
+/// The camera's `(left, true_up, forward)` vectors in world space, for a camera at
+/// `from` looking at `to`, with `up` roughly up. `forward` is a unit vector, and so
+/// are the others when `up` is perpendicular to the line of sight; otherwise, as in
+/// the book, they're shortened by the sine of the angle between them.
+///
+/// Handedness: `left` is `forward × up`, so the basis is left-handed in the sense
+/// that, looking along -z with +y up (the default orientation), `left` is +x. The
+/// camera renders camera-space +x on the left of the image, so world +x appears
+/// on the left when looking toward -z, and on the right when looking toward +z.
+/// Changing the order of either cross product mirrors every image.
+///
+/// If `up` is parallel to the line of sight, it can't orient the camera, so +z is
+/// used as up instead (or +y, when looking along z).
+pub fn view_basis(from: &Point, to: &Point, up: &Vector) -> (Vector, Vector, Vector) {
     let forward = normalize(&(to - from));
-    let upn = normalize(up);
-    let left = cross(&forward, &upn);
+    let mut left = cross(&forward, &normalize(up));
+    if magnitude(&left) < EPSILON {
+        left = normalize(&cross(&forward, &fallback_up(&forward)));
+    }
     let true_up = cross(&left, &forward);
+    (left, true_up, forward)
+}
+
+// An up direction that isn't parallel to `forward`
+fn fallback_up(forward: &Vector) -> Vector {
+    let z = vector(0.0, 0.0, 1.0);
+    if magnitude(&cross(forward, &z)) < EPSILON {
+        vector(0.0, 1.0, 0.0)
+    } else {
+        z
+    }
+}
+
+/// The transform from world space to camera space, in which the camera is at the
+/// origin looking along -z, `left` (see `view_basis`) is +x, and up is +y. An `up`
+/// parallel to the line of sight is replaced, as by `view_basis`; use
+/// `try_view_transform` to treat that as an error instead.
+pub fn view_transform(from: &Point, to: &Point, up: &Vector) -> Matrix4 {
+    let (left, true_up, forward) = view_basis(from, to, up);
 
     #[rustfmt::skip]
     let orientation = matrix4(&[
@@ -102,14 +178,35 @@ pub fn view_transform(from: &Point, to: &Point, up: &Vector) -> Matrix4 {
     orientation * translation(-from.x(), -from.y(), -from.z())
 }
 
+/// As `view_transform`, but an error if `from` and `to` are the same point, or
+/// `up` is parallel to the line of sight, rather than choosing another up.
+pub fn try_view_transform(from: &Point, to: &Point, up: &Vector) -> Result<Matrix4> {
+    let forward = to - from;
+    if magnitude(&forward) < EPSILON {
+        bail!("The camera must look at a point other than its own position");
+    }
+    if magnitude(&cross(&normalize(&forward), &normalize(up))) < EPSILON {
+        bail!("The camera's up vector must not be parallel to its line of sight");
+    }
+    Ok(view_transform(from, to, up))
+}
+
+/// As `view_transform`, with the world's +y as up, and the camera then rolled
+/// anticlockwise about its line of sight by `roll` radians, so that the scene
+/// appears turned clockwise. Looking straight up or down, +z is taken as up instead.
+pub fn view_transform_roll(from: &Point, to: &Point, roll: f64) -> Matrix4 {
+    rotation_z(roll) * view_transform(from, to, &vector(0.0, 1.0, 0.0))
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(non_snake_case)]
 
     use super::*;
-    use crate::matrices::{identity4, inverse};
+    use crate::matrices::{identity4, inverse, transpose};
     use crate::tuples::{point, vector};
     use approx::assert_relative_eq;
+    use rstest::rstest;
     use std::f64::consts::PI;
 
     // Multiplying by a translation matrix
@@ -329,6 +426,89 @@ mod tests {
         assert_eq!(t, identity4());
     }
 
+    // The view basis for the default orientation pins the handedness: looking
+    // toward -z with +y up, +x is to the left
+    #[test]
+    fn view_basis_for_default_orientation() {
+        let from = point(0.0, 0.0, 0.0);
+        let to = point(0.0, 0.0, -1.0);
+        let up = vector(0.0, 1.0, 0.0);
+        let (left, true_up, forward) = view_basis(&from, &to, &up);
+        assert_orthonormal(&left, &true_up, &forward);
+        assert_eq!(left, vector(1.0, 0.0, 0.0));
+        assert_eq!(true_up, vector(0.0, 1.0, 0.0));
+        assert_eq!(forward, vector(0.0, 0.0, -1.0));
+
+        // The view transform maps the basis onto the camera's axes
+        let t = view_transform(&from, &to, &up);
+        assert_eq!(t * left, vector(1.0, 0.0, 0.0));
+        assert_eq!(t * true_up, vector(0.0, 1.0, 0.0));
+        assert_eq!(t * forward, vector(0.0, 0.0, -1.0));
+    }
+
+    fn assert_orthonormal(left: &Vector, true_up: &Vector, forward: &Vector) {
+        for v in [left, true_up, forward] {
+            assert_relative_eq!(magnitude(v), 1.0, epsilon = 1e-12);
+        }
+        assert_relative_eq!(dot(left, true_up), 0.0, epsilon = 1e-12);
+        assert_relative_eq!(dot(left, forward), 0.0, epsilon = 1e-12);
+        assert_relative_eq!(dot(true_up, forward), 0.0, epsilon = 1e-12);
+    }
+
+    // The camera's basis is orthonormal when up is perpendicular to the line of
+    // sight, or parallel to it and so replaced
+    #[rstest]
+    #[case(point(0.0, 0.0, 5.0), point(0.0, 0.0, 0.0), vector(0.0, 2.0, 0.0))]
+    #[case(point(2.0, 2.0, 2.0), point(0.0, 0.0, 0.0), vector(-1.0, -1.0, -1.0))]
+    #[case(point(0.0, 5.0, 0.0), point(0.0, 0.0, 0.0), vector(0.0, 1.0, 0.0))]
+    #[case(point(0.0, 0.0, -5.0), point(0.0, 0.0, 0.0), vector(0.0, 0.0, 3.0))]
+    fn view_basis_is_orthonormal(#[case] from: Point, #[case] to: Point, #[case] up: Vector) {
+        let (left, true_up, forward) = view_basis(&from, &to, &up);
+        assert_orthonormal(&left, &true_up, &forward);
+
+        // So the view transform's rotation is orthonormal too
+        let rotation = view_transform(&from, &to, &up) * translation(from.x(), from.y(), from.z());
+        assert_relative_eq!(rotation.determinant(), 1.0, epsilon = 1e-12);
+        assert_relative_eq!(
+            transpose(&rotation) * rotation,
+            identity4(),
+            epsilon = 1e-12
+        );
+    }
+
+    // An up vector parallel to the line of sight is replaced, rather than giving a
+    // degenerate transform
+    #[rstest]
+    #[case(point(0.0, 5.0, 0.0), vector(0.0, 1.0, 0.0))]
+    #[case(point(0.0, -5.0, 0.0), vector(0.0, 3.0, 0.0))]
+    #[case(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0))]
+    #[case(point(2.0, 2.0, 2.0), vector(-1.0, -1.0, -1.0))]
+    fn view_transformation_with_up_parallel_to_line_of_sight(
+        #[case] from: Point,
+        #[case] up: Vector,
+    ) {
+        let to = point(0.0, 0.0, 0.0);
+        let t = view_transform(&from, &to, &up);
+        let (left, true_up, forward) = view_basis(&from, &to, &up);
+        assert_orthonormal(&left, &true_up, &forward);
+        assert!(t.determinant().abs() > 0.1, "{t:?}");
+        assert_relative_eq!(t.inverse() * t, identity4(), epsilon = 1e-9);
+
+        // The camera still looks at `to`, from `from`
+        assert_relative_eq!(
+            t * to,
+            point(0.0, 0.0, -magnitude(&(from - to))),
+            epsilon = 1e-9
+        );
+
+        assert!(try_view_transform(&from, &to, &up).is_err());
+        assert!(try_view_transform(&from, &from, &vector(0.0, 1.0, 0.0)).is_err());
+        assert_eq!(
+            try_view_transform(&from, &to, &vector(1.0, 0.0, 0.0)).unwrap(),
+            view_transform(&from, &to, &vector(1.0, 0.0, 0.0))
+        );
+    }
+
     // A view transformation matrix looking in positive Z direction
     #[test]
     fn view_transformation_matrix_looking_in_positive_z_direction() {
@@ -349,6 +529,53 @@ mod tests {
         assert_eq!(t, translation(0.0, 0.0, -8.0));
     }
 
+    // With no roll, the view transformation has +y up
+    #[test]
+    fn view_transformation_without_roll() {
+        let from = point(1.0, 3.0, 2.0);
+        let to = point(4.0, -2.0, 8.0);
+        let up = vector(0.0, 1.0, 0.0);
+        assert_relative_eq!(
+            view_transform_roll(&from, &to, 0.0),
+            view_transform(&from, &to, &up),
+            epsilon = 1e-12
+        );
+    }
+
+    // Rolling the view by half a turn flips the image
+    #[test]
+    fn view_transformation_rolled_half_turn() {
+        let from = point(0.0, 0.0, -5.0);
+        let to = point(0.0, 0.0, 0.0);
+        let t = view_transform_roll(&from, &to, PI);
+
+        // Above the line of sight becomes below it (and left becomes right)
+        let unrolled = view_transform_roll(&from, &to, 0.0) * point(0.5, 1.0, 0.0);
+        assert_relative_eq!(unrolled, point(-0.5, 1.0, -5.0), epsilon = 1e-12);
+        let p = t * point(0.5, 1.0, 0.0);
+        assert_relative_eq!(p, point(0.5, -1.0, -5.0), epsilon = 1e-12);
+    }
+
+    // Rolling the view a quarter turn moves the left of the image to the top
+    #[test]
+    fn view_transformation_rolled_quarter_turn() {
+        let t = view_transform_roll(&point(0.0, 0.0, 0.0), &point(0.0, 0.0, 1.0), PI / 2.0);
+        let left = t * point(-1.0, 0.0, 1.0);
+        assert_relative_eq!(left, point(0.0, 1.0, -1.0), epsilon = 1e-12);
+    }
+
+    // Looking straight down still gives a valid view transformation
+    #[test]
+    fn view_transformation_roll_looking_straight_down() {
+        let t = view_transform_roll(&point(0.0, 5.0, 0.0), &point(0.0, 0.0, 0.0), 0.0);
+        assert!(t.is_invertible());
+        assert_relative_eq!(
+            t * point(0.0, 0.0, 0.0),
+            point(0.0, 0.0, -5.0),
+            epsilon = 1e-12
+        );
+    }
+
     // An arbitrary view transformation
     #[test]
     fn arbitrary_view_transformation() {
@@ -364,4 +591,54 @@ mod tests {
                 [  0.00000, 0.00000,  0.00000,  1.00000],
         ]), epsilon=1e-5);
     }
+
+    // Rotating +y onto +x
+    #[test]
+    fn rotation_between_y_and_x() {
+        let t = rotation_between(&vector(0.0, 1.0, 0.0), &vector(1.0, 0.0, 0.0));
+        assert_relative_eq!(t, rotation_z(-PI / 2.0), epsilon = 1e-12);
+        assert_relative_eq!(
+            t * vector(0.0, 1.0, 0.0),
+            vector(1.0, 0.0, 0.0),
+            epsilon = 1e-12
+        );
+        // The axis of rotation, perpendicular to both, is left alone
+        assert_relative_eq!(
+            t * vector(0.0, 0.0, 1.0),
+            vector(0.0, 0.0, 1.0),
+            epsilon = 1e-12
+        );
+    }
+
+    // Rotating between vectors of any length aligns their directions
+    #[rstest]
+    #[case(vector(0.0, 2.0, 0.0), vector(1.0, 1.0, 1.0))]
+    #[case(vector(3.0, -1.0, 0.5), vector(-0.2, 0.4, 2.0))]
+    #[case(vector(0.0, 0.0, 1.0), vector(0.0, 1.0, -0.001))]
+    fn rotation_between_aligns_directions(#[case] from: Vector, #[case] to: Vector) {
+        let t = rotation_between(&from, &to);
+        assert_relative_eq!(t * normalize(&from), normalize(&to), epsilon = 1e-12);
+        assert_relative_eq!(t.determinant(), 1.0, epsilon = 1e-12);
+    }
+
+    // Rotating a vector onto itself is no rotation at all
+    #[test]
+    fn rotation_between_identical_vectors() {
+        let v = vector(1.0, 2.0, 3.0);
+        assert_relative_eq!(rotation_between(&v, &(v * 2.0)), identity4());
+    }
+
+    // Rotating a vector onto its opposite turns half a revolution about a
+    // perpendicular axis
+    #[rstest]
+    #[case(vector(0.0, 1.0, 0.0))]
+    #[case(vector(1.0, 0.0, 0.0))]
+    #[case(vector(1.0, -2.0, 0.5))]
+    fn rotation_between_opposite_vectors(#[case] v: Vector) {
+        let t = rotation_between(&v, &-v);
+        assert_relative_eq!(t * v, -v, epsilon = 1e-12);
+        assert_relative_eq!(t.determinant(), 1.0, epsilon = 1e-12);
+        // Twice is a full revolution
+        assert_relative_eq!(t * t, identity4(), epsilon = 1e-12);
+    }
 }
@@ -0,0 +1,217 @@
+// Chapter 15: Triangles
+
+use crate::intersections::{Intersection, Intersections};
+use crate::math::EPSILON;
+use crate::rays::Ray;
+use crate::tuples::{cross, dot, normalize, Point, Vector};
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Triangle {
+    pub p1: Point,
+    pub p2: Point,
+    pub p3: Point,
+    e1: Vector,
+    e2: Vector,
+    normal: Vector,
+}
+
+impl Triangle {
+    pub fn new(p1: Point, p2: Point, p3: Point) -> Self {
+        let e1 = p2 - p1;
+        let e2 = p3 - p1;
+        Triangle {
+            p1,
+            p2,
+            p3,
+            e1,
+            e2,
+            normal: normalize(&cross(&e2, &e1)),
+        }
+    }
+
+    pub fn e1(&self) -> &Vector {
+        &self.e1
+    }
+
+    pub fn e2(&self) -> &Vector {
+        &self.e2
+    }
+
+    pub fn local_normal_at(&self, _local_point: &Point) -> Vector {
+        self.normal
+    }
+
+    pub fn local_intersect(&self, local_ray: &Ray) -> Intersections<'_> {
+        match intersect_triangle(&self.p1, &self.e1, &self.e2, local_ray) {
+            Some(i) => vec![i],
+            None => vec![],
+        }
+    }
+}
+
+// A triangle with a normal at each vertex, interpolated across its face.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct SmoothTriangle {
+    pub p1: Point,
+    pub p2: Point,
+    pub p3: Point,
+    pub n1: Vector,
+    pub n2: Vector,
+    pub n3: Vector,
+    e1: Vector,
+    e2: Vector,
+}
+
+impl SmoothTriangle {
+    pub fn new(p1: Point, p2: Point, p3: Point, n1: Vector, n2: Vector, n3: Vector) -> Self {
+        SmoothTriangle {
+            p1,
+            p2,
+            p3,
+            n1,
+            n2,
+            n3,
+            e1: p2 - p1,
+            e2: p3 - p1,
+        }
+    }
+
+    // Without the hit's u/v, the best we can do is the average normal
+    pub fn local_normal_at(&self, _local_point: &Point) -> Vector {
+        normalize(&(self.n1 + self.n2 + self.n3))
+    }
+
+    pub fn local_normal_at_uv(&self, u: f64, v: f64) -> Vector {
+        self.n2 * u + self.n3 * v + self.n1 * (1.0 - u - v)
+    }
+
+    pub fn local_intersect(&self, local_ray: &Ray) -> Intersections<'_> {
+        match intersect_triangle(&self.p1, &self.e1, &self.e2, local_ray) {
+            Some(i) => vec![i],
+            None => vec![],
+        }
+    }
+}
+
+// Möller–Trumbore, recording the barycentric u/v of the hit
+fn intersect_triangle<'a>(
+    p1: &Point,
+    e1: &Vector,
+    e2: &Vector,
+    ray: &Ray,
+) -> Option<Intersection<'a>> {
+    let dir_cross_e2 = cross(&ray.direction, e2);
+    let det = dot(e1, &dir_cross_e2);
+    if det.abs() < EPSILON {
+        return None;
+    }
+
+    let f = 1.0 / det;
+    let p1_to_origin = ray.origin - p1;
+    let u = f * dot(&p1_to_origin, &dir_cross_e2);
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let origin_cross_e1 = cross(&p1_to_origin, e1);
+    let v = f * dot(&ray.direction, &origin_cross_e1);
+    if v < 0.0 || (u + v) > 1.0 {
+        return None;
+    }
+
+    let t = f * dot(e2, &origin_cross_e1);
+    Some(Intersection::with_uv(t, None, u, v))
+}
+
+pub fn triangle(p1: Point, p2: Point, p3: Point) -> Triangle {
+    Triangle::new(p1, p2, p3)
+}
+
+pub fn smooth_triangle(
+    p1: Point,
+    p2: Point,
+    p3: Point,
+    n1: Vector,
+    n2: Vector,
+    n3: Vector,
+) -> SmoothTriangle {
+    SmoothTriangle::new(p1, p2, p3, n1, n2, n3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rays::ray;
+    use crate::tuples::{point, vector};
+    use approx::assert_relative_eq;
+    use rstest::rstest;
+
+    fn default_triangle() -> Triangle {
+        triangle(
+            point(0.0, 1.0, 0.0),
+            point(-1.0, 0.0, 0.0),
+            point(1.0, 0.0, 0.0),
+        )
+    }
+
+    fn default_smooth_triangle() -> SmoothTriangle {
+        smooth_triangle(
+            point(0.0, 1.0, 0.0),
+            point(-1.0, 0.0, 0.0),
+            point(1.0, 0.0, 0.0),
+            vector(0.0, 1.0, 0.0),
+            vector(-1.0, 0.0, 0.0),
+            vector(1.0, 0.0, 0.0),
+        )
+    }
+
+    // Constructing a triangle
+    #[test]
+    fn constructing_a_triangle() {
+        let t = default_triangle();
+        assert_eq!(*t.e1(), vector(-1.0, -1.0, 0.0));
+        assert_eq!(*t.e2(), vector(1.0, -1.0, 0.0));
+        assert_eq!(
+            t.local_normal_at(&point(0.0, 0.5, 0.0)),
+            vector(0.0, 0.0, -1.0)
+        );
+    }
+
+    // Intersecting a ray parallel to the triangle, and missing each edge
+    #[rstest]
+    #[case(point(0.0, -1.0, -2.0), vector(0.0, 1.0, 0.0))]
+    #[case(point(1.0, 1.0, -2.0), vector(0.0, 0.0, 1.0))]
+    #[case(point(-1.0, 1.0, -2.0), vector(0.0, 0.0, 1.0))]
+    #[case(point(0.0, -1.0, -2.0), vector(0.0, 0.0, 1.0))]
+    fn ray_misses_triangle(#[case] origin: Point, #[case] direction: Vector) {
+        let t = default_triangle();
+        assert!(t.local_intersect(&ray(origin, direction)).is_empty());
+    }
+
+    // A ray strikes a triangle
+    #[test]
+    fn ray_strikes_triangle() {
+        let t = default_triangle();
+        let xs = t.local_intersect(&ray(point(0.0, 0.5, -2.0), vector(0.0, 0.0, 1.0)));
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].t, 2.0);
+    }
+
+    // An intersection with a smooth triangle stores u/v
+    #[test]
+    fn smooth_triangle_intersection_stores_uv() {
+        let t = default_smooth_triangle();
+        let r = ray(point(-0.2, 0.3, -2.0), vector(0.0, 0.0, 1.0));
+        let xs = t.local_intersect(&r);
+        assert_relative_eq!(xs[0].u.unwrap(), 0.45, epsilon = 1e-9);
+        assert_relative_eq!(xs[0].v.unwrap(), 0.25, epsilon = 1e-9);
+    }
+
+    // A smooth triangle uses u/v to interpolate the normal
+    #[test]
+    fn smooth_triangle_interpolates_normal() {
+        let t = default_smooth_triangle();
+        let n = normalize(&t.local_normal_at_uv(0.45, 0.25));
+        assert_relative_eq!(n, vector(-0.5547, 0.83205, 0.0), epsilon = 1e-5);
+    }
+}
@@ -1,5 +1,6 @@
 use glam::f64::DVec4;
 
+use approx::{AbsDiffEq, RelativeEq};
 use derive_more::Neg;
 
 #[derive(Debug, Default, PartialEq, Copy, Clone, Neg)]
@@ -86,6 +87,32 @@ impl Tuple {
             w: 0.0,
         })
     }
+
+    /// Reflect this vector about the normal `n`. As `Point` and `Vector` are the same
+    /// type, mixing them up is only caught in debug builds.
+    pub fn reflect(&self, n: &Self) -> Self {
+        debug_assert!(self.is_vector() && n.is_vector(), "reflect needs vectors");
+        self - n * 2.0 * self.dot(n)
+    }
+
+    /// Two unit vectors perpendicular to this unit vector and to each other, e.g. axes
+    /// in the tangent plane of a surface with this normal.
+    pub fn tangent_basis(&self) -> (Self, Self) {
+        let axis = if self.x().abs() > 0.9 {
+            vector(0.0, 1.0, 0.0)
+        } else {
+            vector(1.0, 0.0, 0.0)
+        };
+        let u = axis.cross(self).normalize();
+        let v = self.cross(&u);
+        (u, v)
+    }
+
+    /// Distance from this point to `other`.
+    pub fn distance(&self, other: &Self) -> f64 {
+        debug_assert!(self.is_point() && other.is_point(), "distance needs points");
+        (self - other).magnitude()
+    }
 }
 
 macro_rules! tuple_muls {
@@ -195,6 +222,32 @@ impl From<DVec4> for Tuple {
     }
 }
 
+// Approximate equality, for assert_relative_eq! and friends
+impl AbsDiffEq for Tuple {
+    type Epsilon = f64;
+
+    fn default_epsilon() -> f64 {
+        f64::default_epsilon()
+    }
+
+    fn abs_diff_eq(&self, other: &Self, epsilon: f64) -> bool {
+        self.0.abs_diff_eq(other.0, epsilon)
+    }
+}
+
+impl RelativeEq for Tuple {
+    fn default_max_relative() -> f64 {
+        f64::default_max_relative()
+    }
+
+    fn relative_eq(&self, other: &Self, epsilon: f64, max_relative: f64) -> bool {
+        f64::relative_eq(&self.0.x, &other.0.x, epsilon, max_relative)
+            && f64::relative_eq(&self.0.y, &other.0.y, epsilon, max_relative)
+            && f64::relative_eq(&self.0.z, &other.0.z, epsilon, max_relative)
+            && f64::relative_eq(&self.0.w, &other.0.w, epsilon, max_relative)
+    }
+}
+
 pub fn tuple(x: f64, y: f64, z: f64, w: f64) -> Tuple {
     Tuple::new(x, y, z, w)
 }
@@ -230,32 +283,8 @@ pub fn reflect(incoming: &Tuple, normal: &Tuple) -> Tuple {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use approx::{assert_relative_eq, AbsDiffEq, RelativeEq};
-
-    impl AbsDiffEq for Tuple {
-        type Epsilon = f64;
-
-        fn default_epsilon() -> f64 {
-            f64::default_epsilon()
-        }
-
-        fn abs_diff_eq(&self, other: &Self, epsilon: f64) -> bool {
-            self.0.abs_diff_eq(other.0, epsilon)
-        }
-    }
-
-    impl RelativeEq for Tuple {
-        fn default_max_relative() -> f64 {
-            f64::default_max_relative()
-        }
-
-        fn relative_eq(&self, other: &Self, epsilon: f64, max_relative: f64) -> bool {
-            f64::relative_eq(&self.0.x, &other.0.x, epsilon, max_relative)
-                && f64::relative_eq(&self.0.y, &other.0.y, epsilon, max_relative)
-                && f64::relative_eq(&self.0.z, &other.0.z, epsilon, max_relative)
-                && f64::relative_eq(&self.0.w, &other.0.w, epsilon, max_relative)
-        }
-    }
+    use approx::assert_relative_eq;
+    use rstest::rstest;
 
     // Conversion from a DVec4
     #[test]
@@ -509,6 +538,49 @@ mod tests {
         assert_eq!(r, vector(1.0, 1.0, 0.0));
     }
 
+    // Reflecting with the method matches the free function
+    #[rstest]
+    #[case(vector(1.0, -1.0, 0.0), vector(0.0, 1.0, 0.0))]
+    #[case(vector(0.0, -1.0, 0.0), vector(f64::sqrt(2.0) / 2.0, f64::sqrt(2.0) / 2.0, 0.0))]
+    #[case(vector(0.3, -0.5, 2.0), vector(0.0, 0.0, -1.0))]
+    fn reflect_method_matches_function(#[case] v: Vector, #[case] n: Vector) {
+        assert_eq!(v.reflect(&n), reflect(&v, &n));
+    }
+
+    // The distance between two points
+    #[rstest]
+    #[case(point(1.0, 2.0, 3.0), point(1.0, 2.0, 3.0), 0.0)]
+    #[case(point(0.0, 0.0, 0.0), point(3.0, 4.0, 0.0), 5.0)]
+    #[case(point(-1.0, 2.0, -3.0), point(1.0, -2.0, 3.0), f64::sqrt(56.0))]
+    fn distance_between_points(#[case] p: Point, #[case] q: Point, #[case] expected: f64) {
+        assert_relative_eq!(p.distance(&q), expected);
+        assert_relative_eq!(p.distance(&q), magnitude(&(p - q)));
+        assert_relative_eq!(q.distance(&p), expected);
+    }
+
+    // Dot and cross products with the methods match the free functions
+    #[test]
+    fn dot_and_cross_methods_match_functions() {
+        let a = vector(1.0, 2.0, 3.0);
+        let b = vector(2.0, 3.0, 4.0);
+        assert_eq!(a.dot(&b), dot(&a, &b));
+        assert_eq!(a.cross(&b), cross(&a, &b));
+    }
+
+    // A tangent basis is orthonormal, and perpendicular to the vector
+    #[rstest]
+    #[case(vector(0.0, 1.0, 0.0))]
+    #[case(vector(1.0, 0.0, 0.0))]
+    #[case(vector(1.0, -2.0, 3.0).normalize())]
+    fn tangent_basis_is_orthonormal(#[case] n: Tuple) {
+        let (u, v) = n.tangent_basis();
+        assert_relative_eq!(u.magnitude(), 1.0, epsilon = 1e-12);
+        assert_relative_eq!(v.magnitude(), 1.0, epsilon = 1e-12);
+        for (a, b) in [(u, v), (u, n), (v, n)] {
+            assert_relative_eq!(a.dot(&b), 0.0, epsilon = 1e-12);
+        }
+    }
+
     // Reflecting a vector off a slanted surface
     #[test]
     fn reflecting_vector_off_slanted_surface() {
@@ -1,16 +1,28 @@
-use crate::camera::{camera, Resolution};
-use crate::canvas::{ppm_from_canvas, Canvas};
+use crate::bounds::Bounds;
+use crate::camera::{camera, Camera, Resolution};
+use crate::canvas::Canvas;
+use crate::colors::{Color, BLACK};
+use crate::json::load_json5;
 use crate::math::MAX_RECURSIVE_DEPTH;
 use crate::matrices::{identity4, Matrix4};
-use crate::world::World;
+use crate::shapes::{cube, plane, Shape};
+use crate::transformations::uniform_scaling;
+use crate::tuples::{Point, Vector};
+use crate::world::{
+    color_at, color_at_fogged, hit_distance, shadow_catcher_at, Fog, ObjectIndex, World,
+};
+use crate::world_loader::load_world;
+use anyhow::Context;
 use clap::{Args, Parser, ValueEnum};
+use serde::{Deserialize, Serialize};
 use std::f64::consts::PI;
 use std::fs::File;
 use std::io;
-use std::io::Write;
-use std::path::Path;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
-#[derive(Args)]
+#[derive(Args, Clone)]
 #[clap(author, version, about, long_about = None)]
 pub struct RenderArgs {
     /// Optional output filename (omit, or use - for stdout)
@@ -68,6 +80,34 @@ pub struct RenderArgs {
     #[arg(short = 'n', long = "vdiv", default_value_t = 8)]
     #[arg(value_parser = clap::value_parser!(u32).range(1..))]
     pub vdiv: u32,
+
+    /// Supersampling anti-aliasing factor: render at this multiple of the resolution,
+    /// then average down
+    #[arg(short = 's', long = "ssaa")]
+    #[arg(value_parser = clap::value_parser!(u32).range(1..))]
+    pub ssaa: Option<u32>,
+
+    /// Encode the output image as sRGB, rather than writing linear values
+    #[arg(long = "srgb")]
+    pub srgb: bool,
+
+    /// Render only this region of the image, in output pixels
+    #[arg(long = "region", value_name = "X,Y,W,H")]
+    #[arg(value_parser = parse_region)]
+    pub region: Option<(u32, u32, u32, u32)>,
+
+    /// When rendering a region, write only that region rather than the full image
+    #[arg(long = "crop")]
+    pub crop: bool,
+
+    /// Seed for random choices made while rendering; the same seed gives the same image
+    #[arg(long = "seed")]
+    pub seed: Option<u64>,
+
+    /// Also write a depth image, e.g. image_depth.ppm for image.ppm. Its name follows
+    /// the image's, like the alpha mask's, rather than being given separately
+    #[arg(long = "depth")]
+    pub depth: bool,
 }
 
 pub fn parse_filename(name: &str) -> Result<String, String> {
@@ -82,6 +122,17 @@ pub fn parse_filename(name: &str) -> Result<String, String> {
     }
 }
 
+pub fn parse_region(region: &str) -> Result<(u32, u32, u32, u32), String> {
+    let values = region
+        .split(',')
+        .map(|v| v.trim().parse::<u32>().map_err(|e| format!("{v:?}: {e}")))
+        .collect::<Result<Vec<_>, _>>()?;
+    match values[..] {
+        [x, y, w, h] => Ok((x, y, w, h)),
+        _ => Err(format!("expected X,Y,W,H, but got {} values", values.len())),
+    }
+}
+
 #[derive(ValueEnum, Debug, Clone)]
 #[clap(rename_all = "kebab_case")]
 pub enum Resolutions {
@@ -93,9 +144,14 @@ pub enum Resolutions {
     QHD,
     UHD,
     _4K,
+    #[value(name = "dci-2k")]
+    DCI2K,
+    #[value(name = "dci-4k")]
+    DCI4K,
+    CinemaScope,
 }
 
-#[derive(Args)]
+#[derive(Args, Clone)]
 pub struct CommonArgs {
     #[clap(flatten)]
     #[clap(next_help_heading = "Render Options")]
@@ -123,6 +179,9 @@ fn get_resolution(common_args: &CommonArgs, default: Resolution) -> Resolution {
         Some(Resolutions::FHD) => Resolution::FHD,
         Some(Resolutions::QHD) => Resolution::QHD,
         Some(Resolutions::UHD | Resolutions::_4K) => Resolution::UHD_4K,
+        Some(Resolutions::DCI2K) => Resolution::DCI_2K,
+        Some(Resolutions::DCI4K) => Resolution::DCI_4K,
+        Some(Resolutions::CinemaScope) => Resolution::CINEMA_SCOPE,
         _ => default,
     };
 
@@ -146,16 +205,48 @@ fn get_resolution(common_args: &CommonArgs, default: Resolution) -> Resolution {
     }
 }
 
+fn get_region(
+    common_args: &CommonArgs,
+    default: Option<(u32, u32, u32, u32)>,
+) -> Option<(u32, u32, u32, u32)> {
+    // Command-line takes precedence over scene config
+    common_args.render.region.or(default)
+}
+
 fn get_field_of_view(common_args: &CommonArgs, default: f64) -> f64 {
     // Command-line takes precedence over scene config
     common_args.render.field_of_view.unwrap_or(default)
 }
 
-#[derive(Debug, Copy, Clone)]
+// Options describing the scene, rather than how to render it, such as where the
+// camera is, aren't kept in a settings file
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
 pub struct RenderOptions {
+    #[serde(rename = "resolution")]
     pub default_resolution: Resolution,
     pub field_of_view: f64,
+    #[serde(skip)]
     pub camera_transform: Matrix4,
+    pub ssaa: u32, // supersampling factor, 1 for none
+    #[serde(skip)]
+    pub fog: Option<Fog>,
+    pub clamp_indirect: Option<f64>, // maximum luminance of reflected & refracted light
+    pub region: Option<(u32, u32, u32, u32)>, // (x, y, width, height) to render, or all
+    pub russian_roulette: bool,      // follow secondary rays by chance, by weight, seeded per ray
+    pub shadow_samples: u32,         // shadow rays per light at each hit, 1 for hard shadows
+    pub shadow_softness: f64,        // radius over which those shadow rays are spread
+    pub seed: u64,                   // for random choices; the same seed gives the same image
+    pub image_based_lighting: bool,  // the world's environment (if any) lights surfaces
+    pub dispersion: bool,            // refract red, green and blue separately
+    pub light_sampling: Option<u32>, // lights sampled at each hit, or None for all of them
+    #[serde(skip)]
+    pub shadow_catcher: Option<ObjectIndex>, // rendered only as shadows, with an alpha mask
+    pub depth_output: bool,          // also write the distance to each pixel's hit, as grey levels
+    #[serde(skip)]
+    pub clip_planes: Vec<(Point, Vector)>, // (point, normal): geometry behind any is cut away
+    pub near: f64,                   // hits nearer to the camera are ignored
+    pub far: Option<f64>, // hits farther from the camera are ignored, or None for no limit
 }
 
 impl Default for RenderOptions {
@@ -164,20 +255,147 @@ impl Default for RenderOptions {
             default_resolution: Resolution::VGA,
             field_of_view: PI / 3.0,
             camera_transform: identity4(),
+            ssaa: 1,
+            fog: None,
+            clamp_indirect: None,
+            region: None,
+            russian_roulette: false,
+            shadow_samples: 1,
+            shadow_softness: 0.0,
+            seed: 0,
+            image_based_lighting: false,
+            dispersion: false,
+            light_sampling: None,
+            shadow_catcher: None,
+            depth_output: false,
+            clip_planes: vec![],
+            near: 0.0,
+            far: None,
+        }
+    }
+}
+
+// Render settings from a file, e.g. kept alongside a scene, to override those of
+// one of its cameras. Only the settings given are overridden.
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RenderSettings {
+    pub resolution: Option<Resolution>,
+    pub field_of_view: Option<f64>,
+    pub ssaa: Option<u32>,
+    pub clamp_indirect: Option<f64>,
+    pub region: Option<(u32, u32, u32, u32)>,
+    pub russian_roulette: Option<bool>,
+    pub shadow_samples: Option<u32>,
+    pub shadow_softness: Option<f64>,
+    pub seed: Option<u64>,
+    pub image_based_lighting: Option<bool>,
+    pub dispersion: Option<bool>,
+    pub light_sampling: Option<u32>,
+    pub depth_output: Option<bool>,
+    pub near: Option<f64>,
+    pub far: Option<f64>,
+}
+
+impl RenderSettings {
+    pub fn from_json_file(path: &Path) -> anyhow::Result<RenderSettings> {
+        load_json5(path).with_context(|| format!("Failed to read render settings from {path:?}"))
+    }
+}
+
+impl RenderOptions {
+    /// These options (e.g. from a scene), overridden by any given in `settings`.
+    pub fn with_settings(self, settings: &RenderSettings) -> RenderOptions {
+        RenderOptions {
+            default_resolution: settings.resolution.unwrap_or(self.default_resolution),
+            field_of_view: settings.field_of_view.unwrap_or(self.field_of_view),
+            ssaa: settings.ssaa.unwrap_or(self.ssaa),
+            clamp_indirect: settings.clamp_indirect.or(self.clamp_indirect),
+            region: settings.region.or(self.region),
+            russian_roulette: settings.russian_roulette.unwrap_or(self.russian_roulette),
+            shadow_samples: settings.shadow_samples.unwrap_or(self.shadow_samples),
+            shadow_softness: settings.shadow_softness.unwrap_or(self.shadow_softness),
+            seed: settings.seed.unwrap_or(self.seed),
+            image_based_lighting: settings
+                .image_based_lighting
+                .unwrap_or(self.image_based_lighting),
+            dispersion: settings.dispersion.unwrap_or(self.dispersion),
+            light_sampling: settings.light_sampling.or(self.light_sampling),
+            depth_output: settings.depth_output.unwrap_or(self.depth_output),
+            near: settings.near.unwrap_or(self.near),
+            far: settings.far.or(self.far),
+            ..self
+        }
+    }
+
+    /// These options (e.g. from a scene), overridden by any given on the command line.
+    pub fn merge_cli(self, common_args: &CommonArgs) -> RenderOptions {
+        RenderOptions {
+            default_resolution: get_resolution(common_args, self.default_resolution),
+            field_of_view: get_field_of_view(common_args, self.field_of_view),
+            ssaa: common_args.render.ssaa.unwrap_or(self.ssaa),
+            region: get_region(common_args, self.region),
+            seed: common_args.render.seed.unwrap_or(self.seed),
+            depth_output: self.depth_output || common_args.render.depth,
+            ..self
         }
     }
+
+    /// Apply the options that affect shading to the world, and prepare it for rendering.
+    pub fn configure_world(&self, world: &mut World) {
+        world.set_seed(self.seed);
+        world.set_indirect_clamp(self.clamp_indirect);
+        world.set_russian_roulette(self.russian_roulette);
+        world.set_soft_shadows(self.shadow_samples, self.shadow_softness);
+        world.set_image_based_lighting(self.image_based_lighting);
+        world.set_dispersion(self.dispersion);
+        world.set_light_sampling(self.light_sampling);
+        world.set_clip_planes(self.clip_planes.clone());
+        world.prepare();
+    }
+
+    /// The camera described by these options, without supersampling.
+    pub fn camera(&self) -> Camera {
+        let mut cam = camera(self.default_resolution, self.field_of_view);
+        cam.set_transform(&self.camera_transform);
+        cam.set_clip_distances(self.near, self.far.unwrap_or(f64::INFINITY));
+        cam
+    }
 }
 
+/// Render the world as given by the options and command line, and write the image.
+/// The options are applied to a copy of the world, so `world` itself is unchanged.
 pub fn render_world(
     world: &World,
     options: RenderOptions,
     common_args: &CommonArgs,
 ) -> Result<Canvas, io::Error> {
-    let resolution = get_resolution(common_args, options.default_resolution);
+    let options = options.merge_cli(common_args);
+    let ssaa = options.ssaa;
 
-    let field_of_view = get_field_of_view(common_args, options.field_of_view);
+    let mut world = world.clone();
+    options.configure_world(&mut world);
+    let world = &world;
 
-    let pb = indicatif::ProgressBar::new(resolution.num_pixels());
+    // Render at a higher resolution, and downsample the result
+    let mut cam = options.camera().supersampled(ssaa);
+    cam.set_single_threaded(common_args.render.hdiv == 1 && common_args.render.vdiv == 1);
+
+    let region = options
+        .region
+        .map(|(x, y, w, h)| cam.clip_region((x * ssaa, y * ssaa, w * ssaa, h * ssaa)));
+
+    if options.shadow_catcher.is_some() && options.fog.is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Fog can't be used with a shadow catcher",
+        ));
+    }
+
+    let pb = indicatif::ProgressBar::new(match region {
+        Some((_, _, w, h)) => w as u64 * h as u64,
+        None => cam.resolution().num_pixels(),
+    });
     pb.set_style(
         indicatif::ProgressStyle::with_template(
             "[{elapsed_precise}] {wide_bar:.cyan/blue} {pos:>7}/{len:7} {msg}",
@@ -185,17 +403,63 @@ pub fn render_world(
         .expect("style should be valid"),
     );
 
-    let mut cam = camera(resolution, field_of_view);
-
+    // Progress is reported as the total number of pixels rendered so far
     let pb_update = Box::new(|x| {
-        pb.inc(x);
+        pb.set_position(x);
     });
 
-    cam.set_transform(&options.camera_transform);
-
     pb.set_message("Rendering...");
 
-    let canvas = if common_args.render.hdiv == 1 && common_args.render.vdiv == 1 {
+    // A region is rendered in supersampled pixels, then placed over the background,
+    // unless only the region is wanted
+    let place_region = |subimage: Canvas, background: &Color| match region {
+        Some((x, y, _, _)) if !common_args.render.crop => {
+            let resolution = cam.resolution();
+            let mut full = Canvas::filled(resolution.hsize, resolution.vsize, background);
+            full.blit(&subimage, x, y);
+            full
+        }
+        _ => subimage,
+    };
+
+    let mut mask = None;
+    let mut distances = None;
+    let canvas = if options.shadow_catcher.is_some() || options.depth_output {
+        // The image, and its alpha mask or depth image, are rendered in one pass
+        let resolution = cam.resolution();
+        let [image, alpha, distance] = render_layers(
+            world,
+            &cam,
+            &options,
+            common_args.render.max_recursive_depth,
+            region.unwrap_or((0, 0, resolution.hsize, resolution.vsize)),
+            Some(pb_update),
+        )?;
+        if options.shadow_catcher.is_some() {
+            mask = Some(place_region(alpha, &BLACK).downsample(ssaa));
+        }
+        if options.depth_output {
+            distances = Some(place_region(distance, &BLACK).downsample(ssaa));
+        }
+        place_region(image, &world.ambient_background())
+    } else if let Some(region) = region {
+        let depth = common_args.render.max_recursive_depth;
+        let subimage = if let Some(fog) = &options.fog {
+            cam.render_subimage_with(
+                |ray| color_at_fogged(world, ray, depth, fog),
+                region,
+                Some(pb_update),
+            )
+        } else {
+            cam.render_subimage(world, depth, region, Some(pb_update))
+        };
+        place_region(subimage, &world.ambient_background())
+    } else if let Some(fog) = &options.fog {
+        cam.render_with(
+            |ray| color_at_fogged(world, ray, common_args.render.max_recursive_depth, fog),
+            Some(pb_update),
+        )
+    } else if common_args.render.hdiv == 1 && common_args.render.vdiv == 1 {
         cam.render_single_threaded(
             world,
             common_args.render.max_recursive_depth,
@@ -211,18 +475,259 @@ pub fn render_world(
         )
     };
 
-    pb.finish_with_message("Writing...");
+    let canvas = canvas.downsample(ssaa);
+
+    pb.set_message("Writing...");
 
-    write_canvas(&canvas, &common_args.render.output)?;
+    if common_args.render.srgb {
+        write_canvas(&canvas.to_srgb(), &common_args.render.output)?;
+    } else {
+        write_canvas(&canvas, &common_args.render.output)?;
+    }
+    if let Some(mask) = mask {
+        match mask_filename(&common_args.render.output) {
+            Some(filename) => write_canvas(&mask, &filename)?,
+            None => eprintln!("Alpha mask not written, as the image is written to stdout"),
+        }
+    }
+    if let Some(depth) = distances {
+        match depth_filename(&common_args.render.output) {
+            Some(filename) => write_canvas(&depth, &filename)?,
+            None => eprintln!("Depth image not written, as the image is written to stdout"),
+        }
+    }
     pb.finish_with_message("Complete");
 
     Ok(canvas)
 }
 
-pub fn write_canvas(canvas: &Canvas, output_filename: &str) -> io::Result<()> {
-    let ppm = ppm_from_canvas(canvas);
+/// The outcome of rendering one scene of a batch.
+#[derive(Debug)]
+pub struct BatchResult {
+    pub scene: PathBuf,
+    pub output: PathBuf,
+    pub duration: Duration,
+    pub error: Option<anyhow::Error>,
+}
+
+// The name of a scene file, without its .json5 or .json5.gz extension, or None
+// if it isn't a scene
+fn scene_name(path: &Path) -> Option<&str> {
+    let name = path.file_name()?.to_str()?;
+    name.strip_suffix(".json5")
+        .or_else(|| name.strip_suffix(".json5.gz"))
+}
+
+/// Render every `.json5` (or gzipped `.json5.gz`) scene in `input_dir` with the same
+/// command-line options, writing each image to `output_dir` under the scene's name,
+/// e.g. `scenes/cones.json5` to `images/cones.ppm`. A scene that fails to load or
+/// render doesn't stop the rest; its error is recorded in its result instead.
+pub fn render_batch(
+    input_dir: &Path,
+    output_dir: &Path,
+    common_args: &CommonArgs,
+) -> anyhow::Result<Vec<BatchResult>> {
+    let mut scenes = std::fs::read_dir(input_dir)
+        .with_context(|| format!("Reading scene directory {}", input_dir.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    scenes.retain(|path| path.is_file() && scene_name(path).is_some());
+    scenes.sort();
+
+    std::fs::create_dir_all(output_dir)
+        .with_context(|| format!("Creating output directory {}", output_dir.display()))?;
+
+    let render_scene = |scene: &Path, output: &Path| -> anyhow::Result<()> {
+        let (world, render_options) = load_world(scene)?;
+        let options = render_options
+            .get(&common_args.render.camera_name)
+            .with_context(|| format!("No camera \"{}\"", common_args.render.camera_name))?;
+        let mut args = common_args.clone();
+        args.render.output = output.to_string_lossy().into_owned();
+        render_world(&world, options.clone(), &args)?;
+        Ok(())
+    };
+
+    Ok(scenes
+        .into_iter()
+        .map(|scene| {
+            let name = scene_name(&scene).unwrap_or_default();
+            let output = output_dir.join(format!("{name}.ppm"));
+            let start = Instant::now();
+            let error = render_scene(&scene, &output)
+                .with_context(|| format!("Rendering {}", scene.display()))
+                .err();
+            BatchResult {
+                scene,
+                output,
+                duration: start.elapsed(),
+                error,
+            }
+        })
+        .collect())
+}
+
+/// One line per scene of a batch, with how long it took or why it failed, then a total.
+pub fn batch_summary(results: &[BatchResult]) -> String {
+    let mut summary = String::new();
+    for result in results {
+        let name = result
+            .scene
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        match &result.error {
+            None => summary += &format!("{name}: {:.3}s\n", result.duration.as_secs_f64()),
+            Some(error) => summary += &format!("{name}: failed: {error:#}\n"),
+        }
+    }
+    let rendered = results.iter().filter(|r| r.error.is_none()).count();
+    let total: Duration = results.iter().map(|r| r.duration).sum();
+    summary += &format!(
+        "Rendered {rendered} of {} scenes in {:.3}s\n",
+        results.len(),
+        total.as_secs_f64()
+    );
+    summary
+}
+
+/// Update `previous`, an image of the world rendered with these options, after a
+/// change to the world within the world-space `dirty_region`, e.g. an object's
+/// bounds after changing its material. Only the pixels that may see the region are
+/// rendered again, and the rest are copied. The world should already be configured
+/// by `options`. Changes seen elsewhere, e.g. in a mirror or as a moved shadow,
+/// aren't updated unless `dirty_region` includes them too.
+pub fn render_diff(
+    world: &World,
+    options: &RenderOptions,
+    previous: &Canvas,
+    dirty_region: &Bounds,
+    depth: i32,
+) -> Canvas {
+    let ssaa = options.ssaa;
+    let cam = options.camera().supersampled(ssaa);
+    let mut image = previous.clone();
+
+    if let Some((x, y, w, h)) = options.camera().project_bounds(dirty_region) {
+        let region = cam.clip_region((x * ssaa, y * ssaa, w * ssaa, h * ssaa));
+        let subimage = match &options.fog {
+            Some(fog) => cam.render_subimage_with(
+                |ray| color_at_fogged(world, ray, depth, fog),
+                region,
+                None,
+            ),
+            None => cam.render_subimage(world, depth, region, None),
+        };
+        image.blit(&subimage.downsample(ssaa), x, y);
+    }
+    image
+}
 
-    let mut out_writer = match output_filename {
+// The image of the (x, y, width, height) region, with its alpha mask if there's a
+// shadow catcher, and its depth image if depth output is on, rendered in a single
+// pass. The mask and depth image are grey levels. It's an error if the shadow
+// catcher isn't an object in the world.
+fn render_layers(
+    world: &World,
+    cam: &Camera,
+    options: &RenderOptions,
+    depth: i32,
+    region: (u32, u32, u32, u32),
+    progress: Option<Box<dyn FnMut(u64) + Send + '_>>,
+) -> io::Result<[Canvas; 3]> {
+    if let Some(catcher) = options.shadow_catcher {
+        check_shadow_catcher(world, catcher)?;
+    }
+    let [image, alpha, distance] = cam.render_subimage_layers_with(
+        |ray| {
+            let (c, alpha) = match (options.shadow_catcher, &options.fog) {
+                (Some(catcher), _) => shadow_catcher_at(world, ray, depth, catcher),
+                (None, Some(fog)) => (color_at_fogged(world, ray, depth, fog), 1.0),
+                (None, None) => (color_at(world, ray, depth), 1.0),
+            };
+            let distance = if options.depth_output {
+                hit_distance(world, ray)
+            } else {
+                0.0
+            };
+            [c, grey(alpha), grey(distance)]
+        },
+        region,
+        progress,
+    );
+    let distance = if options.depth_output {
+        depth_levels(distance)
+    } else {
+        distance
+    };
+    Ok([image, alpha, distance])
+}
+
+fn check_shadow_catcher(world: &World, catcher: ObjectIndex) -> io::Result<()> {
+    match world.object(catcher) {
+        Some(_) => Ok(()),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Shadow catcher {} is not in the world", catcher.index()),
+        )),
+    }
+}
+
+fn grey(level: f64) -> Color {
+    Color::new(level, level, level)
+}
+
+// Replace the distance to the hit in each pixel with a grey level, from white at the
+// nearest hit in the image to black at the farthest. Pixels that see nothing are
+// black, as far as possible.
+fn depth_levels(mut image: Canvas) -> Canvas {
+    let hits = image
+        .pixels
+        .iter()
+        .map(|p| p.red())
+        .filter(|d| d.is_finite());
+    let (near, far) = hits.fold((f64::INFINITY, -f64::INFINITY), |(near, far), d| {
+        (near.min(d), far.max(d))
+    });
+    for pixel in &mut image.pixels {
+        let distance = pixel.red();
+        let level = if !distance.is_finite() {
+            0.0
+        } else if far > near {
+            (far - distance) / (far - near)
+        } else {
+            1.0
+        };
+        *pixel = Color::new(level, level, level);
+    }
+    image
+}
+
+// Images made alongside the main one are written next to it, with a suffix, e.g.
+// image_alpha.ppm for image.ppm, except when the image is written to stdout
+fn companion_filename(output_filename: &str, suffix: &str) -> Option<String> {
+    if output_filename == "-" {
+        return None;
+    }
+    let path = Path::new(output_filename);
+    let stem = path.file_stem()?.to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{stem}_{suffix}.{}", extension.to_string_lossy()),
+        None => format!("{stem}_{suffix}"),
+    };
+    Some(path.with_file_name(name).to_string_lossy().into_owned())
+}
+
+fn mask_filename(output_filename: &str) -> Option<String> {
+    companion_filename(output_filename, "alpha")
+}
+
+fn depth_filename(output_filename: &str) -> Option<String> {
+    companion_filename(output_filename, "depth")
+}
+
+pub fn write_canvas(canvas: &Canvas, output_filename: &str) -> io::Result<()> {
+    let out_writer = match output_filename {
         "-" => Box::new(io::stdout()) as Box<dyn Write>,
         x => {
             let path = Path::new(x);
@@ -230,6 +735,680 @@ pub fn write_canvas(canvas: &Canvas, output_filename: &str) -> io::Result<()> {
         }
     };
 
-    out_writer.write_all(ppm.as_bytes())?;
-    Ok(())
+    let mut out_writer = BufWriter::new(out_writer);
+    canvas.write_ppm(&mut out_writer)?;
+    out_writer.flush()
+}
+
+/// Render the world as `render_world` would, with its default resolution and to the
+/// default depth, but quietly, on a single thread, and without writing the image.
+/// Returns the image and how long rendering took, for benchmarks.
+pub fn bench_render(world: &mut World, options: &RenderOptions) -> (Canvas, Duration) {
+    options.configure_world(world);
+    let world = &*world;
+
+    let cam = options.camera().supersampled(options.ssaa);
+
+    let start = Instant::now();
+    let canvas = if let Some(fog) = &options.fog {
+        cam.render_with(
+            |ray| color_at_fogged(world, ray, MAX_RECURSIVE_DEPTH, fog),
+            None,
+        )
+    } else {
+        cam.render_single_threaded(world, MAX_RECURSIVE_DEPTH, None)
+    };
+    let canvas = canvas.downsample(options.ssaa);
+
+    (canvas, start.elapsed())
+}
+
+// A color as it would be written to an image file, one byte per channel
+fn quantize(color: &Color) -> [u8; 3] {
+    [color.red(), color.green(), color.blue()].map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+/// A hash of the canvas's size and pixels, as quantized to 8 bits per channel, so
+/// tiny floating-point differences don't change it. It's stable across platforms
+/// and releases (FNV-1a), so it can be recorded in golden-image tests.
+pub fn canvas_hash(canvas: &Canvas) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+    let size = [canvas.width.to_le_bytes(), canvas.height.to_le_bytes()];
+    let pixels = canvas.pixels.iter().flat_map(quantize);
+    size.into_iter()
+        .flatten()
+        .chain(pixels)
+        .fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+        })
+}
+
+/// The number of pixels that differ between two canvases of the same size, once
+/// quantized to 8 bits per channel.
+pub fn count_differing_pixels(a: &Canvas, b: &Canvas) -> usize {
+    assert_eq!(
+        (a.width, a.height),
+        (b.width, b.height),
+        "canvases must be the same size"
+    );
+    a.pixels
+        .iter()
+        .zip(&b.pixels)
+        .filter(|(p, q)| quantize(p) != quantize(q))
+        .count()
+}
+
+/// Assert that a rendered image has the expected hash, from `canvas_hash`.
+#[cfg(test)]
+pub(crate) fn assert_canvas_matches(canvas: &Canvas, expected_hash: u64) {
+    let hash = canvas_hash(canvas);
+    assert_eq!(
+        hash, expected_hash,
+        "canvas hash is {hash:#018x}, expected {expected_hash:#018x}"
+    );
+}
+
+/// Assert that a rendered image differs from a reference image in no more than
+/// `max_differing` pixels, for renders that vary slightly, e.g. by platform.
+#[cfg(test)]
+pub(crate) fn assert_canvas_matches_within(
+    canvas: &Canvas,
+    reference: &Canvas,
+    max_differing: usize,
+) {
+    let differing = count_differing_pixels(canvas, reference);
+    assert!(
+        differing <= max_differing,
+        "{differing} pixels differ, expected at most {max_differing}"
+    );
+}
+
+/// A matte floor at y = 0, optionally reflective.
+pub fn ground_plane(color: Color, reflective: f64) -> Shape {
+    let mut floor = plane();
+    floor.material.color = color;
+    floor.material.specular = 0.0;
+    floor.material.reflective = reflective;
+    floor
+}
+
+/// A cube extending `size` from the origin along each axis, to enclose a scene as
+/// the walls, floor and ceiling of a room. It neither casts nor receives shadows,
+/// so lights may be placed outside it, and has no specular highlights.
+pub fn room(size: f64, color: Color) -> Shape {
+    let mut room = cube();
+    room.set_transform(&uniform_scaling(size));
+    room.material.color = color;
+    room.material.diffuse = 0.3;
+    room.material.ambient = 0.2;
+    room.material.specular = 0.0;
+    room.material.shininess = 1.0;
+    room.material.casts_shadow = false;
+    room.material.receives_shadow = false;
+    room
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colors::color;
+    use crate::intersections::intersect;
+    use crate::lights::point_light;
+    use crate::rays::ray;
+    use crate::shapes::sphere;
+    use crate::shapes::ShapeEnum;
+    use crate::transformations::translation;
+    use crate::transformations::view_transform;
+    use crate::tuples::{point, vector};
+    use crate::world::{default_world, world};
+    use rstest::rstest;
+
+    // Identical canvases hash equally, and changing one pixel changes the hash
+    #[test]
+    fn canvas_hash_detects_one_pixel_change() {
+        let mut a = Canvas::filled(8, 6, &color(0.2, 0.4, 0.6));
+        let b = a.clone();
+        assert_eq!(canvas_hash(&a), canvas_hash(&b));
+        assert_canvas_matches(&a, canvas_hash(&b));
+
+        a.write_pixel(5, 2, &color(0.2, 0.4, 0.61));
+        assert_ne!(canvas_hash(&a), canvas_hash(&b));
+        assert_eq!(count_differing_pixels(&a, &b), 1);
+        assert_canvas_matches_within(&a, &b, 1);
+    }
+
+    // Differences too small to survive quantizing don't change the hash
+    #[test]
+    fn canvas_hash_ignores_tiny_differences() {
+        let mut a = Canvas::filled(4, 4, &color(0.5, 0.5, 0.5));
+        let b = a.clone();
+        a.write_pixel(1, 1, &color(0.5 + 1e-6, 0.5, 0.5));
+        assert_eq!(canvas_hash(&a), canvas_hash(&b));
+        assert_eq!(count_differing_pixels(&a, &b), 0);
+    }
+
+    // The size is part of the hash, so differently shaped canvases of the same
+    // pixels don't collide
+    #[test]
+    fn canvas_hash_includes_size() {
+        let a = Canvas::filled(2, 3, &color(0.0, 0.0, 0.0));
+        let b = Canvas::filled(3, 2, &color(0.0, 0.0, 0.0));
+        assert_ne!(canvas_hash(&a), canvas_hash(&b));
+    }
+
+    // The hash is stable, so recorded hashes stay valid
+    #[test]
+    fn canvas_hash_is_stable() {
+        assert_canvas_matches(&Canvas::new(1, 1), 0x5d1e_e66e_3442_9d67);
+    }
+
+    // Too many differing pixels fails the tolerant comparison
+    #[test]
+    #[should_panic(expected = "2 pixels differ, expected at most 1")]
+    fn tolerant_comparison_counts_differing_pixels() {
+        let a = Canvas::new(3, 3);
+        let mut b = a.clone();
+        b.write_pixel(0, 0, &color(1.0, 0.0, 0.0));
+        b.write_pixel(2, 2, &color(0.0, 1.0, 0.0));
+        assert_canvas_matches_within(&a, &b, 1);
+    }
+
+    // The alpha mask is written next to the image
+    #[rstest]
+    #[case("image.ppm", Some("image_alpha.ppm"))]
+    #[case("out/render.ppm", Some("out/render_alpha.ppm"))]
+    #[case("render", Some("render_alpha"))]
+    #[case("-", None)]
+    fn alpha_mask_filename(#[case] output: &str, #[case] expected: Option<&str>) {
+        assert_eq!(mask_filename(output).as_deref(), expected);
+    }
+
+    // Render settings survive a round trip through a settings file
+    #[test]
+    fn render_settings_round_trip() {
+        let options = RenderOptions {
+            default_resolution: Resolution::new(320, 200),
+            field_of_view: PI / 4.0,
+            ssaa: 3,
+            clamp_indirect: Some(2.5),
+            region: Some((10, 20, 30, 40)),
+            russian_roulette: true,
+            shadow_samples: 16,
+            shadow_softness: 0.25,
+            seed: 42,
+            light_sampling: Some(4),
+            depth_output: true,
+            ..Default::default()
+        };
+        let path = std::env::temp_dir().join(format!("rtc_settings_{}.json5", std::process::id()));
+        std::fs::write(&path, json5::to_string(&options).unwrap()).unwrap();
+        let loaded = RenderSettings::from_json_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            RenderOptions::default().with_settings(&loaded.unwrap()),
+            options
+        );
+    }
+
+    // Rendering a directory of scenes writes an image for each, past any failures
+    #[test]
+    fn rendering_a_batch_of_scenes() {
+        let scene = |color: &str| {
+            format!(
+                "{{
+                    lights: [{{ point_light: {{ position: [-10, 10, -10], intensity: [1, 1, 1] }} }}],
+                    bodies: [{{ sphere: {{ material: {{ color: {color} }} }} }}],
+                    cameras: [{{
+                        name: \"main\", field_of_view: 1.0,
+                        from: [0, 0, -5], to: [0, 0, 0], up: [0, 1, 0],
+                    }}],
+                }}"
+            )
+        };
+        let dir = std::env::temp_dir().join(format!("rtc_batch_{}", std::process::id()));
+        let scenes = dir.join("scenes");
+        let images = dir.join("images");
+        std::fs::create_dir_all(&scenes).unwrap();
+        std::fs::write(scenes.join("red.json5"), scene("[1, 0, 0]")).unwrap();
+        std::fs::write(scenes.join("blue.json5"), scene("[0, 0, 1]")).unwrap();
+        std::fs::write(scenes.join("broken.json5"), "{ bodies: [{ torus: {} }] }").unwrap();
+        std::fs::write(scenes.join("notes.txt"), "not a scene").unwrap();
+
+        let cli = Cli::try_parse_from(["batch", "-x", "8", "-y", "6"]).unwrap();
+        let results = render_batch(&scenes, &images, &cli.common).unwrap();
+        let summary = batch_summary(&results);
+        let outputs = results
+            .iter()
+            .map(|r| (r.output.clone(), r.error.is_none(), r.output.exists()))
+            .collect::<Vec<_>>();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            outputs,
+            vec![
+                (images.join("blue.ppm"), true, true),
+                (images.join("broken.ppm"), false, false),
+                (images.join("red.ppm"), true, true),
+            ]
+        );
+        let lines = summary.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 4, "{summary}");
+        assert!(lines[0].starts_with("blue.json5: ") && lines[0].ends_with('s'));
+        assert!(lines[1].starts_with("broken.json5: failed: "), "{summary}");
+        assert!(lines[2].starts_with("red.json5: ") && lines[2].ends_with('s'));
+        assert!(
+            lines[3].starts_with("Rendered 2 of 3 scenes in "),
+            "{summary}"
+        );
+    }
+
+    // Rendering applies the options to a copy of the world, so the caller's world
+    // keeps its own settings
+    #[test]
+    fn render_world_leaves_world_unchanged() {
+        let mut w = world();
+        w.add_light(point_light(point(-10.0, 10.0, -10.0), color(1.0, 1.0, 1.0)));
+        w.add_object(sphere(1));
+
+        let path = std::env::temp_dir().join(format!("rtc_render_{}.ppm", std::process::id()));
+        let output = path.to_string_lossy().into_owned();
+        let cli = Cli::try_parse_from(["test", "-x", "8", "-y", "6", "-o", &output]).unwrap();
+        let options = RenderOptions {
+            clamp_indirect: Some(1.0),
+            russian_roulette: true,
+            seed: 7,
+            ..Default::default()
+        };
+        let result = render_world(&w, options, &cli.common);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(result.is_ok());
+        assert_eq!(w.indirect_clamp(), None);
+        assert!(!w.russian_roulette());
+    }
+
+    // The depth image is rendered with the image, and cropped to the same region
+    #[test]
+    fn render_world_depth_of_region() {
+        let mut w = world();
+        w.add_light(point_light(point(-10.0, 10.0, -10.0), color(1.0, 1.0, 1.0)));
+        w.add_object(sphere(1));
+
+        let path = std::env::temp_dir().join(format!("rtc_depth_{}.ppm", std::process::id()));
+        let output = path.to_string_lossy().into_owned();
+        let args = [
+            "test", "-x", "8", "-y", "6", "--region", "2,1,4,3", "--crop", "--depth",
+        ];
+        let cli = Cli::try_parse_from(args.into_iter().chain(["-o", &output])).unwrap();
+        let options = RenderOptions {
+            camera_transform: view_transform(
+                &point(0.0, 0.0, -5.0),
+                &point(0.0, 0.0, 0.0),
+                &vector(0.0, 1.0, 0.0),
+            ),
+            ..Default::default()
+        };
+        let image = render_world(&w, options, &cli.common).unwrap();
+        let depth_path = depth_filename(&output).unwrap();
+        let depth = crate::ppm_reader::read_ppm_file(&depth_path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&depth_path).unwrap();
+
+        assert_eq!((image.width, image.height), (4, 3));
+        assert_eq!((depth.width, depth.height), (4, 3));
+        assert!(depth.pixels.iter().any(|p| p.red() > 0.0));
+    }
+
+    // A fogged world renders the same with one division, single-threaded, as with
+    // the default divisions, in parallel
+    #[test]
+    fn render_world_fogged_single_threaded() {
+        let mut w = world();
+        w.add_light(point_light(point(-10.0, 10.0, -10.0), color(1.0, 1.0, 1.0)));
+        w.add_object(sphere(1));
+
+        let path = std::env::temp_dir().join(format!("rtc_fog_{}.ppm", std::process::id()));
+        let output = path.to_string_lossy().into_owned();
+        let options = RenderOptions {
+            camera_transform: view_transform(
+                &point(0.0, 0.0, -5.0),
+                &point(0.0, 0.0, 0.0),
+                &vector(0.0, 1.0, 0.0),
+            ),
+            fog: Some(Fog::new(color(0.5, 0.5, 0.5), 0.1)),
+            ..Default::default()
+        };
+        let render = |divisions: &[&str]| {
+            let args = ["test", "-x", "8", "-y", "6", "-o", &output];
+            let cli = Cli::try_parse_from(args.iter().chain(divisions)).unwrap();
+            render_world(&w, options.clone(), &cli.common).unwrap()
+        };
+        let parallel = render(&[]);
+        let single = render(&["--hdiv", "1", "--vdiv", "1"]);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(single.pixels, parallel.pixels);
+    }
+
+    // Settings not given are left unset, and unknown ones are reported by name
+    #[test]
+    fn render_settings_file_with_unknown_field() {
+        let path =
+            std::env::temp_dir().join(format!("rtc_bad_settings_{}.json5", std::process::id()));
+        std::fs::write(&path, "{ ssaa: 2, shadow_sample: 8 }").unwrap();
+        let err = RenderSettings::from_json_file(&path).unwrap_err();
+        std::fs::write(&path, "{ ssaa: 2 }").unwrap();
+        let partial = RenderSettings::from_json_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let message = format!("{err:#}");
+        assert!(message.contains("rtc_bad_settings"), "{message}");
+        assert!(
+            message.contains("unknown field `shadow_sample`"),
+            "{message}"
+        );
+        assert!(message.contains("shadow_samples"), "{message}");
+        assert_eq!(
+            partial,
+            RenderSettings {
+                ssaa: Some(2),
+                ..Default::default()
+            }
+        );
+    }
+
+    // Settings from a file override only the scene's options that they give
+    #[test]
+    fn render_settings_with_scene() {
+        let scene = RenderOptions {
+            default_resolution: Resolution::new(320, 200),
+            field_of_view: PI / 2.0,
+            camera_transform: translation(1.0, 2.0, 3.0),
+            fog: Some(Fog::new(color(0.5, 0.5, 0.5), 0.1)),
+            ssaa: 4,
+            seed: 7,
+            light_sampling: Some(2),
+            ..Default::default()
+        };
+        let path =
+            std::env::temp_dir().join(format!("rtc_partial_settings_{}.json5", std::process::id()));
+        std::fs::write(&path, "{ seed: 9, shadow_samples: 8 }").unwrap();
+        let settings = RenderSettings::from_json_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let combined = scene.clone().with_settings(&settings.unwrap());
+        assert_eq!(
+            combined,
+            RenderOptions {
+                seed: 9,
+                shadow_samples: 8,
+                ..scene.clone()
+            }
+        );
+
+        // No settings leave the scene's options alone
+        assert_eq!(
+            scene.clone().with_settings(&RenderSettings::default()),
+            scene
+        );
+    }
+
+    // The depth image is written next to the image too
+    #[rstest]
+    #[case("image.ppm", Some("image_depth.ppm"))]
+    #[case("-", None)]
+    fn depth_image_filename(#[case] output: &str, #[case] expected: Option<&str>) {
+        assert_eq!(depth_filename(output).as_deref(), expected);
+    }
+
+    // In the depth image, nearer objects are brighter, and misses are black, as far
+    #[test]
+    fn rendering_depth() {
+        let mut w = world();
+        let mut near = sphere(1);
+        near.set_transform(&translation(-1.5, 0.0, 0.0));
+        w.add_object(near);
+        let mut far = sphere(2);
+        far.set_transform(&translation(1.5, 0.0, 4.0));
+        w.add_object(far);
+        w.prepare();
+
+        let mut cam = camera(Resolution::new(41, 21), PI / 2.0);
+        cam.set_transform(&view_transform(
+            &point(0.0, 0.0, -5.0),
+            &point(0.0, 0.0, 0.0),
+            &vector(0.0, 1.0, 0.0),
+        ));
+        let options = RenderOptions {
+            depth_output: true,
+            ..Default::default()
+        };
+        let [_, _, depth] = render_layers(&w, &cam, &options, 5, (0, 0, 41, 21), None).unwrap();
+        let level = |x, y| depth.pixel_at(x, y).red();
+
+        // Looking toward +z, +x is on the right of the image
+        let (near, far) = (level(14, 10), level(23, 10));
+        assert!(near > far, "near {near}, far {far}");
+        assert!(far > 0.0);
+        assert_eq!(level(20, 0), 0.0);
+        assert!(depth.pixels.iter().all(|p| p.red() <= 1.0));
+        assert!(depth.pixels.iter().any(|p| p.red() == 1.0));
+    }
+
+    // The image and alpha mask of the (x, y, width, height) region, rendered with
+    // `catcher` as the shadow catcher
+    fn render_shadow_catcher(
+        w: &World,
+        cam: &Camera,
+        catcher: ObjectIndex,
+        region: (u32, u32, u32, u32),
+    ) -> io::Result<(Canvas, Canvas)> {
+        let options = RenderOptions {
+            shadow_catcher: Some(catcher),
+            ..Default::default()
+        };
+        let [image, mask, _] = render_layers(w, cam, &options, 5, region, None)?;
+        Ok((image, mask))
+    }
+
+    // A shadow catcher render gives a mask that is opaque only over objects and
+    // shadows
+    #[test]
+    fn rendering_with_shadow_catcher() {
+        let mut w = world();
+        w.add_light(point_light(point(0.0, 10.0, 0.0), color(1.0, 1.0, 1.0)));
+        let floor = w.add_object(ground_plane(color(0.5, 0.5, 0.5), 0.0));
+        let mut ball = sphere(1);
+        ball.set_transform(&translation(0.0, 1.0, 0.0));
+        w.add_object(ball);
+        w.prepare();
+
+        let mut cam = camera(Resolution::new(21, 21), PI / 2.0);
+        cam.set_transform(&view_transform(
+            &point(0.0, 8.0, 0.0),
+            &point(0.0, 0.0, 0.0),
+            &vector(0.0, 0.0, 1.0),
+        ));
+        let (image, mask) = render_shadow_catcher(&w, &cam, floor, (0, 0, 21, 21)).unwrap();
+
+        // The ball is in the middle, the floor around it, and its shadow hidden by it
+        assert_eq!(*mask.pixel_at(10, 10), color(1.0, 1.0, 1.0));
+        assert_ne!(*image.pixel_at(10, 10), color(0.0, 0.0, 0.0));
+        assert_eq!(*mask.pixel_at(0, 0), color(0.0, 0.0, 0.0));
+        assert_eq!(*image.pixel_at(0, 0), color(0.0, 0.0, 0.0));
+    }
+
+    // A shadow catcher render of a region matches that part of the full render
+    #[test]
+    fn rendering_region_with_shadow_catcher() {
+        let mut w = world();
+        w.add_light(point_light(point(0.0, 10.0, 0.0), color(1.0, 1.0, 1.0)));
+        let floor = w.add_object(ground_plane(color(0.5, 0.5, 0.5), 0.0));
+        let mut ball = sphere(1);
+        ball.set_transform(&translation(0.0, 1.0, 0.0));
+        w.add_object(ball);
+        w.prepare();
+
+        let mut cam = camera(Resolution::new(21, 21), PI / 2.0);
+        cam.set_transform(&view_transform(
+            &point(0.0, 8.0, -4.0),
+            &point(0.0, 0.0, 0.0),
+            &vector(0.0, 1.0, 0.0),
+        ));
+        let (image, mask) = render_shadow_catcher(&w, &cam, floor, (0, 0, 21, 21)).unwrap();
+        let (sub_image, sub_mask) = render_shadow_catcher(&w, &cam, floor, (5, 8, 10, 6)).unwrap();
+
+        assert_eq!((sub_mask.width, sub_mask.height), (10, 6));
+        for y in 0..6 {
+            for x in 0..10 {
+                assert_eq!(sub_image.pixel_at(x, y), image.pixel_at(x + 5, y + 8));
+                assert_eq!(sub_mask.pixel_at(x, y), mask.pixel_at(x + 5, y + 8));
+            }
+        }
+    }
+
+    // A shadow catcher that isn't in the world is an error
+    #[test]
+    fn shadow_catcher_not_in_world_is_error() {
+        let mut other = world();
+        other.add_object(sphere(1));
+        let catcher = other.add_object(sphere(2));
+
+        let w = world();
+        let cam = camera(Resolution::new(5, 5), PI / 2.0);
+        assert!(render_shadow_catcher(&w, &cam, catcher, (0, 0, 5, 5)).is_err());
+    }
+
+    // Re-rendering only the pixels that see an edited object gives the same image
+    // as rendering everything again
+    #[test]
+    fn diff_render_matches_full_render() {
+        let mut w = world();
+        crate::scenes::reflective_floor(&mut w, 0.0);
+        crate::scenes::three_spheres(&mut w);
+        crate::scenes::standard_light(&mut w);
+        let mut ball = sphere(7);
+        ball.set_transform(&(translation(1.0, 0.25, -1.5) * uniform_scaling(0.25)));
+        let ball = w.add_object(ball);
+        let options = RenderOptions {
+            default_resolution: Resolution::new(80, 40),
+            camera_transform: view_transform(
+                &point(0.0, 1.5, -5.0),
+                &point(0.0, 1.0, 0.0),
+                &vector(0.0, 1.0, 0.0),
+            ),
+            ssaa: 2,
+            ..Default::default()
+        };
+        options.configure_world(&mut w);
+        let full_render = |w: &World| {
+            let cam = options.camera().supersampled(options.ssaa);
+            cam.render_with_rayon_by_lines(w, 5, None)
+                .downsample(options.ssaa)
+        };
+        let previous = full_render(&w);
+
+        w.object_mut(&ball).unwrap().material.color = color(0.2, 0.2, 1.0);
+        let dirty = w.object_bounds()[ball.index()];
+
+        let diff = render_diff(&w, &options, &previous, &dirty, 5);
+        assert_eq!(diff, full_render(&w));
+        assert_ne!(diff, previous);
+
+        let (_, _, width, height) = options.camera().project_bounds(&dirty).unwrap();
+        assert!(width * height * 10 < 80 * 40, "{width} x {height}");
+    }
+
+    // A ground plane lies at y = 0, with the given material
+    #[test]
+    fn ground_plane_at_origin_with_material() {
+        let floor = ground_plane(color(0.5, 0.6, 0.7), 0.3);
+        assert!(matches!(floor.shape, ShapeEnum::Plane(_)));
+        assert_eq!(*floor.transform(), identity4());
+        assert_eq!(floor.material.color, color(0.5, 0.6, 0.7));
+        assert_eq!(floor.material.reflective, 0.3);
+        assert_eq!(floor.material.specular, 0.0);
+        assert_eq!(
+            floor.normal_at(&point(3.0, 0.0, -2.0)),
+            vector(0.0, 1.0, 0.0)
+        );
+    }
+
+    // A room is a large cube, seen from inside, without shadows or highlights
+    #[test]
+    fn room_is_large_cube_without_shadows() {
+        let r = room(200.0, color(0.8, 0.8, 1.0));
+        assert_eq!(*r.transform(), uniform_scaling(200.0));
+        assert_eq!(r.material.color, color(0.8, 0.8, 1.0));
+        assert_eq!(r.material.specular, 0.0);
+        assert!(!r.material.casts_shadow);
+        assert!(!r.material.receives_shadow);
+
+        // From the centre, a ray hits the wall 200 units away, behind and in front
+        let xs = intersect(&r, &ray(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 1.0)));
+        assert_eq!(
+            xs.iter().map(|i| i.t).collect::<Vec<_>>(),
+            vec![-200.0, 200.0]
+        );
+    }
+
+    // Command-line options override the scene's, which override the defaults
+    #[test]
+    fn merging_command_line_options() {
+        let scene = RenderOptions {
+            default_resolution: Resolution::new(320, 200),
+            field_of_view: PI / 2.0,
+            ssaa: 2,
+            seed: 7,
+            ..Default::default()
+        };
+
+        let cli = Cli::try_parse_from(["test", "-r", "fhd", "--seed", "3"]).unwrap();
+        let merged = scene.clone().merge_cli(&cli.common);
+        assert_eq!(merged.default_resolution, Resolution::FHD);
+        assert_eq!(merged.seed, 3);
+        assert_eq!(merged.field_of_view, PI / 2.0);
+        assert_eq!(merged.ssaa, 2);
+        assert_eq!(merged.region, None);
+
+        let cli = Cli::try_parse_from(["test", "--region", "1,2,3,4"]).unwrap();
+        assert_eq!(cli.common.render.region, Some((1, 2, 3, 4)));
+        let merged = scene.clone().merge_cli(&cli.common);
+        assert_eq!(merged.region, Some((1, 2, 3, 4)));
+        assert!(Cli::try_parse_from(["test", "--region", "1,2,3"]).is_err());
+        assert!(Cli::try_parse_from(["test", "--region", "1,2,3,x"]).is_err());
+
+        // Nothing on the command line leaves the scene's options alone
+        let cli = Cli::try_parse_from(["test"]).unwrap();
+        let merged = scene.clone().merge_cli(&cli.common);
+        assert_eq!(merged.default_resolution, scene.default_resolution);
+        assert_eq!(merged.field_of_view, scene.field_of_view);
+        assert_eq!(merged.ssaa, scene.ssaa);
+        assert_eq!(merged.seed, scene.seed);
+    }
+
+    // Benchmark renders take some time, and finish the whole image
+    #[test]
+    fn bench_render_times_a_whole_render() {
+        let mut w = default_world();
+        let options = RenderOptions {
+            default_resolution: Resolution::new(8, 6),
+            camera_transform: view_transform(
+                &point(0.0, 0.0, -5.0),
+                &point(0.0, 0.0, 0.0),
+                &vector(0.0, 1.0, 0.0),
+            ),
+            ..Default::default()
+        };
+
+        let (image, elapsed) = bench_render(&mut w, &options);
+        assert!(elapsed > Duration::ZERO);
+        assert_eq!((image.width, image.height), (8, 6));
+
+        // The sphere is in the middle of the image, against a black background
+        assert_ne!(*image.pixel_at(4, 3), color(0.0, 0.0, 0.0));
+        assert_eq!(*image.pixel_at(0, 0), color(0.0, 0.0, 0.0));
+    }
 }
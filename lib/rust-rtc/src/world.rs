@@ -1,52 +1,934 @@
 // Chapter 7: Making a Scene
 
-use crate::colors::{color, Color};
+use crate::bounds::Bounds;
+use crate::colors::{color, Color, BLACK, BLUE, GREEN, RED, WHITE};
+use crate::ibl::Environment;
 use crate::intersections::{
-    intersect, prepare_computations_for_refraction, schlick, Intersection, IntersectionComputation,
-    Intersections,
+    intersect, prepare_computations_in_medium, refractive_indices_in_medium, schlick,
+    sort_intersections, Intersection, IntersectionComputation, Intersections,
 };
 use crate::lights::{point_light, PointLight};
-use crate::materials::material;
-use crate::rays::{ray, Ray};
-use crate::shapes::{sphere, Shape};
-use crate::transformations::scaling;
-use crate::tuples::{dot, magnitude, normalize, point, Point};
+use crate::materials::{material, Material, RefractiveIndex};
+use crate::math::{hash_unit, EPSILON};
+use crate::matrices::{identity4, Matrix4};
+use crate::rays::{ray, Ray, RayDifferential, RayKind};
+use crate::shapes::{cylinder, next_shape_id, sphere, Shape, ShapeEnum};
+use crate::transformations::{rotation_x, rotation_z, scaling};
+use crate::tuples::{dot, magnitude, normalize, point, Point, Vector};
+use anyhow::{bail, Context, Result};
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The number of lights above which they're shaded in parallel, by default.
+pub const PARALLEL_LIGHT_THRESHOLD: usize = 32;
+
+/// Stable handle to an object in a World, valid for as long as the world
+/// is not rebuilt. Unlike a `&Shape`, it does not borrow the world.
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Copy, Clone)]
+pub struct ObjectIndex(usize);
+
+impl ObjectIndex {
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Default)]
+struct CullStatistics {
+    tested: u64,
+    culled: u64,
+}
 
+// CullStatistics shared by every thread casting rays. A clone of a world starts
+// counting afresh.
 #[derive(Default, Debug)]
+struct CullCounters {
+    tested: AtomicU64,
+    culled: AtomicU64,
+}
+
+impl Clone for CullCounters {
+    fn clone(&self) -> Self {
+        CullCounters::default()
+    }
+}
+
+/// Exponential distance fog, blending a hit toward `color` with distance.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Fog {
+    pub color: Color,
+    pub density: f64,
+}
+
+impl Fog {
+    pub fn new(color: Color, density: f64) -> Fog {
+        Fog { color, density }
+    }
+
+    /// Blend `c`, seen at world-space `distance`, toward the fog color by
+    /// 1 - exp(-density * distance). A density of zero disables the fog.
+    pub fn apply(&self, c: &Color, distance: f64) -> Color {
+        if self.density <= 0.0 {
+            return *c;
+        }
+        let amount = 1.0 - f64::exp(-self.density * distance);
+        c + (self.color - c) * amount
+    }
+}
+
+#[derive(Default, Debug, Clone)]
 pub struct World {
     lights: Vec<PointLight>,
     objects: Vec<Shape>,
+    object_bounds: Vec<Bounds>, // world-space bounds of each object, for culling
+    bounds_dirty: Vec<bool>,    // object_bounds entries that are stale
+    is_prototype: Vec<bool>,    // objects only seen through their instances
+    ambient_background: Color,  // returned when a ray hits nothing
+    transparent_shadows: bool,  // transparent objects cast tinted, partial shadows
+    indirect_clamp: Option<f64>, // maximum luminance of reflected & refracted light
+    russian_roulette: bool,     // follow secondary rays by chance, by weight, seeded per ray
+    seed: u64,                  // for the random choices made while rendering
+    shadow_samples: u32,        // shadow rays per light, from points around the hit
+    shadow_softness: f64,       // radius of the disk those points lie in
+    ambient_medium: Option<f64>, // refractive index outside all objects, None for vacuum
+    environment: Option<Environment>, // seen in place of the background color, if set
+    image_based_lighting: bool, // the environment also gives surfaces ambient light
+    object_ids: HashSet<u64>,   // ids of all objects, which are kept unique
+    dispersion: bool,           // refract red, green and blue light separately
+    light_sampling: Option<u32>, // lights sampled at each hit, or None for all of them
+    parallel_light_threshold: Option<usize>, // more lights are shaded in parallel
+    clip_planes: Vec<(Point, Vector)>, // (point, normal): hits behind any are ignored
+    cull_statistics: bool,      // count ray/object tests, for cull_ratio
+    cull_counters: CullCounters,
 }
 
 impl World {
     fn new(lights: Vec<PointLight>, objects: Vec<Shape>) -> World {
-        World { lights, objects }
+        let mut world = World {
+            lights,
+            ..Default::default()
+        };
+        for object in objects {
+            world.add_object(object);
+        }
+        world
     }
 
     pub fn add_light(&mut self, light: PointLight) {
         self.lights.push(light);
     }
 
-    pub fn add_object(&mut self, object: Shape) {
+    pub fn lights(&self) -> &[PointLight] {
+        &self.lights
+    }
+
+    pub fn objects(&self) -> &[Shape] {
+        &self.objects
+    }
+
+    /// Add an object to the world. Object ids identify objects while rendering, so
+    /// a clone of an object already in the world is given a new id. Panics if the
+    /// object is an instance that `try_add_object` would reject.
+    pub fn add_object(&mut self, object: Shape) -> ObjectIndex {
+        self.try_add_object(object).expect("invalid instance")
+    }
+
+    /// As `add_object`, but an instance whose prototype isn't in the world, or is or
+    /// contains an instance, is an error. So is an instance of a group added within
+    /// that group, which would contain itself.
+    pub fn try_add_object(&mut self, object: Shape) -> Result<ObjectIndex> {
+        self.check_instance(&object)?;
+        Ok(self.push_object(object))
+    }
+
+    fn check_instance(&self, object: &Shape) -> Result<()> {
+        let ShapeEnum::Instance(instance) = &object.shape else {
+            return Ok(());
+        };
+        let prototype = instance.prototype();
+        self.object(prototype)
+            .context("prototype index out of range")?;
+        if self.contains_instance(prototype) {
+            bail!(
+                "object {} is or contains an instance, so can't be a prototype",
+                prototype.0
+            );
+        }
+
+        let mut ancestor = object.parent();
+        while let Some(a) = ancestor {
+            if a == prototype {
+                bail!("group {} can't contain an instance of itself", prototype.0);
+            }
+            ancestor = self.objects[a.0].parent();
+        }
+        Ok(())
+    }
+
+    // Whether the object is an instance, or a group with one among its members
+    fn contains_instance(&self, index: ObjectIndex) -> bool {
+        match &self.objects[index.0].shape {
+            ShapeEnum::Instance(_) => true,
+            ShapeEnum::Group(group) => group.members().iter().any(|m| self.contains_instance(*m)),
+            _ => false,
+        }
+    }
+
+    fn push_object(&mut self, mut object: Shape) -> ObjectIndex {
+        if !self.object_ids.insert(object.id()) {
+            object.set_id(next_shape_id());
+            self.object_ids.insert(object.id());
+        }
+        self.object_bounds.push(object.world_bounds());
+        self.bounds_dirty.push(false);
+        self.is_prototype.push(false);
         self.objects.push(object);
+        ObjectIndex(self.objects.len() - 1)
+    }
+
+    /// Add an object that is only seen through its instances, not where it is
+    /// itself. See `add_instance`.
+    pub fn add_prototype(&mut self, object: Shape) -> ObjectIndex {
+        let index = self.add_object(object);
+        self.is_prototype[index.0] = true;
+        index
+    }
+
+    /// Add an instance of the `prototype` object, placed by `transform` as if it were
+    /// a group containing just the prototype. The prototype's geometry and material
+    /// are shared, not copied, so large numbers of instances are cheap. The prototype
+    /// may be a group, but not an instance, nor contain one.
+    pub fn add_instance(
+        &mut self,
+        prototype: &ObjectIndex,
+        transform: &Matrix4,
+    ) -> Result<ObjectIndex> {
+        let mut instance = Shape::instance(*prototype);
+        instance.try_set_transform(transform)?;
+        let index = self.try_add_object(instance)?;
+        self.mark_bounds_dirty(&index);
+        Ok(index)
+    }
+
+    // Objects intersected from the top level, rather than via a group or instance
+    fn is_top_level(&self, index: usize) -> bool {
+        self.objects[index].parent().is_none() && !self.is_prototype[index]
+    }
+
+    /// Add `child` as the last member of `group`. The child's transform is relative to
+    /// the group, and should be set before adding it.
+    pub fn add_child(&mut self, group: &ObjectIndex, mut child: Shape) -> Result<ObjectIndex> {
+        let parent = self.object(*group).context("group index out of range")?;
+        if !parent.is_group() {
+            bail!("object {} is not a group", group.0);
+        }
+        child.set_parent(*group, parent.world_transform());
+
+        let child_bounds = child.world_bounds();
+        let index = self.try_add_object(child)?;
+        if let ShapeEnum::Group(g) = &mut self.objects[group.0].shape {
+            g.members.push(index);
+        }
+
+        // Grow the bounds of all enclosing groups
+        let mut ancestor = Some(*group);
+        while let Some(a) = ancestor {
+            self.object_bounds[a.0] = self.object_bounds[a.0].union(&child_bounds);
+            ancestor = self.objects[a.0].parent();
+        }
+
+        Ok(index)
+    }
+
+    /// Replace the group hierarchy with a flat list of its primitives, each with its
+    /// composed transform baked in. Materials are kept. Group objects are removed, so
+    /// any previously obtained ObjectIndex values are invalidated. Instances refer to
+    /// their prototypes by index, so worlds with instances must not be flattened.
+    pub fn flatten_groups(&mut self) {
+        let objects = std::mem::take(&mut self.objects);
+        self.object_bounds.clear();
+        self.bounds_dirty.clear();
+        self.is_prototype.clear();
+        self.object_ids.clear();
+        for mut object in objects.into_iter().filter(|o| !o.is_group()) {
+            object.bake_world_transform();
+            self.push_object(object);
+        }
+    }
+
+    pub fn is_group(&self, index: &ObjectIndex) -> bool {
+        self.object(*index).is_some_and(Shape::is_group)
+    }
+
+    /// Members of a group, in the order they were added.
+    pub fn group_members(&self, index: &ObjectIndex) -> Result<&[ObjectIndex]> {
+        match &self
+            .object(*index)
+            .context("group index out of range")?
+            .shape
+        {
+            ShapeEnum::Group(g) => Ok(g.members()),
+            _ => bail!("object {} is not a group", index.0),
+        }
+    }
+
+    /// For debugging transforms: add thin red, green and blue cylinders of the given
+    /// length along the x, y and z axes of an object's own (object-space) frame.
+    /// They glow rather than being lit, and cast no shadows. The object's current
+    /// world transform is used, so add the gizmo after the object is in place.
+    pub fn add_axis_gizmo(&mut self, at: &ObjectIndex, length: f64) -> Result<[ObjectIndex; 3]> {
+        let frame = *self
+            .object(*at)
+            .context("object index out of range")?
+            .world_transform();
+
+        // Cylinders lie along y, so turn them onto the other axes
+        let axes = [
+            (rotation_z(-PI / 2.0), RED),
+            (identity4(), GREEN),
+            (rotation_x(PI / 2.0), BLUE),
+        ];
+        let radius = length / 50.0;
+
+        Ok(axes.map(|(orientation, color)| {
+            let mut axis = cylinder(0.0, length, true, true);
+            axis.set_transform(&(frame * orientation * scaling(radius, 1.0, radius)));
+            axis.material.color = color;
+            axis.material.ambient = 1.0;
+            axis.material.diffuse = 0.0;
+            axis.material.specular = 0.0;
+            axis.material.casts_shadow = false;
+            self.add_object(axis)
+        }))
+    }
+
+    pub fn set_ambient_background(&mut self, color: &Color) {
+        self.ambient_background = *color;
+    }
+
+    pub fn ambient_background(&self) -> Color {
+        self.ambient_background
+    }
+
+    /// Surround the scene with an environment image, seen by rays that miss every
+    /// object, instead of the background color.
+    pub fn set_environment(&mut self, environment: Option<Environment>) {
+        self.environment = environment;
+    }
+
+    pub fn environment(&self) -> Option<&Environment> {
+        self.environment.as_ref()
+    }
+
+    /// When enabled, surfaces are also lit by the environment (if any): its light
+    /// from the hemisphere above each point is added to the ambient term.
+    pub fn set_image_based_lighting(&mut self, enabled: bool) {
+        self.image_based_lighting = enabled;
+    }
+
+    pub fn image_based_lighting(&self) -> bool {
+        self.image_based_lighting
+    }
+
+    // The color seen by a ray that hits nothing
+    fn background(&self, ray: &Ray) -> Color {
+        match &self.environment {
+            Some(environment) => environment.radiance(&ray.direction),
+            None => self.ambient_background,
+        }
+    }
+
+    /// The refractive index of the medium outside all objects, that camera rays start
+    /// in, e.g. water for an underwater scene. The default is a vacuum.
+    pub fn set_ambient_medium(&mut self, refractive_index: f64) {
+        self.ambient_medium = Some(refractive_index);
+    }
+
+    pub fn ambient_medium(&self) -> f64 {
+        self.ambient_medium.unwrap_or(RefractiveIndex::VACUUM)
+    }
+
+    /// When enabled, materials with per-channel refractive indices (`dispersion`)
+    /// bend red, green and blue light by different amounts, splitting white light
+    /// into colors as a prism does. Each refraction then follows three rays
+    /// rather than one.
+    pub fn set_dispersion(&mut self, enabled: bool) {
+        self.dispersion = enabled;
+    }
+
+    pub fn dispersion(&self) -> bool {
+        self.dispersion
+    }
+
+    /// When enabled, light passing through transparent objects on its way to a surface
+    /// is filtered by their color, rather than being blocked entirely.
+    pub fn set_transparent_shadows(&mut self, enabled: bool) {
+        self.transparent_shadows = enabled;
+    }
+
+    pub fn transparent_shadows(&self) -> bool {
+        self.transparent_shadows
+    }
+
+    /// Rather than a single shadow ray from each hit to each light, cast `samples`
+    /// rays from points spread over a disk of radius `softness` on the surface around
+    /// the hit, and average them. This blurs shadow edges, approximating the soft
+    /// shadows of an area light, and anti-aliases them. One sample (or none) gives
+    /// the usual hard shadows.
+    pub fn set_soft_shadows(&mut self, samples: u32, softness: f64) {
+        self.shadow_samples = samples;
+        self.shadow_softness = softness;
+    }
+
+    pub fn shadow_samples(&self) -> u32 {
+        self.shadow_samples.max(1)
+    }
+
+    pub fn shadow_softness(&self) -> f64 {
+        self.shadow_softness
+    }
+
+    /// Limit the luminance of the light arriving at each surface by reflection or
+    /// refraction, to suppress isolated over-bright "fireflies" from mirrored
+    /// specular highlights. `None` (the default) applies no limit.
+    pub fn set_indirect_clamp(&mut self, max_luminance: Option<f64>) {
+        self.indirect_clamp = max_luminance;
+    }
+
+    pub fn indirect_clamp(&self) -> Option<f64> {
+        self.indirect_clamp
+    }
+
+    /// Rather than always following reflected and refracted rays (to the maximum
+    /// depth), follow each with probability equal to the fraction of light it
+    /// contributes, and give survivors a correspondingly greater weight. The result
+    /// is noisy, but on average the same, and deep recursion becomes much cheaper.
+    pub fn set_russian_roulette(&mut self, enabled: bool) {
+        self.russian_roulette = enabled;
+    }
+
+    pub fn russian_roulette(&self) -> bool {
+        self.russian_roulette
+    }
+
+    /// Seed for random choices, such as Russian roulette. The same seed gives the
+    /// same image.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    /// Rather than shading every light at each hit, choose `samples` of them at
+    /// random, in proportion to their brightness there (intensity over squared
+    /// distance), and weight each by the inverse of its chance of being chosen. The
+    /// result is noisy, but on average the same, and scenes with many lights become
+    /// much cheaper to shade. `None` (the default) shades every light. At least one
+    /// light is sampled, so `Some(0)` is taken as `Some(1)`.
+    pub fn set_light_sampling(&mut self, samples: Option<u32>) {
+        self.light_sampling = samples.map(|samples| samples.max(1));
+    }
+
+    pub fn light_sampling(&self) -> Option<u32> {
+        self.light_sampling
+    }
+
+    /// Shade the lights at each hit in parallel when there are more than `threshold`
+    /// of them, e.g. after expanding area lights into many point lights. Hits are
+    /// already shaded in parallel, so this only pays with many lights. The result is
+    /// the same either way. `None` restores the default, `PARALLEL_LIGHT_THRESHOLD`.
+    pub fn set_parallel_light_threshold(&mut self, threshold: Option<usize>) {
+        self.parallel_light_threshold = threshold;
+    }
+
+    pub fn parallel_light_threshold(&self) -> usize {
+        self.parallel_light_threshold
+            .unwrap_or(PARALLEL_LIGHT_THRESHOLD)
+    }
+
+    /// Cut away geometry for cross-sections: each plane, given by a point on it and
+    /// its normal, hides everything behind it (opposite the normal). Rays pass
+    /// through hidden surfaces, revealing the interiors of objects, and hidden
+    /// surfaces cast no shadows.
+    pub fn set_clip_planes(&mut self, planes: Vec<(Point, Vector)>) {
+        self.clip_planes = planes;
+    }
+
+    pub fn clip_planes(&self) -> &[(Point, Vector)] {
+        &self.clip_planes
+    }
+
+    fn is_clipped(&self, point: &Point) -> bool {
+        self.clip_planes
+            .iter()
+            .any(|(p, n)| dot(&(point - p), n) < 0.0)
+    }
+
+    // The ray's intersections, without those clipped away. A clipped surface doesn't
+    // bound a medium, so mustn't be counted when finding the refractive indices at
+    // the hit.
+    fn intersect_unclipped(&self, ray: &Ray) -> Intersections<'_> {
+        let mut xs = self.intersect(ray);
+        if !self.clip_planes.is_empty() {
+            xs.retain(|x| !self.is_clipped(&ray.position(x.t)));
+        }
+        xs
+    }
+
+    // The nearest intersection in front of the ray's origin, and within its range,
+    // that isn't clipped away or hidden from the kind of ray
+    fn first_hit<'a, 'b>(
+        &self,
+        ray: &Ray,
+        xs: &'b [Intersection<'a>],
+    ) -> Option<&'b Intersection<'a>> {
+        xs.iter().find(|&x| {
+            x.t > 0.0
+                && (ray.t_min..=ray.t_max).contains(&x.t)
+                && !self.is_clipped(&ray.position(x.t))
+                && x.object
+                    .is_none_or(|object| object.material.is_visible_to(ray.path))
+        })
+    }
+
+    fn shades_lights_in_parallel(&self) -> bool {
+        self.lights.len() > self.parallel_light_threshold()
     }
 
-    fn intersect(&self, ray: &Ray) -> Intersections {
+    // The weight to give a secondary ray contributing `fraction` of the light at a
+    // surface, or None if Russian roulette terminates it
+    fn roulette(&self, ray: &Ray, depth: i32, fraction: f64) -> Option<f64> {
+        if !self.russian_roulette || fraction >= 1.0 {
+            return Some(fraction);
+        }
+
+        // Random, but determined by the seed and the ray
+        let key = random_key(
+            self.seed ^ depth as u64,
+            &[
+                ray.origin.x(),
+                ray.origin.y(),
+                ray.origin.z(),
+                ray.direction.x(),
+                ray.direction.y(),
+                ray.direction.z(),
+            ],
+        );
+
+        if hash_unit(key) < fraction {
+            Some(1.0)
+        } else {
+            None
+        }
+    }
+
+    // The lights to shade at `point`, as indices into `lights` with the weight to
+    // give each, or None to shade all of them equally
+    fn sample_lights(&self, point: &Point) -> Option<Vec<(usize, f64)>> {
+        let samples = self.light_sampling?;
+        if samples as usize >= self.lights.len() {
+            return None;
+        }
+
+        let weights: Vec<f64> = self
+            .lights
+            .iter()
+            .map(|light| {
+                let distance_squared = (light.position - *point).magnitude().powi(2);
+                light.intensity.luminance() / distance_squared.max(EPSILON)
+            })
+            .collect();
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return None;
+        }
+
+        // Random, but determined by the seed and the point
+        let key = random_key(self.seed, &[point.x(), point.y(), point.z()]);
+        let chosen = (0..samples)
+            .filter_map(|i| {
+                let mut u = hash_unit(key ^ i as u64) * total;
+                let chosen = weights.iter().position(|&w| {
+                    u -= w;
+                    u < 0.0
+                });
+                // Rounding may leave a sliver past the last light
+                let chosen = chosen.or_else(|| weights.iter().rposition(|&w| w > 0.0))?;
+                let probability = weights[chosen] / total;
+                Some((chosen, 1.0 / (probability * samples as f64)))
+            })
+            .collect();
+        Some(chosen)
+    }
+
+    // Scale the color down, preserving its hue, if it's brighter than the clamp
+    fn clamp_indirect(&self, c: Color) -> Color {
+        match self.indirect_clamp {
+            Some(max) if c.luminance() > max => c * (max / c.luminance()),
+            _ => c,
+        }
+    }
+
+    /// World-space bounding box of all objects.
+    pub fn bounds(&self) -> Bounds {
+        self.objects
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| self.is_top_level(i))
+            .fold(Bounds::default(), |acc, (i, _)| {
+                acc.union(&self.current_bounds(ObjectIndex(i)))
+            })
+    }
+
+    /// A description of the world for debugging scenes, without rendering: each
+    /// object's kind and world-space bounds, the number of triangles in each group,
+    /// the number of lights, and the bounds of the whole scene. Call `prepare` first.
+    pub fn inspect(&self) -> String {
+        let mut report = format!("Objects: {}\n", self.objects.len());
+        for (i, object) in self.objects.iter().enumerate() {
+            let mut description = object.shape.kind().to_string();
+            match &object.shape {
+                ShapeEnum::Group(group) => {
+                    let triangles = self.count_triangles(group.members());
+                    description += &format!(
+                        " ({} members, {triangles} triangles)",
+                        group.members().len()
+                    );
+                }
+                ShapeEnum::Instance(instance) => {
+                    description += &format!(" of {}", instance.prototype.0);
+                }
+                _ => {}
+            }
+            if let Some(parent) = object.parent() {
+                description += &format!(", in group {}", parent.0);
+            }
+            if self.is_prototype[i] {
+                description += ", prototype";
+            }
+            report += &format!(
+                "  {i}: {description}: {}\n",
+                describe_bounds(&self.current_bounds(ObjectIndex(i)))
+            );
+        }
+        report += &format!("Lights: {}\n", self.lights.len());
+        report += &format!("Scene bounds: {}\n", describe_bounds(&self.bounds()));
+        report
+    }
+
+    // The number of triangles among these objects, including within groups
+    fn count_triangles(&self, objects: &[ObjectIndex]) -> usize {
+        objects
+            .iter()
+            .map(|i| match &self.objects[i.0].shape {
+                ShapeEnum::Triangle(_) | ShapeEnum::SmoothTriangle(_) => 1,
+                ShapeEnum::Group(group) => self.count_triangles(group.members()),
+                _ => 0,
+            })
+            .sum()
+    }
+
+    /// Count ray/object tests, and those skipped by bounds culling, for `cull_ratio`.
+    /// Off by default, as every thread casting rays updates the same counters.
+    pub fn set_cull_statistics(&mut self, enabled: bool) {
+        self.cull_statistics = enabled;
+    }
+
+    fn record_cull_statistics(&self, stats: &CullStatistics) {
+        if self.cull_statistics {
+            self.cull_counters
+                .tested
+                .fetch_add(stats.tested, Ordering::Relaxed);
+            self.cull_counters
+                .culled
+                .fetch_add(stats.culled, Ordering::Relaxed);
+        }
+    }
+
+    /// Fraction of ray/object tests skipped by bounds culling since the statistics
+    /// were enabled or last reset. Zero if no rays have been cast, or if they aren't
+    /// enabled.
+    pub fn cull_ratio(&self) -> f64 {
+        let tested = self.cull_counters.tested.load(Ordering::Relaxed);
+        if tested == 0 {
+            0.0
+        } else {
+            self.cull_counters.culled.load(Ordering::Relaxed) as f64 / tested as f64
+        }
+    }
+
+    pub fn reset_cull_statistics(&self) {
+        self.cull_counters.tested.store(0, Ordering::Relaxed);
+        self.cull_counters.culled.store(0, Ordering::Relaxed);
+    }
+
+    /// World-space bounds of each object, indexed as the objects are. A group's
+    /// bounds span all of its members. Entries marked dirty are stale until
+    /// `refresh_bounds` is called.
+    pub fn object_bounds(&self) -> &[Bounds] {
+        &self.object_bounds
+    }
+
+    pub fn object(&self, index: ObjectIndex) -> Option<&Shape> {
+        self.objects.get(index.0)
+    }
+
+    /// Mutable access to an object after it has been added, e.g. to change its
+    /// material, or to animate it. If its world-space extent changes, call
+    /// `mark_bounds_dirty` afterwards.
+    pub fn object_mut(&mut self, index: &ObjectIndex) -> Result<&mut Shape> {
+        let count = self.objects.len();
+        self.objects
+            .get_mut(index.0)
+            .with_context(|| format!("object index {} out of range ({count} objects)", index.0))
+    }
+
+    /// Note that an object's bounds, and so those of every group enclosing it, are
+    /// out of date. They are recomputed when next needed for intersection, and
+    /// stored by `refresh_bounds`.
+    pub fn mark_bounds_dirty(&mut self, index: &ObjectIndex) {
+        let mut next = Some(*index);
+        while let Some(i) = next {
+            self.bounds_dirty[i.0] = true;
+            next = self.objects[i.0].parent();
+        }
+    }
+
+    /// Recompute and store the bounds of all objects marked dirty.
+    pub fn refresh_bounds(&mut self) {
+        for i in 0..self.objects.len() {
+            if self.bounds_dirty[i] {
+                self.object_bounds[i] = self.current_bounds(ObjectIndex(i));
+            }
+        }
+        self.bounds_dirty.fill(false);
+    }
+
+    /// Bring the cached, composed world transforms of all grouped objects, and all
+    /// bounds, up to date with their own transforms, e.g. after editing objects with
+    /// `object_mut`. Call once before rendering, rather than per ray.
+    pub fn prepare(&mut self) {
+        // A group is always added before its members, so in index order each parent's
+        // world transform (and inherited material) is up to date before its members'
+        // are composed from it.
+        for i in 0..self.objects.len() {
+            if let Some(parent) = self.objects[i].parent() {
+                let parent_world_transform = *self.objects[parent.index()].world_transform();
+                self.objects[i].set_parent(parent, &parent_world_transform);
+                if self.objects[i].inherits_material() {
+                    self.objects[i].material = self.objects[parent.index()].material.clone();
+                }
+            }
+        }
+
+        self.bounds_dirty.fill(true);
+        self.refresh_bounds();
+    }
+
+    // The stored bounds of an object, or if they're stale, freshly computed ones
+    fn current_bounds(&self, index: ObjectIndex) -> Bounds {
+        if !self.bounds_dirty[index.0] {
+            return self.object_bounds[index.0];
+        }
+        let object = &self.objects[index.0];
+        match &object.shape {
+            ShapeEnum::Group(group) => group
+                .members()
+                .iter()
+                .fold(object.world_bounds(), |acc, m| {
+                    acc.union(&self.current_bounds(*m))
+                }),
+            ShapeEnum::Instance(instance) => self
+                .current_bounds(instance.prototype)
+                .transform(object.world_transform()),
+            _ => object.world_bounds(),
+        }
+    }
+
+    fn intersect(&self, ray: &Ray) -> Intersections<'_> {
         let mut intersections = Vec::with_capacity(2);
+        let mut stats = CullStatistics::default();
 
-        // Intersections must be in sorted order
-        for object in &self.objects {
-            let xs = intersect(object, ray);
-            // TODO: insert in sorted order?
-            for i in xs {
-                intersections.push(i);
+        // Groups are visited via their parent, so start from the top level
+        for index in 0..self.objects.len() {
+            if self.is_top_level(index) {
+                self.intersect_object(ObjectIndex(index), ray, &mut intersections, &mut stats);
             }
         }
 
-        intersections.sort_by(|a, b| a.t.total_cmp(&b.t));
+        self.record_cull_statistics(&stats);
+
+        // Intersections must be in sorted order
+        sort_intersections(&mut intersections);
+        intersections
+    }
+
+    /// Every intersection of the ray with the world's objects, in ascending order of
+    /// t, including those behind the ray's origin. Their (n1, n2) refractive indices
+    /// are available from `refractive_indices`.
+    pub fn all_intersections(&self, ray: &Ray) -> Intersections<'_> {
+        self.intersect(ray)
+    }
+
+    /// The (n1, n2) refractive indices at every one of a ray's sorted intersections,
+    /// e.g. from `all_intersections`, starting out in the world's ambient medium.
+    pub fn refractive_indices(&self, ray: &Ray, xs: &[Intersection]) -> Vec<(f64, f64)> {
+        refractive_indices_in_medium(ray, xs, self.ambient_medium())
+    }
+
+    /// Intersect a ray with a single object, which should belong to this world. Unlike
+    /// `intersections::intersect`, a group's members are resolved, recursively.
+    pub fn intersect_shape<'a>(&'a self, object: &'a Shape, ray: &Ray) -> Intersections<'a> {
+        let mut intersections = match &object.shape {
+            ShapeEnum::Group(group) => {
+                let mut intersections = vec![];
+                let mut stats = CullStatistics::default();
+                for member in group.members() {
+                    self.intersect_object(*member, ray, &mut intersections, &mut stats);
+                }
+                intersections
+            }
+            ShapeEnum::Instance(instance) => {
+                let local_ray = ray.transform(object.world_inverse_transform());
+                let mut intersections = vec![];
+                let mut stats = CullStatistics::default();
+                self.intersect_object(
+                    instance.prototype,
+                    &local_ray,
+                    &mut intersections,
+                    &mut stats,
+                );
+                for i in &mut intersections {
+                    i.instance = Some(object);
+                }
+                intersections
+            }
+            _ => intersect(object, ray),
+        };
+        sort_intersections(&mut intersections);
         intersections
     }
 
+    /// The hit (nearest intersection with positive t) for each of a packet of rays,
+    /// e.g. the primary rays for a small block of pixels. The loop over objects and
+    /// the bounds tests are shared by the packet: an object, or a whole group, is
+    /// skipped once for every ray that misses its bounds.
+    pub fn intersect_packet(&self, rays: &[Ray]) -> Vec<Option<Intersection<'_>>> {
+        let mut hits = vec![None; rays.len()];
+        let mut stats = CullStatistics::default();
+        let active: Vec<usize> = (0..rays.len()).collect();
+
+        for index in 0..self.objects.len() {
+            if self.is_top_level(index) {
+                self.intersect_object_packet(
+                    ObjectIndex(index),
+                    rays,
+                    &active,
+                    &mut hits,
+                    &mut stats,
+                );
+            }
+        }
+
+        self.record_cull_statistics(&stats);
+
+        hits
+    }
+
+    fn intersect_object_packet<'a>(
+        &'a self,
+        index: ObjectIndex,
+        rays: &[Ray],
+        active: &[usize],
+        hits: &mut [Option<Intersection<'a>>],
+        stats: &mut CullStatistics,
+    ) {
+        let bounds = self.current_bounds(index);
+        let tested = active.len();
+        let active: Vec<usize> = active
+            .iter()
+            .copied()
+            .filter(|&r| bounds.intersects(&rays[r]))
+            .collect();
+        stats.tested += tested as u64;
+        stats.culled += (tested - active.len()) as u64;
+        if active.is_empty() {
+            return;
+        }
+
+        let object = &self.objects[index.0];
+        match &object.shape {
+            ShapeEnum::Group(group) => {
+                for member in group.members() {
+                    self.intersect_object_packet(*member, rays, &active, hits, stats);
+                }
+            }
+            ShapeEnum::Instance(_) => {
+                // Instances are rare enough among the objects of a world to
+                // intersect ray by ray
+                for r in active {
+                    let mut xs = vec![];
+                    self.intersect_object(index, &rays[r], &mut xs, stats);
+                    for i in xs {
+                        if i.t > 0.0 && hits[r].as_ref().is_none_or(|h| is_nearer(&i, h)) {
+                            hits[r] = Some(i);
+                        }
+                    }
+                }
+            }
+            _ => {
+                for r in active {
+                    for mut i in intersect(object, &rays[r]) {
+                        i.object_index = Some(index);
+                        if i.t > 0.0 && hits[r].as_ref().is_none_or(|h| is_nearer(&i, h)) {
+                            hits[r] = Some(i);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn intersect_object<'a>(
+        &'a self,
+        index: ObjectIndex,
+        ray: &Ray,
+        intersections: &mut Intersections<'a>,
+        stats: &mut CullStatistics,
+    ) {
+        stats.tested += 1;
+        if !self.current_bounds(index).intersects(ray) {
+            stats.culled += 1;
+            return;
+        }
+
+        let object = &self.objects[index.0];
+        match &object.shape {
+            ShapeEnum::Group(group) => {
+                for member in group.members() {
+                    self.intersect_object(*member, ray, intersections, stats);
+                }
+            }
+            ShapeEnum::Instance(instance) => {
+                // The prototype is placed in the instance's object space. Transforming
+                // a ray keeps its t values, so they're the same in world space.
+                let local_ray = ray.transform(object.world_inverse_transform());
+                let start = intersections.len();
+                self.intersect_object(instance.prototype, &local_ray, intersections, stats);
+                for i in &mut intersections[start..] {
+                    i.instance = Some(object);
+                }
+            }
+            _ => {
+                // TODO: insert in sorted order?
+                for mut i in intersect(object, ray) {
+                    i.object_index = Some(index);
+                    intersections.push(i);
+                }
+            }
+        }
+    }
+
     fn is_shadowed(&self, point: &Point, light: &PointLight) -> bool {
         // Cast a ray from this point to the light source
         let v = light.position - point;
@@ -64,7 +946,7 @@ impl World {
 
         // No need to call hit() as already sorted
         //if let Some(h) = hit(&mut xs) {
-        let hit = xs.iter().find(|&x| x.t > 0.0);
+        let hit = self.first_hit(&ray, &xs);
         if let Some(h) = hit {
             h.t < distance
         } else {
@@ -72,194 +954,1472 @@ impl World {
         }
     }
 
+    // The fraction of each color component of the light that reaches the point.
+    // Opaque objects block the light entirely, while transparent objects filter it
+    // by their color, once per object, weighted by their transparency.
+    fn shadow_attenuation(&self, point: &Point, light: &PointLight) -> Color {
+        let v = light.position - point;
+        let distance = magnitude(&v);
+        let direction = normalize(&v);
+
+        let ray = ray(*point, direction);
+        let intersections = intersect_world(self, &ray);
+
+        let mut attenuation = WHITE;
+        let mut seen: Vec<(u64, Option<u64>)> = vec![];
+        for x in intersections.iter().filter(|x| x.t > 0.0 && x.t < distance) {
+            if self.is_clipped(&ray.position(x.t)) {
+                continue;
+            }
+            let object = x.object.expect("should be object");
+            // Each instance of a prototype is a separate object
+            let key = (object.id(), x.instance.map(Shape::id));
+            if !object.material.casts_shadow || seen.contains(&key) {
+                continue;
+            }
+            if object.material.transparency == 0.0 {
+                return BLACK;
+            }
+            seen.push(key);
+            attenuation = attenuation * object.material.color * object.material.transparency;
+        }
+        attenuation
+    }
+
     // Returns the color at the intersection encapsulated by comps, in the given world.
     fn shade_hit(&self, comps: &IntersectionComputation, depth: i32) -> Color {
-        let mut surface = color(0.0, 0.0, 0.0);
+        let material = comps
+            .object
+            .material_at(&comps.material_point(&comps.point));
+        self.shade_hit_with(comps, material, depth)
+    }
+
+    // Shade the hit as if the object were made of `material`, which for a blended
+    // material is each of its parts in turn
+    fn shade_hit_with(
+        &self,
+        comps: &IntersectionComputation,
+        material: &Material,
+        depth: i32,
+    ) -> Color {
+        // Patterns are evaluated where the material sees the point, which differs for
+        // an instance
+        let material_point = comps.material_point(&comps.over_point);
+        let footprint = comps.material_footprint();
+
+        if let Some((a, b, t)) =
+            material.blend_at(comps.object, &material_point, footprint.as_ref())
+        {
+            // Don't trace rays for a material that contributes nothing
+            return if t == 0.0 {
+                self.shade_hit_with(comps, a, depth)
+            } else if t == 1.0 {
+                self.shade_hit_with(comps, b, depth)
+            } else {
+                self.shade_hit_with(comps, a, depth) * (1.0 - t)
+                    + self.shade_hit_with(comps, b, depth) * t
+            };
+        }
 
-        for light in &self.lights {
-            let shadowed =
-                comps.object.material.receives_shadow && self.is_shadowed(&comps.over_point, light);
-            let surface_from_light = comps.object.material.lighting(
-                comps.object,
+        let mut surface = color(0.0, 0.0, 0.0);
+        let surface_color =
+            material.color_at_filtered(comps.object, &material_point, footprint.as_ref());
+
+        let shade_light = |light: &PointLight| {
+            let attenuation = if !material.receives_shadow {
+                WHITE
+            } else {
+                self.soft_shadow_attenuation(&comps.over_point, &comps.normalv, light)
+            };
+            material.lighting_colored(
+                &surface_color,
                 &Some(*light),
                 &comps.over_point, // avoid boundary issues
                 &comps.eyev,
                 &comps.normalv,
-                shadowed,
-            );
-            surface += surface_from_light;
+                &attenuation,
+            )
+        };
+
+        match self.sample_lights(&comps.over_point) {
+            // Without any lights, show flat-shaded geometry rather than black, as if
+            // lit by the ambient term of a white light, unless the environment lights it
+            None if self.lights.is_empty()
+                && !(self.image_based_lighting && self.environment.is_some()) =>
+            {
+                surface += surface_color * material.ambient;
+            }
+            Some(sampled) => {
+                for (i, weight) in sampled {
+                    surface += shade_light(&self.lights[i]) * weight;
+                }
+            }
+            None if self.shades_lights_in_parallel() => {
+                // Add the contributions in order, to match the serial sum exactly
+                let contributions: Vec<Color> = self.lights.par_iter().map(shade_light).collect();
+                for c in contributions {
+                    surface += c;
+                }
+            }
+            None => {
+                for light in &self.lights {
+                    surface += shade_light(light);
+                }
+            }
+        }
+
+        if let (true, Some(environment)) = (self.image_based_lighting, &self.environment) {
+            surface += material.color_at(comps.object, &material_point)
+                * environment.irradiance(&comps.normalv)
+                * material.ambient;
         }
 
-        let reflected = self.reflected_color(comps, depth);
-        let refracted = self.refracted_color(comps, depth);
+        let reflected = self.reflected_color_with(comps, material, depth);
+        let refracted = self.refracted_color_with(comps, material, depth);
 
         // Experimental: reduce surface color for reflective materials
         // (Makes reflective objects very dark)
         //let surface = surface * (1.0 - comps.object.material.reflective);
 
-        if comps.object.material.reflective > 0.0 && comps.object.material.transparency > 0.0 {
+        let color = if material.reflective > 0.0 && material.transparency > 0.0 {
             let reflectance = schlick(comps);
             surface + reflected * reflectance + refracted * (1.0 - reflectance)
         } else {
             surface + reflected + refracted
-        }
+        };
+
+        // Emission is added once, whatever the number of lights, and isn't split by
+        // the Fresnel blend of reflected and refracted light
+        color + material.emission
+    }
+
+    // The light reaching `point` from `light`, averaged over the soft shadow samples
+    // around it on the surface with normal `normalv`
+    fn soft_shadow_attenuation(
+        &self,
+        point: &Point,
+        normalv: &Vector,
+        light: &PointLight,
+    ) -> Color {
+        let attenuation = |p: &Point| {
+            if self.transparent_shadows {
+                self.shadow_attenuation(p, light)
+            } else if self.is_shadowed(p, light) {
+                BLACK
+            } else {
+                WHITE
+            }
+        };
+
+        let samples = self.shadow_samples();
+        if samples == 1 || self.shadow_softness == 0.0 {
+            return attenuation(point);
+        }
+
+        let (u, v) = normalv.tangent_basis();
+
+        // Spread the points evenly over the disk, in a golden-angle spiral, so that
+        // the same hit always gives the same result
+        let golden_angle = PI * (3.0 - f64::sqrt(5.0));
+        let mut sum = BLACK;
+        for i in 0..samples {
+            let r = self.shadow_softness * f64::sqrt((i as f64 + 0.5) / samples as f64);
+            let theta = i as f64 * golden_angle;
+            let p = *point + u * (r * theta.cos()) + v * (r * theta.sin());
+            sum += attenuation(&p);
+        }
+        sum / samples as f64
     }
 
     fn color_at(&self, ray: &Ray, depth: i32) -> Color {
-        let xs = self.intersect(ray);
+        self.color_and_distance_at(ray, depth).0
+    }
+
+    /// As `color_at`, with the hit blended into `fog` by its distance from the ray
+    /// origin. Rays that miss everything are fully fogged. Only the given ray is
+    /// fogged, not the reflected and refracted rays it spawns.
+    fn color_at_fogged(&self, ray: &Ray, depth: i32, fog: &Fog) -> Color {
+        let (c, distance) = self.color_and_distance_at(ray, depth);
+        fog.apply(&c, distance)
+    }
+
+    // Color seen along the ray, and the world-space distance to the hit (infinite on a miss)
+    fn color_and_distance_at(&self, ray: &Ray, depth: i32) -> (Color, f64) {
+        let xs = self.intersect_unclipped(ray);
 
         // Sort & Find copied from intersections.hit(), due to borrowing issue
         // No need to sort as self.intersect() already does this.
         //xs.sort_by(|a, b| a.t.total_cmp(&b.t));
-        let hit = xs.iter().find(|&x| x.t > 0.0);
+        let hit = self.first_hit(ray, &xs);
+
+        if let Some(i) = hit {
+            let comps = prepare_computations_in_medium(i, ray, &xs, self.ambient_medium());
+            (
+                self.shade_hit(&comps, depth),
+                i.t * magnitude(&ray.direction),
+            )
+        } else {
+            (self.background(ray), f64::INFINITY)
+        }
+    }
+
+    // The world-space distance along the ray to the nearest hit, infinite on a miss,
+    // without shading it
+    fn hit_distance(&self, ray: &Ray) -> f64 {
+        match self.first_hit(ray, &self.intersect(ray)) {
+            Some(i) => i.t * magnitude(&ray.direction),
+            None => f64::INFINITY,
+        }
+    }
+
+    /// For compositing rendered objects over a photograph: the color seen along the
+    /// ray, and its alpha (opacity). The `catcher` object, e.g. a ground plane, is
+    /// rendered only as the shadows that fall on it, black with an alpha of how much
+    /// light is blocked, so it's transparent where unshadowed. Other objects are
+    /// opaque, and the background is transparent. If `catcher` isn't in the world,
+    /// every object is opaque.
+    fn shadow_catcher_at(&self, ray: &Ray, depth: i32, catcher: ObjectIndex) -> (Color, f64) {
+        let xs = self.intersect_unclipped(ray);
+        let Some(i) = self.first_hit(ray, &xs) else {
+            return (self.background(ray), 0.0);
+        };
+
+        let comps = prepare_computations_in_medium(i, ray, &xs, self.ambient_medium());
+        if self
+            .object(catcher)
+            .is_none_or(|c| c.id() != comps.object.id())
+        {
+            return (self.shade_hit(&comps, depth), 1.0);
+        }
+
+        if self.lights.is_empty() {
+            return (BLACK, 0.0);
+        }
+        let light: f64 = self
+            .lights
+            .iter()
+            .map(|light| {
+                let attenuation =
+                    self.soft_shadow_attenuation(&comps.over_point, &comps.normalv, light);
+                (attenuation.red() + attenuation.green() + attenuation.blue()) / 3.0
+            })
+            .sum();
+        (BLACK, 1.0 - light / self.lights.len() as f64)
+    }
+
+    /// As `color_at`, for a camera ray whose differentials are used to filter
+    /// patterns on the surface it hits. Reflected and refracted rays are unfiltered.
+    fn color_at_differential(&self, rd: &RayDifferential, depth: i32) -> Color {
+        let xs = self.intersect_unclipped(&rd.ray);
+        let hit = self.first_hit(&rd.ray, &xs);
 
         if let Some(i) = hit {
-            let comps = prepare_computations_for_refraction(i, ray, &xs);
+            let mut comps = prepare_computations_in_medium(i, &rd.ray, &xs, self.ambient_medium());
+            comps.footprint = Some(rd.footprint(&comps.point, &comps.normalv));
             self.shade_hit(&comps, depth)
         } else {
-            Color::new(0.0, 0.0, 0.0)
+            self.background(&rd.ray)
         }
     }
 
     fn reflected_color(&self, comps: &IntersectionComputation, depth: i32) -> Color {
-        if comps.object.material.reflective == 0.0 || depth < 1 {
+        self.reflected_color_with(comps, &comps.object.material, depth)
+    }
+
+    fn reflected_color_with(
+        &self,
+        comps: &IntersectionComputation,
+        material: &Material,
+        depth: i32,
+    ) -> Color {
+        if material.reflective == 0.0 || depth < 1 {
             color(0.0, 0.0, 0.0)
         } else {
-            let reflected_ray = ray(comps.over_point, comps.reflectv);
-            let reflected_color = self.color_at(&reflected_ray, depth - 1);
-            reflected_color * comps.object.material.reflective
+            let reflected_ray = ray(comps.over_point, comps.reflectv)
+                .with_path(comps.path)
+                .with_kind(RayKind::Reflection);
+            let reflective = material.reflectivity_at(dot(&comps.eyev, &comps.normalv));
+            match self.roulette(&reflected_ray, depth, reflective) {
+                Some(weight) => {
+                    self.clamp_indirect(self.color_at(&reflected_ray, depth - 1)) * weight
+                }
+                None => BLACK,
+            }
         }
     }
 
     fn refracted_color(&self, comps: &IntersectionComputation, depth: i32) -> Color {
-        if comps.object.material.transparency == 0.0 || depth < 1 {
-            color(0.0, 0.0, 0.0)
+        self.refracted_color_with(comps, &comps.object.material, depth)
+    }
+
+    fn refracted_color_with(
+        &self,
+        comps: &IntersectionComputation,
+        material: &Material,
+        depth: i32,
+    ) -> Color {
+        if material.transparency == 0.0 || depth < 1 {
+            return color(0.0, 0.0, 0.0);
+        }
+
+        if !self.dispersion {
+            return self.refracted_ray_color(comps, comps.n1, comps.n2, material, depth);
+        }
+
+        let (n1, n2) = (comps.n1_rgb, comps.n2_rgb);
+        let uniform = |n: [f64; 3]| n[0] == n[1] && n[1] == n[2];
+        if uniform(n1) && uniform(n2) {
+            return self.refracted_ray_color(comps, n1[0], n2[0], material, depth);
+        }
+
+        // Each channel is bent by a different amount, so follow a ray for each
+        let red = self.refracted_ray_color(comps, n1[0], n2[0], material, depth);
+        let green = self.refracted_ray_color(comps, n1[1], n2[1], material, depth);
+        let blue = self.refracted_ray_color(comps, n1[2], n2[2], material, depth);
+        color(red.red(), green.green(), blue.blue())
+    }
+
+    // The light refracted into the surface, bent according to n1 and n2
+    fn refracted_ray_color(
+        &self,
+        comps: &IntersectionComputation,
+        n1: f64,
+        n2: f64,
+        material: &Material,
+        depth: i32,
+    ) -> Color {
+        // Snell's law:  sin(theta_i) / sin(theta_t) = n2 / n1,
+        // where theta_i is angle of the incoming ray, and theta_t is the angle of the refracted ray
+        // Find theta_i, given theta_t, n1, n2:
+        let n_ratio = n1 / n2;
+
+        // Use fact that cos(theta_i) == dot(eye_vector, normal_vector)
+        let cos_i = dot(&comps.eyev, &comps.normalv);
+
+        // Find sin(theta_2)^2 via trig identity:
+        let sin2_t = n_ratio * n_ratio * (1.0 - cos_i * cos_i);
+
+        // If sin2_t > 1.0, there is no transmission - Total Internal Reflection
+        if sin2_t > 1.0 {
+            return color(0.0, 0.0, 0.0);
+        }
+
+        // Find cos(theta_t) via trig identity:
+        let cos_t = f64::sqrt(1.0 - sin2_t);
+
+        // Compute direction of refracted ray
+        let direction = comps.normalv * (n_ratio * cos_i - cos_t) - comps.eyev * n_ratio;
+
+        let refracted_ray = ray(comps.under_point, direction)
+            .with_path(comps.path)
+            .with_kind(RayKind::Refraction);
+
+        match self.roulette(&refracted_ray, depth, material.transparency) {
+            Some(weight) => self.clamp_indirect(self.color_at(&refracted_ray, depth - 1)) * weight,
+            None => BLACK,
+        }
+    }
+}
+
+fn describe_bounds(bounds: &Bounds) -> String {
+    if bounds.is_empty() {
+        return "empty".to_string();
+    }
+    // Rounded, to be readable
+    let describe = |x: f64| {
+        let s = format!("{x:.3}");
+        let s = s.trim_end_matches('0').trim_end_matches('.');
+        if s == "-0" {
+            "0".to_string()
         } else {
-            // Snell's law:  sin(theta_i) / sin(theta_t) = n2 / n1,
-            // where theta_i is angle of the incoming ray, and theta_t is the angle of the refracted ray
-            // Find theta_i, given theta_t, n1, n2:
-            let n_ratio = comps.n1 / comps.n2;
+            s.to_string()
+        }
+    };
+    let describe_point = |p: &Point| {
+        format!(
+            "({}, {}, {})",
+            describe(p.x()),
+            describe(p.y()),
+            describe(p.z())
+        )
+    };
+    format!(
+        "{} to {}",
+        describe_point(&bounds.min),
+        describe_point(&bounds.max)
+    )
+}
+
+// Whether intersection `i` is nearer than `h`, with the same ordering as
+// sort_intersections, so that the hit of a packet is the same as from `intersect`
+fn is_nearer(i: &Intersection, h: &Intersection) -> bool {
+    i.t.total_cmp(&h.t)
+        .then_with(|| i.object.map(Shape::id).cmp(&h.object.map(Shape::id)))
+        .is_lt()
+}
+
+pub fn world() -> World {
+    World::default()
+}
+
+// Mix `values` into `seed`, giving a key for `hash_unit`
+fn random_key(seed: u64, values: &[f64]) -> u64 {
+    values.iter().fold(seed, |key, v| {
+        (key ^ v.to_bits())
+            .rotate_left(17)
+            .wrapping_mul(0x2545_f491_4f6c_dd1d)
+    })
+}
+
+pub fn default_world() -> World {
+    let mut lights = vec![];
+    let light = point_light(point(-10.0, 10.0, -10.0), color(1.0, 1.0, 1.0));
+    lights.push(light);
+
+    let mut objects = vec![];
+
+    let m = material(color(0.8, 1.0, 0.6), 0.1, 0.7, 0.2, 200.0);
+    let mut s1 = sphere(1);
+    s1.material = m;
+    objects.push(s1);
+
+    let mut s2 = sphere(2);
+    s2.set_transform(&scaling(0.5, 0.5, 0.5));
+    objects.push(s2);
+
+    World::new(lights, objects)
+}
+
+pub fn inspect(world: &World) -> String {
+    world.inspect()
+}
+
+pub fn intersect_world<'a>(world: &'a World, ray: &Ray) -> Intersections<'a> {
+    world.intersect(ray)
+}
+
+pub fn intersect_packet<'a>(world: &'a World, rays: &[Ray]) -> Vec<Option<Intersection<'a>>> {
+    world.intersect_packet(rays)
+}
+
+pub fn is_shadowed(world: &World, point: &Point, light: &PointLight) -> bool {
+    world.is_shadowed(point, light)
+}
+
+pub fn shadow_attenuation(world: &World, point: &Point, light: &PointLight) -> Color {
+    world.shadow_attenuation(point, light)
+}
+
+pub fn shade_hit(world: &World, comps: &IntersectionComputation, depth: i32) -> Color {
+    world.shade_hit(comps, depth)
+}
+
+pub fn color_at(world: &World, ray: &Ray, depth: i32) -> Color {
+    world.color_at(ray, depth)
+}
+
+pub fn color_at_fogged(world: &World, ray: &Ray, depth: i32, fog: &Fog) -> Color {
+    world.color_at_fogged(ray, depth, fog)
+}
+
+pub fn hit_distance(world: &World, ray: &Ray) -> f64 {
+    world.hit_distance(ray)
+}
+
+pub fn shadow_catcher_at(
+    world: &World,
+    ray: &Ray,
+    depth: i32,
+    catcher: ObjectIndex,
+) -> (Color, f64) {
+    world.shadow_catcher_at(ray, depth, catcher)
+}
+
+pub fn color_at_differential(world: &World, rd: &RayDifferential, depth: i32) -> Color {
+    world.color_at_differential(rd, depth)
+}
+
+pub fn reflected_color(world: &World, comps: &IntersectionComputation, depth: i32) -> Color {
+    world.reflected_color(comps, depth)
+}
+
+pub fn refracted_color(world: &World, comps: &IntersectionComputation, depth: i32) -> Color {
+    world.refracted_color(comps, depth)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::{camera, Resolution};
+    use crate::canvas::canvas;
+    use crate::ibl::environment;
+    use crate::intersections::{
+        intersect_in_world, intersection, intersections, prepare_computations,
+        prepare_computations_for_refraction, refractive_indices, Intersection,
+    };
+    use crate::materials::blend_material;
+    use crate::math::EPSILON;
+    use crate::patterns::{gradient_pattern, solid_pattern, stripe_pattern, test_pattern};
+    use crate::rays::ray;
+    use crate::shapes::{cube, cube_with_faces, glass_sphere, group, plane, triangle};
+    use crate::transformations::{rotation_y, translation, view_transform};
+    use crate::tuples::vector;
+    use approx::{assert_relative_eq, assert_relative_ne};
+    use rstest::rstest;
+
+    // Creating an empty world
+    #[test]
+    fn creating_an_empty_world() {
+        let w = world();
+        assert!(w.lights.is_empty());
+        assert!(w.objects.is_empty());
+    }
+
+    // Creating the default world
+    #[test]
+    fn creating_the_default_world() {
+        let light = point_light(point(-10.0, 10.0, -10.0), color(1.0, 1.0, 1.0));
+        let m = material(color(0.8, 1.0, 0.6), 0.1, 0.7, 0.2, 200.0);
+        let mut s1 = sphere(1);
+        s1.material = m;
+        let mut s2 = sphere(2);
+        s2.set_transform(&scaling(0.5, 0.5, 0.5));
+
+        let w = default_world();
+        assert_eq!(w.lights[0], light);
+        assert_eq!(w.objects[0], s1);
+        assert_eq!(w.objects[1], s2);
+    }
+
+    // Intersect a world with a ray
+    #[test]
+    fn intersect_world_with_ray() {
+        let w = default_world();
+        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = intersect_world(&w, &r);
+        assert_eq!(xs.len(), 4);
+        assert_eq!(xs[0].t, 4.0);
+        assert_eq!(xs[1].t, 4.5);
+        assert_eq!(xs[2].t, 5.5);
+        assert_eq!(xs[3].t, 6.0);
+    }
+
+    // Intersections from the world carry an index that resolves to the hit object
+    #[test]
+    fn intersection_object_index_resolves_to_object() {
+        let w = default_world();
+        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = intersect_world(&w, &r);
+        for i in &xs {
+            let index = i.object_index.expect("should have index");
+            let object = w.object(index).expect("index should be valid");
+            assert!(std::ptr::eq(object, i.object.unwrap()));
+        }
+        // Outer sphere (index 0) is hit first and last, the inner one in between
+        assert_eq!(xs.len(), 4);
+        assert_eq!(xs[0].object_index, Some(ObjectIndex(0)));
+        assert_eq!(xs[1].object_index, Some(ObjectIndex(1)));
+        assert_eq!(xs[2].object_index, Some(ObjectIndex(1)));
+        assert_eq!(xs[3].object_index, Some(ObjectIndex(0)));
+    }
+
+    // Adding an object returns its index
+    #[test]
+    fn add_object_returns_index() {
+        let mut w = world();
+        let a = w.add_object(sphere(1));
+        let b = w.add_object(plane());
+        assert_eq!(a.index(), 0);
+        assert_eq!(b.index(), 1);
+        assert!(matches!(
+            w.object(b).unwrap().shape,
+            crate::shapes::ShapeEnum::Plane(_)
+        ));
+        assert!(w.object(ObjectIndex(2)).is_none());
+    }
+
+    // The bounds of a world span all of its objects
+    #[test]
+    fn world_bounds_span_all_objects() {
+        let mut w = world();
+        let mut a = sphere(1);
+        a.set_transform(&translation(-5.0, 0.0, 0.0));
+        let mut b = sphere(2);
+        b.set_transform(&translation(5.0, 0.0, 0.0));
+        w.add_object(a);
+        w.add_object(b);
+
+        let bounds = w.bounds();
+        assert_eq!(bounds.min, point(-6.0, -1.0, -1.0));
+        assert_eq!(bounds.max, point(6.0, 1.0, 1.0));
+    }
+
+    // Objects whose bounds a ray misses are culled
+    #[test]
+    fn ray_culls_objects_outside_bounds() {
+        let mut w = world();
+        let mut a = sphere(1);
+        a.set_transform(&translation(-5.0, 0.0, 0.0));
+        let mut b = sphere(2);
+        b.set_transform(&translation(5.0, 0.0, 0.0));
+        w.add_object(a);
+        w.add_object(b);
+        w.set_cull_statistics(true);
+        assert_eq!(w.cull_ratio(), 0.0);
+
+        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = intersect_world(&w, &r);
+        assert!(xs.is_empty());
+        assert_eq!(w.cull_ratio(), 1.0);
+
+        let r = ray(point(-5.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = intersect_world(&w, &r);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(w.cull_ratio(), 0.75);
+
+        w.reset_cull_statistics();
+        assert_eq!(w.cull_ratio(), 0.0);
+    }
+
+    // Culling isn't counted unless the statistics are enabled
+    #[test]
+    fn cull_statistics_are_off_by_default() {
+        let mut w = world();
+        let mut s = sphere(1);
+        s.set_transform(&translation(-5.0, 0.0, 0.0));
+        w.add_object(s);
+
+        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert!(intersect_world(&w, &r).is_empty());
+        assert_eq!(w.cull_ratio(), 0.0);
+    }
+
+    // Distant hits are blended more toward the fog color than near hits
+    #[test]
+    fn fog_blends_distant_hits_more() {
+        let mut w = world();
+        w.add_light(point_light(point(0.0, 10.0, -10.0), color(1.0, 1.0, 1.0)));
+        let mut near = sphere(1);
+        near.set_transform(&translation(-3.0, 0.0, 0.0));
+        w.add_object(near);
+        let mut far = sphere(2);
+        far.set_transform(&translation(3.0, 0.0, 40.0));
+        w.add_object(far);
+
+        let fog_color = color(0.5, 0.5, 0.6);
+        let fog = Fog::new(fog_color, 0.05);
+        let near_ray = ray(point(-3.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let far_ray = ray(point(3.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+
+        let distance_to_fog = |r: &Ray| {
+            let c = color_at_fogged(&w, r, 1, &fog);
+            (c - fog_color).red().abs() + (c - fog_color).blue().abs()
+        };
+        let unfogged_distance = |r: &Ray| {
+            let c = color_at(&w, r, 1);
+            (c - fog_color).red().abs() + (c - fog_color).blue().abs()
+        };
+        assert!(distance_to_fog(&near_ray) < unfogged_distance(&near_ray));
+        assert!(distance_to_fog(&far_ray) < distance_to_fog(&near_ray));
+
+        // A ray that hits nothing sees only fog:
+        let miss = ray(point(0.0, 0.0, -5.0), vector(0.0, 1.0, 0.0));
+        assert_eq!(color_at_fogged(&w, &miss, 1, &fog), fog_color);
+
+        // Zero density disables fog:
+        let no_fog = Fog::new(fog_color, 0.0);
+        for r in [&near_ray, &far_ray, &miss] {
+            assert_eq!(color_at_fogged(&w, r, 1, &no_fog), color_at(&w, r, 1));
+        }
+    }
+
+    // Intersecting a packet of rays gives the same hits as intersecting each ray
+    #[test]
+    fn packet_hits_match_individual_rays() {
+        let mut w = default_world();
+        let mut floor = plane();
+        floor.set_transform(&translation(0.0, -1.0, 0.0));
+        w.add_object(floor);
+        let g = w.add_object(group());
+        let mut child = cube();
+        child.set_transform(&translation(2.0, 0.0, 1.0));
+        w.add_child(&g, child).unwrap();
+        let mut child = cylinder(-1.0, 1.0, true, true);
+        child.set_transform(&translation(-2.0, 0.0, 1.0));
+        w.add_child(&g, child).unwrap();
+
+        let mut c = camera(Resolution::new(16, 16), PI / 2.0);
+        c.set_transform(&view_transform(
+            &point(0.0, 2.0, -5.0),
+            &point(0.0, 0.0, 0.0),
+            &vector(0.0, 1.0, 0.0),
+        ));
+
+        // 4x4 pixel blocks:
+        for by in (0..16).step_by(4) {
+            for bx in (0..16).step_by(4) {
+                let rays: Vec<Ray> = (0..16)
+                    .map(|i| c.ray_for_pixel(bx + i % 4, by + i / 4))
+                    .collect();
+                let hits = w.intersect_packet(&rays);
+                assert_eq!(hits.len(), rays.len());
+
+                for (r, packet_hit) in rays.iter().zip(hits) {
+                    let xs = intersect_world(&w, r);
+                    let expected = xs.iter().find(|x| x.t > 0.0);
+                    match (packet_hit, expected) {
+                        (Some(a), Some(b)) => {
+                            assert_eq!(a.t, b.t);
+                            assert_eq!(a.object_index, b.object_index);
+                            assert!(std::ptr::eq(a.object.unwrap(), b.object.unwrap()));
+                        }
+                        (None, None) => {}
+                        (a, b) => panic!("packet hit {a:?} differs from {b:?}"),
+                    }
+                }
+            }
+        }
+    }
+
+    // Adding children to a group
+    #[test]
+    fn group_members_in_insertion_order() {
+        let mut w = world();
+        let g = w.add_object(group());
+        let a = w.add_child(&g, sphere(1)).unwrap();
+        let b = w.add_child(&g, plane()).unwrap();
+        assert!(w.is_group(&g));
+        assert!(!w.is_group(&a));
+        assert_eq!(w.group_members(&g).unwrap(), &[a, b]);
+        assert_eq!(w.object(a).unwrap().parent(), Some(g));
+        assert_eq!(w.object(g).unwrap().parent(), None);
+    }
+
+    // Asking for the members of a non-group is an error
+    #[test]
+    fn group_members_of_non_group_is_error() {
+        let mut w = world();
+        let s = w.add_object(sphere(1));
+        assert!(w.group_members(&s).is_err());
+        assert!(w.add_child(&s, sphere(2)).is_err());
+        assert!(w.group_members(&ObjectIndex(99)).is_err());
+    }
+
+    // All intersections of a ray with the default world, in ascending t
+    #[test]
+    fn all_intersections_with_default_world() {
+        let w = default_world();
+        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = w.all_intersections(&r);
+        assert_eq!(
+            xs.iter().map(|i| i.t).collect::<Vec<_>>(),
+            vec![4.0, 4.5, 5.5, 6.0]
+        );
+
+        // Refractive indices for every intersection at once, as for each hit alone
+        let n = refractive_indices(&r, &xs);
+        assert_eq!(n.len(), 4);
+        for (k, i) in xs.iter().enumerate() {
+            let comps = prepare_computations_for_refraction(i, &r, &xs);
+            assert_eq!(n[k], (comps.n1, comps.n2));
+        }
+    }
+
+    // A world's refractive indices start from its ambient medium, as for each hit
+    #[test]
+    fn world_refractive_indices_start_in_ambient_medium() {
+        let mut w = world();
+        w.add_object(glass_sphere());
+        w.set_ambient_medium(RefractiveIndex::WATER);
+        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = w.all_intersections(&r);
+
+        let n = w.refractive_indices(&r, &xs);
+        assert_eq!(
+            n,
+            vec![
+                (RefractiveIndex::WATER, RefractiveIndex::GLASS),
+                (RefractiveIndex::GLASS, RefractiveIndex::WATER)
+            ]
+        );
+        for (k, i) in xs.iter().enumerate() {
+            let comps = prepare_computations_in_medium(i, &r, &xs, w.ambient_medium());
+            assert_eq!(n[k], (comps.n1, comps.n2));
+        }
+    }
+
+    // Dispersion refracts each channel as if the material had that channel's index
+    #[test]
+    fn dispersion_separates_channels() {
+        let render = |dispersion: Option<[f64; 3]>, refractive_index: f64| {
+            let mut w = world();
+            w.add_light(point_light(point(0.0, 10.0, -10.0), WHITE));
+            w.set_dispersion(true);
+
+            // A backdrop getting brighter from left to right
+            let mut backdrop = plane();
+            backdrop.set_transform(&(translation(0.0, 0.0, 10.0) * rotation_x(PI / 2.0)));
+            let mut ramp = gradient_pattern(&BLACK, &WHITE);
+            ramp.set_transform(&(translation(-1.0, 0.0, 0.0) * scaling(2.0, 1.0, 1.0)));
+            backdrop.material.set_pattern(&ramp);
+            backdrop.material.ambient = 1.0;
+            backdrop.material.diffuse = 0.0;
+            backdrop.material.specular = 0.0;
+            w.add_object(backdrop);
+
+            // A glass block, turned so light passing through is displaced sideways
+            let mut block = cube();
+            block.set_transform(&rotation_y(PI / 6.0));
+            block.material.ambient = 0.0;
+            block.material.diffuse = 0.0;
+            block.material.specular = 0.0;
+            block.material.transparency = 1.0;
+            block.material.refractive_index = refractive_index;
+            block.material.dispersion = dispersion;
+            w.add_object(block);
+
+            let r = ray(point(0.2, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+            color_at(&w, &r, 5)
+        };
+
+        let n = [1.4, 1.6, 1.8];
+        let dispersed = render(Some(n), 1.6);
+        let [red, green, blue] = n.map(|n| render(None, n));
+        assert_eq!(dispersed, color(red.red(), green.green(), blue.blue()));
+        // Light bent more is displaced further to the right
+        assert!(dispersed.red() + 0.01 < dispersed.green());
+        assert!(dispersed.green() + 0.01 < dispersed.blue());
+
+        // Without dispersion, white light stays white
+        assert_eq!(red.red(), red.blue());
+
+        // Equal indices are the same as a single index
+        assert_eq!(render(Some([1.6; 3]), 1.6), green);
+        assert_eq!(render(Some([1.6; 3]), 1.0), green);
+    }
+
+    // Overlapping objects of the same refractive index, one a clone of the other,
+    // are still entered and exited separately
+    #[test]
+    fn overlapping_cloned_glass_spheres() {
+        let mut w = world();
+        let a = glass_sphere();
+        let mut b = a.clone();
+        b.set_transform(&translation(0.5, 0.0, 0.0));
+        let a = w.add_object(a);
+        let b = w.add_object(b);
+        assert_ne!(w.object(a).unwrap().id(), w.object(b).unwrap().id());
+
+        let r = ray(point(-3.0, 0.0, 0.0), vector(1.0, 0.0, 0.0));
+        let xs = w.all_intersections(&r);
+        assert_eq!(
+            xs.iter().map(|i| i.t).collect::<Vec<_>>(),
+            vec![2.0, 2.5, 4.0, 4.5]
+        );
 
-            // Use fact that cos(theta_i) == dot(eye_vector, normal_vector)
-            let cos_i = dot(&comps.eyev, &comps.normalv);
+        let glass = RefractiveIndex::GLASS;
+        let expected = vec![
+            (RefractiveIndex::VACUUM, glass), // into a
+            (glass, glass),                   // into b, inside a
+            (glass, glass),                   // out of a, inside b
+            (glass, RefractiveIndex::VACUUM), // out of b
+        ];
+        assert_eq!(refractive_indices(&r, &xs), expected);
+        for (i, n) in xs.iter().zip(expected) {
+            let comps = prepare_computations_for_refraction(i, &r, &xs);
+            assert_eq!((comps.n1, comps.n2), n);
+        }
+    }
 
-            // Find sin(theta_2)^2 via trig identity:
-            let sin2_t = n_ratio * n_ratio * (1.0 - cos_i * cos_i);
+    // Inspecting a world lists its objects, lights and bounds
+    #[test]
+    fn inspecting_two_spheres() {
+        let mut w = world();
+        w.add_light(point_light(point(-10.0, 10.0, -10.0), color(1.0, 1.0, 1.0)));
+        let mut a = sphere(1);
+        a.set_transform(&translation(-2.0, 0.0, 0.0));
+        w.add_object(a);
+        let mut b = sphere(2);
+        b.set_transform(&(translation(3.0, 1.0, 0.0) * scaling(2.0, 2.0, 2.0)));
+        w.add_object(b);
+        w.prepare();
+
+        let report = inspect(&w);
+        assert_eq!(
+            report.lines().collect::<Vec<_>>(),
+            vec![
+                "Objects: 2",
+                "  0: sphere: (-3, -1, -1) to (-1, 1, 1)",
+                "  1: sphere: (1, -1, -2) to (5, 3, 2)",
+                "Lights: 1",
+                "Scene bounds: (-3, -1, -2) to (5, 3, 2)",
+            ]
+        );
+    }
 
-            // If sin2_t > 1.0, there is no transmission - Total Internal Reflection
-            if sin2_t > 1.0 {
-                return color(0.0, 0.0, 0.0);
+    // Inspecting a group counts its triangles, including those in nested groups
+    #[test]
+    fn inspecting_groups_counts_triangles() {
+        let mut w = world();
+        let outer = w.add_object(group());
+        let inner = w.add_child(&outer, group()).unwrap();
+        let tri = || {
+            triangle(
+                point(0.0, 1.0, 0.0),
+                point(-1.0, 0.0, 0.0),
+                point(1.0, 0.0, 0.0),
+            )
+        };
+        w.add_child(&outer, tri()).unwrap();
+        w.add_child(&inner, tri()).unwrap();
+        w.add_child(&inner, tri()).unwrap();
+        w.add_child(&outer, sphere(1)).unwrap();
+        w.prepare();
+
+        let report = inspect(&w);
+        assert!(report.contains("  0: group (3 members, 3 triangles):"));
+        assert!(report.contains("  1: group (2 members, 2 triangles), in group 0:"));
+        assert!(report.contains("Lights: 0"));
+    }
+
+    // A cube in each of a 3 x 3 x 3 grid of places, with a pattern in object space
+    fn cube_grid(instanced: bool) -> World {
+        let mut w = world();
+        w.add_light(point_light(point(-10.0, 10.0, -10.0), color(1.0, 1.0, 1.0)));
+
+        let mut prototype = cube();
+        prototype.set_transform(&(rotation_y(0.3) * scaling(0.3, 0.3, 0.3)));
+        let mut stripes = stripe_pattern(&color(1.0, 0.5, 0.0), &color(0.0, 0.5, 1.0));
+        stripes.set_transform(&scaling(0.5, 0.5, 0.5));
+        prototype.material.set_pattern(&stripes);
+
+        let prototype_index = instanced.then(|| w.add_prototype(prototype.clone()));
+        for x in -1..=1 {
+            for y in -1..=1 {
+                for z in -1..=1 {
+                    let place = translation(x as f64, y as f64, z as f64);
+                    match prototype_index {
+                        Some(p) => {
+                            w.add_instance(&p, &place).unwrap();
+                        }
+                        None => {
+                            let transform = place * prototype.transform();
+                            w.add_object(prototype.clone_with_transform(&transform));
+                        }
+                    }
+                }
+            }
+        }
+        w.prepare();
+        w
+    }
+
+    // A grid of instances looks the same as a grid of copies, but stores one cube
+    #[test]
+    fn instance_grid_matches_cloned_grid() {
+        let instances = cube_grid(true);
+        let copies = cube_grid(false);
+
+        let cubes = |w: &World| w.objects.iter().filter(|o| !o.is_instance()).count();
+        assert_eq!(cubes(&instances), 1);
+        assert_eq!(instances.objects.len(), 1 + 27);
+        assert_eq!(cubes(&copies), 27);
+
+        let mut c = camera(Resolution::new(40, 30), PI / 3.0);
+        c.set_transform(&view_transform(
+            &point(2.0, 3.0, -5.0),
+            &point(0.0, 0.0, 0.0),
+            &vector(0.0, 1.0, 0.0),
+        ));
+        for y in 0..30 {
+            for x in 0..40 {
+                let r = c.ray_for_pixel(x, y);
+                let hit = |w: &World| w.intersect(&r).iter().find(|i| i.t > 0.0).map(|i| i.t);
+                match (hit(&instances), hit(&copies)) {
+                    (Some(a), Some(b)) => assert_relative_eq!(a, b, epsilon = 1e-9),
+                    (a, b) => assert_eq!(a.is_some(), b.is_some()),
+                }
+                assert_relative_eq!(
+                    color_at(&instances, &r, 5),
+                    color_at(&copies, &r, 5),
+                    epsilon = 1e-9
+                );
             }
+        }
+    }
+
+    // Each instance is a separate object when refracting
+    #[test]
+    fn overlapping_glass_instances() {
+        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let places = [translation(0.0, 0.0, -0.5), translation(0.0, 0.0, 0.5)];
+
+        let mut instances = world();
+        let glass = instances.add_prototype(glass_sphere());
+        for place in &places {
+            instances.add_instance(&glass, place).unwrap();
+        }
+
+        let mut copies = world();
+        for place in &places {
+            copies.add_object(glass_sphere().clone_with_transform(place));
+        }
+
+        assert_eq!(
+            refractive_indices(&r, &instances.all_intersections(&r)),
+            refractive_indices(&r, &copies.all_intersections(&r))
+        );
+    }
+
+    // An instance can't be a prototype
+    #[test]
+    fn instance_of_instance_is_error() {
+        let mut w = world();
+        let prototype = w.add_prototype(sphere(1));
+        let instance = w
+            .add_instance(&prototype, &translation(1.0, 0.0, 0.0))
+            .unwrap();
+        assert!(w.add_instance(&instance, &identity4()).is_err());
+    }
+
+    // An instance must refer to a prototype in the world
+    #[test]
+    fn instance_of_missing_prototype_is_error() {
+        let mut w = world();
+        w.add_object(sphere(1));
+        let missing = crate::shapes::instance(ObjectIndex(5));
+        assert!(w.try_add_object(missing).is_err());
+        assert_eq!(w.objects().len(), 1);
+    }
+
+    // A group can't contain an instance of itself, or of a group around it
+    #[test]
+    fn group_containing_instance_of_itself_is_error() {
+        let mut w = world();
+        let outer = w.add_object(group());
+        let inner = w.add_child(&outer, group()).unwrap();
+        w.add_child(&inner, sphere(1)).unwrap();
+        assert!(w.add_child(&inner, crate::shapes::instance(inner)).is_err());
+        assert!(w.add_child(&inner, crate::shapes::instance(outer)).is_err());
+
+        // An instance of a sibling group is fine
+        let other = w.add_object(group());
+        w.add_child(&other, sphere(2)).unwrap();
+        assert!(w.add_child(&inner, crate::shapes::instance(other)).is_ok());
+    }
 
-            // Find cos(theta_t) via trig identity:
-            let cos_t = f64::sqrt(1.0 - sin2_t);
+    // A prototype is only seen through its instances
+    #[test]
+    fn prototype_is_hidden() {
+        let mut w = world();
+        let prototype = w.add_prototype(sphere(1));
+        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert!(w.all_intersections(&r).is_empty());
+
+        let instance = w
+            .add_instance(&prototype, &translation(0.0, 0.0, 1.0))
+            .unwrap();
+        let xs = w.all_intersections(&r);
+        assert_eq!(xs.iter().map(|i| i.t).collect::<Vec<_>>(), vec![5.0, 7.0]);
+        assert_eq!(
+            xs[0].instance.map(Shape::id),
+            w.object(instance).map(Shape::id)
+        );
+        assert_eq!(w.bounds().max.z(), 2.0);
+    }
+
+    // Intersecting a ray with a nonempty group
+    #[test]
+    fn intersecting_ray_with_nonempty_group() {
+        let mut w = world();
+        let g = w.add_object(group());
+        let s1 = w.add_child(&g, sphere(1)).unwrap();
+        let mut s2 = sphere(2);
+        s2.set_transform(&translation(0.0, 0.0, -3.0));
+        let s2 = w.add_child(&g, s2).unwrap();
+        let mut s3 = sphere(3);
+        s3.set_transform(&translation(5.0, 0.0, 0.0));
+        w.add_child(&g, s3).unwrap();
+
+        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = intersect_world(&w, &r);
+        assert_eq!(xs.len(), 4);
+        assert_eq!(xs[0].object_index, Some(s2));
+        assert_eq!(xs[1].object_index, Some(s2));
+        assert_eq!(xs[2].object_index, Some(s1));
+        assert_eq!(xs[3].object_index, Some(s1));
+    }
+
+    // Intersecting a transformed group
+    #[test]
+    fn intersecting_transformed_group() {
+        let mut w = world();
+        let mut g = group();
+        g.set_transform(&scaling(2.0, 2.0, 2.0));
+        let g = w.add_object(g);
+        let mut s = sphere(1);
+        s.set_transform(&translation(5.0, 0.0, 0.0));
+        w.add_child(&g, s).unwrap();
+
+        let r = ray(point(10.0, 0.0, -10.0), vector(0.0, 0.0, 1.0));
+        let xs = intersect_world(&w, &r);
+        assert_eq!(xs.len(), 2);
+    }
+
+    // Intersecting a grouped sphere through the public API
+    #[test]
+    fn intersecting_grouped_sphere_in_world() {
+        let mut w = world();
+        let mut g = group();
+        g.set_transform(&scaling(2.0, 2.0, 2.0));
+        let g = w.add_object(g);
+        let mut s = sphere(1);
+        s.set_transform(&translation(5.0, 0.0, 0.0));
+        let s = w.add_child(&g, s).unwrap();
+
+        let r = ray(point(10.0, 0.0, -10.0), vector(0.0, 0.0, 1.0));
+        let expected: Vec<(f64, Option<ObjectIndex>)> = intersect_world(&w, &r)
+            .iter()
+            .map(|i| (i.t, i.object_index))
+            .collect();
+        assert_eq!(expected, vec![(8.0, Some(s)), (12.0, Some(s))]);
+
+        // The group alone has no geometry
+        let group_shape = w.object(g).unwrap();
+        assert!(intersect(group_shape, &r).is_empty());
+
+        let xs = intersect_in_world(group_shape, &r, &w);
+        let actual: Vec<(f64, Option<ObjectIndex>)> =
+            xs.iter().map(|i| (i.t, i.object_index)).collect();
+        assert_eq!(actual, expected);
+
+        // A grouped child uses its composed transform
+        let xs = intersect_in_world(w.object(s).unwrap(), &r, &w);
+        assert_eq!(xs.iter().map(|i| i.t).collect::<Vec<_>>(), vec![8.0, 12.0]);
+    }
+
+    // Moving a child outside its group's old bounds updates the group bounds
+    #[test]
+    fn moving_child_updates_group_bounds() {
+        let mut w = world();
+        let g = w.add_object(group());
+        let s = w.add_child(&g, sphere(1)).unwrap();
+
+        // Towards where the child will be moved, well outside the group's bounds
+        let r = ray(point(10.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert!(intersect_world(&w, &r).is_empty());
+
+        w.object_mut(&s)
+            .unwrap()
+            .set_transform(&translation(10.0, 0.0, 0.0));
+        w.mark_bounds_dirty(&s);
 
-            // Compute direction of refracted ray
-            let direction = comps.normalv * (n_ratio * cos_i - cos_t) - comps.eyev * n_ratio;
+        let xs = intersect_world(&w, &r);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].object_index, Some(s));
+        assert_eq!(w.bounds().max, point(11.0, 1.0, 1.0));
+
+        // Once refreshed, the stored bounds match too
+        w.refresh_bounds();
+        assert_eq!(w.object_bounds()[g.index()].min, point(9.0, -1.0, -1.0));
+        assert_eq!(intersect_world(&w, &r).len(), 2);
+    }
+
+    // Editing a material after adding the object is reflected in the next render
+    #[test]
+    fn editing_material_through_object_mut() {
+        let mut w = default_world();
+        let mut c = camera(Resolution::new(11, 11), PI / 2.0);
+        c.set_transform(&view_transform(
+            &point(0.0, 0.0, -5.0),
+            &point(0.0, 0.0, 0.0),
+            &vector(0.0, 1.0, 0.0),
+        ));
+        let before = *c.render_single_threaded(&w, 1, None).pixel_at(5, 5);
+
+        let outer = ObjectIndex(0);
+        w.object_mut(&outer).unwrap().material.color = color(0.0, 0.0, 1.0);
+        let after = *c.render_single_threaded(&w, 1, None).pixel_at(5, 5);
+        assert_ne!(after, before);
+        assert_eq!(after.red(), after.green());
+        assert!(after.blue() > after.red());
+    }
+
+    // An object index out of range is an error
+    #[test]
+    fn object_mut_with_index_out_of_range() {
+        let mut w = default_world();
+        let err = w.object_mut(&ObjectIndex(2)).unwrap_err();
+        assert!(err.to_string().contains("out of range"), "{err}");
+    }
 
-            let refracted_ray = ray(comps.under_point, direction);
+    // On average, Russian roulette gives the same result as full recursion
+    #[test]
+    fn russian_roulette_converges_to_full_recursion() {
+        let mut w = default_world();
+        let mut floor = plane();
+        floor.set_transform(&translation(0.0, -1.0, 0.0));
+        floor.material.reflective = 0.5;
+        w.add_object(floor);
+        w.object_mut(&ObjectIndex(0)).unwrap().material.reflective = 0.6;
 
-            self.color_at(&refracted_ray, depth - 1) * comps.object.material.transparency
+        let k = f64::sqrt(2.0) / 2.0;
+        let r = ray(point(0.0, 0.5, -3.0), vector(0.0, -k, k));
+        let depth = 10;
+        let expected = color_at(&w, &r, depth);
+
+        w.set_russian_roulette(true);
+        let samples = 4000;
+        let mut sum = BLACK;
+        let mut distinct = std::collections::HashSet::new();
+        for seed in 0..samples {
+            w.set_seed(seed);
+            let c = color_at(&w, &r, depth);
+            distinct.insert(format!("{c:?}"));
+            sum += c;
         }
-    }
-}
-
-pub fn world() -> World {
-    World::default()
-}
+        let mean = sum / samples as f64;
 
-pub fn default_world() -> World {
-    let mut lights = vec![];
-    let light = point_light(point(-10.0, 10.0, -10.0), color(1.0, 1.0, 1.0));
-    lights.push(light);
+        assert!(distinct.len() > 1, "roulette should change the result");
+        assert_relative_eq!(mean, expected, epsilon = 0.03);
 
-    let mut objects = vec![];
+        // The same seed gives the same result
+        w.set_seed(7);
+        assert_eq!(color_at(&w, &r, depth), color_at(&w, &r, depth));
+    }
 
-    let m = material(color(0.8, 1.0, 0.6), 0.1, 0.7, 0.2, 200.0);
-    let mut s1 = sphere(1);
-    s1.material = m;
-    objects.push(s1);
+    // On average, sampling a few of many lights gives the same result as shading all
+    // of them
+    #[test]
+    fn light_sampling_converges_to_all_lights() {
+        let mut w = default_world();
+        w.lights.clear();
+        w.add_light(point_light(point(-10.0, 10.0, -10.0), color(1.0, 1.0, 1.0)));
+        w.add_light(point_light(point(10.0, 10.0, -10.0), color(0.5, 0.2, 0.2)));
+        w.add_light(point_light(point(0.0, -5.0, -5.0), color(0.1, 0.3, 0.1)));
+        w.add_light(point_light(point(3.0, 2.0, -4.0), color(0.2, 0.2, 0.6)));
+        w.add_light(point_light(point(-2.0, 0.0, -3.0), color(0.05, 0.05, 0.05)));
+
+        // Many rays over the sphere, at a fixed seed
+        let n = 120;
+        let rays: Vec<_> = (0..n * n)
+            .map(|i| {
+                let x = -0.9 + 1.8 * (i % n) as f64 / n as f64;
+                let y = -0.9 + 1.8 * (i / n) as f64 / n as f64;
+                ray(point(x, y, -5.0), vector(0.0, 0.0, 1.0))
+            })
+            .collect();
+        let mean = |w: &World| {
+            rays.iter().fold(BLACK, |sum, r| sum + color_at(w, r, 1)) / rays.len() as f64
+        };
+        let expected = mean(&w);
+
+        w.set_seed(11);
+        w.set_light_sampling(Some(2));
+        let sampled = mean(&w);
+        assert_ne!(sampled, expected, "sampling should change the result");
+        assert_relative_eq!(sampled, expected, epsilon = 0.01);
+
+        // Sampling at least as many lights as there are shades them all
+        w.set_light_sampling(Some(5));
+        assert_eq!(mean(&w), expected);
+
+        // Sampling no lights samples one, rather than leaving the scene unlit
+        w.set_light_sampling(Some(1));
+        let one = mean(&w);
+        w.set_light_sampling(Some(0));
+        assert_eq!(w.light_sampling(), Some(1));
+        assert_eq!(mean(&w), one);
+    }
 
-    let mut s2 = sphere(2);
-    s2.set_transform(&scaling(0.5, 0.5, 0.5));
-    objects.push(s2);
+    // Members without a material of their own take their group's when prepared,
+    // while those with one keep it
+    #[test]
+    fn group_members_inherit_material() {
+        let flat = |c| material(c, 1.0, 0.0, 0.0, 200.0);
 
-    World::new(lights, objects)
-}
+        let mut w = world();
+        w.add_light(point_light(point(0.0, 0.0, -10.0), WHITE));
+        let mut outer = group();
+        outer.material = flat(RED);
+        let outer = w.add_object(outer);
+        let mut inner = group();
+        inner.set_inherit_material(true);
+        let inner = w.add_child(&outer, inner).unwrap();
+
+        let mut plain = sphere(1);
+        plain.set_transform(&translation(-2.0, 0.0, 0.0));
+        plain.set_inherit_material(true);
+        w.add_child(&inner, plain).unwrap();
+        let mut own = sphere(2);
+        own.set_transform(&translation(2.0, 0.0, 0.0));
+        own.material = flat(GREEN);
+        w.add_child(&inner, own).unwrap();
+        w.prepare();
+
+        let shade = |x| color_at(&w, &ray(point(x, 0.0, -5.0), vector(0.0, 0.0, 1.0)), 5);
+        assert_eq!(shade(-2.0), RED);
+        assert_eq!(shade(2.0), GREEN);
+    }
 
-pub fn intersect_world<'a>(world: &'a World, ray: &Ray) -> Intersections<'a> {
-    world.intersect(ray)
-}
+    // Shading many lights in parallel gives exactly the same colors as serially
+    #[test]
+    fn parallel_light_shading_matches_serial() {
+        let mut w = default_world();
+        w.lights.clear();
+        for i in 0..50 {
+            let angle = i as f64 * 0.7;
+            let position = point(8.0 * angle.cos(), 5.0 + (i % 7) as f64, 8.0 * angle.sin());
+            let intensity = color(0.02 * (i % 3) as f64, 0.03, 0.01 * (i % 5) as f64);
+            w.add_light(point_light(position, intensity));
+        }
 
-pub fn is_shadowed(world: &World, point: &Point, light: &PointLight) -> bool {
-    world.is_shadowed(point, light)
-}
+        let rays: Vec<_> = (0..25)
+            .map(|i| {
+                let x = -0.8 + 0.4 * (i % 5) as f64;
+                let y = -0.8 + 0.4 * (i / 5) as f64;
+                ray(point(x, y, -5.0), vector(0.0, 0.0, 1.0))
+            })
+            .collect();
+        let shade_all = |w: &World| rays.iter().map(|r| color_at(w, r, 5)).collect::<Vec<_>>();
+
+        w.set_parallel_light_threshold(Some(usize::MAX));
+        assert!(!w.shades_lights_in_parallel());
+        let serial = shade_all(&w);
+        w.set_parallel_light_threshold(Some(0));
+        assert!(w.shades_lights_in_parallel());
+        let parallel = shade_all(&w);
+        assert_eq!(parallel, serial);
+        assert!(serial.iter().any(|&c| c != BLACK));
+    }
 
-pub fn shade_hit(world: &World, comps: &IntersectionComputation, depth: i32) -> Color {
-    world.shade_hit(comps, depth)
-}
+    // By default, a scene with a few lights is shaded serially, and one with many
+    // in parallel
+    #[test]
+    fn default_parallel_light_threshold() {
+        let mut w = default_world();
+        assert_eq!(w.parallel_light_threshold(), PARALLEL_LIGHT_THRESHOLD);
+        for _ in 0..7 {
+            w.add_light(point_light(point(0.0, 10.0, -10.0), WHITE));
+        }
+        assert!(!w.shades_lights_in_parallel());
 
-pub fn color_at(world: &World, ray: &Ray, depth: i32) -> Color {
-    world.color_at(ray, depth)
-}
+        for _ in 0..50 {
+            w.add_light(point_light(point(0.0, 10.0, -10.0), WHITE));
+        }
+        assert!(w.shades_lights_in_parallel());
 
-pub fn reflected_color(world: &World, comps: &IntersectionComputation, depth: i32) -> Color {
-    world.reflected_color(comps, depth)
-}
+        w.set_parallel_light_threshold(Some(100));
+        assert!(!w.shades_lights_in_parallel());
+        w.set_parallel_light_threshold(None);
+        assert!(w.shades_lights_in_parallel());
+    }
 
-pub fn refracted_color(world: &World, comps: &IntersectionComputation, depth: i32) -> Color {
-    world.refracted_color(comps, depth)
-}
+    // Preparing the world composes the transforms of nested groups
+    #[test]
+    fn prepare_composes_nested_group_transforms() {
+        let mut w = world();
+        let g1 = w.add_object(group());
+        let mut g2 = group();
+        g2.set_transform(&scaling(1.0, 2.0, 3.0));
+        let g2 = w.add_child(&g1, g2).unwrap();
+        let mut s = sphere(1);
+        s.set_transform(&translation(5.0, 0.0, 0.0));
+        let s = w.add_child(&g2, s).unwrap();
+
+        // Edit the outer group after its members were added
+        w.object_mut(&g1)
+            .unwrap()
+            .set_transform(&rotation_y(PI / 2.0));
+        w.prepare();
+
+        // Walk the chain of parents, composing their transforms
+        let mut expected = identity4();
+        let mut next = Some(s);
+        while let Some(i) = next {
+            let object = w.object(i).unwrap();
+            expected = object.transform() * expected;
+            next = object.parent();
+        }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::intersections::{
-        intersection, intersections, prepare_computations, prepare_computations_for_refraction,
-        Intersection,
-    };
-    use crate::patterns::test_pattern;
-    use crate::rays::ray;
-    use crate::shapes::plane;
-    use crate::transformations::translation;
-    use crate::tuples::vector;
-    use approx::assert_relative_eq;
+        let object = w.object(s).unwrap();
+        assert_relative_eq!(*object.world_transform(), expected, epsilon = 1e-12);
+        assert_relative_eq!(
+            *object.world_inverse_transform(),
+            expected.inverse(),
+            epsilon = 1e-12
+        );
 
-    // Creating an empty world
-    #[test]
-    fn creating_an_empty_world() {
-        let w = world();
-        assert!(w.lights.is_empty());
-        assert!(w.objects.is_empty());
+        // Bounds follow the composed transform
+        let r = ray(point(0.0, 0.0, 20.0), vector(0.0, 0.0, -1.0));
+        let xs = intersect_world(&w, &r);
+        assert_eq!(xs.len(), 2);
+        assert_relative_eq!(xs[0].t, 20.0 + 5.0 - 1.0, epsilon = 1e-9);
     }
 
-    // Creating the default world
+    // Flattening groups bakes the composed transforms into the children
     #[test]
-    fn creating_the_default_world() {
-        let light = point_light(point(-10.0, 10.0, -10.0), color(1.0, 1.0, 1.0));
-        let m = material(color(0.8, 1.0, 0.6), 0.1, 0.7, 0.2, 200.0);
+    fn flattening_groups_preserves_intersections() {
+        let mut w = world();
+        let mut g1 = group();
+        g1.set_transform(&translation(0.0, 1.0, 0.0));
+        let g1 = w.add_object(g1);
+        let mut g2 = group();
+        g2.set_transform(&scaling(2.0, 2.0, 2.0));
+        let g2 = w.add_child(&g1, g2).unwrap();
         let mut s1 = sphere(1);
-        s1.material = m;
-        let mut s2 = sphere(2);
-        s2.set_transform(&scaling(0.5, 0.5, 0.5));
+        s1.set_transform(&translation(2.0, 0.0, 0.0));
+        s1.material.ambient = 0.5;
+        w.add_child(&g2, s1).unwrap();
+        w.add_child(&g1, sphere(2)).unwrap();
+        w.add_object(plane());
+
+        let rays = [
+            ray(point(4.0, 1.0, -10.0), vector(0.0, 0.0, 1.0)),
+            ray(point(0.0, 1.0, -10.0), vector(0.0, 0.0, 1.0)),
+            ray(point(0.0, 5.0, -10.0), vector(0.2, -0.5, 1.0).normalize()),
+        ];
+        let before: Vec<Vec<f64>> = rays
+            .iter()
+            .map(|r| intersect_world(&w, r).iter().map(|i| i.t).collect())
+            .collect();
 
-        let w = default_world();
-        assert_eq!(w.lights[0], light);
-        assert_eq!(w.objects[0], s1);
-        assert_eq!(w.objects[1], s2);
+        w.flatten_groups();
+        assert_eq!(w.objects.len(), 3);
+        assert!(w
+            .objects
+            .iter()
+            .all(|o| o.parent().is_none() && !o.is_group()));
+        assert_eq!(w.objects[0].material.ambient, 0.5);
+
+        for (r, expected) in rays.iter().zip(before) {
+            let after: Vec<f64> = intersect_world(&w, r).iter().map(|i| i.t).collect();
+            assert_eq!(after.len(), expected.len());
+            for (a, e) in after.iter().zip(expected) {
+                assert_relative_eq!(*a, e, epsilon = 1e-9);
+            }
+        }
     }
 
-    // Intersect a world with a ray
+    // Finding the normal on a child object
     #[test]
-    fn intersect_world_with_ray() {
-        let w = default_world();
-        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
-        let xs = intersect_world(&w, &r);
-        assert_eq!(xs.len(), 4);
-        assert_eq!(xs[0].t, 4.0);
-        assert_eq!(xs[1].t, 4.5);
-        assert_eq!(xs[2].t, 5.5);
-        assert_eq!(xs[3].t, 6.0);
+    fn normal_on_child_object() {
+        let mut w = world();
+        let mut g1 = group();
+        g1.set_transform(&rotation_y(PI / 2.0));
+        let g1 = w.add_object(g1);
+        let mut g2 = group();
+        g2.set_transform(&scaling(1.0, 2.0, 3.0));
+        let g2 = w.add_child(&g1, g2).unwrap();
+        let mut s = sphere(1);
+        s.set_transform(&translation(5.0, 0.0, 0.0));
+        let s = w.add_child(&g2, s).unwrap();
+
+        let n = w
+            .object(s)
+            .unwrap()
+            .normal_at(&point(1.7321, 1.1547, -5.5774));
+        assert_relative_eq!(n, vector(0.2857, 0.42854, -0.85716), epsilon = 1e-4);
     }
 
     // Shading an intersection
@@ -297,6 +2457,222 @@ mod tests {
         assert_eq!(c, color(0.0, 0.0, 0.0));
     }
 
+    // The color when a ray misses is the world's background color
+    #[test]
+    fn color_when_ray_misses_is_background() {
+        let mut w = default_world();
+        let sky = color(0.5, 0.7, 1.0);
+        w.set_ambient_background(&sky);
+        assert_eq!(w.ambient_background(), sky);
+        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 1.0, 0.0));
+        let c = color_at(&w, &r, 1);
+        assert_eq!(c, sky);
+    }
+
+    // Rays that miss see the environment, rather than the background color
+    #[test]
+    fn color_when_ray_misses_is_environment() {
+        let mut w = default_world();
+        w.set_ambient_background(&color(0.5, 0.7, 1.0));
+        w.set_environment(Some(half_black_environment()));
+        let up = ray(point(0.0, 0.0, -5.0), vector(0.0, 1.0, 0.0));
+        let down = ray(point(0.0, 0.0, -5.0), vector(0.0, -1.0, 0.0));
+        assert_eq!(color_at(&w, &up, 1), WHITE);
+        assert_eq!(color_at(&w, &down, 1), BLACK);
+    }
+
+    fn half_black_environment() -> Environment {
+        let mut image = canvas(16, 8);
+        for x in 0..16 {
+            for y in 0..4 {
+                image.write_pixel(x, y, &WHITE);
+            }
+        }
+        environment(image, 1.0)
+    }
+
+    // Image-based lighting adds ambient light from the environment around each hit
+    #[test]
+    fn image_based_lighting() {
+        let mut w = world();
+        let mut s = sphere(1);
+        s.material.color = color(0.8, 0.6, 0.4);
+        s.material.ambient = 0.5;
+        w.add_object(s);
+
+        let shade_at = |w: &World, y: f64| {
+            let r = ray(point(0.0, y, -5.0), vector(0.0, 0.0, 1.0));
+            color_at(w, &r, 1)
+        };
+
+        // Without image-based lighting, the environment doesn't light the sphere, which
+        // has only its flat ambient color, as there are no lights
+        w.set_environment(Some(half_black_environment()));
+        let flat = color(0.4, 0.3, 0.2);
+        assert_relative_eq!(shade_at(&w, 0.7), flat, epsilon = 1e-9);
+        assert_relative_eq!(shade_at(&w, -0.7), flat, epsilon = 1e-9);
+
+        let mut white = canvas(8, 4);
+        white.pixels.fill(WHITE);
+        w.set_environment(Some(environment(white, 1.0)));
+
+        // A uniform environment brightens everywhere equally
+        w.set_image_based_lighting(true);
+        let expected = color(0.4, 0.3, 0.2);
+        for y in [-0.7, 0.0, 0.7] {
+            assert_relative_eq!(shade_at(&w, y), expected, epsilon = 1e-9);
+        }
+
+        // With light only from above, the top of the sphere is brighter
+        w.set_environment(Some(half_black_environment()));
+        let top = shade_at(&w, 0.7);
+        let bottom = shade_at(&w, -0.7);
+        assert!(top.red() > bottom.red() + 0.1, "{top:?} vs {bottom:?}");
+    }
+
+    // The background color does not contribute to shading a hit
+    #[test]
+    fn background_does_not_affect_shade_hit() {
+        let mut w = default_world();
+        w.set_ambient_background(&color(0.5, 0.7, 1.0));
+        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let shape = &w.objects[0];
+        let i = intersection(4.0, Some(shape));
+        let comps = prepare_computations(&i, &r);
+        let c = shade_hit(&w, &comps, 1);
+        assert_relative_eq!(c, color(0.38066, 0.47583, 0.2855), epsilon = 1e-5);
+    }
+
+    // A colored transparent object casts a tinted shadow
+    #[test]
+    fn stained_glass_casts_tinted_shadow() {
+        let mut w = world();
+        w.add_light(point_light(point(0.0, 10.0, 0.0), color(1.0, 1.0, 1.0)));
+
+        let floor = plane();
+        w.add_object(floor.clone());
+
+        let mut panel = crate::shapes::cube();
+        panel.set_transform(&(translation(0.0, 5.0, 0.0) * scaling(2.0, 0.01, 2.0)));
+        panel.material.color = color(1.0, 0.0, 0.0);
+        panel.material.transparency = 0.8;
+        w.add_object(panel);
+
+        let p = point(0.0, 0.0, 0.0);
+        let light = w.lights[0];
+
+        // Without transparent shadows the panel blocks the light
+        let r = ray(point(0.0, 1.0, -1.0), vector(0.0, -1.0, 1.0).normalize());
+        let i = intersection(f64::sqrt(2.0), Some(&w.objects[0]));
+        let comps = prepare_computations(&i, &r);
+        let hard = shade_hit(&w, &comps, 0);
+        assert_relative_eq!(hard, color(0.1, 0.1, 0.1), epsilon = 1e-5);
+
+        w.set_transparent_shadows(true);
+        assert_relative_eq!(
+            shadow_attenuation(&w, &p, &light),
+            color(0.8, 0.0, 0.0),
+            epsilon = 1e-9
+        );
+        let i = intersection(f64::sqrt(2.0), Some(&w.objects[0]));
+        let comps = prepare_computations(&i, &r);
+        let tinted = shade_hit(&w, &comps, 0);
+        assert!(tinted.red() > hard.red());
+        assert_relative_eq!(tinted.green(), hard.green(), epsilon = 1e-9);
+        assert_relative_eq!(tinted.blue(), hard.blue(), epsilon = 1e-9);
+    }
+
+    // Each instance of a transparent prototype filters the light
+    #[test]
+    fn instances_each_filter_transparent_shadows() {
+        let mut w = world();
+        w.add_light(point_light(point(0.0, 10.0, 0.0), color(1.0, 1.0, 1.0)));
+        w.set_transparent_shadows(true);
+        let mut panel = crate::shapes::cube();
+        panel.set_transform(&scaling(2.0, 0.01, 2.0));
+        panel.material.transparency = 0.5;
+        let prototype = w.add_prototype(panel);
+        for y in [3.0, 6.0] {
+            w.add_instance(&prototype, &translation(0.0, y, 0.0))
+                .unwrap();
+        }
+        w.prepare();
+
+        let light = w.lights[0];
+        assert_relative_eq!(
+            shadow_attenuation(&w, &point(0.0, 0.0, 0.0), &light),
+            color(0.25, 0.25, 0.25),
+            epsilon = 1e-9
+        );
+    }
+
+    // Opaque objects still block light when transparent shadows are enabled
+    #[test]
+    fn opaque_objects_block_light_with_transparent_shadows() {
+        let mut w = default_world();
+        w.set_transparent_shadows(true);
+        let light = w.lights[0];
+        let p = point(10.0, -10.0, 10.0);
+        assert_eq!(shadow_attenuation(&w, &p, &light), BLACK);
+        let p = point(-20.0, 20.0, -20.0);
+        assert_eq!(shadow_attenuation(&w, &p, &light), WHITE);
+    }
+
+    // A shadow catcher is transparent where unshadowed, and dark under a shadow
+    #[test]
+    fn shadow_catcher_shows_only_shadows() {
+        let mut w = world();
+        w.add_light(point_light(point(0.0, 10.0, 0.0), color(1.0, 1.0, 1.0)));
+        let floor = w.add_object(plane());
+        let mut ball = sphere(1);
+        ball.set_transform(&translation(0.0, 2.0, 0.0));
+        w.add_object(ball);
+        w.set_ambient_background(&color(0.2, 0.3, 0.4));
+        w.prepare();
+
+        // Unshadowed floor is transparent
+        let r = ray(point(5.0, 1.0, -5.0), vector(0.0, -1.0, 0.0));
+        let (c, alpha) = shadow_catcher_at(&w, &r, 5, floor);
+        assert_eq!(alpha, 0.0);
+        assert_eq!(c, BLACK);
+
+        // The floor under the ball is opaque black
+        let r = ray(point(0.0, 0.5, -5.0), vector(0.0, -0.5, 5.0));
+        let (c, alpha) = shadow_catcher_at(&w, &r, 5, floor);
+        assert_eq!(alpha, 1.0);
+        assert_eq!(c, BLACK);
+
+        // The ball itself is opaque, and shaded as usual
+        let r = ray(point(0.0, 2.0, -5.0), vector(0.0, 0.0, 1.0));
+        let (c, alpha) = shadow_catcher_at(&w, &r, 5, floor);
+        assert_eq!(alpha, 1.0);
+        assert_eq!(c, color_at(&w, &r, 5));
+
+        // The background is transparent
+        let r = ray(point(0.0, 2.0, -5.0), vector(0.0, 1.0, 0.0));
+        let (c, alpha) = shadow_catcher_at(&w, &r, 5, floor);
+        assert_eq!(alpha, 0.0);
+        assert_eq!(c, color(0.2, 0.3, 0.4));
+    }
+
+    // A soft shadow's penumbra on a shadow catcher is partly transparent
+    #[test]
+    fn shadow_catcher_penumbra_is_partly_transparent() {
+        let mut w = world();
+        w.add_light(point_light(point(0.0, 10.0, 0.0), color(1.0, 1.0, 1.0)));
+        let floor = w.add_object(plane());
+        let mut ball = sphere(1);
+        ball.set_transform(&translation(0.0, 2.0, 0.0));
+        w.add_object(ball);
+        w.set_soft_shadows(32, 0.5);
+        w.prepare();
+
+        // The umbra is about 1.25 units in radius; look just beyond it
+        let r = ray(point(1.25, 1.0, 0.0), vector(0.0, -1.0, 0.0));
+        let (_, alpha) = shadow_catcher_at(&w, &r, 5, floor);
+        assert!(alpha > 0.0 && alpha < 1.0, "alpha {alpha}");
+    }
+
     // The color when a ray hits
     #[test]
     fn color_when_ray_hits() {
@@ -620,4 +2996,429 @@ mod tests {
         let color_ = shade_hit(&w, &comps, 5);
         assert_relative_eq!(color_, color(0.93391, 0.69643, 0.69243), epsilon = 1e-5);
     }
+
+    // shade_hit() with a blended material mixes the shading of its two materials
+    #[rstest]
+    #[case(0.0)]
+    #[case(0.5)]
+    #[case(1.0)]
+    fn shade_hit_with_blended_material(#[case] mask: f64) {
+        let mut red = material(color(1.0, 0.0, 0.0), 0.2, 0.7, 0.3, 50.0);
+        red.reflective = 0.5;
+        let blue = material(color(0.0, 0.0, 1.0), 0.1, 0.9, 0.9, 200.0);
+
+        let shade = |m: Material| {
+            let mut w = default_world();
+            let mut floor = plane();
+            floor.set_transform(&translation(0.0, -1.0, 0.0));
+            floor.material = m;
+            w.add_object(floor);
+
+            let floor = w.objects.last();
+            let k = f64::sqrt(2.0) / 2.0;
+            let r = ray(point(0.0, 0.0, -3.0), vector(0.0, -k, k));
+            let xs = intersections!(Intersection::new(f64::sqrt(2.0), floor));
+            let comps = prepare_computations_for_refraction(&xs[0], &r, &xs);
+            shade_hit(&w, &comps, 5)
+        };
+
+        let mask_pattern = solid_pattern(&color(mask, mask, mask));
+        let blended = shade(blend_material(red.clone(), blue.clone(), mask_pattern));
+        let expected = shade(red) * (1.0 - mask) + shade(blue) * mask;
+        assert_relative_eq!(blended, expected, epsilon = 1e-9);
+    }
+
+    // A camera in a denser medium sees refraction through a glass object differently
+    #[test]
+    fn camera_in_ambient_medium() {
+        let mut w = world();
+        w.add_light(point_light(point(-10.0, 10.0, -10.0), color(1.0, 1.0, 1.0)));
+
+        let mut backdrop = plane();
+        backdrop.set_transform(&(translation(0.0, 0.0, 5.0) * rotation_x(PI / 2.0)));
+        backdrop.material.set_pattern(&test_pattern());
+        w.add_object(backdrop);
+
+        let mut glass = sphere(1);
+        glass.material.ambient = 0.0;
+        glass.material.diffuse = 0.0;
+        glass.material.specular = 0.0;
+        glass.material.transparency = 1.0;
+        glass.material.refractive_index = RefractiveIndex::GLASS;
+        w.add_object(glass);
+
+        let r = ray(point(0.0, 0.5, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = w.intersect(&r);
+        let comps = prepare_computations_in_medium(&xs[0], &r, &xs, RefractiveIndex::WATER);
+        assert_eq!(comps.n1, RefractiveIndex::WATER);
+        assert_eq!(comps.n2, RefractiveIndex::GLASS);
+
+        assert_eq!(w.ambient_medium(), RefractiveIndex::VACUUM);
+        let in_vacuum = color_at(&w, &r, 5);
+        w.set_ambient_medium(RefractiveIndex::WATER);
+        let in_water = color_at(&w, &r, 5);
+        assert_relative_ne!(in_water, in_vacuum, epsilon = 0.01);
+    }
+
+    // An axis gizmo shows an object's own x, y and z axes in world space
+    #[test]
+    fn axis_gizmo_follows_object_transform() {
+        let mut w = world();
+        let mut s = sphere(1);
+        s.set_transform(&(translation(1.0, 2.0, 3.0) * rotation_y(PI / 2.0)));
+        let target = w.add_object(s);
+
+        let gizmo = w.add_axis_gizmo(&target, 2.0).unwrap();
+        assert_eq!(w.objects.len(), 4);
+
+        // Each axis starts at the object's origin, and ends `length` along its axis
+        let expected_tips = [
+            point(1.0, 2.0, 1.0), // object x is world -z
+            point(1.0, 4.0, 3.0),
+            point(3.0, 2.0, 3.0), // object z is world x
+        ];
+        let expected_colors = [RED, GREEN, BLUE];
+        for ((index, tip), c) in gizmo.iter().zip(expected_tips).zip(expected_colors) {
+            let axis = w.object(*index).unwrap();
+            assert!(matches!(axis.shape, ShapeEnum::Cylinder(_)));
+            assert_eq!(axis.material.color, c);
+            let t = axis.world_transform();
+            assert_relative_eq!(
+                *t * point(0.0, 0.0, 0.0),
+                point(1.0, 2.0, 3.0),
+                epsilon = EPSILON
+            );
+            assert_relative_eq!(*t * point(0.0, 2.0, 0.0), tip, epsilon = EPSILON);
+        }
+
+        assert!(w.add_axis_gizmo(&ObjectIndex(10), 1.0).is_err());
+    }
+
+    // Soft shadows blur the shadow's edge, but not its inside or the lit surface
+    #[test]
+    fn soft_shadow_penumbra() {
+        let mut w = world();
+        let light = point_light(point(0.0, 10.0, 0.0), color(1.0, 1.0, 1.0));
+        w.add_light(light);
+        w.add_object(plane());
+
+        // A slab over x < 0 casts a shadow with its edge along x = 0
+        let mut slab = cube();
+        slab.set_transform(&(translation(-5.0, 1.0, 0.0) * scaling(5.0, 0.1, 5.0)));
+        w.add_object(slab);
+        w.prepare();
+
+        let up = vector(0.0, 1.0, 0.0);
+        let shade =
+            |w: &World, x: f64| w.soft_shadow_attenuation(&point(x, EPSILON, 0.0), &up, &light);
+
+        let hard = [-3.0, -0.05, 0.05, 3.0].map(|x| shade(&w, x));
+        assert_eq!(hard, [BLACK, BLACK, WHITE, WHITE]);
+
+        w.set_soft_shadows(16, 0.5);
+        assert_eq!(shade(&w, -3.0), BLACK);
+        assert_eq!(shade(&w, 3.0), WHITE);
+
+        let inside = shade(&w, -0.05);
+        let outside = shade(&w, 0.05);
+        for c in [inside, outside] {
+            assert!(c.red() > 0.0 && c.red() < 1.0, "{c:?} should be partly lit");
+        }
+        assert!(inside.red() < outside.red());
+    }
+
+    // shade_hit() adds emission once, on top of reflected and refracted light
+    #[rstest]
+    #[case(1)]
+    #[case(2)]
+    #[case(0)]
+    fn shade_hit_with_emissive_reflective_transparent_material(#[case] lights: usize) {
+        let emission = color(0.2, 0.3, 0.4);
+        let shade = |emission: Color| {
+            let mut w = default_world();
+            let light = w.lights[0];
+            w.lights = vec![light; lights];
+
+            let mut floor = plane();
+            floor.set_transform(&translation(0.0, -1.0, 0.0));
+            floor.material.reflective = 0.5;
+            floor.material.transparency = 0.5;
+            floor.material.refractive_index = 1.5;
+            floor.material.emission = emission;
+            w.add_object(floor);
+
+            let floor = w.objects.last();
+            let k = f64::sqrt(2.0) / 2.0;
+            let r = ray(point(0.0, 0.0, -3.0), vector(0.0, -k, k));
+            let xs = intersections!(Intersection::new(f64::sqrt(2.0), floor));
+            let comps = prepare_computations_for_refraction(&xs[0], &r, &xs);
+            shade_hit(&w, &comps, 5)
+        };
+
+        let without = shade(BLACK);
+        let with = shade(emission);
+        assert_relative_eq!(with, without + emission, epsilon = 1e-9);
+        assert!(with.red() >= emission.red());
+    }
+
+    fn max_luminance(w: &World) -> f64 {
+        // Look into a mirror, which reflects a very shiny sphere behind the camera
+        let mut c = camera(Resolution::new(11, 11), PI / 4.0);
+        c.set_transform(&view_transform(
+            &point(0.0, 0.0, -5.0),
+            &point(0.0, 0.0, 0.0),
+            &vector(0.0, 1.0, 0.0),
+        ));
+        let image = c.render_single_threaded(w, 5, None);
+        (0..11)
+            .flat_map(|y| (0..11).map(move |x| (x, y)))
+            .map(|(x, y)| image.pixel_at(x, y).luminance())
+            .fold(0.0, f64::max)
+    }
+
+    fn firefly_world() -> World {
+        let mut w = world();
+        w.add_light(point_light(point(0.0, 0.0, -8.0), color(50.0, 50.0, 50.0)));
+
+        // The mirror itself contributes nothing but its reflection
+        let mut mirror = plane();
+        mirror.set_transform(&rotation_x(PI / 2.0));
+        mirror.material.ambient = 0.0;
+        mirror.material.diffuse = 0.0;
+        mirror.material.specular = 0.0;
+        mirror.material.reflective = 1.0;
+        w.add_object(mirror);
+
+        let mut s = sphere(1);
+        s.set_transform(&translation(0.0, 0.0, -10.0));
+        s.material.shininess = 300.0;
+        w.add_object(s);
+        w
+    }
+
+    // A cube with a material for each face shades each face with its own material,
+    // in the order +x, -x, +y, -y, +z, -z
+    #[rstest]
+    #[case(point(5.0, 0.2, 0.3), vector(-1.0, 0.0, 0.0), 0)]
+    #[case(point(-5.0, 0.2, 0.3), vector(1.0, 0.0, 0.0), 1)]
+    #[case(point(0.2, 5.0, 0.3), vector(0.0, -1.0, 0.0), 2)]
+    #[case(point(0.2, -5.0, 0.3), vector(0.0, 1.0, 0.0), 3)]
+    #[case(point(0.2, 0.3, 5.0), vector(0.0, 0.0, -1.0), 4)]
+    #[case(point(0.2, 0.3, -5.0), vector(0.0, 0.0, 1.0), 5)]
+    fn cube_with_a_material_per_face(
+        #[case] origin: Point,
+        #[case] direction: Vector,
+        #[case] face: usize,
+    ) {
+        let colors = [
+            RED,
+            GREEN,
+            BLUE,
+            WHITE,
+            color(1.0, 1.0, 0.0),
+            color(0.0, 1.0, 1.0),
+        ];
+        let materials = colors.map(|c| {
+            // Unlit, so each face shades exactly as its color
+            let mut m = Material::matte(c);
+            m.ambient = 1.0;
+            m.diffuse = 0.0;
+            m
+        });
+
+        let mut w = world();
+        w.add_light(point_light(point(-10.0, 10.0, -10.0), WHITE));
+        let mut c = cube_with_faces(materials);
+        c.set_transform(&(rotation_y(PI / 2.0) * scaling(2.0, 2.0, 2.0)));
+        w.add_object(c);
+
+        // The faces are of the cube itself, so turn with it
+        let r = ray(
+            rotation_y(PI / 2.0) * origin,
+            rotation_y(PI / 2.0) * direction,
+        );
+        assert_relative_eq!(w.color_at(&r, 5), colors[face]);
+    }
+
+    // An object hidden from reflections doesn't appear in a mirror, but is still
+    // seen directly
+    #[test]
+    fn object_hidden_from_reflections() {
+        let mut w = world();
+        w.add_light(point_light(point(0.0, 0.0, -10.0), color(1.0, 1.0, 1.0)));
+
+        let mut mirror = plane();
+        mirror.set_transform(&rotation_x(PI / 2.0));
+        mirror.material.ambient = 0.0;
+        mirror.material.diffuse = 0.0;
+        mirror.material.specular = 0.0;
+        mirror.material.reflective = 1.0;
+        w.add_object(mirror);
+
+        let mut s = sphere(1);
+        s.set_transform(&translation(0.0, 0.0, -5.0));
+        let gizmo = w.add_object(s);
+
+        // Reflected by the mirror at (2, 0, 0) back along the z axis to the sphere
+        let reflected = ray(point(4.0, 0.0, -5.0), vector(-2.0, 0.0, 5.0).normalize());
+        let direct = ray(point(0.0, 0.0, -10.0), vector(0.0, 0.0, 1.0));
+        assert_ne!(w.color_at(&reflected, 5), BLACK);
+
+        w.object_mut(&gizmo)
+            .unwrap()
+            .material
+            .visible_in_reflections = false;
+        assert_eq!(w.color_at(&reflected, 5), BLACK);
+        assert_ne!(w.color_at(&direct, 5), BLACK);
+    }
+
+    // An object hidden from reflections stays hidden when the reflection is seen
+    // through glass, although the last ray to reach it is a refraction
+    #[test]
+    fn object_hidden_from_reflections_seen_through_glass() {
+        let mut w = world();
+        w.add_light(point_light(point(0.0, 0.0, -10.0), color(1.0, 1.0, 1.0)));
+
+        let mut mirror = plane();
+        mirror.set_transform(&rotation_x(PI / 2.0));
+        mirror.material.ambient = 0.0;
+        mirror.material.diffuse = 0.0;
+        mirror.material.specular = 0.0;
+        mirror.material.reflective = 1.0;
+        w.add_object(mirror);
+
+        // A clear pane across the reflected ray, which it passes straight through
+        let mut pane = cube();
+        pane.set_transform(&(translation(1.0, 0.0, -2.5) * scaling(1.0, 1.0, 0.1)));
+        pane.material = Material::glass();
+        pane.material.diffuse = 0.0;
+        pane.material.specular = 0.0;
+        pane.material.reflective = 0.0;
+        pane.material.refractive_index = RefractiveIndex::VACUUM;
+        w.add_object(pane);
+
+        let mut s = sphere(1);
+        s.set_transform(&translation(0.0, 0.0, -5.0));
+        let gizmo = w.add_object(s);
+
+        let reflected = ray(point(4.0, 0.0, -5.0), vector(-2.0, 0.0, 5.0).normalize());
+        assert_ne!(w.color_at(&reflected, 5), BLACK);
+
+        w.object_mut(&gizmo)
+            .unwrap()
+            .material
+            .visible_in_reflections = false;
+        assert_eq!(w.color_at(&reflected, 5), BLACK);
+    }
+
+    // Clamping indirect light caps the luminance of reflected highlights
+    #[test]
+    fn clamping_indirect_light_caps_reflected_highlights() {
+        let mut w = firefly_world();
+        assert_eq!(w.indirect_clamp(), None);
+        assert!(max_luminance(&w) > 10.0);
+
+        w.set_indirect_clamp(Some(1.0));
+        let clamped = max_luminance(&w);
+        assert!(clamped > 0.5);
+        assert!(clamped <= 1.0 + EPSILON);
+    }
+
+    // Without a clamp, indirect light is unaffected
+    #[test]
+    fn no_indirect_clamp_leaves_reflections_unchanged() {
+        let mut w = default_world();
+        let mut shape = plane();
+        shape.material.reflective = 0.5;
+        shape.set_transform(&translation(0.0, -1.0, 0.0));
+        w.add_object(shape);
+        w.set_indirect_clamp(Some(f64::INFINITY));
+
+        let k = f64::sqrt(2.0) / 2.0;
+        let r = ray(point(0.0, 0.0, -3.0), vector(0.0, -k, k));
+        let c = color_at(&w, &r, 5);
+        w.set_indirect_clamp(None);
+        assert_eq!(color_at(&w, &r, 5), c);
+    }
+
+    // A mirrored (negatively scaled) sphere shades like its mirror image, not dark
+    #[rstest]
+    #[case(0.5, 0.3)]
+    #[case(-0.4, -0.2)]
+    #[case(0.0, 0.6)]
+    fn mirrored_sphere_shades_like_its_mirror_image(#[case] x: f64, #[case] y: f64) {
+        let mut mirrored = world();
+        mirrored.add_light(point_light(point(-10.0, 10.0, -10.0), WHITE));
+        let mut s = sphere(1);
+        s.set_transform(&scaling(-1.0, 1.0, 1.0));
+        mirrored.add_object(s);
+
+        let mut image = world();
+        image.add_light(point_light(point(10.0, 10.0, -10.0), WHITE));
+        image.add_object(sphere(1));
+
+        let r = ray(point(x, y, -5.0), vector(0.0, 0.0, 1.0));
+        let c = color_at(&mirrored, &r, 1);
+        let r = ray(point(-x, y, -5.0), vector(0.0, 0.0, 1.0));
+        assert_relative_eq!(c, color_at(&image, &r, 1), epsilon = 1e-9);
+        assert!(c.red() > 0.1);
+
+        // The normal still points out of the sphere
+        let p = point(x, y, -f64::sqrt(1.0 - x * x - y * y));
+        let n = mirrored.objects[0].normal_at(&p);
+        assert_relative_eq!(n, p - point(0.0, 0.0, 0.0), epsilon = 1e-9);
+    }
+
+    // A world without lights shades objects with their ambient color alone
+    #[test]
+    fn world_without_lights_shades_ambient_color() {
+        let mut w = default_world();
+        w.lights.clear();
+        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let c = color_at(&w, &r, 1);
+        assert_ne!(c, BLACK);
+        let m = &w.objects[0].material;
+        assert_relative_eq!(c, m.color * m.ambient, epsilon = 1e-9);
+    }
+
+    // A clip plane through a sphere's center cuts away its near half, so the ray
+    // passes through and shades the inside of the far half
+    #[test]
+    fn clip_plane_reveals_interior() {
+        let mut w = world();
+        w.add_light(point_light(point(0.0, 0.0, -10.0), WHITE));
+        w.add_object(sphere(1));
+        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert_eq!(hit_distance(&w, &r), 4.0);
+        let outside = color_at(&w, &r, 5);
+
+        w.set_clip_planes(vec![(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 1.0))]);
+        assert_eq!(hit_distance(&w, &r), 6.0);
+        let inside = color_at(&w, &r, 5);
+        let ambient = w.objects[0].material.color * w.objects[0].material.ambient;
+        assert_ne!(inside, outside);
+        // The near half no longer shadows the inside of the far half
+        assert!(inside.red() > ambient.red(), "the interior should be lit");
+
+        // A plane facing the other way cuts away the whole sphere from this ray
+        w.set_clip_planes(vec![(point(0.0, 0.0, -2.0), vector(0.0, 0.0, -1.0))]);
+        assert_eq!(color_at(&w, &r, 5), w.ambient_background());
+    }
+
+    // A surface cut away by a clip plane doesn't bound a medium, so a ray through
+    // the cut into a glass sphere enters the glass at the far half, from vacuum
+    #[test]
+    fn clipped_surfaces_do_not_count_as_containers() {
+        let mut w = world();
+        w.add_object(glass_sphere());
+        w.set_clip_planes(vec![(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 1.0))]);
+        let r = ray(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+
+        let xs = w.intersect_unclipped(&r);
+        let hit = w.first_hit(&r, &xs).unwrap();
+        assert_eq!(hit.t, 6.0);
+        let comps = prepare_computations_in_medium(hit, &r, &xs, RefractiveIndex::VACUUM);
+        assert_eq!(comps.n1, RefractiveIndex::VACUUM);
+        assert_eq!(comps.n2, RefractiveIndex::GLASS);
+    }
 }
@@ -1,5 +1,6 @@
 use crate::camera::Resolution;
 use crate::colors::{color, colori, Color};
+use crate::ibl::Environment;
 use crate::json;
 use crate::lights::point_light;
 use crate::materials::{default_material, Material};
@@ -14,7 +15,7 @@ use crate::transformations::{
 };
 use crate::tuples::{point, Tuple};
 use crate::utils::RenderOptions;
-use crate::world::{world, World};
+use crate::world::{world, Fog, World};
 use anyhow::{Context, Result};
 use std::collections::HashMap;
 use std::path::Path;
@@ -88,11 +89,16 @@ fn build_material(material: &json::Material) -> Material {
     m.diffuse = material.diffuse;
     m.specular = material.specular;
     m.shininess = material.shininess;
+    m.specular_color = material.specular_color.map(Into::into);
     m.reflective = material.reflective;
+    m.fresnel = material.fresnel;
     m.transparency = material.transparency;
     m.refractive_index = material.refractive_index;
+    m.dispersion = material.dispersion;
     m.casts_shadow = material.casts_shadow;
     m.receives_shadow = material.receives_shadow;
+    m.visible_in_reflections = material.visible_in_reflections;
+    m.visible_in_refractions = material.visible_in_refractions;
 
     if let Some(base_pattern) = &material.pattern {
         m.set_pattern(&build_pattern(base_pattern));
@@ -137,14 +143,37 @@ pub fn load_world(filename: &Path) -> Result<(World, HashMap<String, RenderOptio
     let mut world = world();
     let scene = json::load_scene(filename)?;
 
+    if let Some(settings) = scene.world {
+        if let Some(background) = settings.background {
+            world.set_ambient_background(&background.into());
+        }
+        if let Some(refractive_index) = settings.ambient_medium {
+            world.set_ambient_medium(refractive_index);
+        }
+        if let Some(environment) = settings.environment {
+            let path = filename
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(&environment.filename);
+            world.set_environment(Some(Environment::load(
+                path,
+                environment.intensity.unwrap_or(1.0),
+            )?));
+        }
+    }
+
     if let Some(lights) = scene.lights {
         for light in lights {
             match light {
                 json::Light::PointLight {
                     position,
                     intensity,
+                    affects_diffuse,
+                    affects_specular,
                 } => {
-                    let l = point_light(position.into(), intensity.into());
+                    let mut l = point_light(position.into(), intensity.into());
+                    l.affects_diffuse = affects_diffuse.unwrap_or(true);
+                    l.affects_specular = affects_specular.unwrap_or(true);
                     world.add_light(l);
                 }
             }
@@ -156,7 +185,10 @@ pub fn load_world(filename: &Path) -> Result<(World, HashMap<String, RenderOptio
             let shape = match body {
                 json::Body::Plane(plane) => {
                     let mut shape = crate::shapes::plane();
-                    shape.set_transform(&build_transform(&identity4(), &plane.common.transforms));
+                    shape.try_set_transform(&build_transform(
+                        &identity4(),
+                        &plane.common.transforms,
+                    ))?;
                     if let Some(m) = plane.common.material {
                         shape.material = build_material(&m);
                     };
@@ -164,7 +196,10 @@ pub fn load_world(filename: &Path) -> Result<(World, HashMap<String, RenderOptio
                 }
                 json::Body::Sphere(sphere) => {
                     let mut shape = crate::shapes::sphere(1);
-                    shape.set_transform(&build_transform(&identity4(), &sphere.common.transforms));
+                    shape.try_set_transform(&build_transform(
+                        &identity4(),
+                        &sphere.common.transforms,
+                    ))?;
                     if let Some(m) = sphere.common.material {
                         shape.material = build_material(&m);
                     };
@@ -179,7 +214,14 @@ pub fn load_world(filename: &Path) -> Result<(World, HashMap<String, RenderOptio
                     if let Some(maximum_y) = cone.maximum_y {
                         p.maximum_y = maximum_y;
                     }
-                    shape.set_transform(&build_transform(&identity4(), &cone.common.transforms));
+                    if let Some(radius) = cone.radius {
+                        p.radius = radius;
+                    }
+                    shape.validate()?;
+                    shape.try_set_transform(&build_transform(
+                        &identity4(),
+                        &cone.common.transforms,
+                    ))?;
                     if let Some(m) = cone.common.material {
                         shape.material = build_material(&m);
                     };
@@ -191,17 +233,44 @@ pub fn load_world(filename: &Path) -> Result<(World, HashMap<String, RenderOptio
                     let closed_min = cylinder.closed_min.unwrap_or(true);
                     let closed_max = cylinder.closed_max.unwrap_or(true);
 
-                    let mut shape = crate::shapes::cylinder(min_y, max_y, closed_min, closed_max);
-                    shape
-                        .set_transform(&build_transform(&identity4(), &cylinder.common.transforms));
+                    let mut shape =
+                        crate::shapes::try_cylinder(min_y, max_y, closed_min, closed_max)?;
+                    if let Some(radius) = cylinder.radius {
+                        let p = shape
+                            .as_cylinder_primitive()
+                            .context("should be a cylinder")?;
+                        p.radius = radius;
+                    }
+                    shape.try_set_transform(&build_transform(
+                        &identity4(),
+                        &cylinder.common.transforms,
+                    ))?;
                     if let Some(m) = cylinder.common.material {
                         shape.material = build_material(&m);
                     };
                     shape
                 }
+                json::Body::Capsule(capsule) => {
+                    let mut shape = crate::shapes::capsule(
+                        capsule.minimum_y.unwrap_or(-1.0),
+                        capsule.maximum_y.unwrap_or(1.0),
+                        capsule.radius.unwrap_or(1.0),
+                    );
+                    shape.try_set_transform(&build_transform(
+                        &identity4(),
+                        &capsule.common.transforms,
+                    ))?;
+                    if let Some(m) = capsule.common.material {
+                        shape.material = build_material(&m);
+                    };
+                    shape
+                }
                 json::Body::Cube(cube) => {
                     let mut shape = crate::shapes::cube();
-                    shape.set_transform(&build_transform(&identity4(), &cube.common.transforms));
+                    shape.try_set_transform(&build_transform(
+                        &identity4(),
+                        &cube.common.transforms,
+                    ))?;
                     if let Some(m) = cube.common.material {
                         shape.material = build_material(&m);
                     };
@@ -232,6 +301,29 @@ pub fn load_world(filename: &Path) -> Result<(World, HashMap<String, RenderOptio
             if let Some(fov) = camera.field_of_view {
                 render_options.field_of_view = fov;
             }
+            if let Some(ssaa) = camera.ssaa {
+                render_options.ssaa = ssaa.max(1);
+            }
+            if let Some(fog) = camera.fog {
+                render_options.fog = Some(Fog::new(fog.color.into(), fog.density));
+            }
+            render_options.clamp_indirect = camera.clamp_indirect;
+            render_options.region = camera.region;
+            render_options.russian_roulette = camera.russian_roulette.unwrap_or(false);
+            if let Some(samples) = camera.shadow_samples {
+                render_options.shadow_samples = samples.max(1);
+            }
+            if let Some(softness) = camera.shadow_softness {
+                render_options.shadow_softness = softness;
+            }
+            if let Some(seed) = camera.seed {
+                render_options.seed = seed;
+            }
+            render_options.image_based_lighting = camera.image_based_lighting.unwrap_or(false);
+            render_options.dispersion = camera.dispersion.unwrap_or(false);
+            render_options.light_sampling = camera.light_sampling.map(|samples| samples.max(1));
+            render_options.near = camera.near.unwrap_or(0.0);
+            render_options.far = camera.far;
 
             coll.insert(camera.name, render_options);
         }
@@ -239,3 +331,46 @@ pub fn load_world(filename: &Path) -> Result<(World, HashMap<String, RenderOptio
 
     Ok((world, coll))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    // A gzipped scene loads the same as the original
+    #[test]
+    fn loading_gzipped_scene() {
+        let scene = std::fs::read(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../scenes/test1.json5"
+        ))
+        .unwrap();
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(&scene).unwrap();
+        let dir = std::env::temp_dir();
+        let plain = dir.join(format!("rtc_scene_{}.json5", std::process::id()));
+        let gzipped = dir.join(format!("rtc_scene_{}.json5.gz", std::process::id()));
+        std::fs::write(&plain, &scene).unwrap();
+        std::fs::write(&gzipped, encoder.finish().unwrap()).unwrap();
+
+        let original = load_world(&plain);
+        let decompressed = load_world(&gzipped);
+        std::fs::remove_file(&plain).unwrap();
+        std::fs::remove_file(&gzipped).unwrap();
+        let (mut original, original_cameras) = original.unwrap();
+        let (mut decompressed, decompressed_cameras) = decompressed.unwrap();
+
+        assert_eq!(decompressed_cameras, original_cameras);
+        assert_eq!(decompressed.lights(), original.lights());
+        assert_eq!(decompressed.objects().len(), original.objects().len());
+        for (a, b) in decompressed.objects().iter().zip(original.objects()) {
+            assert_eq!(a.material, b.material);
+            assert_eq!(a.transform(), b.transform());
+        }
+        original.prepare();
+        decompressed.prepare();
+        assert_eq!(decompressed.inspect(), original.inspect());
+    }
+}
@@ -0,0 +1,90 @@
+// Rendering is deterministic: every random choice is derived from the seed, so the
+// same seed gives an identical image, however the work is split between threads,
+// and a different seed gives a different one. Run with `cargo test -p rust-rtc`.
+
+use rust_rtc::camera::Resolution;
+use rust_rtc::canvas::Canvas;
+use rust_rtc::colors::color;
+use rust_rtc::lights::point_light;
+use rust_rtc::materials::Material;
+use rust_rtc::math::MAX_RECURSIVE_DEPTH;
+use rust_rtc::shapes::sphere;
+use rust_rtc::transformations::{translation, view_transform};
+use rust_rtc::tuples::{point, vector};
+use rust_rtc::utils::{bench_render, ground_plane, RenderOptions};
+use rust_rtc::world::{color_at, world, World};
+
+// Spheres, one of glass, on a reflective floor, lit by three colored lights
+fn scene() -> World {
+    let mut w = world();
+    w.add_light(point_light(point(-10.0, 10.0, -10.0), color(1.0, 0.2, 0.2)));
+    w.add_light(point_light(point(10.0, 10.0, -10.0), color(0.2, 1.0, 0.2)));
+    w.add_light(point_light(point(0.0, 10.0, 10.0), color(0.2, 0.2, 1.0)));
+    w.add_object(ground_plane(color(0.5, 0.5, 0.5), 0.5));
+
+    let mut glass = sphere(1);
+    glass.set_transform(&translation(0.0, 1.0, 0.0));
+    glass.material = Material::glass();
+    w.add_object(glass);
+
+    for x in [-2.5, 2.5] {
+        let mut s = sphere(1);
+        s.set_transform(&translation(x, 1.0, 1.0));
+        s.material.reflective = 0.5;
+        w.add_object(s);
+    }
+    w
+}
+
+// Every option that makes random choices, plus supersampling and soft shadows
+fn options(seed: u64) -> RenderOptions {
+    RenderOptions {
+        default_resolution: Resolution::new(40, 30),
+        camera_transform: view_transform(
+            &point(0.0, 3.0, -6.0),
+            &point(0.0, 1.0, 0.0),
+            &vector(0.0, 1.0, 0.0),
+        ),
+        ssaa: 2,
+        russian_roulette: true,
+        shadow_samples: 8,
+        shadow_softness: 0.5,
+        light_sampling: Some(1),
+        seed,
+        ..Default::default()
+    }
+}
+
+// Rendered on one thread
+fn render_single_threaded(seed: u64) -> Canvas {
+    bench_render(&mut scene(), &options(seed)).0
+}
+
+// Rendered on many threads
+fn render_in_parallel(seed: u64) -> Canvas {
+    let options = options(seed);
+    let mut w = scene();
+    options.configure_world(&mut w);
+    let cam = options.camera().supersampled(options.ssaa);
+    cam.render_with(|ray| color_at(&w, ray, MAX_RECURSIVE_DEPTH), None)
+        .downsample(options.ssaa)
+}
+
+#[test]
+fn same_seed_gives_identical_images() {
+    let image = render_single_threaded(5);
+    assert_eq!(
+        render_single_threaded(5).to_ppm_binary(),
+        image.to_ppm_binary()
+    );
+    assert_eq!(render_in_parallel(5).to_ppm_binary(), image.to_ppm_binary());
+    assert_eq!(render_in_parallel(5), image);
+}
+
+#[test]
+fn different_seeds_give_different_images() {
+    assert_ne!(
+        render_single_threaded(5).to_ppm_binary(),
+        render_single_threaded(6).to_ppm_binary()
+    );
+}